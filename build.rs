@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main() {
+    // Expose the git revision to `q=version` when building from a checkout
+    if let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+    {
+        if output.status.success() {
+            let hash = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=VUEFINDER_GIT_HASH={}", hash.trim());
+        }
+    }
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,495 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::payload::Query;
+use crate::storages::join_path;
+use crate::trash::TRASH_DIR;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+// Grants `permissions` below `path`, a glob relative to the adapter root where
+// `*` stays within one folder and `**` spans any depth. A rule applies to the
+// paths it matches and everything inside them; items a rule doesn't allow to
+// read are hidden from listings.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRule {
+    // Every adapter when unset
+    pub adapter: Option<String>,
+    pub path: String,
+    pub permissions: Vec<Permission>,
+}
+
+// What a command needs on one of the paths it touches
+#[derive(Debug, PartialEq)]
+pub struct Access {
+    pub path: String,
+    pub permission: Permission,
+    // The command also acts on everything inside `path`
+    pub recursive: bool,
+}
+
+// `local://a/./c/../b/` -> (`local`, `/a/b`), `None` when `..` leaves the root
+// or there's no scheme, as storages take those paths relative to their root
+// whatever adapter the rules name
+pub(crate) fn split(path: &str) -> Option<(&str, String)> {
+    let (adapter, rest) = path.split_once("://")?;
+    let mut names = Vec::new();
+    for name in rest.split('/') {
+        match name {
+            "" | "." => {}
+            ".." => {
+                names.pop()?;
+            }
+            name => names.push(name),
+        }
+    }
+    Some((adapter, format!("/{}", names.join("/"))))
+}
+
+pub(crate) fn glob_match(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| glob_match(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            matches!(path, [c, tail @ ..] if *c != '/' && glob_match(rest, tail))
+        }
+        [p, rest @ ..] => matches!(path, [c, tail @ ..] if c == p && glob_match(rest, tail)),
+    }
+}
+
+impl AccessRule {
+    fn pattern(&self) -> String {
+        format!("/{}", self.path.trim_matches('/'))
+    }
+
+    fn applies_to(&self, adapter: &str) -> bool {
+        self.adapter.as_deref().is_none_or(|a| a == adapter)
+    }
+
+    // Whether the rule covers `path` (relative to the adapter root)
+    fn matches(&self, path: &str) -> bool {
+        let pattern: Vec<char> = self.pattern().chars().collect();
+        // `/docs/**` also covers `/docs` itself
+        let dir = pattern.strip_suffix(&['/', '*', '*']).map(|dir| {
+            if dir.is_empty() {
+                &['/'][..]
+            } else {
+                dir
+            }
+        });
+
+        let mut current = path.to_string();
+        loop {
+            let candidate: Vec<char> = current.chars().collect();
+            if glob_match(&pattern, &candidate) || dir == Some(&candidate[..]) {
+                return true;
+            }
+            match current.rfind('/') {
+                Some(0) if current.len() > 1 => current.truncate(1),
+                Some(i) if i > 0 => current.truncate(i),
+                _ => return false,
+            }
+        }
+    }
+
+    // The part of the pattern before any wildcard
+    fn literal_prefix(&self) -> String {
+        let pattern = self.pattern();
+        let end = pattern.find(['*', '?']).unwrap_or(pattern.len());
+        pattern[..end].to_string()
+    }
+}
+
+// The first rule covering `path` decides, paths without one are unrestricted
+pub fn allowed(rules: &[AccessRule], path: &str, permission: Permission) -> bool {
+    let Some((adapter, relative)) = split(path) else {
+        return false;
+    };
+    rules
+        .iter()
+        .find(|rule| rule.applies_to(adapter) && rule.matches(&relative))
+        .is_none_or(|rule| rule.permissions.contains(&permission))
+}
+
+// Like `allowed`, and also refuses when a rule rooted inside `path` withholds
+// the permission, for commands that act on a whole folder
+pub fn allowed_recursive(rules: &[AccessRule], path: &str, permission: Permission) -> bool {
+    let Some((adapter, relative)) = split(path) else {
+        return false;
+    };
+    let inside = format!("{}/", relative.trim_end_matches('/'));
+    allowed(rules, path, permission)
+        && !rules.iter().any(|rule| {
+            rule.applies_to(adapter)
+                && !rule.permissions.contains(&permission)
+                && rule.literal_prefix().starts_with(&inside)
+        })
+}
+
+// The paths `command` reads or writes, from its query and JSON payload.
+// `adapter` is the adapter used when the query has no path. `None` for
+// commands that don't declare theirs, which are refused.
+pub fn command_access(
+    command: &str,
+    query: &Query,
+    payload: Option<&Value>,
+    adapter: &str,
+) -> Option<Vec<Access>> {
+    let root = format!("{}://", adapter);
+    // Storages read an empty path as their root
+    let dir = query
+        .path
+        .clone()
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| root.clone());
+    let field = |name: &str| {
+        payload
+            .and_then(|payload| payload[name].as_str())
+            .map(str::to_string)
+    };
+    let items: Vec<String> = payload
+        .and_then(|payload| payload["items"].as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item["path"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let access = |path: String, permission: Permission, recursive: bool| Access {
+        path,
+        permission,
+        recursive,
+    };
+    let read = |path: String| access(path, Permission::Read, false);
+    let write = |path: String| access(path, Permission::Write, false);
+    let named = |dir: &str| field("name").map(|name| write(join_path(dir, &name)));
+
+    let mut accesses: Vec<Access> = match command {
+        "index" | "subfolders" | "download" | "preview" | "qr" | "thumbnail" | "mime" | "stat"
+        | "size" | "hash" | "watch" | "search" | "search-content" => vec![read(dir)],
+        "download-archive" => match &query.items {
+            Some(items) => serde_json::from_str::<Vec<String>>(items)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|path| access(path, Permission::Read, true))
+                .collect(),
+            None => vec![access(dir, Permission::Read, true)],
+        },
+        "duplicates" | "content-reindex" => vec![access(dir, Permission::Read, true)],
+        "locks" | "versions/list" | "versions/diff" => vec![read(dir)],
+        "trash/list" => vec![read(join_path(&root, TRASH_DIR))],
+        "trash/restore" | "trash/empty" => vec![write(join_path(&root, TRASH_DIR))],
+        "save" | "cas-save" | "append" | "upload" | "reorder" | "versions/restore" => {
+            vec![write(dir)]
+        }
+        "newfolder" | "newfile" | "upload-init" | "archive" | "fetch-url" => {
+            let mut accesses: Vec<_> = named(&dir).into_iter().collect();
+            accesses.push(write(dir));
+            accesses
+        }
+        // Renamed into the queried folder, wherever `item` is
+        "rename" => {
+            let mut accesses: Vec<_> = named(&dir).into_iter().collect();
+            accesses.extend(
+                field("item")
+                    .into_iter()
+                    .map(|item| access(item, Permission::Write, true)),
+            );
+            accesses
+        }
+        "move" | "copy" | "lock" | "unlock" => field("item").into_iter().map(write).collect(),
+        "unarchive" => {
+            let mut accesses: Vec<_> = field("item").into_iter().map(read).collect();
+            accesses.push(write(dir));
+            accesses
+        }
+        "read-entry" => field("archive").into_iter().map(read).collect(),
+        "diff" => ["left", "right"]
+            .into_iter()
+            .filter_map(field)
+            .map(read)
+            .collect(),
+        "diff-dirs" => ["left", "right"]
+            .into_iter()
+            .filter_map(field)
+            .map(|path| access(path, Permission::Read, true))
+            .collect(),
+        "copy-between-adapters" => field("target").into_iter().map(write).collect(),
+        "sync" => {
+            let mut accesses: Vec<_> = field("source")
+                .into_iter()
+                .map(|path| access(path, Permission::Read, true))
+                .collect();
+            accesses.extend(
+                field("target")
+                    .into_iter()
+                    .map(|path| access(path, Permission::Write, true)),
+            );
+            accesses
+        }
+        "favorites/add" => field("path").into_iter().map(read).collect(),
+        // Anyone with the link can read everything below the shared path
+        "share/create" => field("path")
+            .into_iter()
+            .map(|path| access(path, Permission::Read, true))
+            .collect(),
+        // Only what their `items` name
+        "delete" | "bulk-rename" => Vec::new(),
+        // Sessions were checked by `upload-init`, links and favorites of
+        // paths by the commands creating them
+        "upload-chunk" | "upload-finalize" | "upload-abort" | "upload-status" | "job-status"
+        | "share/list" | "share/revoke" | "share/download" | "favorites/list"
+        | "favorites/remove" | "recent" | "version" | "storages" => Vec::new(),
+        _ => return None,
+    };
+
+    // Selections are removed by `move`, `delete` and `bulk-rename`, read by the others
+    let permission = match command {
+        "move" | "delete" | "bulk-rename" => Permission::Write,
+        _ => Permission::Read,
+    };
+    accesses.extend(items.into_iter().map(|path| access(path, permission, true)));
+    Some(accesses)
+}
+
+// The first path `command` isn't allowed to touch, or the command itself when
+// it doesn't declare its paths
+pub fn denied(
+    rules: &[AccessRule],
+    command: &str,
+    query: &Query,
+    payload: Option<&Value>,
+    adapter: &str,
+) -> Option<String> {
+    if rules.is_empty() {
+        return None;
+    }
+    let Some(accesses) = command_access(command, query, payload, adapter) else {
+        return Some(command.to_string());
+    };
+    accesses
+        .into_iter()
+        .find(|access| {
+            if access.recursive {
+                !allowed_recursive(rules, &access.path, access.permission)
+            } else {
+                !allowed(rules, &access.path, access.permission)
+            }
+        })
+        .map(|access| access.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::{VueFinderAppConfig, VueFinderAppExt};
+    use crate::finder::VueFinderConfig;
+    use crate::storages::local::LocalStorage;
+    use actix_web::test::{
+        call_and_read_body, call_and_read_body_json, call_service, init_service, TestRequest,
+    };
+    use actix_web::App;
+    use serde_json::json;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn rules() -> Vec<AccessRule> {
+        serde_json::from_value(json!([
+            { "adapter": "local", "path": "/public/**", "permissions": ["read", "write"] },
+            { "adapter": "local", "path": "/protected/**", "permissions": ["read"] },
+            { "path": "/secret", "permissions": [] },
+            { "path": "**/*.key", "permissions": [] }
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_allowed() {
+        let rules = rules();
+        assert!(allowed(&rules, "local://protected", Permission::Read));
+        assert!(allowed(
+            &rules,
+            "local://protected/a/b.txt",
+            Permission::Read
+        ));
+        assert!(!allowed(&rules, "local://protected", Permission::Write));
+        assert!(!allowed(
+            &rules,
+            "local://protected/a/b.txt",
+            Permission::Write
+        ));
+        assert!(allowed(
+            &rules,
+            "other://protected/a.txt",
+            Permission::Write
+        ));
+        assert!(!allowed(&rules, "other://secret/x/y", Permission::Read));
+        assert!(!allowed(&rules, "local://docs/id.key", Permission::Read));
+        assert!(allowed(&rules, "local://docs/id.keys", Permission::Read));
+        assert!(allowed(&rules, "local://", Permission::Write));
+
+        // Paths are matched once resolved, and may not leave the root
+        assert!(!allowed(&rules, "local://./secret/x", Permission::Read));
+        assert!(!allowed(
+            &rules,
+            "local://pub/../secret/x",
+            Permission::Read
+        ));
+        assert!(!allowed(
+            &rules,
+            "local://docs//..//secret",
+            Permission::Read
+        ));
+        assert!(!allowed(&rules, "local://../docs", Permission::Read));
+        assert!(allowed(&rules, "local://docs/./a/../b", Permission::Read));
+
+        // Without a scheme no adapter's rules would apply
+        assert!(!allowed(&rules, "protected/a.txt", Permission::Write));
+        assert!(!allowed(&rules, "/docs/a.txt", Permission::Read));
+
+        // Removing a folder would remove the read-only one inside it
+        assert!(!allowed_recursive(&rules, "local://", Permission::Write));
+        assert!(allowed_recursive(&rules, "local://docs", Permission::Write));
+    }
+
+    #[test]
+    fn test_denied() {
+        let rules = rules();
+        let query: Query = serde_urlencoded::from_str("q=move&path=local://docs").unwrap();
+        let copy =
+            json!({ "item": "local://public", "items": [{ "path": "local://protected/a.txt" }] });
+
+        assert_eq!(denied(&rules, "copy", &query, Some(&copy), "local"), None);
+        assert_eq!(
+            denied(&rules, "move", &query, Some(&copy), "local").as_deref(),
+            Some("local://protected/a.txt")
+        );
+        let rename = json!({ "item": "local://a.txt", "name": "secret" });
+        let query: Query = serde_urlencoded::from_str("q=rename").unwrap();
+        assert_eq!(
+            denied(&rules, "rename", &query, Some(&rename), "other").as_deref(),
+            Some("other://secret")
+        );
+        // The new name is checked where the handler puts it, in `path`
+        let rename = json!({ "item": "local://public/p.txt", "name": "planted.txt" });
+        let query: Query = serde_urlencoded::from_str("q=rename&path=local://protected").unwrap();
+        assert_eq!(
+            denied(&rules, "rename", &query, Some(&rename), "local").as_deref(),
+            Some("local://protected/planted.txt")
+        );
+
+        // Commands that don't declare their paths are refused
+        assert_eq!(
+            denied(&rules, "something-new", &query, None, "local").as_deref(),
+            Some("something-new")
+        );
+        assert_eq!(denied(&rules, "storages", &query, None, "local"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_router_checks_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("protected")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("secret")).unwrap();
+        std::fs::write(temp_dir.path().join("protected/a.txt"), "a").unwrap();
+
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            finder_config: Arc::new(VueFinderConfig {
+                access_rules: rules(),
+                ..VueFinderConfig::default()
+            }),
+            ..VueFinderAppConfig::default()
+        };
+        let app = init_service(App::new().configure_vuefinder(config)).await;
+
+        let request = TestRequest::get()
+            .uri("/api?q=index&adapter=local&path=local://")
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, request).await;
+        let names: Vec<_> = body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["basename"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["protected"]);
+
+        let request = TestRequest::post()
+            .uri("/api?q=delete&adapter=local&path=local://protected")
+            .set_json(json!({ "items": [{ "path": "local://protected/a.txt" }] }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 403);
+        assert!(temp_dir.path().join("protected/a.txt").exists());
+
+        // Paths must name their adapter, an empty one is the root
+        let request = TestRequest::post()
+            .uri("/api?q=save&adapter=local&path=protected/a.txt")
+            .set_json(json!({ "content": "changed" }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 403);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("protected/a.txt")).unwrap(),
+            "a"
+        );
+        let request = TestRequest::get()
+            .uri("/api?q=index&adapter=local&path=")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 200);
+
+        let request = TestRequest::get()
+            .uri("/api?q=index&adapter=local&path=local://secret")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), 403);
+
+        // Walks leave out what wildcard rules deny inside the folder
+        std::fs::create_dir_all(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a.txt"), "a").unwrap();
+        std::fs::write(temp_dir.path().join("docs/id.key"), "key").unwrap();
+        let request = TestRequest::get()
+            .uri("/api?q=download-archive&adapter=local&path=local://docs")
+            .to_request();
+        let body = call_and_read_body(&app, request).await;
+        let archive = zip::ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+        let mut names: Vec<_> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["docs/", "docs/a.txt"]);
+        let request = TestRequest::get()
+            .uri("/api?q=size&adapter=local&path=local://docs")
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, request).await;
+        assert_eq!(
+            (body["files"].as_u64(), body["size"].as_u64()),
+            (Some(1), Some(1))
+        );
+
+        // Versions and the trash are only reached through their commands
+        std::fs::create_dir_all(temp_dir.path().join(".versions/abc")).unwrap();
+        std::fs::write(temp_dir.path().join(".versions/abc/1"), "old").unwrap();
+        for path in [
+            "local://.versions/abc/1",
+            "local://public/../.versions/abc/1",
+        ] {
+            let request = TestRequest::get()
+                .uri(&format!("/api?q=preview&adapter=local&path={}", path))
+                .to_request();
+            let response = call_service(&app, request).await;
+            assert_eq!(response.status(), 403);
+        }
+    }
+}
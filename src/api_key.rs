@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// Header carrying the key of automation requests
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+// What a key may do, each scope includes the ones before it
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApiScope::Read => "read",
+            ApiScope::Write => "write",
+            ApiScope::Admin => "admin",
+        })
+    }
+}
+
+// A key given to a script. Stored in the request extensions once matched, so
+// handlers can take it as `web::ReqData<ApiKey>`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKey {
+    // Shown in errors and logs instead of the key
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.iter().any(|granted| *granted >= scope)
+    }
+}
+
+// Scope a key needs to run `command`. Commands that only look at files need
+// `read`, managing share links needs `admin` and everything else `write`.
+pub fn required_scope(command: &str) -> ApiScope {
+    match command {
+        "index" | "subfolders" | "download" | "download-archive" | "preview" | "qr"
+        | "thumbnail" | "mime" | "stat" | "size" | "hash" | "duplicates" | "watch" | "search"
+        | "search-content" | "read-entry" | "diff-dirs" | "diff" | "upload-status"
+        | "job-status" | "trash/list" | "share/download" | "locks" | "versions/list"
+        | "versions/diff" | "version" | "storages" | "favorites/list" | "recent" => ApiScope::Read,
+        "share/create" | "share/list" | "share/revoke" => ApiScope::Admin,
+        _ => ApiScope::Write,
+    }
+}
+
+// Keys kept outside the config file, e.g. in a database
+#[async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    async fn find(&self, key: &str) -> Option<ApiKey>;
+}
+
+// Compared as digests so the time taken doesn't tell how much of a key matched
+fn same_key(a: &str, b: &str) -> bool {
+    Sha256::digest(a.as_bytes()) == Sha256::digest(b.as_bytes())
+}
+
+// The key matching `key`, from `keys` first and then `store`
+pub async fn find_key(
+    keys: &[ApiKey],
+    store: Option<&dyn ApiKeyStore>,
+    key: &str,
+) -> Option<ApiKey> {
+    if let Some(found) = keys.iter().find(|candidate| same_key(&candidate.key, key)) {
+        return Some(found.clone());
+    }
+    match store {
+        Some(store) => store.find(key).await,
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Store;
+
+    #[async_trait]
+    impl ApiKeyStore for Store {
+        async fn find(&self, key: &str) -> Option<ApiKey> {
+            (key == "stored").then(|| ApiKey {
+                name: "stored".to_string(),
+                key: key.to_string(),
+                scopes: vec![ApiScope::Admin],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_key() {
+        let keys: Vec<ApiKey> = serde_json::from_value(json!([
+            { "name": "backup", "key": "k1", "scopes": ["read"] },
+            { "name": "sync", "key": "k2", "scopes": ["write"] }
+        ]))
+        .unwrap();
+
+        let backup = find_key(&keys, None, "k1").await.unwrap();
+        assert_eq!(backup.name, "backup");
+        assert!(backup.allows(required_scope("download")));
+        assert!(!backup.allows(required_scope("upload")));
+
+        let sync = find_key(&keys, Some(&Store), "k2").await.unwrap();
+        assert!(sync.allows(required_scope("delete")));
+        assert!(!sync.allows(required_scope("share/create")));
+
+        let stored = find_key(&keys, Some(&Store), "stored").await.unwrap();
+        assert!(stored.allows(required_scope("share/revoke")));
+        assert!(find_key(&keys, Some(&Store), "unknown").await.is_none());
+    }
+}
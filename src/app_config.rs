@@ -1,29 +1,96 @@
 use actix_web::dev::ServiceRequest;
-use actix_web::{dev::ServiceFactory, web, App, Error};
+use actix_web::middleware::from_fn;
+use actix_web::{dev::ServiceFactory, web, App, Error, HttpResponse};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::api_key::ApiKeyStore;
+use crate::auth::jwt_auth;
+use crate::content_index::ContentIndex;
+use crate::favorites::Favorites;
+use crate::fetch_url::UrlFetcher;
 use crate::finder::{VueFinder, VueFinderConfig};
+use crate::folder_size::FolderSizes;
+use crate::hooks::FinderHooks;
+use crate::jobs::Jobs;
+use crate::limiter::UploadLimiter;
+use crate::locks::FileLocks;
+use crate::middleware::FinderMiddleware;
+use crate::payload::Query;
+use crate::recent::RecentFiles;
+use crate::reload::ConfigCell;
 use crate::router::finder_router;
-use crate::storages::StorageAdapter;
+use crate::scan::Scanner;
+use crate::share::ShareLinks;
+use crate::storages::Storages;
+use crate::upload_session::UploadSessions;
+use crate::watch::WatchHub;
+use crate::webhook::Webhooks;
 
 #[derive(Clone)]
 pub struct VueFinderAppConfig {
     pub api_path: String,
-    pub json_limit: usize,
-    pub payload_limit: usize,
-    pub storages: Arc<HashMap<String, Arc<dyn StorageAdapter>>>,
+    pub storages: Storages,
     pub finder_config: Arc<VueFinderConfig>,
+    // Command hooks, `before` runs in order and `after` in reverse
+    pub middlewares: Arc<Vec<Arc<dyn FinderMiddleware>>>,
+    // File operation hooks, run in order
+    pub hooks: Arc<Vec<Arc<dyn FinderHooks>>>,
+    // Shared by all workers so upload limits apply per client, not per worker
+    pub upload_limiter: Arc<UploadLimiter>,
+    // Resumable uploads in progress, chunks may arrive on any worker
+    pub upload_sessions: Arc<UploadSessions>,
+    // Directory watches, shared by everyone watching the same folder
+    pub watchers: Arc<WatchHub>,
+    // Cached `size` results, see `VueFinderConfig::folder_size_cache_secs`
+    pub folder_sizes: Arc<FolderSizes>,
+    // Background jobs such as `duplicates`, polled from any worker
+    pub jobs: Arc<Jobs>,
+    // Advisory locks taken with `lock`, see `VueFinderConfig::lock_ttl_secs`
+    pub locks: Arc<FileLocks>,
+    // Share links, see `VueFinderConfig::share_store`
+    pub shares: Arc<ShareLinks>,
+    // Bookmarked paths, see `VueFinderConfig::favorites_store`
+    pub favorites: Arc<Favorites>,
+    // Recently opened and modified paths, see `VueFinderConfig::recent_store`
+    pub recent: Arc<RecentFiles>,
+    // Delivers `VueFinderConfig::webhooks` events
+    pub webhooks: Arc<Webhooks>,
+    // Downloads for `fetch-url`, see `VueFinderConfig::fetch_url`
+    pub fetcher: Arc<UrlFetcher>,
+    // Keys looked up after `VueFinderConfig::api_keys`
+    pub api_key_store: Option<Arc<dyn ApiKeyStore>>,
+    // Checks uploads and saves for malware, see `VueFinderConfig::clamd`
+    pub scanner: Option<Arc<dyn Scanner>>,
+    // Answers `search-content`, see `VueFinderConfig::content_index`
+    pub content_index: Option<Arc<dyn ContentIndex>>,
+    // Reloaded config replacing `finder_config`, see `reload::watch_config`
+    pub live_config: Option<Arc<ConfigCell>>,
 }
 
 impl Default for VueFinderAppConfig {
     fn default() -> Self {
         Self {
             api_path: "/api".to_string(),
-            json_limit: 100 * 1024 * 1024,    // 100MB
-            payload_limit: 100 * 1024 * 1024, // 100MB
             storages: Arc::new(HashMap::new()),
             finder_config: Arc::new(VueFinderConfig::default()),
+            middlewares: Arc::new(Vec::new()),
+            hooks: Arc::new(Vec::new()),
+            upload_limiter: Arc::new(UploadLimiter::default()),
+            upload_sessions: Arc::new(UploadSessions::default()),
+            watchers: Arc::new(WatchHub::default()),
+            folder_sizes: Arc::new(FolderSizes::default()),
+            jobs: Arc::new(Jobs::default()),
+            locks: Arc::new(FileLocks::default()),
+            shares: Arc::new(ShareLinks::default()),
+            favorites: Arc::new(Favorites::default()),
+            recent: Arc::new(RecentFiles::default()),
+            webhooks: Arc::new(Webhooks::default()),
+            fetcher: Arc::new(UrlFetcher::default()),
+            api_key_store: None,
+            scanner: None,
+            content_index: None,
+            live_config: None,
         }
     }
 }
@@ -40,11 +107,71 @@ where
         let vue_finder = web::Data::new(VueFinder {
             storages: config.storages,
             config: config.finder_config,
+            middlewares: config.middlewares,
+            hooks: config.hooks,
+            upload_limiter: config.upload_limiter,
+            upload_sessions: config.upload_sessions,
+            watchers: config.watchers,
+            folder_sizes: config.folder_sizes,
+            jobs: config.jobs,
+            locks: config.locks,
+            shares: config.shares,
+            favorites: config.favorites,
+            recent: config.recent,
+            webhooks: config.webhooks,
+            fetcher: config.fetcher,
+            api_key_store: config.api_key_store,
+            scanner: config.scanner,
+            content_index: config.content_index,
+            live_config: config.live_config,
+            principal: None,
+            changed_paths: Default::default(),
         });
 
-        self.app_data(web::JsonConfig::default().limit(config.json_limit))
-            .app_data(web::PayloadConfig::default().limit(config.payload_limit))
+        let html_index_path = vue_finder.config.html_index_path.clone();
+        // Body limits come from the finder config, so axum and actix-web agree
+        let (json_limit, payload_limit) = (
+            vue_finder.config.json_limit,
+            vue_finder.config.payload_limit,
+        );
+
+        let app = self
+            .app_data(web::JsonConfig::default().limit(json_limit))
+            .app_data(web::PayloadConfig::default().limit(payload_limit))
             .app_data(vue_finder)
-            .service(web::resource(config.api_path).route(web::route().to(finder_router)))
+            .service(
+                web::resource(config.api_path.clone())
+                    .wrap(from_fn(jwt_auth))
+                    .route(web::route().to(finder_router)),
+            );
+
+        // Optional plain HTML directory listing for debugging without the frontend
+        match html_index_path {
+            Some(index_path) => {
+                let api_path = config.api_path;
+                app.service(
+                    web::resource(index_path.clone())
+                        .wrap(from_fn(jwt_auth))
+                        .route(web::get().to(
+                            move |data: web::Data<VueFinder>, query: web::Query<Query>| {
+                                let api_path = api_path.clone();
+                                let index_path = index_path.clone();
+                                async move {
+                                    HttpResponse::from(
+                                        VueFinder::html_index(
+                                            data.into_inner(),
+                                            query.into_inner(),
+                                            &api_path,
+                                            &index_path,
+                                        )
+                                        .await,
+                                    )
+                                }
+                            },
+                        )),
+                )
+            }
+            None => app,
+        }
     }
 }
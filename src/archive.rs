@@ -0,0 +1,418 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use std::io::{Cursor, Read, Write};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz", alias = "tgz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    // From the leading bytes, then the name's extension
+    pub fn detect(name: &str, contents: &[u8]) -> Option<Self> {
+        if contents.starts_with(b"PK\x03\x04") || contents.starts_with(b"PK\x05\x06") {
+            return Some(ArchiveFormat::Zip);
+        }
+        if contents.starts_with(&[0x1f, 0x8b]) {
+            return Some(ArchiveFormat::TarGz);
+        }
+        if contents.get(257..262) == Some(b"ustar") {
+            return Some(ArchiveFormat::Tar);
+        }
+        Self::from_name(name)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        [
+            (".tar.gz", ArchiveFormat::TarGz),
+            (".tgz", ArchiveFormat::TarGz),
+            (".tar", ArchiveFormat::Tar),
+            (".zip", ArchiveFormat::Zip),
+        ]
+        .into_iter()
+        .find(|(suffix, _)| name.ends_with(suffix))
+        .map(|(_, format)| format)
+    }
+}
+
+// `photos.tar.gz` -> `photos`, names without an archive extension lose their last one
+pub fn archive_stem(name: &str) -> &str {
+    let lower = name.to_lowercase();
+    for suffix in [".tar.gz", ".tgz", ".tar", ".zip"] {
+        if lower.ends_with(suffix) {
+            return &name[..name.len() - suffix.len()];
+        }
+    }
+    std::path::Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(name)
+}
+
+// An entry's path relative to the extraction folder, without `.` or empty
+// segments. `None` for absolute paths and ones with `..` segments.
+pub fn entry_path(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    let has_drive = name.as_bytes().get(1) == Some(&b':');
+    if name.starts_with('/') || has_drive {
+        return None;
+    }
+    let mut segments = Vec::new();
+    for segment in name.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+// Builds an archive in memory, names use `/` and folders end without one
+pub enum ArchiveWriter {
+    Zip(ZipWriter<Cursor<Vec<u8>>>),
+    Tar(tar::Builder<Vec<u8>>),
+    TarGz(tar::Builder<GzEncoder<Vec<u8>>>),
+}
+
+fn tar_header(size: u64, mode: u32, entry_type: tar::EntryType) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_entry_type(entry_type);
+    header.set_mtime(
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    );
+    header
+}
+
+impl ArchiveWriter {
+    pub fn new(format: ArchiveFormat) -> Self {
+        match format {
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new(Cursor::new(Vec::new()))),
+            ArchiveFormat::Tar => ArchiveWriter::Tar(tar::Builder::new(Vec::new())),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(GzEncoder::new(
+                Vec::new(),
+                Compression::default(),
+            ))),
+        }
+    }
+
+    fn zip_options() -> FileOptions {
+        FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(0o755)
+    }
+
+    pub fn add_directory(&mut self, name: &str) -> std::io::Result<()> {
+        let mut header = tar_header(0, 0o755, tar::EntryType::Directory);
+        match self {
+            ArchiveWriter::Zip(zip) => zip
+                .add_directory(name, Self::zip_options())
+                .map_err(std::io::Error::other),
+            ArchiveWriter::Tar(tar) => {
+                tar.append_data(&mut header, format!("{}/", name), std::io::empty())
+            }
+            ArchiveWriter::TarGz(tar) => {
+                tar.append_data(&mut header, format!("{}/", name), std::io::empty())
+            }
+        }
+    }
+
+    pub fn add_file(&mut self, name: &str, contents: &[u8]) -> std::io::Result<()> {
+        let mut header = tar_header(contents.len() as u64, 0o644, tar::EntryType::Regular);
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                zip.start_file(name, Self::zip_options())
+                    .map_err(std::io::Error::other)?;
+                zip.write_all(contents)
+            }
+            ArchiveWriter::Tar(tar) => tar.append_data(&mut header, name, contents),
+            ArchiveWriter::TarGz(tar) => tar.append_data(&mut header, name, contents),
+        }
+    }
+
+    pub fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            ArchiveWriter::Zip(mut zip) => zip
+                .finish()
+                .map(Cursor::into_inner)
+                .map_err(std::io::Error::other),
+            ArchiveWriter::Tar(tar) => tar.into_inner(),
+            ArchiveWriter::TarGz(tar) => tar.into_inner()?.finish(),
+        }
+    }
+}
+
+// Entry of an opened archive, folders end with `/`
+pub struct ExtractedEntry {
+    pub name: String,
+    // `None` for folders
+    pub contents: Option<Vec<u8>>,
+}
+
+// Reads an archive's entries. ZIP entries are decompressed one at a time, tar
+// ones have to be read in order so they're all read on open.
+pub struct ArchiveReader {
+    entries: Entries,
+    // Bytes that may still be decompressed out of `limit`. Sizes in headers
+    // can't be trusted, so what's read is counted instead.
+    remaining: u64,
+    limit: u64,
+}
+
+enum Entries {
+    Zip(ZipArchive<Cursor<Vec<u8>>>),
+    Tar(Vec<ExtractedEntry>),
+}
+
+fn too_large(limit: u64) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::FileTooLarge,
+        format!("Archive contents exceed {} bytes.", limit),
+    )
+}
+
+// All of `reader`, failing once it passes `remaining` bytes. The buffer grows
+// with what's read rather than with the size an archive claims.
+pub fn read_limited<R: Read>(
+    reader: R,
+    remaining: &mut u64,
+    limit: u64,
+) -> std::io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    reader
+        .take(remaining.saturating_add(1))
+        .read_to_end(&mut contents)?;
+    *remaining = remaining
+        .checked_sub(contents.len() as u64)
+        .ok_or_else(|| too_large(limit))?;
+    Ok(contents)
+}
+
+fn read_tar<R: Read>(
+    reader: R,
+    remaining: &mut u64,
+    limit: u64,
+) -> std::io::Result<Vec<ExtractedEntry>> {
+    let mut entries = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().replace('\\', "/");
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => entries.push(ExtractedEntry {
+                name: format!("{}/", name.trim_end_matches('/')),
+                contents: None,
+            }),
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let contents = read_limited(&mut entry, remaining, limit)?;
+                entries.push(ExtractedEntry {
+                    name,
+                    contents: Some(contents),
+                });
+            }
+            // Links and special files aren't extracted
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+impl ArchiveReader {
+    // Entries of `contents`, of which at most `limit` bytes are decompressed
+    // in total. Going past it fails with `ErrorKind::FileTooLarge`.
+    pub fn open(format: ArchiveFormat, contents: Vec<u8>, limit: u64) -> std::io::Result<Self> {
+        let mut remaining = limit;
+        let entries = match format {
+            ArchiveFormat::Zip => ZipArchive::new(Cursor::new(contents))
+                .map(Entries::Zip)
+                .map_err(std::io::Error::other)?,
+            ArchiveFormat::Tar => {
+                Entries::Tar(read_tar(Cursor::new(contents), &mut remaining, limit)?)
+            }
+            ArchiveFormat::TarGz => Entries::Tar(read_tar(
+                GzDecoder::new(Cursor::new(contents)),
+                &mut remaining,
+                limit,
+            )?),
+        };
+        let mut reader = Self {
+            entries,
+            remaining,
+            limit,
+        };
+        // Refused before anything is extracted when the sizes given are
+        // already too large
+        if reader.declared_size() > limit {
+            return Err(too_large(limit));
+        }
+        Ok(reader)
+    }
+
+    // Total size the archive claims for its files
+    fn declared_size(&mut self) -> u64 {
+        match &mut self.entries {
+            Entries::Zip(zip) => (0..zip.len())
+                .filter_map(|index| zip.by_index_raw(index).ok().map(|file| file.size()))
+                .fold(0, u64::saturating_add),
+            Entries::Tar(entries) => entries
+                .iter()
+                .filter_map(|entry| entry.contents.as_ref())
+                .map(|contents| contents.len() as u64)
+                .fold(0, u64::saturating_add),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.entries {
+            Entries::Zip(zip) => zip.len(),
+            Entries::Tar(entries) => entries.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn name(&mut self, index: usize) -> std::io::Result<String> {
+        match &mut self.entries {
+            Entries::Zip(zip) => zip
+                .by_index(index)
+                .map(|file| file.name().to_string())
+                .map_err(std::io::Error::other),
+            Entries::Tar(entries) => Ok(entries[index].name.clone()),
+        }
+    }
+
+    // Up to the first `len` bytes of a file entry, enough to tell its type
+    pub fn head(&mut self, index: usize, len: usize) -> std::io::Result<Vec<u8>> {
+        match &mut self.entries {
+            Entries::Zip(zip) => {
+                let file = zip.by_index(index).map_err(std::io::Error::other)?;
+                let mut head = Vec::new();
+                file.take(len as u64).read_to_end(&mut head)?;
+                Ok(head)
+            }
+            Entries::Tar(entries) => Ok(entries[index]
+                .contents
+                .as_deref()
+                .map(|contents| contents[..contents.len().min(len)].to_vec())
+                .unwrap_or_default()),
+        }
+    }
+
+    pub fn entry(&mut self, index: usize) -> std::io::Result<ExtractedEntry> {
+        match &mut self.entries {
+            Entries::Zip(zip) => {
+                let mut file = zip.by_index(index).map_err(std::io::Error::other)?;
+                let name = file.name().to_string();
+                if name.ends_with('/') {
+                    return Ok(ExtractedEntry {
+                        name,
+                        contents: None,
+                    });
+                }
+                let contents = read_limited(&mut file, &mut self.remaining, self.limit)?;
+                Ok(ExtractedEntry {
+                    name,
+                    contents: Some(contents),
+                })
+            }
+            Entries::Tar(entries) => {
+                let entry = &mut entries[index];
+                Ok(ExtractedEntry {
+                    name: entry.name.clone(),
+                    contents: entry.contents.take(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for format in [ArchiveFormat::Zip, ArchiveFormat::Tar, ArchiveFormat::TarGz] {
+            let mut writer = ArchiveWriter::new(format);
+            writer.add_directory("docs").unwrap();
+            writer.add_file("docs/a.txt", b"hello").unwrap();
+            let contents = writer.finish().unwrap();
+
+            let name = format!("bundle.{}", format.extension());
+            assert_eq!(
+                ArchiveFormat::detect("renamed.bin", &contents),
+                Some(format)
+            );
+            assert_eq!(archive_stem(&name), "bundle");
+
+            // Five bytes of contents, more than four can be read out
+            let refused = ArchiveReader::open(format, contents.clone(), 4)
+                .err()
+                .unwrap();
+            assert_eq!(refused.kind(), std::io::ErrorKind::FileTooLarge);
+
+            let mut reader = ArchiveReader::open(format, contents, 5).unwrap();
+            assert_eq!(reader.len(), 2);
+            let entries: Vec<_> = (0..reader.len())
+                .map(|i| reader.entry(i).unwrap())
+                .map(|entry| (entry.name, entry.contents))
+                .collect();
+            assert_eq!(
+                entries,
+                vec![
+                    ("docs/".to_string(), None),
+                    ("docs/a.txt".to_string(), Some(b"hello".to_vec()))
+                ]
+            );
+        }
+        assert_eq!(
+            ArchiveFormat::detect("a.tgz", b""),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::detect("a.txt", b"text"), None);
+
+        // Whatever sizes the headers claim
+        let mut remaining = 4;
+        assert!(read_limited(&b"hello"[..], &mut remaining, 4).is_err());
+        assert_eq!(
+            read_limited(&b"hey"[..], &mut remaining, 4).unwrap(),
+            b"hey"
+        );
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn test_entry_path() {
+        assert_eq!(entry_path("docs/./a.txt").as_deref(), Some("docs/a.txt"));
+        assert_eq!(entry_path("docs/").as_deref(), Some("docs"));
+        assert_eq!(entry_path("docs\\a.txt").as_deref(), Some("docs/a.txt"));
+        assert_eq!(entry_path("../evil.txt"), None);
+        assert_eq!(entry_path("docs/../../evil.txt"), None);
+        assert_eq!(entry_path("/etc/passwd"), None);
+        assert_eq!(entry_path("C:\\evil.txt"), None);
+    }
+}
@@ -0,0 +1,422 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use http::header::WWW_AUTHENTICATE;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::api_key::{find_key, required_scope, ApiKey, API_KEY_HEADER};
+use crate::finder::VueFinder;
+use crate::finder_core::FinderResponse;
+use crate::payload::Query;
+use crate::response::ErrorResponse;
+
+// Bearer tokens signed with a shared secret (HS256)
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtConfig {
+    pub secret: String,
+    // Required `iss` claim, not checked when unset
+    pub issuer: Option<String>,
+    // Required `aud` claim, not checked when unset
+    pub audience: Option<String>,
+    // Clock skew tolerated on `exp` and `nbf`
+    #[serde(default = "default_leeway")]
+    pub leeway_secs: u64,
+}
+
+fn default_leeway() -> u64 {
+    60
+}
+
+// Claims of the request's token. Stored in the request extensions, so handlers
+// can take them as `web::ReqData<Claims>`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Claims {
+    pub sub: Option<String>,
+    pub exp: u64,
+    // Every other claim, for application specific checks
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+// Give every token holder their own folder, e.g. `users/alice`, shown to them
+// as the root of the adapter
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct UserRootConfig {
+    // Claim naming the user, `sub` or any other string or number claim
+    pub claim: String,
+    // Folder of each user, `{user}` is replaced by the claim
+    pub path: String,
+    // Adapters scoped this way, all of them when empty
+    pub adapters: Vec<String>,
+}
+
+impl Default for UserRootConfig {
+    fn default() -> Self {
+        Self {
+            claim: "sub".to_string(),
+            path: "users/{user}".to_string(),
+            adapters: Vec::new(),
+        }
+    }
+}
+
+impl UserRootConfig {
+    // Folder of the token's user, `None` when the claim is missing or can't be
+    // used as a single path segment
+    pub fn root(&self, claims: &Claims) -> Option<String> {
+        let user = match (self.claim.as_str(), claims.extra.get(&self.claim)) {
+            ("sub", _) => claims.sub.clone()?,
+            (_, Some(Value::String(user))) => user.clone(),
+            (_, Some(Value::Number(user))) => user.to_string(),
+            _ => return None,
+        };
+        let valid = !matches!(user.as_str(), "" | "." | "..")
+            && !user
+                .chars()
+                .any(|c| c == '/' || c == '\\' || c.is_control());
+        valid.then(|| self.path.replace("{user}", &user))
+    }
+
+    pub fn applies_to(&self, adapter: &str) -> bool {
+        self.adapters.is_empty() || self.adapters.iter().any(|name| name == adapter)
+    }
+}
+
+// Claims of the bearer token in an `Authorization` header
+pub fn authenticate(config: &JwtConfig, authorization: Option<&str>) -> Result<Claims, String> {
+    let token = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or("Missing bearer token.")?;
+
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = config.leeway_secs;
+    match &config.issuer {
+        Some(issuer) => validation.set_issuer(&[issuer]),
+        None => validation.iss = None,
+    }
+    match &config.audience {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+
+    decode::<Claims>(
+        token.trim(),
+        &DecodingKey::from_secret(config.secret.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| format!("Invalid token: {}", e))
+}
+
+// `401` asking the client for a bearer token
+pub(crate) fn unauthorized(message: String) -> FinderResponse {
+    FinderResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, "Bearer"))
+        .json(ErrorResponse::new(message))
+}
+
+// Commands open to requests without a token, share links carry their own checks
+pub(crate) fn is_public(command: &str) -> bool {
+    command == "share/download"
+}
+
+// Who a request was authenticated as
+pub(crate) enum Credentials {
+    Token(Claims),
+    Key(ApiKey),
+}
+
+// Who holds the file locks a request takes: the token's `sub`, or `key:`
+// and the API key's name
+pub(crate) fn principal(claims: Option<&Claims>, key: Option<&ApiKey>) -> Option<String> {
+    claims
+        .and_then(|claims| claims.sub.clone())
+        .or_else(|| key.map(|key| format!("key:{}", key.name)))
+}
+
+// Check the request's API key, or its `Authorization` bearer token when it has
+// none. Once keys or `jwt` are configured every non-public command needs one of
+// them; `Err` is the response refusing the request.
+pub(crate) async fn check_credentials(
+    data: &VueFinder,
+    command: &str,
+    authorization: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<Option<Credentials>, FinderResponse> {
+    if is_public(command) {
+        return Ok(None);
+    }
+    let config = data.current_config();
+
+    if let Some(key) = api_key {
+        let Some(key) = find_key(&config.api_keys, data.api_key_store.as_deref(), key).await else {
+            return Err(FinderResponse::Unauthorized().json(ErrorResponse::new("Invalid API key.")));
+        };
+        let scope = required_scope(command);
+        if !key.allows(scope) {
+            return Err(FinderResponse::Forbidden().json(ErrorResponse::new(format!(
+                "API key {} lacks the `{}` scope.",
+                key.name, scope
+            ))));
+        }
+        return Ok(Some(Credentials::Key(key)));
+    }
+
+    match &config.jwt {
+        Some(config) => authenticate(config, authorization)
+            .map(|claims| Some(Credentials::Token(claims)))
+            .map_err(unauthorized),
+        None if !config.api_keys.is_empty() || data.api_key_store.is_some() => {
+            Err(FinderResponse::Unauthorized().json(ErrorResponse::new("Missing API key.")))
+        }
+        None => Ok(None),
+    }
+}
+
+// Reject requests without a valid API key or token, see `check_credentials`
+pub async fn jwt_auth<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if let Some(data) = req.app_data::<web::Data<VueFinder>>().cloned() {
+        let command = web::Query::<Query>::from_query(req.query_string())
+            .map(|query| query.into_inner().q)
+            .unwrap_or_default();
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap_or_default())
+        };
+        let credentials = check_credentials(
+            &data,
+            &command,
+            header(AUTHORIZATION.as_str()),
+            header(API_KEY_HEADER),
+        )
+        .await;
+        match credentials {
+            Ok(Some(Credentials::Token(claims))) => {
+                req.extensions_mut().insert(claims);
+            }
+            Ok(Some(Credentials::Key(key))) => {
+                req.extensions_mut().insert(key);
+            }
+            Ok(None) => {}
+            Err(response) => {
+                let response = HttpResponse::from(response);
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::{VueFinderAppConfig, VueFinderAppExt};
+    use crate::finder::VueFinderConfig;
+    use crate::storages::local::LocalStorage;
+    use actix_web::http::header::WWW_AUTHENTICATE;
+    use actix_web::{test, App};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    fn token(secret: &str, claims: Value) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[actix_web::test]
+    async fn test_jwt_auth() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            finder_config: Arc::new(VueFinderConfig {
+                jwt: Some(JwtConfig {
+                    secret: "secret".to_string(),
+                    issuer: Some("vuefinder".to_string()),
+                    audience: None,
+                    leeway_secs: 0,
+                }),
+                ..VueFinderConfig::default()
+            }),
+            ..VueFinderAppConfig::default()
+        };
+        let app = test::init_service(App::new().configure_vuefinder(config)).await;
+
+        let request = |token: Option<String>| {
+            let mut request = test::TestRequest::get().uri("/api?q=index&adapter=local");
+            if let Some(token) = token {
+                request = request.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+            }
+            request.to_request()
+        };
+
+        let response = test::call_service(&app, request(None)).await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+
+        let valid = json!({ "sub": "alice", "iss": "vuefinder", "exp": now() + 60 });
+        let response =
+            test::call_service(&app, request(Some(token("secret", valid.clone())))).await;
+        assert_eq!(response.status(), 200);
+
+        let rejected = [
+            token("other", valid.clone()),
+            token(
+                "secret",
+                json!({ "sub": "alice", "iss": "other", "exp": now() + 60 }),
+            ),
+            token(
+                "secret",
+                json!({ "sub": "alice", "iss": "vuefinder", "exp": now() - 60 }),
+            ),
+        ];
+        for token in rejected {
+            let response = test::call_service(&app, request(Some(token))).await;
+            assert_eq!(response.status(), 401);
+        }
+
+        // Share links are checked by the command itself
+        let request = test::TestRequest::get()
+            .uri("/api?q=share/download&id=missing")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_api_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            finder_config: Arc::new(VueFinderConfig {
+                api_keys: serde_json::from_value(json!([
+                    { "name": "backup", "key": "read-key", "scopes": ["read"] },
+                    { "name": "sync", "key": "write-key", "scopes": ["write"] }
+                ]))
+                .unwrap(),
+                ..VueFinderConfig::default()
+            }),
+            ..VueFinderAppConfig::default()
+        };
+        let app = test::init_service(App::new().configure_vuefinder(config)).await;
+
+        let request = |q: &str, key: Option<&str>| {
+            let mut request = if q == "newfolder" {
+                test::TestRequest::post().set_json(json!({ "name": "docs" }))
+            } else {
+                test::TestRequest::get()
+            }
+            .uri(&format!("/api?q={}&adapter=local&path=local://", q));
+            if let Some(key) = key {
+                request = request.insert_header((API_KEY_HEADER, key));
+            }
+            request.to_request()
+        };
+
+        let cases = [
+            ("index", None, 401),
+            ("index", Some("wrong"), 401),
+            ("index", Some("read-key"), 200),
+            ("newfolder", Some("read-key"), 403),
+            ("newfolder", Some("write-key"), 200),
+            ("share/list", Some("write-key"), 403),
+        ];
+        for (q, key, status) in cases {
+            let response = test::call_service(&app, request(q, key)).await;
+            assert_eq!(response.status(), status, "{} with {:?}", q, key);
+        }
+        assert!(temp_dir.path().join("docs").is_dir());
+    }
+
+    #[actix_web::test]
+    async fn test_user_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            finder_config: Arc::new(VueFinderConfig {
+                jwt: Some(JwtConfig {
+                    secret: "secret".to_string(),
+                    issuer: None,
+                    audience: None,
+                    leeway_secs: 0,
+                }),
+                user_roots: Some(UserRootConfig::default()),
+                ..VueFinderConfig::default()
+            }),
+            ..VueFinderAppConfig::default()
+        };
+        let app = test::init_service(App::new().configure_vuefinder(config)).await;
+
+        let request = |q: &str, claims: Value| {
+            if q == "newfolder" {
+                test::TestRequest::post().set_json(json!({ "name": "docs" }))
+            } else {
+                test::TestRequest::get()
+            }
+            .uri(&format!("/api?q={}&adapter=local&path=local://", q))
+            .insert_header((AUTHORIZATION, format!("Bearer {}", token("secret", claims))))
+            .to_request()
+        };
+        let alice = json!({ "sub": "alice", "exp": now() + 60 });
+        let bob = json!({ "sub": "bob", "exp": now() + 60 });
+
+        let response = test::call_service(&app, request("newfolder", alice.clone())).await;
+        assert_eq!(response.status(), 200);
+        assert!(temp_dir.path().join("users/alice/docs").is_dir());
+
+        let response: Value = test::call_and_read_body_json(&app, request("index", alice)).await;
+        assert_eq!(response["files"][0]["path"], "local://docs");
+        let response: Value = test::call_and_read_body_json(&app, request("index", bob)).await;
+        assert_eq!(response["files"].as_array().unwrap().len(), 0);
+
+        let nobody = json!({ "sub": "..", "exp": now() + 60 });
+        let response = test::call_service(&app, request("index", nobody)).await;
+        assert_eq!(response.status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn test_claims() {
+        let config = JwtConfig {
+            secret: "secret".to_string(),
+            issuer: None,
+            audience: None,
+            leeway_secs: 60,
+        };
+        let authorization = format!(
+            "Bearer {}",
+            token(
+                "secret",
+                json!({ "sub": "alice", "exp": now() + 60, "role": "admin" })
+            )
+        );
+
+        let claims = authenticate(&config, Some(&authorization)).unwrap();
+        assert_eq!(claims.sub.as_deref(), Some("alice"));
+        assert_eq!(claims.extra["role"], "admin");
+    }
+}
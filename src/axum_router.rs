@@ -0,0 +1,260 @@
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, IF_MATCH, RANGE};
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::Router;
+use futures_util::TryStreamExt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::api_key::API_KEY_HEADER;
+use crate::auth::{self, check_credentials, Credentials};
+use crate::finder::VueFinder;
+use crate::finder_core::{
+    self, FinderBody, FinderMethod, FinderPayload, FinderRequest, FinderResponse,
+};
+use crate::payload::Query;
+use crate::response::ErrorResponse;
+use crate::share::SHARE_PASSWORD_HEADER;
+
+// Mount with `Router::new().nest("/api", finder_router(finder))`. Bearer
+// tokens are checked when `VueFinderConfig::jwt` is set; the HTML index isn't
+// served. Serve it with `into_make_service_with_connect_info::<SocketAddr>()`
+// so anonymous clients are told apart by address: without it they share file
+// locks and `max_concurrent_uploads` doesn't apply to them.
+pub fn finder_router(finder: VueFinder) -> Router {
+    let data = Arc::new(finder);
+    Router::new().route(
+        "/",
+        any(move |request: Request| handle(data.clone(), request)),
+    )
+}
+
+impl IntoResponse for FinderResponse {
+    fn into_response(self) -> Response {
+        let (status, headers, body) = self.into_parts();
+        let body = match body {
+            FinderBody::Empty => Body::empty(),
+            FinderBody::Bytes(bytes) => Body::from(bytes),
+            FinderBody::Stream(stream) => Body::from_stream(stream),
+        };
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        response
+    }
+}
+
+fn bad_request(message: String) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        axum::Json(ErrorResponse::new(message)),
+    )
+        .into_response()
+}
+
+async fn handle(data: Arc<VueFinder>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let header = |name: &str| {
+        parts
+            .headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    let mut query: Query = match serde_urlencoded::from_str(parts.uri.query().unwrap_or_default()) {
+        Ok(query) => query,
+        Err(e) => return bad_request(e.to_string()),
+    };
+    query.password = header(SHARE_PASSWORD_HEADER);
+    let method = match parts.method {
+        Method::GET => FinderMethod::Get,
+        Method::POST => FinderMethod::Post,
+        _ => FinderMethod::Other,
+    };
+
+    let credentials = check_credentials(
+        &data,
+        &query.q,
+        header(AUTHORIZATION.as_str()).as_deref(),
+        header(API_KEY_HEADER).as_deref(),
+    )
+    .await;
+    let (claims, key) = match credentials {
+        Ok(Some(Credentials::Token(claims))) => (Some(claims), None),
+        Ok(Some(Credentials::Key(key))) => (None, Some(key)),
+        Ok(None) => (None, None),
+        Err(response) => return response.into_response(),
+    };
+
+    let content_type = header(CONTENT_TYPE.as_str()).unwrap_or_default();
+    let payload = if method != FinderMethod::Post {
+        FinderPayload::None
+    } else if content_type.starts_with("multipart/form-data") {
+        let body = body.into_data_stream().map_err(std::io::Error::other);
+        FinderPayload::multipart(&content_type, body)
+    } else if content_type.starts_with("application/json") {
+        let json = match axum::body::to_bytes(body, data.config.json_limit).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match json {
+            Ok(json) => FinderPayload::Json(json),
+            Err(message) => return bad_request(message),
+        }
+    } else {
+        FinderPayload::None
+    };
+
+    let client = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip().to_string());
+    let request = FinderRequest {
+        method,
+        query,
+        payload,
+        range: header(RANGE.as_str()),
+        if_match: header(IF_MATCH.as_str()),
+        client,
+        principal: auth::principal(claims.as_ref(), key.as_ref()),
+        claims,
+    };
+    finder_core::handle(data, request).await.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::local::LocalStorage;
+    use bytes::Bytes;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    async fn call(router: &Router, request: Request) -> (StatusCode, Bytes) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_axum_router() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "hello").unwrap();
+        let router = Router::new().nest(
+            "/api",
+            finder_router(VueFinder {
+                storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+                ..VueFinder::default()
+            }),
+        );
+
+        let request = Request::get("/api?q=index&adapter=local&path=local://")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = call(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["files"][0]["basename"], "a.txt");
+
+        let request = Request::get("/api?q=download&adapter=local&path=local://a.txt")
+            .header(RANGE, "bytes=1-3")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = call(&router, request).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, "ell");
+
+        let request = Request::post("/api?q=newfolder&adapter=local&path=local://")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name":"docs"}"#))
+            .unwrap();
+        let (status, _) = call(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(temp_dir.path().join("docs").is_dir());
+
+        let request = Request::post("/api?q=upload&adapter=local&path=local://docs")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=BOUNDARY")
+            .body(Body::from(
+                "--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                b.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"b.txt\"\r\n\r\n\
+                uploaded\r\n\
+                --BOUNDARY--\r\n",
+            ))
+            .unwrap();
+        let (status, _) = call(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("docs/b.txt")).unwrap(),
+            "uploaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_axum_jwt() {
+        let router = finder_router(VueFinder {
+            config: Arc::new(crate::finder::VueFinderConfig {
+                jwt: Some(crate::auth::JwtConfig {
+                    secret: "secret".to_string(),
+                    issuer: None,
+                    audience: None,
+                    leeway_secs: 0,
+                }),
+                ..Default::default()
+            }),
+            ..VueFinder::default()
+        });
+
+        let request = Request::get("/?q=version").body(Body::empty()).unwrap();
+        let (status, _) = call(&router, request).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_axum_upload_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let router = finder_router(VueFinder {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            config: Arc::new(crate::finder::VueFinderConfig {
+                max_concurrent_uploads: Some(0),
+                ..Default::default()
+            }),
+            ..VueFinder::default()
+        });
+        let upload = |connect_info: Option<SocketAddr>| {
+            let mut request = Request::post("/?q=upload&adapter=local&path=local://")
+                .header(CONTENT_TYPE, "multipart/form-data; boundary=BOUNDARY")
+                .body(Body::from(
+                    "--BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                    a.txt\r\n\
+                    --BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n\
+                    a\r\n\
+                    --BOUNDARY--\r\n",
+                ))
+                .unwrap();
+            if let Some(address) = connect_info {
+                request.extensions_mut().insert(ConnectInfo(address));
+            }
+            request
+        };
+
+        let (status, _) = call(&router, upload(Some(([10, 0, 0, 1], 80).into()))).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        // Requests without an address aren't limited instead of sharing one client's slots
+        let (status, _) = call(&router, upload(None)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
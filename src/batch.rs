@@ -0,0 +1,76 @@
+use http::StatusCode;
+
+use crate::error::FinderError;
+use crate::finder_core::FinderResponse;
+use crate::response::{BatchReport, BatchResponse, BatchResult};
+
+// Outcomes of the items of a command acting on a selection. Commands go on
+// past failed items so clients learn what was done.
+#[derive(Default)]
+pub struct Batch {
+    results: Vec<BatchResult>,
+    // Status and message of the first failure
+    first_error: Option<(StatusCode, String)>,
+}
+
+impl Batch {
+    pub fn succeeded(&mut self, path: &str, target: Option<String>) {
+        self.push(path, target, false, None);
+    }
+
+    pub fn skipped(&mut self, path: &str, target: String) {
+        self.push(path, Some(target), true, None);
+    }
+
+    pub fn failed(&mut self, path: &str, error: FinderError) {
+        let message = error.to_string();
+        self.first_error
+            .get_or_insert_with(|| (error.status_code(), message.clone()));
+        self.push(path, None, false, Some(message));
+    }
+
+    fn push(&mut self, path: &str, target: Option<String>, skipped: bool, message: Option<String>) {
+        self.results.push(BatchResult {
+            path: path.to_string(),
+            status: message.is_none(),
+            target,
+            skipped,
+            message,
+        });
+    }
+
+    fn failures(&self) -> usize {
+        self.results.iter().filter(|result| !result.status).count()
+    }
+
+    pub fn into_report(self) -> BatchReport {
+        let failed = self.failures();
+        BatchReport {
+            status: failed == 0,
+            succeeded: self.results.len() - failed,
+            failed,
+            results: self.results,
+        }
+    }
+
+    // `None` when every item succeeded. Otherwise the results of all items,
+    // with `207 Multi-Status` when only some failed and the status of the
+    // first failure when nothing was done.
+    pub fn into_response(self) -> Option<FinderResponse> {
+        let failed = self.failures();
+        let (status, message) = self.first_error?;
+        let succeeded = self.results.len() - failed;
+        let status = if succeeded > 0 {
+            StatusCode::MULTI_STATUS
+        } else {
+            status
+        };
+        Some(FinderResponse::build(status).json(BatchResponse {
+            status: false,
+            message,
+            succeeded,
+            failed,
+            results: self.results,
+        }))
+    }
+}
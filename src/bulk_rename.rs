@@ -0,0 +1,142 @@
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+
+// Largest compiled `find` pattern, bounding what a request can make the server build
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+enum Find {
+    // Replace the whole name
+    Name,
+    Text(String),
+    Regex(Regex),
+}
+
+// New names for the items of a `bulk-rename`. `replace` may hold `{n}`, the
+// item's number counting from `start`, `{n:3}` for it padded with zeros to 3
+// digits, `{name}` for the name without its extension and `{ext}` for the
+// extension with its dot. With a regular expression it may also refer to the
+// groups of `find`, `$1` or `${group}`.
+pub struct Renamer {
+    find: Find,
+    replace: String,
+    start: u64,
+}
+
+impl Renamer {
+    // Errors when `find` isn't a valid regular expression
+    pub fn new(find: &str, replace: &str, regex: bool, start: u64) -> Result<Self, String> {
+        let find = if find.is_empty() {
+            Find::Name
+        } else if regex {
+            Find::Regex(
+                RegexBuilder::new(find)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            )
+        } else {
+            Find::Text(find.to_string())
+        };
+        Ok(Self {
+            find,
+            replace: replace.to_string(),
+            start,
+        })
+    }
+
+    // New name of the `index`th item, now called `name`
+    pub fn rename(&self, index: usize, name: &str) -> String {
+        let regex = matches!(self.find, Find::Regex(_));
+        let replace = expand(&self.replace, self.start + index as u64, name, regex);
+        match &self.find {
+            Find::Name => replace,
+            Find::Text(text) => name.replace(text.as_str(), &replace),
+            Find::Regex(find) => find.replace_all(name, replace.as_str()).into_owned(),
+        }
+    }
+}
+
+// `template` with its placeholders filled in for the item `name` numbered
+// `n`. Unknown placeholders are kept as they are.
+fn expand(template: &str, n: u64, name: &str, regex: bool) -> String {
+    let path = Path::new(name);
+    let stem = path
+        .file_stem()
+        .map_or(name.into(), |stem| stem.to_string_lossy());
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    // Names may hold a `$`, which `Regex::replace_all` would take for a group
+    let literal = |value: &str| {
+        if regex {
+            value.replace('$', "$$")
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let Some(close) = rest.find('}') else {
+            break;
+        };
+        let value = match &rest[1..close] {
+            "n" => Some(n.to_string()),
+            "name" => Some(literal(&stem)),
+            "ext" => Some(literal(&ext)),
+            placeholder => placeholder
+                .strip_prefix("n:")
+                .and_then(|width| width.parse::<usize>().ok())
+                .filter(|width| *width <= 20)
+                .map(|width| format!("{:0width$}", n, width = width)),
+        };
+        match value {
+            Some(value) => {
+                result.push_str(&value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename() {
+        let renamer = Renamer::new("", "photo_{n:3}{ext}", false, 1).unwrap();
+        assert_eq!(renamer.rename(0, "IMG_1234.JPG"), "photo_001.JPG");
+        assert_eq!(renamer.rename(41, "b.png"), "photo_042.png");
+
+        let renamer = Renamer::new("draft", "final", false, 1).unwrap();
+        assert_eq!(
+            renamer.rename(0, "draft-2 (draft).md"),
+            "final-2 (final).md"
+        );
+
+        let renamer = Renamer::new(r"^(\d{4})-(\d{2})-(\d{2})", "$3.$2.$1 {n}", true, 7).unwrap();
+        assert_eq!(
+            renamer.rename(0, "2024-05-31 notes.txt"),
+            "31.05.2024 7 notes.txt"
+        );
+
+        let renamer = Renamer::new(r"\.txt$", "-{name}.md", true, 1).unwrap();
+        assert_eq!(renamer.rename(0, "$5.txt"), "$5-$5.md");
+
+        let renamer = Renamer::new("", "{name} {unknown} {n", false, 1).unwrap();
+        assert_eq!(renamer.rename(0, ".env"), ".env {unknown} {n");
+
+        assert!(Renamer::new("(", "", true, 1).is_err());
+    }
+}
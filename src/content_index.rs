@@ -0,0 +1,626 @@
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::jobs::JobProgress;
+use crate::response::ContentReindexResponse;
+use crate::storages::{StorageAdapter, StorageError};
+use crate::trash::TRASH_DIR;
+use crate::versions::VERSIONS_DIR;
+
+// Characters of context around the matches in a snippet
+const SNIPPET_CHARS: usize = 160;
+
+// Files sent to the index at once while rebuilding it
+const BATCH_SIZE: usize = 64;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ContentIndexConfig {
+    // Folder keeping the index across restarts, needs the `fulltext` feature.
+    // Without one the index is kept in memory and rebuilt on start.
+    pub path: Option<String>,
+    // Files larger than this, in bytes, are left out
+    pub max_file_size: u64,
+}
+
+impl Default for ContentIndexConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_file_size: 1024 * 1024,
+        }
+    }
+}
+
+// A file whose contents match a `search-content` query
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ContentMatch {
+    pub path: String,
+    pub score: f32,
+    // Text around the best match
+    pub snippet: String,
+    // Byte ranges of `snippet` matching the query
+    pub highlights: Vec<[usize; 2]>,
+}
+
+// Text of a file to index. Paths are as the adapter shares them, see
+// `StorageAdapter::shared_path`.
+#[derive(Clone, Debug)]
+pub struct ContentDocument {
+    pub adapter: String,
+    pub path: String,
+    pub text: String,
+}
+
+// Full-text index of the text files of every adapter, updated as files are
+// written and deleted
+#[async_trait]
+pub trait ContentIndex: Send + Sync {
+    // Add the documents, replacing what was indexed for their paths
+    async fn upsert(&self, documents: Vec<ContentDocument>) -> Result<(), String>;
+
+    // Drop `path` and, for folders, everything below it
+    async fn remove(&self, adapter: &str, path: &str) -> Result<(), String>;
+
+    // Best `limit` matches of `query` among the files of `adapter`, only
+    // those below the folder `scope` when given
+    async fn search(
+        &self,
+        adapter: &str,
+        scope: Option<&str>,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ContentMatch>, String>;
+}
+
+// Whether `path` is worth indexing, judged by its name
+pub fn is_text(path: &str) -> bool {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    mime.type_() == mime_guess::mime::TEXT
+        || matches!(
+            mime.subtype().as_str(),
+            "json" | "xml" | "javascript" | "x-yaml" | "toml" | "x-sh" | "sql" | "x-tex"
+        )
+}
+
+// Folder a search is limited to, `None` for the whole adapter
+pub fn search_scope(path: &str) -> Option<String> {
+    let (scheme, key) = path.split_once("://")?;
+    let key = key.trim_end_matches('/');
+    (!key.is_empty()).then(|| format!("{}://{}", scheme, key))
+}
+
+fn index_error(error: String) -> StorageError {
+    StorageError::Io(std::io::Error::other(error))
+}
+
+// Contents of `path` to index, `None` when it isn't text or is too large
+async fn read_text(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    max_file_size: u64,
+) -> Result<Option<ContentDocument>, StorageError> {
+    if !is_text(path) {
+        return Ok(None);
+    }
+    let contents = storage.read_range(path, 0, max_file_size + 1).await?;
+    if contents.len() as u64 > max_file_size {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(contents)
+        .ok()
+        .map(|text| ContentDocument {
+            adapter: storage.name(),
+            path: storage.shared_path(path),
+            text,
+        }))
+}
+
+// Bring the index up to date after `path` was written, with everything below
+// it for folders
+pub async fn update(
+    index: &dyn ContentIndex,
+    storage: &dyn StorageAdapter,
+    path: &str,
+    max_file_size: u64,
+    limit: usize,
+) -> Result<(), StorageError> {
+    if storage.is_dir(path).await? {
+        return reindex(index, storage, path, max_file_size, limit, None)
+            .await
+            .map(|_| ());
+    }
+
+    let updated = match read_text(storage, path, max_file_size).await? {
+        Some(document) => index.upsert(vec![document]).await,
+        // No longer text, or too large
+        None => {
+            index
+                .remove(&storage.name(), &storage.shared_path(path))
+                .await
+        }
+    };
+    updated.map_err(index_error)
+}
+
+// Index every text file below `path` again, leaving out the trash and
+// versions. Stops after visiting `limit` entries with `complete: false`.
+pub async fn reindex(
+    index: &dyn ContentIndex,
+    storage: &dyn StorageAdapter,
+    path: &str,
+    max_file_size: u64,
+    limit: usize,
+    progress: Option<&JobProgress>,
+) -> Result<ContentReindexResponse, StorageError> {
+    let adapter = storage.name();
+    let root = format!("{}://", adapter);
+    let skipped = [
+        format!("{}{}", root, TRASH_DIR),
+        format!("{}{}", root, VERSIONS_DIR),
+    ];
+    index
+        .remove(&adapter, &storage.shared_path(path))
+        .await
+        .map_err(index_error)?;
+
+    let mut result = ContentReindexResponse {
+        indexed: 0,
+        complete: true,
+    };
+    let mut visited = 0;
+    let mut batch = Vec::new();
+    let mut pending = vec![path.to_string()];
+    if let Some(progress) = progress {
+        progress.phase("indexing", None);
+    }
+    'walk: while let Some(dir) = pending.pop() {
+        let mut contents = storage.list_stream(&dir);
+        while let Some(item) = contents.try_next().await? {
+            if visited >= limit {
+                result.complete = false;
+                break 'walk;
+            }
+            visited += 1;
+
+            if item.node_type == "dir" {
+                if !skipped.contains(&item.path) {
+                    pending.push(item.path);
+                }
+                continue;
+            }
+            if item
+                .size
+                .is_some_and(|size| size > max_file_size || size == 0)
+            {
+                continue;
+            }
+            match read_text(storage, &item.path, max_file_size).await {
+                Ok(Some(document)) => batch.push(document),
+                // Not text after all, or removed since it was listed
+                Ok(None) | Err(StorageError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            }
+            if batch.len() >= BATCH_SIZE {
+                result.indexed += batch.len();
+                index
+                    .upsert(std::mem::take(&mut batch))
+                    .await
+                    .map_err(index_error)?;
+            }
+            if let Some(progress) = progress {
+                progress.advance(1);
+            }
+        }
+    }
+    result.indexed += batch.len();
+    index.upsert(batch).await.map_err(index_error)?;
+    Ok(result)
+}
+
+// Index kept in memory matching every word of the query, case-insensitively
+// for ASCII. Fine for small mounts and tests; the `fulltext` feature adds
+// `TantivyIndex` for larger ones.
+#[derive(Default)]
+pub struct MemoryContentIndex {
+    // Adapter and text of each path
+    documents: RwLock<HashMap<String, (String, String)>>,
+}
+
+#[async_trait]
+impl ContentIndex for MemoryContentIndex {
+    async fn upsert(&self, documents: Vec<ContentDocument>) -> Result<(), String> {
+        let mut indexed = self.documents.write().unwrap();
+        for document in documents {
+            indexed.insert(document.path, (document.adapter, document.text));
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, adapter: &str, path: &str) -> Result<(), String> {
+        let path = path.trim_end_matches('/');
+        let below = format!("{}/", path);
+        self.documents
+            .write()
+            .unwrap()
+            .retain(|key, (key_adapter, _)| {
+                key_adapter != adapter || !(key == path || key.starts_with(&below))
+            });
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        adapter: &str,
+        scope: Option<&str>,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ContentMatch>, String> {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| word.to_ascii_lowercase())
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let below = scope.map(|scope| format!("{}/", scope.trim_end_matches('/')));
+
+        let documents = self.documents.read().unwrap();
+        let mut matches: Vec<ContentMatch> = documents
+            .iter()
+            .filter(|(path, (key_adapter, _))| {
+                key_adapter == adapter
+                    && below
+                        .as_ref()
+                        .is_none_or(|below| path.starts_with(below.as_str()))
+            })
+            .filter_map(|(path, (_, text))| {
+                let lower = text.to_ascii_lowercase();
+                let counts: Vec<usize> = words
+                    .iter()
+                    .map(|word| lower.matches(word.as_str()).count())
+                    .collect();
+                if counts.contains(&0) {
+                    return None;
+                }
+                let (snippet, highlights) = snippet(text, &lower, &words);
+                Some(ContentMatch {
+                    path: path.clone(),
+                    score: counts.iter().sum::<usize>() as f32,
+                    snippet,
+                    highlights,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(&b.path)));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+// Up to `SNIPPET_CHARS` bytes of `text` around the first match of `words`,
+// with the ranges every word matches in it
+fn snippet(text: &str, lower: &str, words: &[String]) -> (String, Vec<[usize; 2]>) {
+    let first = words
+        .iter()
+        .filter_map(|word| lower.find(word.as_str()))
+        .min()
+        .unwrap_or_default();
+    let floor = |mut index: usize| {
+        while !text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    };
+    let start = floor(first.saturating_sub(SNIPPET_CHARS / 4));
+    let end = floor((start + SNIPPET_CHARS).min(text.len()));
+
+    let window = &lower[start..end];
+    let mut highlights: Vec<[usize; 2]> = words
+        .iter()
+        .flat_map(|word| {
+            window
+                .match_indices(word.as_str())
+                .map(|(at, word)| [at, at + word.len()])
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    highlights.sort();
+    (text[start..end].to_string(), highlights)
+}
+
+#[cfg(feature = "fulltext")]
+pub use self::fulltext::TantivyIndex;
+
+#[cfg(feature = "fulltext")]
+mod fulltext {
+    use async_trait::async_trait;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use tantivy::collector::TopDocs;
+    use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, TermQuery};
+    use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+    use tantivy::snippet::SnippetGenerator;
+    use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+    use super::{ContentDocument, ContentIndex, ContentMatch, SNIPPET_CHARS};
+
+    // Memory the writer may use before flushing a segment
+    const WRITER_MEMORY: usize = 50 * 1024 * 1024;
+
+    struct Inner {
+        index: Index,
+        reader: IndexReader,
+        writer: Mutex<IndexWriter>,
+        adapter: Field,
+        path: Field,
+        // The path and every folder it's in, for removing and searching folders
+        scope: Field,
+        body: Field,
+    }
+
+    // Index built with tantivy, ranking matches by BM25. Queries use its
+    // syntax: `"exact phrase"`, `+required -excluded`, `word*`.
+    pub struct TantivyIndex {
+        inner: Arc<Inner>,
+    }
+
+    impl TantivyIndex {
+        // Index kept in `dir`, created when missing, or in memory without one
+        pub fn open(dir: Option<&Path>) -> tantivy::Result<Self> {
+            let mut builder = Schema::builder();
+            let adapter = builder.add_text_field("adapter", STRING);
+            let path = builder.add_text_field("path", STRING | STORED);
+            let scope = builder.add_text_field("scope", STRING);
+            let body = builder.add_text_field("body", TEXT | STORED);
+            let schema = builder.build();
+
+            let index = match dir {
+                Some(dir) => {
+                    std::fs::create_dir_all(dir)?;
+                    let directory = tantivy::directory::MmapDirectory::open(dir)?;
+                    Index::open_or_create(directory, schema)?
+                }
+                None => Index::create_in_ram(schema),
+            };
+            let reader = index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .try_into()?;
+            let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+            Ok(Self {
+                inner: Arc::new(Inner {
+                    index,
+                    reader,
+                    writer: Mutex::new(writer),
+                    adapter,
+                    path,
+                    scope,
+                    body,
+                }),
+            })
+        }
+
+        // Run `task` off the async workers, tantivy blocks on disk access
+        async fn blocking<T, F>(&self, task: F) -> Result<T, String>
+        where
+            F: FnOnce(&Inner) -> tantivy::Result<T> + Send + 'static,
+            T: Send + 'static,
+        {
+            let inner = self.inner.clone();
+            tokio::task::spawn_blocking(move || task(&inner))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    // `path` and the folders it's in, below the adapter root
+    fn scopes(path: &str) -> Vec<String> {
+        let (scheme, key) = path.split_once("://").unwrap_or(("", path));
+        let names: Vec<&str> = key.split('/').filter(|name| !name.is_empty()).collect();
+        (1..=names.len())
+            .map(|n| format!("{}://{}", scheme, names[..n].join("/")))
+            .collect()
+    }
+
+    impl Inner {
+        fn commit(&self, writer: &mut IndexWriter) -> tantivy::Result<()> {
+            writer.commit()?;
+            self.reader.reload()
+        }
+    }
+
+    #[async_trait]
+    impl ContentIndex for TantivyIndex {
+        async fn upsert(&self, documents: Vec<ContentDocument>) -> Result<(), String> {
+            if documents.is_empty() {
+                return Ok(());
+            }
+            self.blocking(move |inner| {
+                let mut writer = inner.writer.lock().unwrap();
+                for document in documents {
+                    writer.delete_term(Term::from_field_text(inner.path, &document.path));
+                    let mut doc = TantivyDocument::default();
+                    doc.add_text(inner.adapter, &document.adapter);
+                    doc.add_text(inner.path, &document.path);
+                    for scope in scopes(&document.path) {
+                        doc.add_text(inner.scope, scope);
+                    }
+                    doc.add_text(inner.body, &document.text);
+                    writer.add_document(doc)?;
+                }
+                inner.commit(&mut writer)
+            })
+            .await
+        }
+
+        async fn remove(&self, adapter: &str, path: &str) -> Result<(), String> {
+            let (adapter, path) = (adapter.to_string(), path.trim_end_matches('/').to_string());
+            self.blocking(move |inner| {
+                let mut writer = inner.writer.lock().unwrap();
+                // The adapter root has no scope of its own
+                let root = path
+                    .split_once("://")
+                    .is_some_and(|(_, key)| key.is_empty());
+                if root {
+                    writer.delete_term(Term::from_field_text(inner.adapter, &adapter));
+                } else {
+                    writer.delete_term(Term::from_field_text(inner.scope, &path));
+                }
+                inner.commit(&mut writer)
+            })
+            .await
+        }
+
+        async fn search(
+            &self,
+            adapter: &str,
+            scope: Option<&str>,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<ContentMatch>, String> {
+            let (adapter, scope, query) = (
+                adapter.to_string(),
+                scope.map(str::to_string),
+                query.to_string(),
+            );
+            self.blocking(move |inner| {
+                let searcher = inner.reader.searcher();
+                let parser = QueryParser::for_index(&inner.index, vec![inner.body]);
+                // Stray quotes or operators shouldn't fail the search
+                let (text_query, _) = parser.parse_query_lenient(&query);
+
+                let term = |field: Field, value: &str| -> Box<dyn Query> {
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, value),
+                        IndexRecordOption::Basic,
+                    ))
+                };
+                let mut clauses = vec![
+                    (Occur::Must, text_query.box_clone()),
+                    (Occur::Must, term(inner.adapter, &adapter)),
+                ];
+                if let Some(scope) = &scope {
+                    clauses.push((Occur::Must, term(inner.scope, scope)));
+                }
+                let top =
+                    searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))?;
+
+                let mut snippets = SnippetGenerator::create(&searcher, &*text_query, inner.body)?;
+                snippets.set_max_num_chars(SNIPPET_CHARS);
+                top.into_iter()
+                    .map(|(score, address)| {
+                        let doc: TantivyDocument = searcher.doc(address)?;
+                        let snippet = snippets.snippet_from_doc(&doc);
+                        Ok(ContentMatch {
+                            path: doc
+                                .get_first(inner.path)
+                                .and_then(|value| value.as_str())
+                                .unwrap_or_default()
+                                .to_string(),
+                            score,
+                            snippet: snippet.fragment().to_string(),
+                            highlights: snippet
+                                .highlighted()
+                                .iter()
+                                .map(|range| [range.start, range.end])
+                                .collect(),
+                        })
+                    })
+                    .collect()
+            })
+            .await
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::scopes;
+
+        #[test]
+        fn test_scopes() {
+            assert_eq!(
+                scopes("local://docs/a/b.md"),
+                vec!["local://docs", "local://docs/a", "local://docs/a/b.md"]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(search_scope("local://"), None);
+        assert_eq!(
+            search_scope("local://docs/").as_deref(),
+            Some("local://docs")
+        );
+        assert!(is_text("a.md") && is_text("b.json") && !is_text("c.png"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_index() {
+        let storage = MemoryStorage::new("mem").with_files([
+            (
+                "docs/report.md",
+                "Quarterly revenue grew.\nRevenue by region follows.",
+            ),
+            ("docs/notes.txt", "Nothing about money here."),
+            ("photo.png", "revenue"),
+            (".trash/old.txt", "revenue"),
+        ]);
+        let index = MemoryContentIndex::default();
+        let indexed = reindex(&index, &storage, "mem://", 1024, 100, None)
+            .await
+            .unwrap();
+        assert_eq!((indexed.indexed, indexed.complete), (2, true));
+
+        let matches = index.search("mem", None, "REVENUE", 10).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, "mem://docs/report.md");
+        assert_eq!(matches[0].score, 2.0);
+        let [start, end] = matches[0].highlights[0];
+        assert_eq!(&matches[0].snippet[start..end], "revenue");
+        assert!(index
+            .search("mem", Some("mem://other"), "revenue", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(index
+            .search("mem", None, "revenue money", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        storage
+            .write("mem://docs/notes.txt", b"Revenue, finally.".to_vec())
+            .await
+            .unwrap();
+        update(&index, &storage, "mem://docs/notes.txt", 1024, 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            index
+                .search("mem", None, "revenue", 10)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        index.remove("mem", "mem://docs").await.unwrap();
+        assert!(index
+            .search("mem", None, "revenue", 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+
+use crate::jobs::JobProgress;
+use crate::response::{DuplicateSet, DuplicatesResponse};
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::{StorageAdapter, StorageError};
+
+// Files below `path` with the same contents. Only files sharing their size
+// with another one are hashed, empty files are left out. The walk stops after
+// `limit` entries with `complete: false`, later duplicates are then missed.
+// Entries `skip` accepts are left out along with their contents.
+pub async fn find_duplicates(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    limit: usize,
+    skip: &(dyn Fn(&str) -> bool + Sync),
+    progress: &JobProgress,
+) -> Result<DuplicatesResponse, StorageError> {
+    let mut complete = true;
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    let mut visited = 0;
+    let mut pending = vec![path.to_string()];
+
+    progress.phase("scanning", None);
+    'walk: while let Some(dir) = pending.pop() {
+        let mut contents = storage.list_stream(&dir);
+        while let Some(item) = contents.try_next().await? {
+            if skip(&item.path) {
+                continue;
+            }
+            if visited >= limit {
+                complete = false;
+                break 'walk;
+            }
+            visited += 1;
+            progress.advance(1);
+
+            if item.node_type == "dir" {
+                pending.push(item.path);
+            } else if let Some(size) = item.size.filter(|&size| size > 0) {
+                by_size.entry(size).or_default().push(item.path);
+            }
+        }
+    }
+
+    by_size.retain(|_, paths| paths.len() > 1);
+    let candidates = by_size.values().map(|paths| paths.len() as u64).sum();
+    progress.phase("hashing", Some(candidates));
+
+    let mut sets = Vec::new();
+    for (size, paths) in by_size {
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            match storage.checksum(&path, ChecksumAlgorithm::Sha256).await {
+                Ok(hash) => by_hash.entry(hash).or_default().push(path),
+                // Removed since it was listed
+                Err(StorageError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+            progress.advance(1);
+        }
+        sets.extend(
+            by_hash
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|(hash, mut paths)| {
+                    paths.sort();
+                    DuplicateSet { size, hash, paths }
+                }),
+        );
+    }
+
+    // Most space to win back first
+    sets.sort_by(|a, b| {
+        let wasted = |set: &DuplicateSet| set.size * (set.paths.len() as u64 - 1);
+        wasted(b)
+            .cmp(&wasted(a))
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    Ok(DuplicatesResponse { sets, complete })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::{JobState, Jobs};
+    use crate::storages::memory::MemoryStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let storage = Arc::new(MemoryStorage::new("mem").with_files([
+            ("a.txt", "same"),
+            ("docs/b.txt", "same"),
+            ("docs/old/c.txt", "same"),
+            ("docs/d.txt", "diff"),
+            ("big.bin", "larger file"),
+            ("docs/big copy.bin", "larger file"),
+            ("empty.txt", ""),
+            ("docs/empty.txt", ""),
+        ]));
+
+        let jobs = Jobs::default();
+        let id = jobs.spawn("duplicates", |progress| async move {
+            find_duplicates(storage.as_ref(), "mem://", 100, &|_| false, &progress).await
+        });
+        let status = loop {
+            let status = jobs.status(&id).unwrap();
+            if status.state != JobState::Running {
+                break status;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!((status.phase.as_str(), status.done), ("hashing", 6));
+
+        let found: DuplicatesResponse = serde_json::from_value(status.result.unwrap()).unwrap();
+        assert!(found.complete);
+        let paths: Vec<_> = found.sets.iter().map(|set| set.paths.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                vec!["mem://big.bin", "mem://docs/big copy.bin"],
+                vec!["mem://a.txt", "mem://docs/b.txt", "mem://docs/old/c.txt"],
+            ]
+        );
+        assert_eq!(found.sets[1].size, 4);
+    }
+}
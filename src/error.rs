@@ -0,0 +1,158 @@
+use http::StatusCode;
+use thiserror::Error;
+
+use crate::finder_core::FinderResponse;
+use crate::list_store::ListStoreError;
+use crate::locks::LockError;
+use crate::response::ErrorResponse;
+use crate::share::ShareError;
+use crate::storages::StorageError;
+use crate::upload_session::UploadSessionError;
+
+// Failure of a finder command, answered with an `ErrorResponse` and the
+// status matching its cause
+#[derive(Error, Debug)]
+pub enum FinderError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    UploadSession(#[from] UploadSessionError),
+    #[error(transparent)]
+    Share(#[from] ShareError),
+    #[error(transparent)]
+    Store(#[from] ListStoreError),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    // An `If-Match` that no longer holds
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    UnsupportedMediaType(String),
+    // Refused by the malware scanner
+    #[error("{0}")]
+    Infected(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+fn io_status(error: &std::io::Error) -> StatusCode {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        std::io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        std::io::ErrorKind::AlreadyExists => StatusCode::CONFLICT,
+        std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidFilename => {
+            StatusCode::BAD_REQUEST
+        }
+        std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => {
+            StatusCode::INSUFFICIENT_STORAGE
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// Status for a storage failure, also used where the message gets extra context
+pub fn storage_status(error: &StorageError) -> StatusCode {
+    match error {
+        StorageError::Io(error) => io_status(error),
+        StorageError::NotFound(_) => StatusCode::NOT_FOUND,
+        StorageError::InvalidPath(_) => StatusCode::BAD_REQUEST,
+        StorageError::ReadOnly(_) => StatusCode::FORBIDDEN,
+        StorageError::LimitExceeded(_) => StatusCode::BAD_REQUEST,
+        StorageError::InsufficientStorage(_) => StatusCode::INSUFFICIENT_STORAGE,
+        StorageError::AlreadyExists(_) => StatusCode::CONFLICT,
+        StorageError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+        StorageError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+        StorageError::NotADirectory(_) => StatusCode::BAD_REQUEST,
+    }
+}
+
+impl FinderError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            FinderError::Storage(error) => storage_status(error),
+            FinderError::UploadSession(error) => match error {
+                UploadSessionError::NotFound(_) => StatusCode::NOT_FOUND,
+                UploadSessionError::OffsetMismatch(_) => StatusCode::CONFLICT,
+                UploadSessionError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                UploadSessionError::Incomplete { .. } => StatusCode::BAD_REQUEST,
+                UploadSessionError::Io(error) => io_status(error),
+            },
+            FinderError::Share(error) => match error {
+                ShareError::NotFound(_) => StatusCode::NOT_FOUND,
+                ShareError::Expired(_) | ShareError::LimitReached(_) => StatusCode::GONE,
+                ShareError::Password(_) => StatusCode::UNAUTHORIZED,
+                ShareError::InvalidExpiry(_) => StatusCode::BAD_REQUEST,
+                ShareError::Io(error) => io_status(error),
+                ShareError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            FinderError::Store(error) => match error {
+                ListStoreError::Io(error) => io_status(error),
+                ListStoreError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            FinderError::Lock(error) => match error {
+                LockError::Locked(_) => StatusCode::LOCKED,
+                LockError::NotFound(_) => StatusCode::NOT_FOUND,
+                LockError::Io(error) => io_status(error),
+            },
+            FinderError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            FinderError::Forbidden(_) => StatusCode::FORBIDDEN,
+            FinderError::NotFound(_) => StatusCode::NOT_FOUND,
+            FinderError::Conflict(_) => StatusCode::CONFLICT,
+            FinderError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            FinderError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            FinderError::Infected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            FinderError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_response(&self) -> FinderResponse {
+        let mut body = ErrorResponse::new(self.to_string());
+        // Tell the client where to resume
+        if let FinderError::UploadSession(UploadSessionError::OffsetMismatch(offset)) = self {
+            body.offset = Some(*offset);
+        }
+        FinderResponse::build(self.status_code()).json(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        let status = |error: StorageError| FinderError::from(error).status_code();
+        assert_eq!(
+            status(StorageError::NotFound("a".into())),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(StorageError::InvalidPath("a".into())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(StorageError::AlreadyExists("a".into())),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(std::io::Error::other("disk on fire").into()),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            FinderError::from(UploadSessionError::OffsetMismatch(3)).status_code(),
+            StatusCode::CONFLICT
+        );
+    }
+}
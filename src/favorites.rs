@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+use crate::list_store::{ListStore, ListStoreError, StoredList};
+
+// A bookmarked file or folder
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Favorite {
+    // Who bookmarked it, `None` for favorites of everyone when there's no
+    // authentication
+    pub owner: Option<String>,
+    pub adapter: String,
+    pub path: String,
+    // Unix seconds
+    pub created_at: u64,
+}
+
+impl Favorite {
+    fn is(&self, adapter: &str, path: &str) -> bool {
+        self.adapter == adapter && self.path.trim_end_matches('/') == path.trim_end_matches('/')
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Favorites of every user, shared by all workers
+#[derive(Default)]
+pub struct Favorites {
+    list: StoredList<Favorite>,
+}
+
+impl Favorites {
+    pub fn new(store: impl ListStore<Favorite> + 'static) -> Self {
+        Self {
+            list: StoredList::new(store),
+        }
+    }
+
+    // Returns whether the path wasn't a favorite of `owner` yet
+    pub async fn add(
+        &self,
+        owner: Option<&str>,
+        adapter: &str,
+        path: &str,
+    ) -> Result<bool, ListStoreError> {
+        let mut added = false;
+        self.list
+            .update(|favorites| {
+                added = !favorites.iter().any(|favorite| {
+                    favorite.owner.as_deref() == owner && favorite.is(adapter, path)
+                });
+                if added {
+                    favorites.push(Favorite {
+                        owner: owner.map(str::to_string),
+                        adapter: adapter.to_string(),
+                        path: path.to_string(),
+                        created_at: now(),
+                    });
+                }
+                added
+            })
+            .await?;
+        Ok(added)
+    }
+
+    // Returns whether the path was a favorite of `owner`
+    pub async fn remove(
+        &self,
+        owner: Option<&str>,
+        adapter: &str,
+        path: &str,
+    ) -> Result<bool, ListStoreError> {
+        let mut removed = false;
+        self.list
+            .update(|favorites| {
+                let count = favorites.len();
+                favorites.retain(|favorite| {
+                    favorite.owner.as_deref() != owner || !favorite.is(adapter, path)
+                });
+                removed = favorites.len() != count;
+                removed
+            })
+            .await?;
+        Ok(removed)
+    }
+
+    // Favorites of `owner` on `adapter`, or on every adapter when `None`, in
+    // the order they were added
+    pub async fn list(
+        &self,
+        owner: Option<&str>,
+        adapter: Option<&str>,
+    ) -> Result<Vec<Favorite>, ListStoreError> {
+        let mut favorites = self.list.items().await?;
+        favorites.retain(|favorite| {
+            favorite.owner.as_deref() == owner
+                && adapter.is_none_or(|adapter| favorite.adapter == adapter)
+        });
+        Ok(favorites)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_store::JsonListStore;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_favorites() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("favorites.json");
+        let favorites = Favorites::new(JsonListStore::new(&file));
+
+        assert!(favorites
+            .add(Some("ann"), "local", "local://docs")
+            .await
+            .unwrap());
+        assert!(!favorites
+            .add(Some("ann"), "local", "local://docs/")
+            .await
+            .unwrap());
+        assert!(favorites
+            .add(Some("bob"), "local", "local://docs")
+            .await
+            .unwrap());
+        assert!(favorites
+            .add(Some("ann"), "media", "media://photos")
+            .await
+            .unwrap());
+
+        // Persisted across instances, each user seeing their own
+        let favorites = Favorites::new(JsonListStore::new(&file));
+        let paths = |list: Vec<Favorite>| -> Vec<String> {
+            list.into_iter().map(|favorite| favorite.path).collect()
+        };
+        assert_eq!(
+            paths(favorites.list(Some("ann"), None).await.unwrap()),
+            ["local://docs", "media://photos"]
+        );
+        assert_eq!(
+            paths(favorites.list(Some("ann"), Some("media")).await.unwrap()),
+            ["media://photos"]
+        );
+        assert!(favorites.list(None, None).await.unwrap().is_empty());
+
+        assert!(favorites
+            .remove(Some("ann"), "local", "local://docs")
+            .await
+            .unwrap());
+        assert!(!favorites
+            .remove(Some("ann"), "local", "local://docs")
+            .await
+            .unwrap());
+        assert_eq!(favorites.list(Some("bob"), None).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,325 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use url::{Host, Url};
+
+// Limits of `fetch-url`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FetchUrlConfig {
+    // Schemes URLs may have, redirects included
+    pub allowed_schemes: Vec<String>,
+    // Largest download in bytes, `max_upload_size` applies when unset
+    pub max_size: Option<u64>,
+    // Allow hosts on loopback, private and link-local addresses, which
+    // otherwise can't be reached through the server
+    pub allow_private_hosts: bool,
+    // How long a download may take, the body included
+    pub timeout_secs: u64,
+}
+
+impl Default for FetchUrlConfig {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: vec!["https".to_string()],
+            max_size: None,
+            allow_private_hosts: false,
+            timeout_secs: 5 * 60,
+        }
+    }
+}
+
+// A response being downloaded
+pub struct Download {
+    // Name from the `Content-Disposition` header
+    pub filename: Option<String>,
+    // `Content-Length` of the response
+    pub length: Option<u64>,
+    pub body: BoxStream<'static, Result<Bytes, String>>,
+}
+
+// Downloads URLs, replaceable to get them some other way
+#[async_trait]
+pub trait FetchTransport: Send + Sync {
+    // Start downloading `url`, which passed `check_url`. Redirects have to be
+    // checked with it as well.
+    async fn get(&self, url: &Url, config: &FetchUrlConfig) -> Result<Download, String>;
+}
+
+#[cfg(feature = "fetch")]
+struct HttpTransport;
+
+#[cfg(feature = "fetch")]
+#[async_trait]
+impl FetchTransport for HttpTransport {
+    async fn get(&self, url: &Url, config: &FetchUrlConfig) -> Result<Download, String> {
+        use futures_util::TryStreamExt;
+
+        let redirects = config.clone();
+        let mut client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= 10 {
+                    return attempt.error("Too many redirects");
+                }
+                match check_url(attempt.url(), &redirects) {
+                    Ok(()) => attempt.follow(),
+                    Err(message) => attempt.error(message),
+                }
+            }));
+        // Names are checked once resolved, so they can't point inside the network
+        if !config.allow_private_hosts {
+            client = client.dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = client.build().map_err(|e| e.to_string())?;
+
+        let response = client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let filename = response
+            .headers()
+            .get(reqwest::header::CONTENT_DISPOSITION)
+            .and_then(|value| disposition_filename(value.as_bytes()));
+        Ok(Download {
+            filename,
+            length: response.content_length(),
+            body: Box::pin(response.bytes_stream().map_err(|e| e.to_string())),
+        })
+    }
+}
+
+// Resolves names to their public addresses only
+#[cfg(feature = "fetch")]
+struct PublicResolver;
+
+#[cfg(feature = "fetch")]
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+// Downloads for `fetch-url`
+pub struct UrlFetcher {
+    // `None` without the `fetch` feature and a custom transport
+    transport: Option<Arc<dyn FetchTransport>>,
+}
+
+impl Default for UrlFetcher {
+    #[cfg(feature = "fetch")]
+    fn default() -> Self {
+        Self::new(HttpTransport)
+    }
+
+    #[cfg(not(feature = "fetch"))]
+    fn default() -> Self {
+        Self { transport: None }
+    }
+}
+
+impl UrlFetcher {
+    pub fn new(transport: impl FetchTransport + 'static) -> Self {
+        Self {
+            transport: Some(Arc::new(transport)),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    // Start downloading `url`, which has to have passed `check_url`
+    pub async fn fetch(&self, url: &Url, config: &FetchUrlConfig) -> Result<Download, String> {
+        let Some(transport) = &self.transport else {
+            return Err("This build doesn't have the `fetch` feature".to_string());
+        };
+        transport.get(url, config).await
+    }
+}
+
+// Whether `fetch-url` may download `url`. Hosts given by name are checked by
+// the transport once resolved.
+pub fn check_url(url: &Url, config: &FetchUrlConfig) -> Result<(), String> {
+    let scheme = url.scheme();
+    if !config
+        .allowed_schemes
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    {
+        return Err(format!("URL scheme not allowed: {}", scheme));
+    }
+    let ip = match url.host() {
+        None => return Err("URL has no host".to_string()),
+        Some(Host::Domain(_)) => return Ok(()),
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+    };
+    if !config.allow_private_hosts && !is_public(ip) {
+        return Err(format!("URL host not allowed: {}", ip));
+    }
+    Ok(())
+}
+
+// Addresses outside loopback, private, link-local and other special ranges
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // Shared address space (carrier-grade NAT) and reserved
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local and link-local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// Name a download is saved under when the request doesn't give one
+pub fn file_name(url: &Url, download: &Download) -> Option<String> {
+    download
+        .filename
+        .clone()
+        .or_else(|| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .map(percent_decode)
+        })
+        .filter(|name| !name.is_empty())
+}
+
+// `filename*`, or else `filename`, of a `Content-Disposition` header
+#[cfg(feature = "fetch")]
+fn disposition_filename(value: &[u8]) -> Option<String> {
+    use actix_web::http::header::{ContentDisposition, HeaderValue};
+
+    let value = HeaderValue::from_bytes(value).ok()?;
+    let disposition = ContentDisposition::from_raw(&value).ok()?;
+    disposition
+        .get_filename_ext()
+        .map(|ext| String::from_utf8_lossy(&ext.value).to_string())
+        .or_else(|| disposition.get_filename().map(str::to_string))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_url() {
+        let config = FetchUrlConfig::default();
+        let check =
+            |url: &str, config: &FetchUrlConfig| check_url(&Url::parse(url).unwrap(), config);
+        assert!(check("https://example.com/a.zip", &config).is_ok());
+        assert!(check("http://example.com/a.zip", &config).is_err());
+        assert!(check("file:///etc/passwd", &config).is_err());
+        assert!(check("https://93.184.215.14/a.zip", &config).is_ok());
+        for url in [
+            "https://127.0.0.1/",
+            "https://10.1.2.3/",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/",
+            "https://[::ffff:192.168.0.1]/",
+            "https://[fd00::1]/",
+        ] {
+            assert!(check(url, &config).is_err(), "{}", url);
+        }
+
+        let config = FetchUrlConfig {
+            allowed_schemes: vec!["HTTP".to_string()],
+            allow_private_hosts: true,
+            ..FetchUrlConfig::default()
+        };
+        assert!(check("http://127.0.0.1:8080/", &config).is_ok());
+        assert!(check("https://example.com/", &config).is_err());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let download = |filename: Option<&str>| Download {
+            filename: filename.map(str::to_string),
+            length: None,
+            body: Box::pin(futures_util::stream::empty()),
+        };
+        let url = Url::parse("https://example.com/files/My%20Report.pdf?v=2").unwrap();
+        assert_eq!(
+            file_name(&url, &download(None)).as_deref(),
+            Some("My Report.pdf")
+        );
+        assert_eq!(
+            file_name(&url, &download(Some("a.pdf"))).as_deref(),
+            Some("a.pdf")
+        );
+        let url = Url::parse("https://example.com/").unwrap();
+        assert_eq!(file_name(&url, &download(None)), None);
+    }
+
+    #[cfg(feature = "fetch")]
+    #[test]
+    fn test_disposition_filename() {
+        assert_eq!(
+            disposition_filename(b"attachment; filename=\"plain.txt\"").as_deref(),
+            Some("plain.txt")
+        );
+        assert_eq!(
+            disposition_filename(b"attachment; filename=a.txt; filename*=UTF-8''%C3%A9t%C3%A9.txt")
+                .as_deref(),
+            Some("été.txt")
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use crate::error::FinderError;
+use crate::finder::VueFinderConfig;
+
+// Lowercased extension of `name` without the dot, `None` for names like `.bashrc`
+fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+// Configured extensions may be written with or without the leading dot
+fn listed(extensions: &[String], ext: &str) -> bool {
+    extensions
+        .iter()
+        .any(|listed| listed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+}
+
+// `text/*` matches every text type, anything else must match exactly
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(kind) => mime
+            .split_once('/')
+            .is_some_and(|(mime_kind, _)| mime_kind.eq_ignore_ascii_case(kind)),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+fn check_mime(config: &VueFinderConfig, mime: &str) -> Result<(), FinderError> {
+    if config
+        .denied_mime_types
+        .iter()
+        .any(|pattern| mime_matches(pattern, mime))
+    {
+        return Err(FinderError::UnsupportedMediaType(format!(
+            "Files of type {} are not allowed.",
+            mime
+        )));
+    }
+    Ok(())
+}
+
+// Refuse file names the `allowed_extensions`, `denied_extensions` and
+// `denied_mime_types` rules block, the type being guessed from the extension
+pub fn check_name(config: &VueFinderConfig, name: &str) -> Result<(), FinderError> {
+    let ext = extension(name);
+    let allowed = match &ext {
+        Some(ext) => {
+            (config.allowed_extensions.is_empty() || listed(&config.allowed_extensions, ext))
+                && !listed(&config.denied_extensions, ext)
+        }
+        None => config.allowed_extensions.is_empty(),
+    };
+    if !allowed {
+        return Err(FinderError::BadRequest(match ext {
+            Some(ext) => format!("Files with the .{} extension are not allowed.", ext),
+            None => "Files without an extension are not allowed.".to_string(),
+        }));
+    }
+
+    for mime in mime_guess::from_path(name).iter() {
+        check_mime(config, mime.essence_str())?;
+    }
+    Ok(())
+}
+
+// Refuse contents whose first bytes show a denied MIME type, whatever the name says
+pub fn check_contents(config: &VueFinderConfig, sample: &[u8]) -> Result<(), FinderError> {
+    match infer::get(sample) {
+        Some(kind) => check_mime(config, kind.mime_type()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_types() {
+        let config = VueFinderConfig {
+            denied_extensions: vec![".exe".to_string(), "sh".to_string()],
+            denied_mime_types: vec![
+                "application/x-msdownload".to_string(),
+                "application/vnd.microsoft.portable-executable".to_string(),
+                "text/javascript".to_string(),
+            ],
+            ..VueFinderConfig::default()
+        };
+        assert!(check_name(&config, "notes.txt").is_ok());
+        assert!(check_name(&config, "Makefile").is_ok());
+        assert!(matches!(
+            check_name(&config, "setup.EXE"),
+            Err(FinderError::BadRequest(_))
+        ));
+        assert!(check_name(&config, "run.sh").is_err());
+        assert!(matches!(
+            check_name(&config, "app.js"),
+            Err(FinderError::UnsupportedMediaType(_))
+        ));
+
+        // A Windows program renamed to look harmless
+        assert!(check_name(&config, "photo.jpg").is_ok());
+        assert!(check_contents(&config, b"MZ\x90\x00\x03").is_err());
+        assert!(check_contents(&config, b"plain text").is_ok());
+
+        let config = VueFinderConfig {
+            allowed_extensions: vec!["png".to_string(), "jpg".to_string()],
+            ..VueFinderConfig::default()
+        };
+        assert!(check_name(&config, "photo.PNG").is_ok());
+        assert!(check_name(&config, "notes.txt").is_err());
+        assert!(check_name(&config, "README").is_err());
+    }
+}
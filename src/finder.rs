@@ -1,60 +1,627 @@
-use actix_multipart::Multipart;
-use actix_web::{web, HttpResponse};
-use futures_util::TryStreamExt;
+use base64::prelude::*;
+use bytes::Bytes;
+use figment::providers::{Env, Format, Json, Toml, Yaml};
+use figment::Figment;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use http::{header, StatusCode};
 use serde::Deserialize;
-use serde::Serialize;
-use serde_json::json;
+use std::collections::HashSet;
 use std::io::Cursor;
-use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
-use zip::{write::FileOptions, ZipWriter};
+use std::time::Duration;
 
+use crate::acl::{self, AccessRule, Permission};
+use crate::api_key::{ApiKey, ApiKeyStore};
+use crate::archive::{
+    archive_stem, entry_path, read_limited, ArchiveFormat, ArchiveReader, ArchiveWriter,
+};
+use crate::auth::{JwtConfig, UserRootConfig};
+use crate::batch::Batch;
+use crate::bulk_rename::Renamer;
+use crate::content_index::{self, ContentIndex, ContentIndexConfig};
+use crate::duplicates::find_duplicates;
+use crate::error::{storage_status, FinderError};
+use crate::favorites::Favorites;
+use crate::fetch_url::{self, FetchUrlConfig, UrlFetcher};
+use crate::file_types;
+use crate::finder_core::{FinderResponse, FinderResponseBuilder};
+use crate::folder_size::{folder_size, FolderSizes};
+use crate::hidden;
+use crate::hooks::FinderHooks;
+use crate::jobs::{JobProgress, Jobs};
+use crate::limiter::{UploadLimitMode, UploadLimiter};
+use crate::locks::{FileLock, FileLocks};
+use crate::middleware::FinderMiddleware;
 use crate::payload::{
-    ArchiveRequest, DeleteRequest, MoveRequest, NewFileRequest, NewFolderRequest, Query,
-    RenameRequest, SaveRequest, UnarchiveRequest,
+    AppendRequest, ArchiveRequest, BulkRenameRequest, CasSaveRequest, CopyBetweenAdaptersRequest,
+    CopyRequest, DeleteRequest, DiffCompare, DiffDirsRequest, DiffRequest, FavoriteRequest,
+    FetchUrlRequest, FileItem, LockRequest, MoveRequest, NewFileRequest, NewFolderRequest, Query,
+    ReadEntryRequest, RenameRequest, ReorderDirection, ReorderRequest, SaveRequest,
+    ShareCreateRequest, ShareRevokeRequest, SyncRequest, TransferRequest, TrashRequest,
+    UnarchiveRequest, UnlockRequest, UploadInitRequest, UploadSessionRequest,
+    VersionRestoreRequest,
+};
+use crate::recent::{RecentAction, RecentFiles};
+use crate::reload::ConfigCell;
+use crate::response::{
+    ArchiveEntry, ArchiveListingResponse, BulkRenameResponse, BulkRenameResult, CasSaveResponse,
+    ChecksumResponse, ContentSearchResponse, DiffDirsResponse, ErrorResponse, FavoriteResponse,
+    FavoritesResponse, FileNode, Folder, IndexResponse, InvalidEntry, JobResponse, LocksResponse,
+    MimeResponse, Pagination, RecentItem, RecentResponse, RestoreResult, SearchResponse,
+    ShareLinkResponse, ShareListResponse, StatusResponse, StorageInfo, StoragesResponse,
+    SubfoldersResponse, SyncFailure, SyncResponse, TrashListResponse, TrashRestoreResponse,
+    UploadSessionResponse, VersionResponse, VersionsListResponse,
 };
-use crate::storages::StorageAdapter;
+use crate::sanitize::{self, NameRule};
+use crate::scan::{ScanVerdict, Scanner};
+use crate::search::{relative_path, NameFilter};
+use crate::share::{NewShareLink, ShareLink, ShareLinks};
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::collision::{resolve_collision, CollisionPolicy};
+use crate::storages::config::MountConfig;
+use crate::storages::gcs::GcsConfig;
+use crate::storages::scoped::ScopedStorage;
+use crate::storages::transfer::{copy_file, copy_item, move_between, move_item, CopyProgress};
+#[cfg(windows)]
+use crate::storages::validate_windows_name;
 use crate::storages::StorageItem;
+use crate::storages::{
+    content_hash, join_path, parent_path, random_id, walk, StorageAdapter, StorageError, Storages,
+    WalkEntry,
+};
+use crate::sync;
+use crate::text_diff;
+use crate::trash::{self, TRASH_DIR};
+use crate::upload_session::{UploadSessionError, UploadSessions};
+use crate::versions::{self, VERSIONS_DIR};
+use crate::watch::WatchHub;
+use crate::webhook::{WebhookConfig, WebhookEvent, Webhooks};
+use crate::zip_stream::{zip_stream, ZipEntry};
+
+// Largest JSON and raw request bodies unless configured, 100MB
+pub const DEFAULT_BODY_LIMIT: usize = 100 * 1024 * 1024;
+
+pub const DEFAULT_EXTRACT_LIMIT: u64 = 1024 * 1024 * 1024;
 
 // Default configuration functions
 #[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
 pub struct VueFinderConfig {
     pub public_links: Option<std::collections::HashMap<String, String>>,
+    // Route of the built-in HTML directory listing, disabled when unset
+    pub html_index_path: Option<String>,
+    // Normalize names and filters to Unicode NFC before search matching
+    pub search_normalization: bool,
+    // Preview archives as a JSON listing of their entries instead of raw bytes
+    pub archive_preview: bool,
+    // Reject uploads whose part `Content-Length` differs from the bytes received
+    pub enforce_content_length: bool,
+    // Maximum number of entries visited by recursive operations
+    pub walk_limit: usize,
+    // How long `size` results are reused, computed on every request when 0
+    pub folder_size_cache_secs: u64,
+    // Reject uploads that won't fit in the storage's available space
+    pub check_disk_space: bool,
+    // On Windows, reject names with trailing dots/spaces and reserved device names
+    pub windows_name_checks: bool,
+    // Check every archive entry against the target adapter before extracting any
+    pub validate_archive_entries: bool,
+    // Concurrent uploads allowed per client, unlimited when unset
+    pub max_concurrent_uploads: Option<usize>,
+    // Whether uploads over the limit are rejected with 429 or queued
+    pub upload_limit_mode: UploadLimitMode,
+    // Send `X-Content-Type-Options: nosniff` with file contents
+    pub nosniff: bool,
+    // Window in which `watch` merges filesystem events for the same path
+    pub watch_debounce_ms: u64,
+    // How often `watch` lists storages that can't notify changes
+    pub watch_poll_interval_ms: u64,
+    // Bounding box of `thumbnail` images, aspect ratio is kept
+    pub thumbnail_max_width: u32,
+    pub thumbnail_max_height: u32,
+    // Directory for generated thumbnails, a `.thumbs` folder at each adapter's root when unset
+    pub thumbnail_cache_dir: Option<String>,
+    // Google Cloud Storage buckets to mount, needs the `gcs` feature
+    pub gcs: Vec<GcsConfig>,
+    // Adapters to mount by name, replacing the server's default local storage
+    pub storages: std::collections::HashMap<String, MountConfig>,
+    // Mount every adapter of the server binary read-only
+    pub read_only: bool,
+    // JSON file keeping share links across restarts, in memory when unset
+    pub share_store: Option<String>,
+    // JSON file keeping favorites across restarts, in memory when unset
+    pub favorites_store: Option<String>,
+    // JSON file keeping recent files across restarts, in memory when unset
+    pub recent_store: Option<String>,
+    // Recent files kept per user, 0 stops tracking them
+    pub recent_limit: usize,
+    // Notified after commands that change files, needs the `webhooks` feature
+    pub webhooks: Vec<WebhookConfig>,
+    // OTLP/HTTP traces endpoint receiving the request and storage spans, needs the `otel` feature
+    pub otlp_endpoint: Option<String>,
+    // Resumable upload sessions idle for longer than this are discarded
+    pub upload_session_ttl_secs: u64,
+    // Move deleted items to a `.trash` folder at each adapter's root instead of removing them
+    pub trash: bool,
+    // Trashed items older than this many days are purged, kept until emptied when unset
+    pub trash_retention_days: Option<u64>,
+    // Keep the previous contents of files overwritten by `save` and uploads in
+    // a `.versions` folder at each adapter's root
+    pub versioning: bool,
+    // Versions kept per file, the oldest dropped first; all of them when unset
+    pub max_versions: Option<usize>,
+    // Versions older than this many days are dropped, kept when unset
+    pub version_retention_days: Option<u64>,
+    // Require a valid bearer token on every request when set
+    pub jwt: Option<JwtConfig>,
+    // Keys for scripts, sent in `X-API-Key` instead of a token
+    pub api_keys: Vec<ApiKey>,
+    // Show each token holder only their own folder of the adapters, needs `jwt`
+    pub user_roots: Option<UserRootConfig>,
+    // Per-path permissions checked before every command, the first matching rule applies
+    pub access_rules: Vec<AccessRule>,
+    // Extensions files may have, any when empty
+    pub allowed_extensions: Vec<String>,
+    // Extensions files may not have, e.g. `exe`
+    pub denied_extensions: Vec<String>,
+    // MIME types files may not have, guessed from the name and sniffed from uploads and saves
+    pub denied_mime_types: Vec<String>,
+    // Largest JSON request body in bytes, e.g. `save` contents
+    pub json_limit: usize,
+    // Largest raw request body in bytes
+    pub payload_limit: usize,
+    // Largest uploaded file in bytes, unlimited when unset
+    pub max_upload_size: Option<u64>,
+    // Most bytes `unarchive` and `read-entry` decompress out of one archive
+    pub max_extract_size: u64,
+    // Schemes, hosts and size of downloads made by `fetch-url`, which needs the `fetch` feature
+    pub fetch_url: FetchUrlConfig,
+    // Checks on the names of new files and folders besides the ones always applied
+    pub name_rules: Vec<NameRule>,
+    // Leave names starting with a dot out of `index`, `subfolders` and `search`
+    pub hide_dotfiles: bool,
+    // Names left out of listings as well, globs such as `node_modules` or `*.db`
+    pub hidden_patterns: Vec<String>,
+    // Ignore `show_hidden=true` and refuse commands on hidden paths
+    pub enforce_hidden: bool,
+    // clamd address scanning uploads and saves, `host:port` or a Unix socket path; needs the `clamav` feature
+    pub clamd: Option<String>,
+    // Index the text of files for `search-content`, see `ContentIndexConfig`
+    pub content_index: Option<ContentIndexConfig>,
+    // PEM certificate chain and key the server binary serves HTTPS with, needs the `tls` feature
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    // How long the server binary waits for requests in flight and webhook deliveries when stopping
+    pub shutdown_timeout_secs: u64,
+    // How long a `lock` lasts unless renewed, clients may ask for less
+    pub lock_ttl_secs: u64,
+}
+
+impl Default for VueFinderConfig {
+    fn default() -> Self {
+        Self {
+            public_links: None,
+            html_index_path: None,
+            search_normalization: true,
+            archive_preview: false,
+            enforce_content_length: true,
+            walk_limit: 10_000,
+            folder_size_cache_secs: 0,
+            check_disk_space: true,
+            windows_name_checks: true,
+            validate_archive_entries: true,
+            max_concurrent_uploads: None,
+            upload_limit_mode: UploadLimitMode::default(),
+            nosniff: true,
+            watch_debounce_ms: 200,
+            watch_poll_interval_ms: 2_000,
+            thumbnail_max_width: 256,
+            thumbnail_max_height: 256,
+            thumbnail_cache_dir: None,
+            gcs: Vec::new(),
+            storages: std::collections::HashMap::new(),
+            read_only: false,
+            share_store: None,
+            favorites_store: None,
+            recent_store: None,
+            recent_limit: 50,
+            webhooks: Vec::new(),
+            otlp_endpoint: None,
+            upload_session_ttl_secs: 24 * 60 * 60,
+            trash: true,
+            trash_retention_days: Some(30),
+            versioning: false,
+            max_versions: Some(20),
+            version_retention_days: None,
+            jwt: None,
+            api_keys: Vec::new(),
+            user_roots: None,
+            access_rules: Vec::new(),
+            allowed_extensions: Vec::new(),
+            denied_extensions: Vec::new(),
+            denied_mime_types: Vec::new(),
+            json_limit: DEFAULT_BODY_LIMIT,
+            payload_limit: DEFAULT_BODY_LIMIT,
+            max_upload_size: None,
+            max_extract_size: DEFAULT_EXTRACT_LIMIT,
+            fetch_url: FetchUrlConfig::default(),
+            name_rules: vec![NameRule::Strict],
+            hide_dotfiles: false,
+            hidden_patterns: Vec::new(),
+            enforce_hidden: false,
+            clamd: None,
+            content_index: None,
+            tls_cert: None,
+            tls_key: None,
+            shutdown_timeout_secs: 30,
+            lock_ttl_secs: 5 * 60,
+        }
+    }
 }
 
+// Prefix of environment variables overriding config values, nested fields are
+// separated by `__` too, e.g. `VUEFINDER__JWT__SECRET`
+pub const ENV_PREFIX: &str = "VUEFINDER__";
+
 impl VueFinderConfig {
+    // Read a JSON, TOML (`.toml`) or YAML (`.yaml`/`.yml`) file, then apply the
+    // `VUEFINDER__*` environment variables
     pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let extension = Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
         let content = std::fs::read_to_string(path)?;
-        let config: VueFinderConfig = serde_json::from_str(&content)?;
-        Ok(config)
+        let figment = match extension.as_deref() {
+            Some("toml") => Figment::from(Toml::string(&content)),
+            Some("yaml" | "yml") => Figment::from(Yaml::string(&content)),
+            _ => Figment::from(Json::string(&content)),
+        };
+        Ok(figment
+            .merge(Env::prefixed(ENV_PREFIX).split("__"))
+            .extract()?)
+    }
+
+    // The defaults with the `VUEFINDER__*` environment variables applied
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Figment::new()
+            .merge(Env::prefixed(ENV_PREFIX).split("__"))
+            .extract()?)
     }
 }
 
-impl Default for VueFinderConfig {
+#[derive(Clone)]
+pub struct VueFinder {
+    pub storages: Storages,
+    pub config: Arc<VueFinderConfig>,
+    pub middlewares: Arc<Vec<Arc<dyn FinderMiddleware>>>,
+    pub hooks: Arc<Vec<Arc<dyn FinderHooks>>>,
+    pub upload_limiter: Arc<UploadLimiter>,
+    pub upload_sessions: Arc<UploadSessions>,
+    pub watchers: Arc<WatchHub>,
+    pub folder_sizes: Arc<FolderSizes>,
+    pub jobs: Arc<Jobs>,
+    pub locks: Arc<FileLocks>,
+    pub shares: Arc<ShareLinks>,
+    pub favorites: Arc<Favorites>,
+    pub recent: Arc<RecentFiles>,
+    pub webhooks: Arc<Webhooks>,
+    pub fetcher: Arc<UrlFetcher>,
+    pub api_key_store: Option<Arc<dyn ApiKeyStore>>,
+    pub scanner: Option<Arc<dyn Scanner>>,
+    pub content_index: Option<Arc<dyn ContentIndex>>,
+    // Replaces `config` once reloaded, see `reload::watch_config`
+    pub live_config: Option<Arc<ConfigCell>>,
+    // Who the current request acts as, set per request, see `auth::principal`
+    pub principal: Option<String>,
+    // Paths the current request wrote or deleted, for its webhook event
+    pub changed_paths: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl Default for VueFinder {
     fn default() -> Self {
-        Self { public_links: None }
+        Self {
+            storages: Arc::new(std::collections::HashMap::new()),
+            config: Arc::new(VueFinderConfig::default()),
+            middlewares: Arc::new(Vec::new()),
+            hooks: Arc::new(Vec::new()),
+            upload_limiter: Arc::new(UploadLimiter::default()),
+            upload_sessions: Arc::new(UploadSessions::default()),
+            watchers: Arc::new(WatchHub::default()),
+            folder_sizes: Arc::new(FolderSizes::default()),
+            jobs: Arc::new(Jobs::default()),
+            locks: Arc::new(FileLocks::default()),
+            shares: Arc::new(ShareLinks::default()),
+            favorites: Arc::new(Favorites::default()),
+            recent: Arc::new(RecentFiles::default()),
+            webhooks: Arc::new(Webhooks::default()),
+            fetcher: Arc::new(UrlFetcher::default()),
+            api_key_store: None,
+            scanner: None,
+            content_index: None,
+            live_config: None,
+            principal: None,
+            changed_paths: Arc::default(),
+        }
     }
 }
 
-#[derive(Debug, Serialize)]
-struct FileNode {
-    #[serde(flatten)]
-    storage_item: StorageItem,
-    url: Option<String>,
-    // search result supported
-    dir: Option<String>,
+// Check an entry against the `min_size`/`max_size` query bounds, sizeless folders never match
+fn in_size_range(query: &Query, item: &StorageItem) -> bool {
+    if query.min_size.is_none() && query.max_size.is_none() {
+        return true;
+    }
+
+    match item.size {
+        Some(size) => {
+            query.min_size.is_none_or(|min| size >= min)
+                && query.max_size.is_none_or(|max| size <= max)
+        }
+        None => false,
+    }
 }
 
-#[derive(Clone)]
-pub struct VueFinder {
-    pub storages: Arc<std::collections::HashMap<String, Arc<dyn StorageAdapter>>>,
-    pub config: Arc<VueFinderConfig>,
+// Check an entry against the `modified_after`/`modified_before` query bounds.
+// Entries without a modification time never match an active window.
+fn in_modified_window(query: &Query, item: &StorageItem) -> bool {
+    if query.modified_after.is_none() && query.modified_before.is_none() {
+        return true;
+    }
+
+    match item.last_modified {
+        Some(modified) => {
+            query.modified_after.is_none_or(|after| modified >= after)
+                && query
+                    .modified_before
+                    .is_none_or(|before| modified <= before)
+        }
+        None => false,
+    }
+}
+
+// Sidecar listing basenames in their manual order
+const ORDER_FILE: &str = ".order.json";
+
+fn order_file_path(dirname: &str) -> String {
+    format!("{}/{}", dirname, ORDER_FILE)
+}
+
+// Folder at each adapter's root caching generated thumbnails
+const THUMBS_DIR: &str = ".thumbs";
+
+// Folder at each adapter's root holding uploads until they're scanned
+const UPLOADS_DIR: &str = ".uploads";
+
+// Folders at each adapter's root holding the server's own data. Only their
+// commands reach them, which check access to the original paths.
+const INTERNAL_DIRS: [&str; 4] = [THUMBS_DIR, TRASH_DIR, VERSIONS_DIR, UPLOADS_DIR];
+
+// Whether `path` is inside one of the `INTERNAL_DIRS`, or climbs above the root
+fn is_internal(path: &str) -> bool {
+    match acl::split(path) {
+        Some((_, relative)) => relative
+            .split('/')
+            .nth(1)
+            .is_some_and(|name| INTERNAL_DIRS.contains(&name)),
+        None => true,
+    }
+}
+
+// Shrink an image to fit the bounding box and encode it, small images are only re-encoded
+fn render_thumbnail(
+    contents: &[u8],
+    max_width: u32,
+    max_height: u32,
+    format: image::ImageFormat,
+) -> image::ImageResult<Vec<u8>> {
+    let mut image = image::load_from_memory(contents)?;
+    if image.width() > max_width || image.height() > max_height {
+        image = image.thumbnail(max_width, max_height);
+    }
+    // JPEG has no alpha channel
+    if format == image::ImageFormat::Jpeg {
+        image = image::DynamicImage::ImageRgb8(image.to_rgb8());
+    }
+
+    let mut encoded = Cursor::new(Vec::new());
+    image.write_to(&mut encoded, format)?;
+    Ok(encoded.into_inner())
+}
+
+// A missing or malformed sidecar means no manual order
+async fn read_manual_order(storage: &dyn StorageAdapter, dirname: &str) -> Vec<String> {
+    match storage.read(&order_file_path(dirname)).await {
+        Ok(contents) => serde_json::from_slice(&contents).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Entries listed in the sidecar come first in that order, the rest follow alphabetically
+fn sort_manually(files: &mut [FileNode], order: &[String]) {
+    files.sort_by(|a, b| {
+        let position = |node: &FileNode| {
+            order
+                .iter()
+                .position(|name| *name == node.storage_item.basename)
+                .unwrap_or(usize::MAX)
+        };
+        position(a)
+            .cmp(&position(b))
+            .then_with(|| a.storage_item.basename.cmp(&b.storage_item.basename))
+    });
+}
+
+// Page size of `index` when only `page` is given
+const DEFAULT_PER_PAGE: usize = 100;
+
+// Order a listing by `query.sort` (`name`, `size` or `mtime`) in `query.order`
+// (`asc` or `desc`). Listings keep the adapter's order when neither is given.
+fn sort_listing(files: &mut [FileNode], query: &Query) -> Result<(), String> {
+    let descending = match query.order.as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(order) => return Err(format!("Unknown order: {}", order)),
+    };
+    let sort = match (query.sort.as_deref(), query.order.is_some()) {
+        (None, false) => return Ok(()),
+        (None, true) => "name",
+        (Some(sort), _) => sort,
+    };
+
+    let name = |node: &FileNode| node.storage_item.basename.to_lowercase();
+    match sort {
+        "name" => files.sort_by_cached_key(name),
+        "size" => files.sort_by_key(|node| node.storage_item.size.unwrap_or(0)),
+        "mtime" => files.sort_by_key(|node| node.storage_item.last_modified.unwrap_or(0)),
+        _ => return Err(format!("Unknown sort: {}", sort)),
+    }
+    if descending {
+        files.reverse();
+    }
+    Ok(())
+}
+
+// The one range of a `Range` header. Several ranges would need a multipart
+// body, those get the whole file instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteRange {
+    // `bytes=start-` or `bytes=start-end`
+    From(u64, Option<u64>),
+    // `bytes=-length`, the end of the file
+    Last(u64),
+}
+
+impl std::str::FromStr for ByteRange {
+    type Err = ();
+
+    fn from_str(header: &str) -> Result<Self, ()> {
+        let spec = header.trim().strip_prefix("bytes=").ok_or(())?;
+        if spec.contains(',') {
+            return Err(());
+        }
+        let (start, end) = spec.split_once('-').ok_or(())?;
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            return end.parse().map(ByteRange::Last).map_err(|_| ());
+        }
+        let start = start.parse().map_err(|_| ())?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse().map_err(|_| ())?),
+        };
+        match end {
+            Some(end) if end < start => Err(()),
+            _ => Ok(ByteRange::From(start, end)),
+        }
+    }
+}
+
+impl ByteRange {
+    // First and last byte of the range in a file of `size` bytes, `None` when
+    // it starts past the end
+    pub fn satisfiable(self, size: u64) -> Option<(u64, u64)> {
+        match self {
+            ByteRange::From(start, end) if start < size => {
+                Some((start, end.map_or(size - 1, |end| end.min(size - 1))))
+            }
+            ByteRange::Last(length) if length > 0 && size > 0 => {
+                Some((size - length.min(size), size - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Number of leading bytes inspected to detect a text charset
+const CHARSET_SAMPLE: usize = 8 * 1024;
+
+// Textual types whose rendering depends on a charset
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json" | "application/javascript" | "application/xml"
+        )
+}
+
+// Charset of a text sample from its BOM or UTF-8 validity. A sample cut in
+// the middle of a character still counts as UTF-8.
+fn detect_charset(sample: &[u8]) -> Option<&'static str> {
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return Some("utf-16le");
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return Some("utf-16be");
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => Some("utf-8"),
+        Err(e) if e.error_len().is_none() => Some("utf-8"),
+        Err(_) => None,
+    }
+}
+
+// Content type for a file body, with a `charset` parameter on text types
+fn content_type(mime: &mime_guess::Mime, contents: &[u8]) -> String {
+    let sample = &contents[..contents.len().min(CHARSET_SAMPLE)];
+    match detect_charset(sample) {
+        Some(charset) if is_text_mime(mime.essence_str()) => {
+            format!("{}; charset={}", mime.essence_str(), charset)
+        }
+        _ => mime.to_string(),
+    }
+}
+
+// Answer for uploads over `max_upload_size`
+fn upload_too_large(limit: u64) -> FinderResponse {
+    FinderResponse::PayloadTooLarge().json(ErrorResponse::new(format!(
+        "Upload exceeds the maximum size of {} bytes.",
+        limit
+    )))
+}
+
+// Answer for an archive that can't be read, `413` past `max_extract_size`
+fn archive_error(error: std::io::Error, status: StatusCode, context: &str) -> FinderResponse {
+    let status = match error.kind() {
+        std::io::ErrorKind::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => status,
+    };
+    FinderResponse::build(status).json(ErrorResponse::new(format!("{}: {}", context, error)))
+}
+
+// A stream error means the client went away mid-upload
+fn upload_interrupted(error: multer::Error) -> FinderResponse {
+    FinderResponse::BadRequest().json(ErrorResponse::new(format!("Upload interrupted: {}", error)))
+}
+
+// Split a listing's `files` into `dirs` and `files`, keeping the order within each
+fn group_by_type(body: &mut IndexResponse) {
+    let (dirs, files) = std::mem::take(&mut body.files)
+        .into_iter()
+        .partition(|node| node.storage_item.node_type == "dir");
+    body.dirs = Some(dirs);
+    body.files = files;
+}
+
+// Replace control characters in names we don't control, such as archive entries
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_control() { '_' } else { c })
+        .collect()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
 
 // Request handling functions
 impl VueFinder {
-    fn get_default_adapter(&self, adapter: Option<String>) -> String {
+    pub(crate) fn get_default_adapter(&self, adapter: Option<String>) -> String {
         // If adapter is empty, return the first available adapter
         if let Some(adapter) = adapter {
             if self.storages.contains_key(&adapter) {
@@ -66,17 +633,163 @@ impl VueFinder {
         self.storages.keys().next().cloned().unwrap_or_default()
     }
 
+    // The name to store for a user-supplied one, see `name_rules`
+    fn clean_name(&self, name: &str) -> Result<String, String> {
+        let name = sanitize::sanitize(&self.config.name_rules, name)?;
+        #[cfg(windows)]
+        if self.config.windows_name_checks {
+            validate_windows_name(&name)?;
+        }
+        Ok(name)
+    }
+
     fn set_public_links(&self, node: &mut FileNode) {
-        if let Some(public_links) = &self.config.public_links {
-            if node.storage_item.node_type != "dir" {
-                for (public_link, domain) in public_links {
-                    if node.storage_item.path.starts_with(public_link) {
-                        node.url = Some(node.storage_item.path.replace(public_link, domain));
-                        break;
-                    }
-                }
+        if node.storage_item.node_type != "dir" {
+            node.url = self.public_url(&node.storage_item.path);
+        }
+    }
+
+    // Public URL of a path under one of the configured `public_links` prefixes
+    fn public_url(&self, path: &str) -> Option<String> {
+        let public_links = self.config.public_links.as_ref()?;
+        public_links
+            .iter()
+            .find(|(public_link, _)| path.starts_with(public_link.as_str()))
+            .map(|(public_link, domain)| path.replace(public_link.as_str(), domain))
+    }
+
+    // The first path `command` may not touch: one inside the `INTERNAL_DIRS`,
+    // one denied by `access_rules` or, with `enforce_hidden`, a hidden one
+    pub(crate) fn denied_path(
+        &self,
+        command: &str,
+        query: &Query,
+        payload: Option<&serde_json::Value>,
+    ) -> Option<String> {
+        let adapter = self.get_default_adapter(query.adapter.clone());
+        // The trash commands name the trash itself
+        if !command.starts_with("trash/") {
+            let internal = acl::command_access(command, query, payload, &adapter)
+                .into_iter()
+                .flatten()
+                .map(|access| access.path)
+                .find(|path| is_internal(path));
+            if internal.is_some() {
+                return internal;
+            }
+        }
+        acl::denied(&self.config.access_rules, command, query, payload, &adapter).or_else(|| {
+            if !self.config.enforce_hidden {
+                return None;
             }
+            acl::command_access(command, query, payload, &adapter)
+                .into_iter()
+                .flatten()
+                .map(|access| access.path)
+                .find(|path| hidden::is_hidden(&self.config, query.show_hidden, path))
+        })
+    }
+
+    // The config to handle a request with, the reloaded one if it changed
+    pub(crate) fn current_config(&self) -> Arc<VueFinderConfig> {
+        match &self.live_config {
+            Some(live_config) => live_config.get(),
+            None => self.config.clone(),
+        }
+    }
+
+    // This finder with the config reloaded since it was built
+    pub(crate) fn refreshed(data: Arc<Self>) -> Arc<Self> {
+        let config = data.current_config();
+        if Arc::ptr_eq(&config, &data.config) {
+            return data;
+        }
+        Arc::new(Self {
+            config,
+            ..data.as_ref().clone()
+        })
+    }
+
+    // This finder as seen by the user whose folder is `root`: the adapters
+    // selected by `config` only show that folder
+    pub(crate) fn for_user(&self, config: &UserRootConfig, root: &str) -> Self {
+        let storages = self
+            .storages
+            .iter()
+            .map(|(name, storage)| {
+                let storage: Arc<dyn StorageAdapter> = if config.applies_to(name) {
+                    Arc::new(ScopedStorage::new(storage.clone(), root))
+                } else {
+                    storage.clone()
+                };
+                (name.clone(), storage)
+            })
+            .collect();
+        Self {
+            storages: Arc::new(storages),
+            ..self.clone()
+        }
+    }
+
+    // Shared `path` of `adapter` as the request's user knows it, `None` when
+    // it's outside of what they see
+    fn visible_path(&self, adapter: &str, path: &str) -> Option<String> {
+        let Some(storage) = self.storages.get(adapter) else {
+            return Some(path.to_string());
+        };
+        let root = storage.shared_path(&format!("{}://", adapter));
+        let scheme = format!("{}://", adapter);
+        if root == scheme {
+            return Some(path.to_string());
+        }
+        let key = path.strip_prefix(root.trim_end_matches('/'))?;
+        if !key.is_empty() && !key.starts_with('/') {
+            return None;
         }
+        Some(format!("{}{}", scheme, key.trim_start_matches('/')))
+    }
+
+    fn visible_link(&self, mut link: ShareLink) -> Option<ShareLink> {
+        link.path = self.visible_path(&link.adapter, &link.path)?;
+        Some(link)
+    }
+
+    // Who holds the locks this request takes
+    fn lock_owner(&self) -> &str {
+        self.principal.as_deref().unwrap_or("anonymous")
+    }
+
+    // `Locked` when someone else holds a lock on `path` or a file below it
+    async fn check_lock(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderError> {
+        self.locks
+            .check(
+                &storage.name(),
+                &storage.shared_path(path),
+                self.lock_owner(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Hidden items and those without read permission are left out of listings
+    fn visible(&self, query: &Query, path: &str) -> bool {
+        acl::allowed(&self.config.access_rules, path, Permission::Read)
+            && !hidden::is_hidden(&self.config, query.show_hidden, path)
+    }
+
+    // Whether a command reading everything below a folder leaves out `path`:
+    // the `INTERNAL_DIRS`, which only their own commands reach, what the access
+    // rules don't let read (wildcard rules aren't seen by `allowed_recursive`)
+    // and with `enforce_hidden` hidden items, as unreachable as by path
+    fn walk_skips(&self, query: &Query, path: &str) -> bool {
+        is_internal(path)
+            || !acl::allowed(&self.config.access_rules, path, Permission::Read)
+            || (self.config.enforce_hidden
+                && hidden::is_hidden(&self.config, query.show_hidden, path))
     }
 
     fn get_storage(&self, adapter: Option<String>) -> Option<&Arc<dyn StorageAdapter>> {
@@ -87,675 +800,7378 @@ impl VueFinder {
         })
     }
 
-    pub async fn index(data: web::Data<VueFinder>, query: web::Query<Query>) -> HttpResponse {
-        let adapter = data.get_default_adapter(query.adapter.clone());
+    // Run the `before_write` hooks, the first refusal is the response
+    async fn hook_before_write(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderResponse> {
+        self.check_write(storage, path)
+            .await
+            .map_err(|e| e.error_response())
+    }
+
+    // The access rules', locks' and hooks' verdict on writing `path`, for
+    // commands reporting it per item. The rules are checked again here as a
+    // command's checked paths don't include the names it resolves itself.
+    async fn check_write(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderError> {
+        if !acl::allowed(&self.config.access_rules, path, Permission::Write) {
+            return Err(FinderError::Forbidden(format!(
+                "Permission denied: {}",
+                path
+            )));
+        }
+        self.check_lock(storage, path).await?;
+        for hook in self.hooks.iter() {
+            hook.before_write(&storage.name(), path).await?;
+        }
+        Ok(())
+    }
+
+    // `Forbidden` when copying the folder `source` to `target` would create a
+    // path the access rules don't let write
+    async fn check_tree_write(
+        &self,
+        from: &dyn StorageAdapter,
+        source: &str,
+        target: &str,
+    ) -> Result<(), FinderError> {
+        if self.config.access_rules.is_empty() || !from.is_dir(source).await.unwrap_or(false) {
+            return Ok(());
+        }
+        for entry in walk(from, source, self.config.walk_limit).await? {
+            let path = join_path(target, &entry.relative_path);
+            if !acl::allowed(&self.config.access_rules, &path, Permission::Write) {
+                return Err(FinderError::Forbidden(format!(
+                    "Permission denied: {}",
+                    path
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn hook_after_write(&self, storage: &dyn StorageAdapter, path: &str) {
+        self.changed_paths.lock().unwrap().push(path.to_string());
+        self.index_content(storage, path).await;
+        self.remember(storage, path, RecentAction::Modified).await;
+        for hook in self.hooks.iter() {
+            hook.after_write(&storage.name(), path).await;
+        }
+    }
+
+    async fn hook_before_delete(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderResponse> {
+        for hook in self.hooks.iter() {
+            if let Err(e) = hook.before_delete(&storage.name(), path).await {
+                return Err(e.error_response());
+            }
+        }
+        Ok(())
+    }
+
+    async fn hook_after_delete(&self, storage: &dyn StorageAdapter, path: &str) {
+        self.changed_paths.lock().unwrap().push(path.to_string());
+        self.unindex_content(storage, path).await;
+        if let Err(e) = self
+            .recent
+            .forget(&storage.name(), &storage.shared_path(path))
+            .await
+        {
+            log::warn!("Failed to drop {} from recent files: {}", path, e);
+        }
+        for hook in self.hooks.iter() {
+            hook.after_delete(&storage.name(), path).await;
+        }
+    }
+
+    // Bring the `content_index` up to date with what's now at `path`. Failures
+    // leave it stale until the next `content-reindex`.
+    async fn index_content(&self, storage: &dyn StorageAdapter, path: &str) {
+        let (Some(index), Some(config)) = (&self.content_index, &self.config.content_index) else {
+            return;
+        };
+        let indexed = content_index::update(
+            index.as_ref(),
+            storage,
+            path,
+            config.max_file_size,
+            self.config.walk_limit,
+        )
+        .await;
+        if let Err(e) = indexed {
+            log::warn!("Failed to index contents of {}: {}", path, e);
+        }
+    }
+
+    async fn unindex_content(&self, storage: &dyn StorageAdapter, path: &str) {
+        let Some(index) = &self.content_index else {
+            return;
+        };
+        if let Err(e) = index
+            .remove(&storage.name(), &storage.shared_path(path))
+            .await
+        {
+            log::warn!("Failed to drop contents of {} from the index: {}", path, e);
+        }
+    }
+
+    // Put `path` first in the request's user's recent files. Failures only
+    // lose the entry.
+    async fn remember(&self, storage: &dyn StorageAdapter, path: &str, action: RecentAction) {
+        let limit = self.config.recent_limit;
+        if limit == 0 {
+            return;
+        }
+        let recorded = self
+            .recent
+            .record(
+                self.principal.as_deref(),
+                &storage.name(),
+                &storage.shared_path(path),
+                action,
+                limit,
+            )
+            .await;
+        if let Err(e) = recorded {
+            log::warn!("Failed to record {} in recent files: {}", path, e);
+        }
+    }
+
+    // Count the file a download or preview answered with as opened. Seeking
+    // within it doesn't count as opening it again.
+    async fn remember_opened(&self, query: &Query, status: StatusCode, headers: &http::HeaderMap) {
+        let seeking = headers
+            .get(header::CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .is_some_and(|range| !range.starts_with("bytes 0-"));
+        if !status.is_success() || seeking {
+            return;
+        }
+        let adapter = query.adapter.clone().unwrap_or_default();
+        if let Some(storage) = self.storages.get(&adapter) {
+            let path = query.path.clone().unwrap_or_default();
+            self.remember(storage.as_ref(), &path, RecentAction::Opened)
+                .await;
+        }
+    }
+
+    async fn hook_before_download(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderResponse> {
+        for hook in self.hooks.iter() {
+            if let Err(e) = hook.before_download(&storage.name(), path).await {
+                return Err(e.error_response());
+            }
+        }
+        Ok(())
+    }
+
+    // Pass `contents` meant for `path` through the `scanner`, if any. Infected
+    // files are logged and sent to the webhooks as an `infected` event.
+    async fn scan(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+        contents: BoxStream<'static, Result<Bytes, StorageError>>,
+    ) -> Result<(), FinderResponse> {
+        let Some(scanner) = &self.scanner else {
+            return Ok(());
+        };
+        let signature = match scanner.scan(contents).await {
+            Ok(ScanVerdict::Clean) => return Ok(()),
+            Ok(ScanVerdict::Infected(signature)) => signature,
+            Err(e) => {
+                log::error!("Cannot scan {}: {}", path, e);
+                let error = FinderError::Internal("The file could not be scanned.".to_string());
+                return Err(error.error_response());
+            }
+        };
+
+        log::warn!("Refused {}, infected with {}", path, signature);
+        let event = WebhookEvent::new(
+            "infected",
+            Some(storage.name()),
+            Some(path.to_string()),
+            serde_json::json!({ "signature": signature }),
+        );
+        self.webhooks.spawn_notify(&self.config.webhooks, event);
+        let error = FinderError::Infected(format!("File is infected with {}.", signature));
+        Err(error.error_response())
+    }
+
+    // Scan contents about to be written to `path`
+    async fn scan_contents(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+        contents: &[u8],
+    ) -> Result<(), FinderResponse> {
+        if self.scanner.is_none() {
+            return Ok(());
+        }
+        let contents = Bytes::copy_from_slice(contents);
+        let stream = futures_util::stream::once(async move { Ok(contents) }).boxed();
+        self.scan(storage, path, stream).await
+    }
+
+    // Where a file streamed to `path` is written: a staging file under
+    // `UPLOADS_DIR` until it's scanned when there's a `scanner`, `path` itself
+    // otherwise
+    fn staging_path(&self, storage: &dyn StorageAdapter, path: &str) -> String {
+        if self.scanner.is_none() {
+            return path.to_string();
+        }
+        format!("{}://{}/{}", storage.name(), UPLOADS_DIR, random_id())
+    }
+
+    // Scan the file streamed to `staged` and move it to `path` once it's
+    // clean, so refused files never show up at `path`
+    async fn scan_written(
+        &self,
+        storage: &dyn StorageAdapter,
+        staged: &str,
+        path: &str,
+    ) -> Result<(), FinderResponse> {
+        if staged == path {
+            return Ok(());
+        }
+        let scanned = match storage.read_stream(staged).await {
+            Ok(stream) => self.scan(storage, path, stream).await,
+            Err(e) => Err(FinderError::from(e).error_response()),
+        };
+        let moved = match scanned {
+            Ok(()) => move_item(storage, staged, path, self.config.walk_limit)
+                .await
+                .map_err(|e| FinderError::from(e).error_response()),
+            Err(response) => Err(response),
+        };
+        if moved.is_err() {
+            self.discard_staged(storage, staged, path).await;
+        }
+        moved
+    }
+
+    // Remove what was staged for `path` after a failed write
+    async fn discard_staged(&self, storage: &dyn StorageAdapter, staged: &str, path: &str) {
+        if staged == path {
+            return;
+        }
+        if let Err(e) = storage.delete(staged).await {
+            log::error!("Cannot remove staged upload {}: {}", staged, e);
+        }
+    }
+
+    pub async fn index(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        match data.list_directory(&query).await {
+            Ok(mut body) => {
+                if query.group_by.as_deref() == Some("type") {
+                    group_by_type(&mut body);
+                }
+                FinderResponse::Ok().json(body)
+            }
+            Err(response) => response,
+        }
+    }
+
+    // Build the index listing for the queried directory
+    async fn list_directory(&self, query: &Query) -> Result<IndexResponse, FinderResponse> {
+        let adapter = self.get_default_adapter(query.adapter.clone());
         let dirname = query
             .path
             .clone()
             .unwrap_or_else(|| format!("{}://", adapter));
 
         // Get directory contents
-        let storage = match data.get_storage(query.adapter.clone()) {
+        let storage = match self.get_storage(query.adapter.clone()) {
             Some(s) => s,
             None => {
-                return HttpResponse::BadRequest().json(json!({
-                    "status": false,
-                    "message": "No storage adapters available"
-                }))
+                return Err(FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("No storage adapters available")))
             }
         };
 
-        let list_contents = match storage.list_contents(&dirname).await {
-            Ok(contents) => contents,
+        // Tell a missing directory apart from an empty one
+        match storage.is_dir(&dirname).await {
+            Ok(true) => {}
+            Ok(false) if storage.exists(&dirname).await.unwrap_or(false) => {
+                return Err(FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("Path is not a directory.")))
+            }
+            Ok(false) => {
+                return Err(
+                    FinderResponse::NotFound().json(ErrorResponse::new("Directory not found."))
+                )
+            }
             Err(e) => {
-                return HttpResponse::InternalServerError().json(json!({
-                    "status": false,
-                    "message": e.to_string()
-                }))
+                return Err(FinderResponse::BadRequest().json(ErrorResponse::new(e.to_string())))
             }
+        }
+
+        let list_contents = match storage.list_contents(&dirname).await {
+            Ok(contents) => contents,
+            Err(e) => return Err(FinderError::from(e).error_response()),
         };
 
         // Convert to FileNode
-        let files: Vec<FileNode> = list_contents
+        let mut files: Vec<FileNode> = list_contents
             .into_iter()
+            .filter(|item| {
+                item.basename != ORDER_FILE
+                    && !INTERNAL_DIRS
+                        .iter()
+                        .any(|dir| item.path == join_path(&format!("{}://", storage.name()), dir))
+                    && in_modified_window(query, item)
+                    && self.visible(query, &item.path)
+            })
             .map(|item| {
                 let mut node = FileNode {
                     storage_item: item,
                     url: None,
                     dir: None,
                 };
-                data.set_public_links(&mut node);
+                self.set_public_links(&mut node);
                 node
             })
             .collect();
 
-        HttpResponse::Ok().json(json!({
-            "adapter": adapter,
-            "storages": data.storages.keys().collect::<Vec<_>>(),
-            "dirname": dirname,
-            "files": files
-        }))
+        if query.order.as_deref() == Some("manual") {
+            let order = read_manual_order(storage.as_ref(), &dirname).await;
+            sort_manually(&mut files, &order);
+        } else if let Err(message) = sort_listing(&mut files, query) {
+            return Err(FinderResponse::BadRequest().json(ErrorResponse::new(message)));
+        }
+        if query.dirs_first.unwrap_or(false) {
+            // Stable, so the order within dirs and files is kept
+            files.sort_by_key(|node| node.storage_item.node_type != "dir");
+        }
+
+        let mut pagination = None;
+        if query.page.is_some() || query.per_page.is_some() {
+            let total = files.len();
+            let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+            let page = query.page.unwrap_or(1);
+            if per_page == 0 || page == 0 {
+                return Err(FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("`page` and `per_page` start at 1.")));
+            }
+            files = files
+                .into_iter()
+                .skip((page - 1).saturating_mul(per_page))
+                .take(per_page)
+                .collect();
+            pagination = Some(Pagination {
+                page,
+                per_page,
+                total,
+                total_pages: total.div_ceil(per_page),
+            });
+        }
+
+        Ok(IndexResponse {
+            adapter,
+            storages: self.storages.keys().cloned().collect(),
+            dirname,
+            files,
+            dirs: None,
+            pagination,
+            item: None,
+        })
     }
 
-    pub async fn sub_folders(data: web::Data<VueFinder>, query: web::Query<Query>) -> HttpResponse {
-        let adapter = data.get_default_adapter(query.adapter.clone());
-        let dirname = query.path.clone().unwrap_or_default();
+    pub async fn html_index(
+        data: Arc<VueFinder>,
+        query: Query,
+        api_path: &str,
+        index_path: &str,
+    ) -> FinderResponse {
+        let body = match data.list_directory(&query).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
 
-        let storage = match data.storages.get(&adapter) {
-            Some(s) => s,
-            None => {
-                return HttpResponse::BadRequest().json(json!({
-                    "status": false,
-                    "message": "Invalid storage adapter"
-                }))
-            }
+        let adapter = body.adapter.as_str();
+        let dirname = body.dirname.as_str();
+        let link = |base: &str, params: &[(&str, &str)]| {
+            format!(
+                "{}?{}",
+                base,
+                serde_urlencoded::to_string(params).unwrap_or_default()
+            )
         };
 
-        match storage.list_contents(&dirname).await {
-            Ok(contents) => {
-                let folders: Vec<_> = contents
-                    .into_iter()
-                    .filter(|item| item.node_type == "dir")
-                    .map(|item| {
-                        json!({
-                            "adapter": adapter,
-                            "path": item.path,
-                            "basename": item.basename,
-                        })
-                    })
-                    .collect();
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n<h1>{}</h1>\n<ul>\n",
+            escape_html(dirname),
+            escape_html(dirname)
+        );
 
-                HttpResponse::Ok().json(json!({ "folders": folders }))
+        // Link back to the parent directory
+        if let Some((scheme, rest)) = dirname.split_once("://") {
+            let rest = rest.trim_matches('/');
+            if !rest.is_empty() {
+                let parent = match rest.rsplit_once('/') {
+                    Some((parent, _)) => format!("{}://{}", scheme, parent),
+                    None => format!("{}://", scheme),
+                };
+                let href = link(index_path, &[("adapter", adapter), ("path", &parent)]);
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">..</a></li>\n",
+                    escape_html(&href)
+                ));
             }
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
         }
+
+        for file in &body.files {
+            let path = file.storage_item.path.as_str();
+            let basename = escape_html(&file.storage_item.basename);
+
+            if file.storage_item.node_type == "dir" {
+                let href = link(index_path, &[("adapter", adapter), ("path", path)]);
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">{}/</a></li>\n",
+                    escape_html(&href),
+                    basename
+                ));
+            } else {
+                let download = link(
+                    api_path,
+                    &[("q", "download"), ("adapter", adapter), ("path", path)],
+                );
+                let preview = link(
+                    api_path,
+                    &[("q", "preview"), ("adapter", adapter), ("path", path)],
+                );
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a> (<a href=\"{}\">preview</a>)</li>\n",
+                    escape_html(&download),
+                    basename,
+                    escape_html(&preview)
+                ));
+            }
+        }
+
+        html.push_str("</ul>\n</body>\n</html>\n");
+
+        FinderResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(html)
     }
 
-    pub async fn download(data: web::Data<VueFinder>, query: web::Query<Query>) -> HttpResponse {
-        let storage = match data
-            .storages
-            .get(&query.adapter.clone().unwrap_or_default())
-        {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
-        };
+    pub async fn version(data: Arc<VueFinder>) -> FinderResponse {
+        let mut features = Vec::new();
+        if cfg!(feature = "binary") {
+            features.push("binary");
+        }
+        if cfg!(feature = "gcs") {
+            features.push("gcs");
+        }
+        if cfg!(feature = "dropbox") {
+            features.push("dropbox");
+        }
+        if cfg!(feature = "gdrive") {
+            features.push("gdrive");
+        }
+        if cfg!(feature = "sqlite") {
+            features.push("sqlite");
+        }
+        if cfg!(feature = "opendal") {
+            features.push("opendal");
+        }
+        if cfg!(feature = "fulltext") {
+            features.push("fulltext");
+        }
+        if cfg!(feature = "fetch") {
+            features.push("fetch");
+        }
 
-        match storage.read(&query.path.clone().unwrap_or_default()).await {
-            Ok(contents) => {
-                let path = query.path.clone().unwrap_or_default();
-                let filename = Path::new(&path)
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy();
+        FinderResponse::Ok().json(VersionResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: option_env!("VUEFINDER_GIT_HASH").map(str::to_string),
+            adapters: data.storages.keys().cloned().collect(),
+            features: features.into_iter().map(str::to_string).collect(),
+        })
+    }
 
-                let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    // Every adapter with what it supports, so clients can leave out actions
+    // that don't work on it
+    pub async fn storages(data: Arc<VueFinder>) -> FinderResponse {
+        let mut names: Vec<_> = data.storages.keys().cloned().collect();
+        names.sort();
 
-                HttpResponse::Ok()
-                    .content_type(mime.as_ref())
-                    .append_header((
-                        "Content-Disposition",
-                        format!("attachment; filename=\"{}\"", filename),
-                    ))
-                    .body(contents)
-            }
-            Err(_) => HttpResponse::NotFound().finish(),
+        let mut storages = Vec::new();
+        for name in names {
+            let storage = &data.storages[&name];
+            let mut capabilities = storage.capabilities();
+            let scheme = format!("{}://", name);
+            capabilities.public_urls |= data
+                .config
+                .public_links
+                .as_ref()
+                .is_some_and(|links| links.keys().any(|link| link.starts_with(&scheme)));
+            let usage = match storage.usage().await {
+                Ok(usage) => usage,
+                Err(e) => {
+                    log::warn!("Failed to get usage of {}: {}", name, e);
+                    None
+                }
+            };
+            storages.push(StorageInfo {
+                name,
+                read_only: capabilities.read_only,
+                capabilities,
+                usage,
+            });
         }
+
+        FinderResponse::Ok().json(StoragesResponse { storages })
     }
 
-    pub async fn preview(data: web::Data<VueFinder>, query: web::Query<Query>) -> HttpResponse {
+    // Server-sent events for changes inside a directory, until the client disconnects
+    pub async fn watch(data: Arc<VueFinder>, query: Query) -> FinderResponse {
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            Some(s) => s.clone(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        match storage.read(&query.path.clone().unwrap_or_default()).await {
-            Ok(contents) => {
-                let mime = mime_guess::from_path(&query.path.clone().unwrap_or_default())
-                    .first_or_octet_stream();
-
-                HttpResponse::Ok()
-                    .content_type(mime.as_ref())
-                    .body(contents)
-            }
-            Err(_) => HttpResponse::NotFound().finish(),
+        let path = query.path.clone().unwrap_or_default();
+        if !storage.is_dir(&path).await.unwrap_or(false) {
+            return FinderResponse::NotFound().json(ErrorResponse::new("Directory not found."));
         }
+
+        let events = data
+            .watchers
+            .subscribe(
+                storage,
+                path,
+                Duration::from_millis(data.config.watch_debounce_ms),
+                Duration::from_millis(data.config.watch_poll_interval_ms),
+            )
+            .map(|event| {
+                let kind = serde_json::to_value(event.kind).unwrap_or_default();
+                let payload = serde_json::to_string(&event).unwrap_or_default();
+                Ok::<_, std::io::Error>(Bytes::from(format!(
+                    "event: {}\ndata: {}\n\n",
+                    kind.as_str().unwrap_or_default(),
+                    payload
+                )))
+            });
+
+        FinderResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(("Cache-Control", "no-cache"))
+            .streaming(events)
     }
 
-    pub async fn search(data: web::Data<VueFinder>, query: web::Query<Query>) -> HttpResponse {
-        let adapter = query.adapter.clone().unwrap_or_default();
+    pub async fn sub_folders(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let dirname = query.path.clone().unwrap_or_default();
+
         let storage = match data.storages.get(&adapter) {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => {
+                return FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("Invalid storage adapter"))
+            }
         };
 
-        let base_path = query.path.clone().unwrap_or_default();
-        let filter = query.filter.clone().unwrap_or_default().to_lowercase();
-
-        async fn search_dir(
-            storage: &Arc<dyn StorageAdapter>,
-            current_path: String,
-            filter: &str,
-            results: &mut Vec<FileNode>,
-        ) -> Result<(), Box<dyn std::error::Error>> {
-            let contents = storage.list_contents(&current_path).await?;
-
-            for item in contents {
-                if item.node_type == "file" && item.basename.to_lowercase().contains(filter) {
-                    let dir = if let Some(parent) = Path::new(&item.path).parent() {
-                        parent.to_string_lossy().to_string()
-                    } else {
-                        String::new()
-                    };
+        match storage.list_contents(&dirname).await {
+            Ok(contents) => {
+                let folders: Vec<_> = contents
+                    .into_iter()
+                    .filter(|item| {
+                        item.node_type == "dir"
+                            && !is_internal(&item.path)
+                            && data.visible(&query, &item.path)
+                    })
+                    .map(|item| Folder {
+                        adapter: adapter.clone(),
+                        path: item.path,
+                        basename: item.basename,
+                    })
+                    .collect();
 
-                    results.push(FileNode {
-                        storage_item: item,
-                        url: None,
-                        dir: Some(dir),
-                    });
-                } else if item.node_type == "dir" {
-                    let sub_path = if current_path.is_empty() {
-                        item.basename
-                    } else {
-                        format!("{}/{}", current_path, item.basename)
-                    };
-                    Box::pin(search_dir(storage, sub_path, filter, results)).await?;
-                }
+                FinderResponse::Ok().json(SubfoldersResponse { folders })
             }
-            Ok(())
-        }
-
-        let mut files = Vec::new();
-        match search_dir(storage, base_path, &filter, &mut files).await {
-            Ok(_) => HttpResponse::Ok().json(json!({
-                "adapter": adapter,
-                "storages": data.storages.keys().collect::<Vec<_>>(),
-                "dirname": query.path,
-                "files": files
-            })),
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
+            Err(e) => FinderError::from(e).error_response(),
         }
     }
 
-    pub async fn new_folder(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<NewFolderRequest>,
-    ) -> HttpResponse {
+    pub async fn download(
+        data: Arc<VueFinder>,
+        query: Query,
+        range: Option<ByteRange>,
+    ) -> FinderResponse {
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        let new_path = format!(
-            "{}/{}",
-            query.path.clone().unwrap_or_default(),
-            payload.name
-        );
-
-        match storage.create_dir(&new_path).await {
-            Ok(_) => Self::index(data, query).await,
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
+        let path = query.path.clone().unwrap_or_default();
+        let filename = Path::new(&path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        if let Err(response) = data.hook_before_download(storage.as_ref(), &path).await {
+            return response;
         }
+
+        let mut response = FinderResponse::Ok();
+        response.insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        ));
+        let response = data
+            .file_response(storage.as_ref(), &path, range, response)
+            .await;
+        data.remember_opened(&query, response.status(), response.headers())
+            .await;
+        response
     }
 
-    pub async fn new_file(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<NewFileRequest>,
-    ) -> HttpResponse {
-        let storage = match data
-            .storages
-            .get(&query.adapter.clone().unwrap_or_default())
-        {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+    // Stream a ZIP of the selected files and folders, built while it is sent
+    pub async fn download_archive(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s.clone(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        let new_path = format!(
-            "{}/{}",
-            query.path.clone().unwrap_or_default(),
-            payload.name
-        );
+        let paths: Vec<String> = match (&query.items, &query.path) {
+            (Some(items), _) => match serde_json::from_str(items) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    return FinderResponse::BadRequest()
+                        .json(ErrorResponse::new(format!("Invalid items: {}", e)))
+                }
+            },
+            (None, Some(path)) => vec![path.clone()],
+            (None, None) => Vec::new(),
+        };
+        if paths.is_empty() {
+            return FinderResponse::BadRequest().json(ErrorResponse::new("Nothing to download"));
+        }
+
+        for path in &paths {
+            if let Err(response) = data.hook_before_download(storage.as_ref(), path).await {
+                return response;
+            }
+        }
+
+        let mut entries = Vec::new();
+        for path in &paths {
+            let name = Path::new(path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string();
 
-        match storage.write(&new_path, vec![]).await {
-            Ok(_) => Self::index(data, query).await,
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
+            if storage.is_dir(path).await.unwrap_or(false) {
+                let walked = match walk(storage.as_ref(), path, data.config.walk_limit).await {
+                    Ok(walked) => walked,
+                    Err(e) => return FinderError::from(e).error_response(),
+                };
+                entries.push(ZipEntry {
+                    name: name.clone(),
+                    source: None,
+                    last_modified: None,
+                    size: None,
+                });
+                entries.extend(
+                    walked
+                        .into_iter()
+                        .filter(|entry| !data.walk_skips(&query, &entry.item.path))
+                        .map(|entry| ZipEntry {
+                            name: format!("{}/{}", name, entry.relative_path),
+                            source: (entry.item.node_type != "dir").then_some(entry.item.path),
+                            last_modified: entry.item.last_modified,
+                            size: entry.item.size,
+                        }),
+                );
+            } else if storage.exists(path).await.unwrap_or(false) {
+                entries.push(ZipEntry {
+                    name,
+                    source: Some(path.clone()),
+                    last_modified: None,
+                    size: storage.file_size(path).await.ok(),
+                });
+            } else {
+                return FinderResponse::NotFound()
+                    .json(ErrorResponse::new(format!("Path not found: {}", path)));
+            }
         }
+
+        let filename = match paths.as_slice() {
+            [single] => format!(
+                "{}.zip",
+                Path::new(single)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ),
+            _ => "archive.zip".to_string(),
+        };
+
+        FinderResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ))
+            .streaming(zip_stream(storage, entries))
     }
 
-    pub async fn rename(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<RenameRequest>,
-    ) -> HttpResponse {
-        let storage = match data
-            .storages
-            .get(&query.adapter.clone().unwrap_or_default())
-        {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+    // Stream a file body, or the part of it asked for by a single-range `Range` header.
+    // The charset of text types is detected from the first bytes only.
+    async fn file_response(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+        range: Option<ByteRange>,
+        mut response: FinderResponseBuilder,
+    ) -> FinderResponse {
+        let size = match storage.file_size(path).await {
+            Ok(size) => size,
+            Err(_) => return FinderResponse::NotFound().finish(),
         };
+        response.insert_header((header::ACCEPT_RANGES, "bytes"));
 
-        let new_path = format!(
-            "{}/{}",
-            query.path.clone().unwrap_or_default(),
-            payload.name
-        );
+        let range = match range.map(|range| range.satisfiable(size)) {
+            Some(None) => {
+                return FinderResponse::RangeNotSatisfiable()
+                    .insert_header((header::CONTENT_RANGE, format!("bytes */{}", size)))
+                    .finish()
+            }
+            Some(range) => range,
+            None => None,
+        };
 
-        // First read the original file content
-        match storage.read(&payload.item).await {
-            Ok(contents) => {
-                // Write the new file
-                if let Err(e) = storage.write(&new_path, contents).await {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": e.to_string()
-                    }));
-                }
-                // Delete the original file
-                if let Err(e) = storage.delete(&payload.item).await {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": e.to_string()
-                    }));
-                }
-                Self::index(data, query).await
+        let (body, length) = match range {
+            Some((start, end)) => {
+                response.status(StatusCode::PARTIAL_CONTENT);
+                response.insert_header((
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, size),
+                ));
+                let length = end - start + 1;
+                (storage.read_stream_range(path, start, length).await, length)
             }
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
+            None => (storage.read_stream(path).await, size),
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err(_) => return FinderResponse::NotFound().finish(),
+        };
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        let sample = if is_text_mime(mime.essence_str()) {
+            storage
+                .read_range(path, 0, CHARSET_SAMPLE as u64)
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        response.content_type(content_type(&mime, &sample));
+        if self.config.nosniff {
+            response.insert_header(("X-Content-Type-Options", "nosniff"));
         }
+        response.no_chunking(length).streaming(body)
     }
 
-    pub async fn r#move(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<MoveRequest>,
-    ) -> HttpResponse {
+    // Downscaled copy of an image for grid views, cached per file version
+    pub async fn thumbnail(data: Arc<VueFinder>, query: Query) -> FinderResponse {
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        // Check if the target path conflicts with existing files
-        for item in &payload.items {
-            let target = format!(
-                "{}/{}",
-                payload.item,
-                Path::new(&item.path)
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap()
-            );
-            if storage.exists(&target).await.unwrap_or(false) {
-                return HttpResponse::BadRequest().json(json!({
-                    "status": false,
-                    "message": "One of the files already exists."
-                }));
+        let format = match query.format.as_deref() {
+            None | Some("jpeg") | Some("jpg") => image::ImageFormat::Jpeg,
+            Some("webp") => image::ImageFormat::WebP,
+            Some("png") => image::ImageFormat::Png,
+            Some(other) => {
+                return FinderResponse::BadRequest().json(ErrorResponse::new(format!(
+                    "Unsupported thumbnail format: {}",
+                    other
+                )))
             }
+        };
+        let extension = format.extensions_str()[0];
+
+        // The listing entry tells when the original changes
+        let path = query.path.clone().unwrap_or_default();
+        let item = match storage.list_contents(&parent_path(&path)).await {
+            Ok(items) => items
+                .into_iter()
+                .find(|item| item.path == path && item.node_type == "file"),
+            Err(_) => None,
+        };
+        let item = match item {
+            Some(item) => item,
+            None => return FinderResponse::NotFound().finish(),
+        };
+        // A thumbnail shows the file as much as a download does
+        if let Err(response) = data.hook_before_download(storage.as_ref(), &path).await {
+            return response;
         }
 
-        // Execute move operation
-        for item in &payload.items {
-            let target = format!(
-                "{}/{}",
-                payload.item,
-                Path::new(&item.path)
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap()
-            );
+        let (max_width, max_height) = (
+            data.config.thumbnail_max_width,
+            data.config.thumbnail_max_height,
+        );
+        let key = content_hash(
+            format!(
+                "{}:{:?}:{:?}:{}x{}",
+                storage.shared_path(&path),
+                item.last_modified,
+                item.size,
+                max_width,
+                max_height
+            )
+            .as_bytes(),
+        );
+        let cache_file = format!("{}.{}", key, extension);
+
+        let cached = match &data.config.thumbnail_cache_dir {
+            Some(dir) => tokio::fs::read(Path::new(dir).join(&cache_file)).await.ok(),
+            None => storage
+                .read(&format!(
+                    "{}://{}/{}",
+                    storage.name(),
+                    THUMBS_DIR,
+                    cache_file
+                ))
+                .await
+                .ok(),
+        };
 
-            // Read source file content
-            match storage.read(&item.path).await {
-                Ok(contents) => {
-                    // Write to target location
-                    if let Err(e) = storage.write(&target, contents).await {
-                        return HttpResponse::InternalServerError().json(json!({
-                            "status": false,
-                            "message": e.to_string()
-                        }));
+        let thumbnail = match cached {
+            Some(thumbnail) => thumbnail,
+            None => {
+                let contents = match storage.read(&path).await {
+                    Ok(contents) => contents,
+                    Err(_) => return FinderResponse::NotFound().finish(),
+                };
+                let rendered = tokio::task::spawn_blocking(move || {
+                    render_thumbnail(&contents, max_width, max_height, format)
+                })
+                .await;
+                let thumbnail = match rendered {
+                    Ok(Ok(thumbnail)) => thumbnail,
+                    Ok(Err(e)) => {
+                        return FinderResponse::UnsupportedMediaType().json(ErrorResponse::new(
+                            format!("Cannot create a thumbnail: {}", e),
+                        ))
                     }
-                    // Delete source file
-                    if let Err(e) = storage.delete(&item.path).await {
-                        return HttpResponse::InternalServerError().json(json!({
-                            "status": false,
-                            "message": e.to_string()
-                        }));
+                    Err(e) => {
+                        return FinderResponse::InternalServerError()
+                            .json(ErrorResponse::new(e.to_string()))
+                    }
+                };
+
+                // Caching is best effort, read-only adapters just regenerate
+                match &data.config.thumbnail_cache_dir {
+                    Some(dir) => {
+                        if tokio::fs::create_dir_all(dir).await.is_ok() {
+                            let _ = tokio::fs::write(Path::new(dir).join(&cache_file), &thumbnail)
+                                .await;
+                        }
+                    }
+                    None => {
+                        let _ = storage
+                            .write(
+                                &format!("{}://{}/{}", storage.name(), THUMBS_DIR, cache_file),
+                                thumbnail.clone(),
+                            )
+                            .await;
                     }
                 }
-                Err(e) => {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": e.to_string()
-                    }))
-                }
+                thumbnail
             }
-        }
+        };
 
-        Self::index(data, query).await
+        FinderResponse::Ok()
+            .content_type(format.to_mime_type())
+            .body(thumbnail)
     }
 
-    pub async fn delete(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<DeleteRequest>,
-    ) -> HttpResponse {
+    pub async fn qr(data: Arc<VueFinder>, query: Query) -> FinderResponse {
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        for item in &payload.items {
-            if let Err(e) = storage.delete(&item.path).await {
-                return HttpResponse::InternalServerError().json(json!({
-                    "status": false,
-                    "message": e.to_string()
-                }));
-            }
+        let path = query.path.clone().unwrap_or_default();
+        match storage.exists(&path).await {
+            Ok(true) => {}
+            _ => return FinderResponse::NotFound().finish(),
         }
 
-        Self::index(data, query).await
-    }
-
-    pub async fn upload(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        mut payload: Multipart,
-    ) -> HttpResponse {
-        let storage = match data.get_storage(query.adapter.clone()) {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+        let url = match data.public_url(&path) {
+            Some(url) => url,
+            None => {
+                return FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("This item is not shareable."))
+            }
         };
 
-        let mut filename = String::new();
-        let mut file_data = Vec::new();
-
-        // Process multipart form fields
-        while let Ok(Some(mut field)) = payload.try_next().await {
-            let content_disposition = field.content_disposition();
+        let code = match qrcode::QrCode::new(url.as_bytes()) {
+            Ok(code) => code,
+            Err(e) => {
+                return FinderResponse::InternalServerError().json(ErrorResponse::new(format!(
+                    "Failed to generate QR code: {}",
+                    e
+                )))
+            }
+        };
 
-            match content_disposition.get_name() {
-                Some("name") => {
-                    if let Ok(Some(chunk)) = field.try_next().await {
-                        filename = String::from_utf8_lossy(&chunk).to_string();
-                    }
-                }
-                Some("file") => {
-                    while let Ok(Some(chunk)) = field.try_next().await {
-                        file_data.extend_from_slice(&chunk);
-                    }
+        match query.format.as_deref().unwrap_or("png") {
+            "svg" => {
+                let svg = code
+                    .render::<qrcode::render::svg::Color>()
+                    .min_dimensions(200, 200)
+                    .build();
+                FinderResponse::Ok().content_type("image/svg+xml").body(svg)
+            }
+            "png" => {
+                let image = code
+                    .render::<image::Luma<u8>>()
+                    .min_dimensions(200, 200)
+                    .build();
+                let mut png = std::io::Cursor::new(Vec::new());
+                match image.write_to(&mut png, image::ImageFormat::Png) {
+                    Ok(_) => FinderResponse::Ok()
+                        .content_type("image/png")
+                        .body(png.into_inner()),
+                    Err(e) => FinderResponse::InternalServerError().json(ErrorResponse::new(
+                        format!("Failed to encode QR code: {}", e),
+                    )),
                 }
-                _ => continue,
+            }
+            _ => {
+                FinderResponse::BadRequest().json(ErrorResponse::new("Unsupported QR code format."))
             }
         }
+    }
 
-        if filename.is_empty() || file_data.is_empty() {
-            return HttpResponse::BadRequest().json(json!({
-                "status": false,
-                "message": "Missing file or filename"
-            }));
-        }
+    // Total size and number of files and folders below a folder. The walk
+    // stops at `walk_limit` entries and when the client goes away, as the
+    // request's future is dropped.
+    pub async fn size(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let Some(storage) = data
+            .storages
+            .get(&data.get_default_adapter(query.adapter.clone()))
+        else {
+            return FinderResponse::BadRequest().finish();
+        };
 
-        // Build file path and save file
-        let filepath = format!("{}/{}", query.path.clone().unwrap_or_default(), filename);
-        if let Err(e) = storage.write(&filepath, file_data).await {
-            return HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            }));
+        let path = query
+            .path
+            .clone()
+            .unwrap_or_else(|| format!("{}://", storage.name()));
+        let ttl = Duration::from_secs(data.config.folder_size_cache_secs);
+        let key = storage.shared_path(&path);
+        if let Some(size) = data.folder_sizes.get(&key, ttl) {
+            return FinderResponse::Ok().json(size);
         }
 
-        Self::index(data, query).await
+        let skip = |path: &str| data.walk_skips(&query, path);
+        match folder_size(storage.as_ref(), &path, data.config.walk_limit, &skip).await {
+            Ok(size) => {
+                if !ttl.is_zero() {
+                    data.folder_sizes.insert(key, size.clone(), ttl);
+                }
+                FinderResponse::Ok().json(size)
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
     }
 
-    pub async fn archive(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<ArchiveRequest>,
-    ) -> HttpResponse {
-        let storage = match data
+    // Start looking for duplicate files below a folder, answering with the id
+    // of the job. Its result is a `DuplicatesResponse`.
+    pub async fn duplicates(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let Some(storage) = data
             .storages
-            .get(&query.adapter.clone().unwrap_or_default())
-        {
-            Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            .get(&data.get_default_adapter(query.adapter.clone()))
+            .cloned()
+        else {
+            return FinderResponse::BadRequest().finish();
         };
 
-        let zip_path = format!(
-            "{}/{}.zip",
-            query.path.clone().unwrap_or_default(),
-            payload.name
-        );
+        let path = query
+            .path
+            .clone()
+            .unwrap_or_else(|| format!("{}://", storage.name()));
+        let limit = data.config.walk_limit;
+        let finder = data.clone();
+        let id = data.jobs.spawn("duplicates", |progress| async move {
+            let skip = |path: &str| finder.walk_skips(&query, path);
+            find_duplicates(storage.as_ref(), &path, limit, &skip, &progress).await
+        });
+        FinderResponse::Accepted().json(JobResponse { id })
+    }
 
-        // Check if file already exists
-        if storage.exists(&zip_path).await.unwrap_or(false) {
-            return HttpResponse::BadRequest().json(json!({
-                "status": false,
-                "message": "Zip file already exists. Please use a different name."
-            }));
+    // Progress of a background job, with its result once it's done
+    pub async fn job_status(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let id = query.id.clone().unwrap_or_default();
+        match data.jobs.status(&id) {
+            Some(status) => FinderResponse::Ok().json(status),
+            None => {
+                FinderResponse::NotFound().json(ErrorResponse::new(format!("Unknown job: {}", id)))
+            }
         }
+    }
 
-        // Create ZIP file
-        let mut zip_buffer = Vec::new();
-        {
-            let cursor = Cursor::new(&mut zip_buffer);
-            let mut zip = ZipWriter::new(cursor);
-
-            let options = FileOptions::default()
-                .compression_method(zip::CompressionMethod::Deflated)
-                .unix_permissions(0o755);
-
-            for item in &payload.items {
-                match storage.read(&item.path).await {
-                    Ok(contents) => {
-                        let file_name = Path::new(&item.path)
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or_default();
-
-                        if let Err(e) = zip.start_file(file_name, options) {
-                            return HttpResponse::InternalServerError().json(json!({
-                                "status": false,
-                                "message": format!("Failed to add file to ZIP: {}", e)
-                            }));
-                        }
-
-                        if let Err(e) = zip.write_all(&contents) {
-                            return HttpResponse::InternalServerError().json(json!({
-                                "status": false,
-                                "message": format!("Failed to write file content: {}", e)
-                            }));
-                        }
-                    }
-                    Err(e) => {
-                        return HttpResponse::InternalServerError().json(json!({
-                            "status": false,
-                            "message": format!("Failed to read source file: {}", e)
-                        }));
-                    }
-                }
-            }
+    // Digest of a file's contents, for verifying downloads and finding duplicates
+    pub async fn hash(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let Some(storage) = data
+            .storages
+            .get(&data.get_default_adapter(query.adapter.clone()))
+        else {
+            return FinderResponse::BadRequest().finish();
+        };
 
-            if let Err(e) = zip.finish() {
-                return HttpResponse::InternalServerError().json(json!({
-                    "status": false,
-                    "message": format!("Failed to finalize ZIP file: {}", e)
-                }));
-            }
+        let path = query.path.clone().unwrap_or_default();
+        if storage.is_dir(&path).await.unwrap_or(false) {
+            return FinderError::BadRequest("Folders can't be hashed".to_string()).error_response();
         }
-
-        // Save ZIP file
-        if let Err(e) = storage.write(&zip_path, zip_buffer).await {
-            return HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": format!("Failed to save ZIP file: {}", e)
-            }));
+        let algorithm = query.algorithm.unwrap_or_default();
+        match storage.checksum(&path, algorithm).await {
+            Ok(hash) => FinderResponse::Ok().json(ChecksumResponse { algorithm, hash }),
+            Err(e) => FinderError::from(e).error_response(),
         }
+    }
 
-        Self::index(data, query).await
+    // Metadata of a single file or folder, without listing its parent
+    pub async fn stat(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let Some(storage) = data
+            .storages
+            .get(&data.get_default_adapter(query.adapter.clone()))
+        else {
+            return FinderResponse::BadRequest().finish();
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        match storage.metadata(&path).await {
+            Ok(storage_item) => {
+                let mut node = FileNode {
+                    storage_item,
+                    url: None,
+                    dir: None,
+                };
+                data.set_public_links(&mut node);
+                FinderResponse::Ok().json(node)
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
     }
 
-    pub async fn unarchive(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<UnarchiveRequest>,
-    ) -> HttpResponse {
+    // Content type of a file from its extension, sniffing the first bytes
+    // when the extension doesn't say anything useful
+    pub async fn mime(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        const SNIFF_LENGTH: u64 = 512;
+
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        // Read ZIP file
-        let zip_contents = match storage.read(&payload.item).await {
-            Ok(contents) => contents,
-            Err(e) => {
-                return HttpResponse::InternalServerError().json(json!({
-                    "status": false,
-                    "message": format!("Failed to read ZIP file: {}", e)
-                }));
-            }
+        let path = query.path.clone().unwrap_or_default();
+        let sample = match storage.read_range(&path, 0, SNIFF_LENGTH).await {
+            Ok(sample) => sample,
+            Err(_) => return FinderResponse::NotFound().finish(),
         };
 
-        let cursor = Cursor::new(zip_contents);
-        let mut archive = match zip::ZipArchive::new(cursor) {
-            Ok(archive) => archive,
-            Err(e) => {
-                return HttpResponse::InternalServerError().json(json!({
-                    "status": false,
-                    "message": format!("Failed to open ZIP file: {}", e)
-                }));
+        let mut mime = mime_guess::from_path(&path)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string();
+        let mut sniffed = false;
+        if mime == "application/octet-stream" {
+            if let Some(kind) = infer::get(&sample) {
+                mime = kind.mime_type().to_string();
+                sniffed = true;
             }
+        }
+
+        let charset = if is_text_mime(&mime) {
+            detect_charset(&sample)
+        } else {
+            None
         };
 
-        // Extract files
-        let extract_path = format!(
-            "{}/{}",
-            query.path.clone().unwrap_or_default(),
-            Path::new(&payload.item)
-                .file_stem()
-                .and_then(|n| n.to_str())
-                .unwrap_or_default()
-        );
+        FinderResponse::Ok().json(MimeResponse {
+            mime,
+            charset: charset.map(str::to_string),
+            sniffed,
+        })
+    }
 
-        // Create extraction target directory
-        if let Err(e) = storage.create_dir(&extract_path).await {
-            return HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": format!("Failed to create extraction directory: {}", e)
-            }));
+    pub async fn preview(
+        data: Arc<VueFinder>,
+        query: Query,
+        range: Option<ByteRange>,
+    ) -> FinderResponse {
+        let response = Self::preview_file(&data, &query, range).await;
+        data.remember_opened(&query, response.status(), response.headers())
+            .await;
+        response
+    }
+
+    // `preview` without counting the file as opened, also answering `save`
+    async fn preview_file(
+        data: &VueFinder,
+        query: &Query,
+        range: Option<ByteRange>,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        let mime = mime_guess::from_path(&path).first_or_octet_stream();
+        if let Err(response) = data.hook_before_download(storage.as_ref(), &path).await {
+            return response;
+        }
+
+        // Archive listings need the whole file, everything else is streamed
+        if data.config.archive_preview && mime.essence_str() == "application/zip" {
+            return match storage.read(&path).await {
+                Ok(contents) => Self::archive_listing(contents),
+                Err(_) => FinderResponse::NotFound().finish(),
+            };
+        }
+
+        // Text files small enough to be saved back get an ETag for `save`'s `If-Match`
+        let mut response = FinderResponse::Ok();
+        if is_text_mime(mime.essence_str()) {
+            if let Some(etag) = Self::etag(storage.as_ref(), &path, data.config.json_limit).await {
+                response.insert_header((header::ETAG, etag));
+            }
         }
+        data.file_response(storage.as_ref(), &path, range, response)
+            .await
+    }
+
+    // Quoted content hash of a file of at most `limit` bytes
+    async fn etag(storage: &dyn StorageAdapter, path: &str, limit: usize) -> Option<String> {
+        let size = storage.file_size(path).await.ok()?;
+        if size > limit as u64 {
+            return None;
+        }
+        let hash = storage
+            .checksum(path, ChecksumAlgorithm::Sha256)
+            .await
+            .ok()?;
+        Some(format!("\"{}\"", hash))
+    }
+
+    // List the entries of a ZIP archive for inline preview
+    fn archive_listing(contents: Vec<u8>) -> FinderResponse {
+        let mut archive = match zip::ZipArchive::new(Cursor::new(contents)) {
+            Ok(archive) => archive,
+            Err(e) => {
+                return FinderResponse::BadRequest().json(ErrorResponse::new(format!(
+                    "Failed to open ZIP file: {}",
+                    e
+                )));
+            }
+        };
 
+        let mut entries = Vec::with_capacity(archive.len());
         for i in 0..archive.len() {
-            let mut file = match archive.by_index(i) {
-                Ok(file) => file,
+            match archive.by_index(i) {
+                Ok(file) => entries.push(ArchiveEntry {
+                    name: file.name().to_string(),
+                    node_type: if file.is_dir() { "dir" } else { "file" }.to_string(),
+                    size: file.size(),
+                    compressed_size: file.compressed_size(),
+                }),
                 Err(e) => {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": format!("Failed to read ZIP file entry: {}", e)
-                    }));
+                    return FinderResponse::BadRequest().json(ErrorResponse::new(format!(
+                        "Failed to read ZIP file entry: {}",
+                        e
+                    )));
                 }
-            };
+            }
+        }
+
+        FinderResponse::Ok().json(ArchiveListingResponse { entries })
+    }
+
+    // Return a single entry of a ZIP archive without extracting the rest
+    pub async fn read_entry(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: ReadEntryRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        // Only plain relative entry names, never `..` or absolute paths
+        let traversal = Path::new(&payload.entry)
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)));
+        if payload.entry.is_empty() || traversal {
+            return FinderResponse::BadRequest()
+                .json(ErrorResponse::new("Invalid archive entry name."));
+        }
+
+        if let Err(response) = data
+            .hook_before_download(storage.as_ref(), &payload.archive)
+            .await
+        {
+            return response;
+        }
+        let contents = match storage.read(&payload.archive).await {
+            Ok(contents) => contents,
+            Err(_) => return FinderResponse::NotFound().finish(),
+        };
+
+        let mut archive = match zip::ZipArchive::new(Cursor::new(contents)) {
+            Ok(archive) => archive,
+            Err(e) => {
+                return FinderResponse::BadRequest().json(ErrorResponse::new(format!(
+                    "Failed to open ZIP file: {}",
+                    e
+                )));
+            }
+        };
+
+        let mut file = match archive.by_name(&payload.entry) {
+            Ok(file) if file.is_file() => file,
+            _ => {
+                return FinderResponse::NotFound()
+                    .json(ErrorResponse::new("Archive entry not found."));
+            }
+        };
+
+        let limit = data.config.max_extract_size;
+        let mut remaining = limit;
+        let buffer = match read_limited(&mut file, &mut remaining, limit) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                return archive_error(
+                    e,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to read ZIP file entry",
+                )
+            }
+        };
+
+        let mime = mime_guess::from_path(&payload.entry).first_or_octet_stream();
+        FinderResponse::Ok()
+            .content_type(mime.as_ref())
+            .body(buffer)
+    }
+
+    pub async fn search(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let adapter = query.adapter.clone().unwrap_or_default();
+        let storage = match data.storages.get(&adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let base_path = query.path.clone().unwrap_or_default();
+        let filter = match NameFilter::new(
+            query.mode.unwrap_or_default(),
+            &query.filter.clone().unwrap_or_default(),
+            query.case_sensitive.unwrap_or(false),
+            data.config.search_normalization,
+        ) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return FinderError::BadRequest(format!("Invalid search pattern: {}", e))
+                    .error_response()
+            }
+        };
 
-            let outpath = format!("{}/{}", extract_path, file.name());
+        async fn search_dir(
+            storage: &Arc<dyn StorageAdapter>,
+            current_path: String,
+            base_path: &str,
+            filter: &NameFilter,
+            query: &Query,
+            skip: &(dyn Fn(&str) -> bool + Sync),
+            results: &mut Vec<FileNode>,
+        ) -> Result<(), StorageError> {
+            // Stream entries so large directories are never fully buffered
+            let mut contents = storage.list_stream(&current_path);
 
-            if file.name().ends_with('/') {
-                // Create directory
-                if let Err(e) = storage.create_dir(&outpath).await {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": format!("Failed to create directory: {}", e)
-                    }));
+            while let Some(item) = contents.try_next().await? {
+                if skip(&item.path) {
+                    continue;
                 }
-            } else {
-                // Ensure parent directory exists
-                if let Some(p) = Path::new(&outpath).parent() {
-                    if let Some(parent_path) = p.to_str() {
-                        if let Err(e) = storage.create_dir(parent_path).await {
-                            return HttpResponse::InternalServerError().json(json!({
-                                "status": false,
-                                "message": format!("Failed to create parent directory: {}", e)
-                            }));
-                        }
+                let is_dir = item.node_type == "dir";
+                let sub_path = is_dir.then(|| {
+                    if current_path.is_empty() {
+                        item.basename.clone()
+                    } else {
+                        format!("{}/{}", current_path, item.basename)
                     }
-                }
+                });
+                let wanted = match query.entry_type {
+                    Some(entry_type) => entry_type.matches(&item),
+                    None => !is_dir,
+                };
+                if wanted
+                    && filter.matches(&item.basename, relative_path(base_path, &item.path))
+                    && in_modified_window(query, &item)
+                    && in_size_range(query, &item)
+                {
+                    let dir = if let Some(parent) = Path::new(&item.path).parent() {
+                        parent.to_string_lossy().to_string()
+                    } else {
+                        String::new()
+                    };
 
-                // Read and write file contents
-                let mut buffer = Vec::new();
-                if let Err(e) = std::io::copy(&mut file, &mut buffer) {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": format!("Failed to read ZIP file content: {}", e)
-                    }));
+                    results.push(FileNode {
+                        storage_item: item,
+                        url: None,
+                        dir: Some(dir),
+                    });
                 }
-
-                if let Err(e) = storage.write(&outpath, buffer).await {
-                    return HttpResponse::InternalServerError().json(json!({
-                        "status": false,
-                        "message": format!("Failed to write extracted file: {}", e)
-                    }));
+                if let Some(sub_path) = sub_path {
+                    Box::pin(search_dir(
+                        storage, sub_path, base_path, filter, query, skip, results,
+                    ))
+                    .await?;
                 }
             }
+            Ok(())
         }
 
-        Self::index(data, query).await
+        let mut files = Vec::new();
+        let skip = |path: &str| data.walk_skips(&query, path);
+        let searched = search_dir(
+            storage,
+            base_path.clone(),
+            &base_path,
+            &filter,
+            &query,
+            &skip,
+            &mut files,
+        )
+        .await;
+        match searched {
+            Ok(_) => FinderResponse::Ok().json(SearchResponse {
+                adapter,
+                storages: data.storages.keys().cloned().collect(),
+                dirname: query.path.clone(),
+                files: files
+                    .into_iter()
+                    .filter(|file| data.visible(&query, &file.storage_item.path))
+                    .collect(),
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Text files below `path` matching `filter`, best first, with a snippet of
+    // each. `per_page` caps the number of matches.
+    pub async fn search_content(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let Some(index) = &data.content_index else {
+            return FinderError::BadRequest("Content search isn't enabled.".to_string())
+                .error_response();
+        };
+        let Some(storage) = data
+            .storages
+            .get(&data.get_default_adapter(query.adapter.clone()))
+        else {
+            return FinderResponse::BadRequest().finish();
+        };
+
+        let adapter = storage.name();
+        let path = query
+            .path
+            .clone()
+            .unwrap_or_else(|| format!("{}://", adapter));
+        let scope = content_index::search_scope(&storage.shared_path(&path));
+        let text = query.filter.clone().unwrap_or_default();
+        let limit = query.per_page.unwrap_or(50).clamp(1, 500);
+        let matches = match index.search(&adapter, scope.as_deref(), &text, limit).await {
+            Ok(matches) => matches,
+            Err(e) => return FinderError::Internal(e).error_response(),
+        };
+        // Snippets show contents, so the download hooks have a say
+        let mut visible = Vec::new();
+        for mut found in matches {
+            let Some(path) = data.visible_path(&adapter, &found.path) else {
+                continue;
+            };
+            found.path = path;
+            if data.visible(&query, &found.path)
+                && data
+                    .hook_before_download(storage.as_ref(), &found.path)
+                    .await
+                    .is_ok()
+            {
+                visible.push(found);
+            }
+        }
+        FinderResponse::Ok().json(ContentSearchResponse {
+            matches: visible,
+            adapter,
+        })
     }
 
-    pub async fn save(
-        data: web::Data<VueFinder>,
-        query: web::Query<Query>,
-        payload: web::Json<SaveRequest>,
-    ) -> HttpResponse {
+    // Rebuild the `content_index` for the files below `path` in the
+    // background, answering with the id of the job. Its result is a
+    // `ContentReindexResponse`.
+    pub async fn content_reindex(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let (Some(index), Some(config)) = (
+            data.content_index.clone(),
+            data.config.content_index.clone(),
+        ) else {
+            return FinderError::BadRequest("Content search isn't enabled.".to_string())
+                .error_response();
+        };
+        let Some(storage) = data
+            .storages
+            .get(&data.get_default_adapter(query.adapter.clone()))
+            .cloned()
+        else {
+            return FinderResponse::BadRequest().finish();
+        };
+
+        let path = query
+            .path
+            .clone()
+            .unwrap_or_else(|| format!("{}://", storage.name()));
+        let limit = data.config.walk_limit;
+        let id = data.jobs.spawn("content-reindex", |progress| async move {
+            content_index::reindex(
+                index.as_ref(),
+                storage.as_ref(),
+                &path,
+                config.max_file_size,
+                limit,
+                Some(&progress),
+            )
+            .await
+        });
+        FinderResponse::Accepted().json(JobResponse { id })
+    }
+
+    pub async fn new_folder(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: NewFolderRequest,
+    ) -> FinderResponse {
         let storage = match data
             .storages
             .get(&query.adapter.clone().unwrap_or_default())
         {
             Some(s) => s,
-            None => return HttpResponse::BadRequest().finish(),
+            None => return FinderResponse::BadRequest().finish(),
         };
 
-        match storage
-            .write(
-                &query.path.clone().unwrap_or_default(),
-                payload.content.as_bytes().to_vec(),
-            )
+        let name = match data.clean_name(&payload.name) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+
+        let new_path = format!("{}/{}", query.path.clone().unwrap_or_default(), name);
+
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &new_path).await {
+            return response;
+        }
+
+        match storage.create_dir(&new_path).await {
+            Ok(_) => {
+                data.hook_after_write(storage.as_ref(), &new_path).await;
+                Self::index(data, query).await
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn new_file(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: NewFileRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let name = match data.clean_name(&payload.name) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+        if let Err(e) = file_types::check_name(&data.config, &name) {
+            return e.error_response();
+        }
+
+        let new_path = format!("{}/{}", query.path.clone().unwrap_or_default(), name);
+
+        // Never overwrite an existing file
+        if storage.exists(&new_path).await.unwrap_or(false) {
+            return FinderError::Conflict("File already exists.".to_string()).error_response();
+        }
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &new_path).await {
+            return response;
+        }
+
+        let contents = match &payload.content {
+            Some(content) if payload.base64 => match BASE64_STANDARD.decode(content) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return FinderResponse::BadRequest()
+                        .json(ErrorResponse::new(format!("Invalid base64 content: {}", e)))
+                }
+            },
+            Some(content) => content.as_bytes().to_vec(),
+            None => vec![],
+        };
+        if let Err(e) = file_types::check_contents(&data.config, &contents) {
+            return e.error_response();
+        }
+
+        if let Err(e) = storage.write(&new_path, contents).await {
+            return FinderError::from(e).error_response();
+        }
+        data.hook_after_write(storage.as_ref(), &new_path).await;
+
+        // Return the listing along with the created item
+        match data.list_directory(&query).await {
+            Ok(mut body) => {
+                body.item = body
+                    .files
+                    .iter()
+                    .find(|file| file.storage_item.basename == name)
+                    .cloned();
+                FinderResponse::Ok().json(body)
+            }
+            Err(response) => response,
+        }
+    }
+
+    pub async fn rename(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: RenameRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let name = match data.clean_name(&payload.name) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+        // Folders may be named freely
+        if !storage.is_dir(&payload.item).await.unwrap_or(false) {
+            if let Err(e) = file_types::check_name(&data.config, &name) {
+                return e.error_response();
+            }
+        }
+
+        let new_path = join_path(&query.path.clone().unwrap_or_default(), &name);
+        let policy = query.conflict.unwrap_or(CollisionPolicy::Overwrite);
+        let resolved = match resolve_collision(storage.as_ref(), &new_path, policy).await {
+            Ok(resolved) => resolved,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        if resolved.skip {
+            return Self::index(data, query).await;
+        }
+        if let Err(e) = data.check_lock(storage.as_ref(), &payload.item).await {
+            return e.error_response();
+        }
+        if let Err(response) = data
+            .hook_before_write(storage.as_ref(), &resolved.path)
             .await
         {
-            Ok(_) => Self::preview(data, query).await,
-            Err(e) => HttpResponse::InternalServerError().json(json!({
-                "status": false,
-                "message": e.to_string()
-            })),
+            return response;
+        }
+
+        let moved = move_item(
+            storage.as_ref(),
+            &payload.item,
+            &resolved.path,
+            data.config.walk_limit,
+        )
+        .await;
+        match moved {
+            Ok(()) => {
+                data.unindex_content(storage.as_ref(), &payload.item).await;
+                data.hook_after_write(storage.as_ref(), &resolved.path)
+                    .await;
+                Self::index(data, query).await
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Rename `items` in their folders after a pattern, all of them or none.
+    // Dry runs, and requests where some of the names don't work out, are
+    // answered with a `BulkRenameResponse` instead of the listing.
+    pub async fn bulk_rename(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: BulkRenameRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+        let renamer = match Renamer::new(
+            &payload.find,
+            &payload.replace,
+            payload.regex,
+            payload.start.unwrap_or(1),
+        ) {
+            Ok(renamer) => renamer,
+            Err(e) => {
+                return FinderError::BadRequest(format!("Invalid pattern: {}", e)).error_response()
+            }
+        };
+
+        let sources: HashSet<&str> = payload
+            .items
+            .iter()
+            .map(|item| item.path.as_str())
+            .collect();
+        let mut targets = HashSet::new();
+        let mut results = Vec::new();
+        for (index, item) in payload.items.iter().enumerate() {
+            let dir = parent_path(&item.path);
+            let name = renamer.rename(index, item.path.rsplit('/').next().unwrap_or_default());
+            let (new_path, mut message) = match data.clean_name(&name) {
+                Ok(name) => {
+                    let new_path = join_path(&dir, &name);
+                    let checked = data
+                        .check_bulk_rename(storage.as_ref(), &item.path, &new_path, &sources)
+                        .await;
+                    (new_path, checked.err())
+                }
+                Err(message) => (join_path(&dir, &name), Some(message)),
+            };
+            if !targets.insert(new_path.clone()) {
+                message.get_or_insert_with(|| "Another item gets the same name".to_string());
+            }
+            results.push(BulkRenameResult {
+                path: item.path.clone(),
+                new_path,
+                message,
+            });
+        }
+
+        let status = results.iter().all(|result| result.message.is_none());
+        if payload.dry_run || !status {
+            let response = BulkRenameResponse { status, results };
+            return if payload.dry_run {
+                FinderResponse::Ok().json(response)
+            } else {
+                FinderResponse::Conflict().json(response)
+            };
+        }
+
+        let renames: Vec<(String, String)> = results
+            .into_iter()
+            .filter(|result| result.path != result.new_path)
+            .map(|result| (result.path, result.new_path))
+            .collect();
+        // Names other items are leaving are freed up first by going through
+        // temporary names
+        let steps: Vec<(String, String)> = if renames
+            .iter()
+            .any(|(_, new_path)| sources.contains(new_path.as_str()))
+        {
+            let temporary: Vec<String> = renames
+                .iter()
+                .enumerate()
+                .map(|(index, (path, _))| {
+                    let name = path.rsplit('/').next().unwrap_or_default();
+                    join_path(&parent_path(path), &format!(".{}.renaming-{}", name, index))
+                })
+                .collect();
+            let away = renames
+                .iter()
+                .zip(&temporary)
+                .map(|((path, _), tmp)| (path, tmp));
+            let back = renames
+                .iter()
+                .zip(&temporary)
+                .map(|((_, new), tmp)| (tmp, new));
+            away.chain(back)
+                .map(|(from, to)| (from.clone(), to.clone()))
+                .collect()
+        } else {
+            renames.clone()
+        };
+
+        let limit = data.config.walk_limit;
+        let mut done: Vec<&(String, String)> = Vec::new();
+        for step in &steps {
+            if let Err(e) = move_item(storage.as_ref(), &step.0, &step.1, limit).await {
+                // Put back what was renamed so far
+                for (from, to) in done.into_iter().rev() {
+                    if let Err(e) = move_item(storage.as_ref(), to, from, limit).await {
+                        log::warn!("Failed to undo renaming {} to {}: {}", from, to, e);
+                    }
+                }
+                return FinderError::from(e).error_response();
+            }
+            done.push(step);
+        }
+        for (path, new_path) in &renames {
+            data.unindex_content(storage.as_ref(), path).await;
+            data.hook_after_write(storage.as_ref(), new_path).await;
+        }
+
+        Self::index(data, query).await
+    }
+
+    // Why `path` can't be renamed to `new_path` in a `bulk-rename` also
+    // renaming `sources`
+    async fn check_bulk_rename(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+        new_path: &str,
+        sources: &HashSet<&str>,
+    ) -> Result<(), String> {
+        if path == new_path {
+            return Ok(());
+        }
+        // Folders may be named freely
+        if !storage.is_dir(path).await.unwrap_or(false) {
+            let name = new_path.rsplit('/').next().unwrap_or_default();
+            file_types::check_name(&self.config, name).map_err(|e| e.to_string())?;
+        }
+        if !acl::allowed(&self.config.access_rules, new_path, Permission::Write) {
+            return Err(format!("Permission denied: {}", new_path));
+        }
+        if !sources.contains(new_path) && storage.exists(new_path).await.unwrap_or(false) {
+            return Err(format!("{} already exists", new_path));
+        }
+        self.check_lock(storage, path)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.check_write(storage, new_path)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn r#move(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: MoveRequest,
+    ) -> FinderResponse {
+        Self::transfer(data, query, payload, true).await
+    }
+
+    pub async fn copy(data: Arc<VueFinder>, query: Query, payload: CopyRequest) -> FinderResponse {
+        Self::transfer(data, query, payload, false).await
+    }
+
+    // Copy `items` into the folder `destination`, removing the sources
+    // afterwards when moving. Between two adapters this runs as a job whose
+    // result is a `BatchReport`.
+    async fn transfer(
+        data: Arc<VueFinder>,
+        query: Query,
+        request: TransferRequest,
+        remove_source: bool,
+    ) -> FinderResponse {
+        let TransferRequest {
+            item: destination,
+            items,
+            from_adapter,
+            to_adapter,
+            conflict,
+        } = request;
+        let from_adapter = from_adapter
+            .or_else(|| query.adapter.clone())
+            .unwrap_or_default();
+        let to_adapter = to_adapter.unwrap_or_else(|| from_adapter.clone());
+        let (Some(from), Some(to)) = (
+            data.storages.get(&from_adapter).cloned(),
+            data.storages.get(&to_adapter).cloned(),
+        ) else {
+            return FinderResponse::BadRequest().finish();
+        };
+        let policy = conflict.or(query.conflict).unwrap_or(CollisionPolicy::Fail);
+
+        if from_adapter != to_adapter {
+            let finder = data.clone();
+            let kind = if remove_source { "move" } else { "copy" };
+            let id = data.jobs.spawn(kind, |progress| async move {
+                let batch = finder
+                    .transfer_items(
+                        from.as_ref(),
+                        to.as_ref(),
+                        &destination,
+                        &items,
+                        (remove_source, policy),
+                        Some(&progress),
+                    )
+                    .await;
+                Ok::<_, StorageError>(batch.into_report())
+            });
+            return FinderResponse::Accepted().json(JobResponse { id });
+        }
+
+        let batch = data
+            .transfer_items(
+                from.as_ref(),
+                to.as_ref(),
+                &destination,
+                &items,
+                (remove_source, policy),
+                None,
+            )
+            .await;
+        match batch.into_response() {
+            Some(response) => response,
+            None => Self::index(data, query).await,
+        }
+    }
+
+    async fn transfer_items(
+        &self,
+        from: &dyn StorageAdapter,
+        to: &dyn StorageAdapter,
+        destination: &str,
+        items: &[FileItem],
+        (remove_source, policy): (bool, CollisionPolicy),
+        progress: Option<&JobProgress>,
+    ) -> Batch {
+        let limit = self.config.walk_limit;
+        let same_storage = from.name() == to.name();
+        let copied = progress.map(|progress| progress as &dyn CopyProgress);
+        if let Some(progress) = progress {
+            progress.phase("scanning", None);
+            let mut bytes = 0;
+            for item in items {
+                bytes += match from.metadata(&item.path).await {
+                    Ok(meta) if meta.node_type == "dir" => {
+                        folder_size(from, &item.path, limit, &|_| false)
+                            .await
+                            .map(|size| size.size)
+                            .unwrap_or_default()
+                    }
+                    Ok(meta) => meta.size.unwrap_or_default(),
+                    Err(_) => 0,
+                };
+            }
+            progress.phase("copying", Some(bytes));
         }
+
+        let mut batch = Batch::default();
+        for item in items {
+            let name = Path::new(&item.path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let target = join_path(destination, name);
+            if policy == CollisionPolicy::Fail && to.exists(&target).await.unwrap_or(false) {
+                batch.failed(
+                    &item.path,
+                    FinderError::Conflict(format!("{} already exists.", target)),
+                );
+                continue;
+            }
+            if same_storage
+                && (destination == item.path || destination.starts_with(&format!("{}/", item.path)))
+            {
+                batch.failed(
+                    &item.path,
+                    FinderError::BadRequest(if remove_source {
+                        "A folder cannot be moved into itself.".to_string()
+                    } else {
+                        "A folder cannot be copied into itself.".to_string()
+                    }),
+                );
+                continue;
+            }
+            if remove_source {
+                if let Err(e) = self.check_lock(from, &item.path).await {
+                    batch.failed(&item.path, e);
+                    continue;
+                }
+            }
+
+            let resolved = match resolve_collision(to, &target, policy).await {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    batch.failed(&item.path, e.into());
+                    continue;
+                }
+            };
+            if resolved.skip {
+                batch.skipped(&item.path, resolved.path);
+                continue;
+            }
+            if let Err(e) = self.check_write(to, &resolved.path).await {
+                batch.failed(&item.path, e);
+                continue;
+            }
+            if let Err(e) = self
+                .check_tree_write(from, &item.path, &resolved.path)
+                .await
+            {
+                batch.failed(&item.path, e);
+                continue;
+            }
+
+            let result = match (remove_source, same_storage) {
+                (true, true) => move_item(from, &item.path, &resolved.path, limit).await,
+                (true, false) => {
+                    move_between(from, &item.path, to, &resolved.path, limit, copied).await
+                }
+                (false, _) => copy_item(from, &item.path, to, &resolved.path, limit, copied)
+                    .await
+                    .map(|_| ()),
+            };
+            match result {
+                Ok(()) => {
+                    if remove_source {
+                        self.unindex_content(from, &item.path).await;
+                    }
+                    self.hook_after_write(to, &resolved.path).await;
+                    batch.succeeded(&item.path, Some(resolved.path));
+                }
+                Err(e) => batch.failed(&item.path, e.into()),
+            }
+        }
+        batch
+    }
+
+    // Mirror the folder `source` into `target`, possibly on another adapter, as
+    // a job whose result is a `SyncResponse`
+    pub async fn sync(data: Arc<VueFinder>, query: Query, request: SyncRequest) -> FinderResponse {
+        let from_adapter = request
+            .from_adapter
+            .clone()
+            .or_else(|| query.adapter.clone())
+            .unwrap_or_default();
+        let to_adapter = request
+            .to_adapter
+            .clone()
+            .unwrap_or_else(|| from_adapter.clone());
+        let (Some(from), Some(to)) = (
+            data.storages.get(&from_adapter).cloned(),
+            data.storages.get(&to_adapter).cloned(),
+        ) else {
+            return FinderResponse::BadRequest()
+                .json(ErrorResponse::new("Unknown source or destination adapter."));
+        };
+
+        match from.metadata(&request.source).await {
+            Ok(item) if item.node_type == "dir" => {}
+            Ok(_) => {
+                return FinderError::BadRequest("Only folders can be synced.".to_string())
+                    .error_response()
+            }
+            Err(e) => return FinderError::from(e).error_response(),
+        }
+        let inside = |dir: &str, path: &str| {
+            let dir = dir.trim_end_matches('/');
+            path == dir || path.starts_with(&format!("{}/", dir))
+        };
+        if from.name() == to.name()
+            && (inside(&request.source, &request.target)
+                || inside(&request.target, &request.source))
+        {
+            return FinderError::BadRequest(
+                "A folder cannot be synced with itself or a folder inside it.".to_string(),
+            )
+            .error_response();
+        }
+
+        let finder = data.clone();
+        let id = data.jobs.spawn("sync", |progress| async move {
+            finder
+                .sync_dirs(from.as_ref(), to.as_ref(), &request, &progress)
+                .await
+        });
+        FinderResponse::Accepted().json(JobResponse { id })
+    }
+
+    async fn sync_dirs(
+        &self,
+        from: &dyn StorageAdapter,
+        to: &dyn StorageAdapter,
+        request: &SyncRequest,
+        progress: &JobProgress,
+    ) -> Result<SyncResponse, FinderError> {
+        progress.phase("scanning", None);
+        let plan = sync::plan(
+            from,
+            &request.source,
+            to,
+            &request.target,
+            request.compare,
+            request.delete,
+            self.config.walk_limit,
+        )
+        .await?;
+        let bytes = plan.size();
+        let mut result = SyncResponse {
+            unchanged: plan.unchanged,
+            failed: plan
+                .conflicts
+                .into_iter()
+                .map(|path| SyncFailure {
+                    path,
+                    message: "An entry is in the way, sync with `delete` to replace it."
+                        .to_string(),
+                })
+                .collect(),
+            ..SyncResponse::default()
+        };
+        if request.dry_run {
+            result.status = result.failed.is_empty();
+            result.copied = plan.copy.into_iter().map(|(path, _)| path).collect();
+            result.created = plan.create;
+            result.deleted = plan.delete;
+            return Ok(result);
+        }
+
+        progress.phase("copying", Some(bytes));
+        if !to.exists(&request.target).await? {
+            self.check_write(to, &request.target).await?;
+            to.create_dir(&request.target).await?;
+        }
+        let failed = |path: String, e: FinderError| SyncFailure {
+            path,
+            message: e.to_string(),
+        };
+
+        // Deleted first, so entries in the way are gone before the copies
+        for relative in plan.delete {
+            let path = join_path(&request.target, &relative);
+            match self.sync_delete(to, &path).await {
+                Ok(()) => result.deleted.push(relative),
+                Err(e) => result.failed.push(failed(relative, e)),
+            }
+        }
+        if !result.deleted.is_empty() {
+            self.purge_trash(to).await;
+        }
+        for relative in plan.create {
+            let path = join_path(&request.target, &relative);
+            let created = match self.check_write(to, &path).await {
+                Ok(()) => to.create_dir(&path).await.map_err(FinderError::from),
+                Err(e) => Err(e),
+            };
+            match created {
+                Ok(()) => {
+                    self.hook_after_write(to, &path).await;
+                    result.created.push(relative);
+                }
+                Err(e) => result.failed.push(failed(relative, e)),
+            }
+        }
+        for (relative, item) in plan.copy {
+            let path = join_path(&request.target, &relative);
+            let copied = match self.check_write(to, &path).await {
+                Ok(()) => copy_file(from, &item.path, to, &path, Some(progress))
+                    .await
+                    .map_err(FinderError::from),
+                Err(e) => Err(e),
+            };
+            match copied {
+                Ok(()) => {
+                    self.hook_after_write(to, &path).await;
+                    result.copied.push(relative);
+                }
+                Err(e) => result.failed.push(failed(relative, e)),
+            }
+        }
+        result.status = result.failed.is_empty();
+        Ok(result)
+    }
+
+    // Delete `path` for `sync`, into the trash when it's on
+    async fn sync_delete(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderError> {
+        self.check_lock(storage, path).await?;
+        for hook in self.hooks.iter() {
+            hook.before_delete(&storage.name(), path).await?;
+        }
+        if self.config.trash && !trash::in_trash(storage, path) {
+            trash::move_to_trash(storage, path, self.config.walk_limit).await?;
+        } else {
+            storage.delete(path).await?;
+        }
+        self.hook_after_delete(storage, path).await;
+        Ok(())
+    }
+
+    // Copy items from one adapter to another, reporting the outcome of each
+    // item. Checked like a `copy` between adapters, but answered right away
+    // instead of as a job.
+    pub async fn copy_between_adapters(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: CopyBetweenAdaptersRequest,
+    ) -> FinderResponse {
+        let policy = query.conflict.unwrap_or(CollisionPolicy::Fail);
+
+        let (from, to) = match (
+            data.storages.get(&payload.from_adapter),
+            data.storages.get(&payload.to_adapter),
+        ) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                return FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("Unknown source or destination adapter."))
+            }
+        };
+
+        if let Err(response) = data.hook_before_write(to.as_ref(), &payload.target).await {
+            return response;
+        }
+        // Also checks that the destination accepts writes
+        if let Err(e) = to.create_dir(&payload.target).await {
+            return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(format!(
+                "Destination is not writable: {}",
+                e
+            )));
+        }
+
+        let batch = data
+            .transfer_items(
+                from.as_ref(),
+                to.as_ref(),
+                &payload.target,
+                &payload.items,
+                (false, policy),
+                None,
+            )
+            .await;
+        FinderResponse::Ok().json(batch.into_report())
+    }
+
+    pub async fn delete(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: DeleteRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        // Nothing is deleted when a hook refuses one of the items
+        for item in &payload.items {
+            if let Err(response) = data.hook_before_delete(storage.as_ref(), &item.path).await {
+                return response;
+            }
+        }
+
+        let mut batch = Batch::default();
+        for item in &payload.items {
+            if let Err(e) = data.check_lock(storage.as_ref(), &item.path).await {
+                batch.failed(&item.path, e);
+                continue;
+            }
+            // Items already in the trash are deleted for good
+            let deleted = if data.config.trash && !trash::in_trash(storage.as_ref(), &item.path) {
+                trash::move_to_trash(storage.as_ref(), &item.path, data.config.walk_limit)
+                    .await
+                    .map(|_| ())
+            } else {
+                storage.delete(&item.path).await
+            };
+            match deleted {
+                Ok(()) => {
+                    data.hook_after_delete(storage.as_ref(), &item.path).await;
+                    batch.succeeded(&item.path, None);
+                }
+                Err(e) => batch.failed(&item.path, e.into()),
+            }
+        }
+        data.purge_trash(storage.as_ref()).await;
+
+        match batch.into_response() {
+            Some(response) => response,
+            None => Self::index(data, query).await,
+        }
+    }
+
+    // Drop trashed items past the retention period; failures are retried on the next call
+    async fn purge_trash(&self, storage: &dyn StorageAdapter) {
+        if let Some(days) = self.config.trash_retention_days {
+            if let Err(e) = trash::purge(storage, days * 24 * 60 * 60).await {
+                log::warn!("Failed to purge trash of {}: {}", storage.name(), e);
+            }
+        }
+    }
+
+    // Keep what's at `path` as a version before it's overwritten, when `versioning` is on
+    async fn snapshot(
+        &self,
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> Result<(), FinderResponse> {
+        if !self.config.versioning {
+            return Ok(());
+        }
+        if let Err(e) = versions::snapshot(storage, path).await {
+            return Err(FinderError::from(e).error_response());
+        }
+        self.prune_versions(storage, path).await;
+        Ok(())
+    }
+
+    // Drop versions of `path` past `max_versions` and `version_retention_days`;
+    // failures are retried on the next save
+    async fn prune_versions(&self, storage: &dyn StorageAdapter, path: &str) {
+        let max_age_secs = self
+            .config
+            .version_retention_days
+            .map(|days| days * 24 * 60 * 60);
+        if let Err(e) = versions::prune(storage, path, self.config.max_versions, max_age_secs).await
+        {
+            log::warn!("Failed to prune versions of {}: {}", path, e);
+        }
+    }
+
+    pub async fn trash_list(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        data.purge_trash(storage.as_ref()).await;
+        match trash::list(storage.as_ref()).await {
+            Ok(items) => FinderResponse::Ok().json(TrashListResponse {
+                adapter: storage.name(),
+                items,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Move trashed items back to where they were deleted from
+    pub async fn trash_restore(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: TrashRequest,
+    ) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+        let policy = query.conflict.unwrap_or(CollisionPolicy::Rename);
+
+        let mut results = Vec::with_capacity(payload.items.len());
+        for id in &payload.items {
+            let restored =
+                trash::restore(storage.as_ref(), id, policy, data.config.walk_limit).await;
+            let mut result = RestoreResult {
+                id: id.clone(),
+                status: restored.is_ok(),
+                path: None,
+                skipped: false,
+                message: None,
+            };
+            match restored {
+                Ok(Some(path)) => result.path = Some(path),
+                Ok(None) => result.skipped = true,
+                Err(e) => result.message = Some(e.to_string()),
+            }
+            results.push(result);
+        }
+
+        let restored = results.iter().filter(|r| r.status).count();
+        FinderResponse::Ok().json(TrashRestoreResponse {
+            status: restored == results.len(),
+            restored,
+            total: results.len(),
+            results,
+        })
+    }
+
+    // Permanently delete the given trashed items, or all of them
+    pub async fn trash_empty(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: TrashRequest,
+    ) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let emptied = if payload.items.is_empty() {
+            trash::purge(storage.as_ref(), 0).await
+        } else {
+            let mut result = Ok(());
+            for id in &payload.items {
+                result = trash::remove(storage.as_ref(), id).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            result
+        };
+        if let Err(e) = emptied {
+            return FinderError::from(e).error_response();
+        }
+
+        Self::trash_list(data, query).await
+    }
+
+    // Earlier contents of the file at `path`, newest first
+    pub async fn versions_list(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        data.prune_versions(storage.as_ref(), &path).await;
+        match versions::list(storage.as_ref(), &path).await {
+            Ok(versions) => FinderResponse::Ok().json(VersionsListResponse { path, versions }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Put a version of the file at `path` back, answered with its versions.
+    // The contents it replaces are kept as the newest version.
+    pub async fn versions_restore(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: VersionRestoreRequest,
+    ) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &path).await {
+            return response;
+        }
+        if let Err(e) = versions::restore(storage.as_ref(), &path, &payload.id).await {
+            return FinderError::from(e).error_response();
+        }
+        data.hook_after_write(storage.as_ref(), &path).await;
+
+        Self::versions_list(data, query).await
+    }
+
+    // Unified diff from version `id` of the file at `path` to its current contents
+    pub async fn versions_diff(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+        let Some(id) = query.id.clone() else {
+            return FinderResponse::BadRequest().json(ErrorResponse::new("Missing version id"));
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        if let Err(response) = data.hook_before_download(storage.as_ref(), &path).await {
+            return response;
+        }
+        Self::text_diff(storage.as_ref(), (&path, Some(&id)), (&path, None)).await
+    }
+
+    pub async fn upload(
+        data: Arc<VueFinder>,
+        query: Query,
+        mut payload: multer::Multipart<'static>,
+    ) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        // The file is streamed to storage as it arrives, so the `name` and
+        // `on_conflict` fields have to come before it. Without a name the
+        // part's own filename is used.
+        let mut filename = String::new();
+        let mut policy = query.conflict;
+        loop {
+            let mut field = match payload.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(e) => return upload_interrupted(e),
+            };
+
+            match field.name() {
+                Some(name @ ("name" | "on_conflict")) => {
+                    let name = name.to_string();
+                    let mut value = Vec::new();
+                    loop {
+                        match field.try_next().await {
+                            Ok(Some(chunk)) => value.extend_from_slice(&chunk),
+                            Ok(None) => break,
+                            Err(e) => return upload_interrupted(e),
+                        }
+                    }
+                    let value = String::from_utf8_lossy(&value).to_string();
+                    if name == "name" {
+                        filename = value;
+                        continue;
+                    }
+                    match value.trim().parse() {
+                        Ok(conflict) => policy = Some(conflict),
+                        Err(message) => {
+                            return FinderResponse::BadRequest().json(ErrorResponse::new(message))
+                        }
+                    }
+                }
+                Some("file") => {
+                    if filename.is_empty() {
+                        filename = field.file_name().unwrap_or_default().to_string();
+                    }
+                    let storage = storage.clone();
+                    return Self::store_upload(data, query, storage, filename, policy, field).await;
+                }
+                _ => continue,
+            }
+        }
+
+        FinderResponse::BadRequest().json(ErrorResponse::new("Missing file or filename"))
+    }
+
+    // Stream one uploaded file into `storage`
+    async fn store_upload(
+        data: Arc<VueFinder>,
+        query: Query,
+        storage: Arc<dyn StorageAdapter>,
+        filename: String,
+        policy: Option<CollisionPolicy>,
+        mut field: multer::Field<'static>,
+    ) -> FinderResponse {
+        if filename.is_empty() {
+            return FinderResponse::BadRequest()
+                .json(ErrorResponse::new("Missing file or filename"));
+        }
+
+        let filename = match data.clean_name(&filename) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+        if let Err(e) = file_types::check_name(&data.config, &filename) {
+            return e.error_response();
+        }
+
+        let declared_length = field
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+
+        // Refuse uploads that would fill the volume, up front when the size is
+        // declared and otherwise once more than the free space has arrived
+        let available = if data.config.check_disk_space {
+            storage
+                .usage()
+                .await
+                .ok()
+                .flatten()
+                .map(|usage| usage.available)
+        } else {
+            None
+        };
+        let no_space = || {
+            FinderResponse::InsufficientStorage().json(ErrorResponse::new(
+                "Not enough storage space for this upload.",
+            ))
+        };
+        if let (Some(available), Some(declared_length)) = (available, declared_length) {
+            if available < declared_length {
+                return no_space();
+            }
+        }
+        if let (Some(max), Some(declared_length)) = (data.config.max_upload_size, declared_length) {
+            if declared_length > max {
+                return upload_too_large(max);
+            }
+        }
+
+        // Build file path
+        let filepath = format!("{}/{}", query.path.clone().unwrap_or_default(), filename);
+        let policy = policy.unwrap_or(CollisionPolicy::Overwrite);
+        let resolved = match resolve_collision(storage.as_ref(), &filepath, policy).await {
+            Ok(resolved) => resolved,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        if resolved.skip {
+            return Self::index(data, query).await;
+        }
+        if let Err(response) = data
+            .hook_before_write(storage.as_ref(), &resolved.path)
+            .await
+        {
+            return response;
+        }
+        if let Err(response) = data.snapshot(storage.as_ref(), &resolved.path).await {
+            return response;
+        }
+
+        let enforce_content_length = data.config.enforce_content_length;
+        let config = data.config.clone();
+        // The multipart field can't leave this task, so it is pumped through a
+        // channel into the storage write running alongside
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Result<Bytes, StorageError>>(8);
+        let chunks = Box::pin(async_stream::stream! {
+            while let Some(chunk) = receiver.recv().await {
+                yield chunk;
+            }
+        });
+        let pump = async move {
+            let mut received = 0u64;
+            let failure = loop {
+                let chunk = match field.try_next().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break None,
+                    Err(e) => break Some(upload_interrupted(e)),
+                };
+                // The first chunk shows what the file really is
+                if received == 0 {
+                    if let Err(e) = file_types::check_contents(&config, &chunk) {
+                        break Some(e.error_response());
+                    }
+                }
+                received += chunk.len() as u64;
+                if let Some(max) = config.max_upload_size.filter(|max| received > *max) {
+                    break Some(upload_too_large(max));
+                }
+                if available.is_some_and(|available| received > available) {
+                    break Some(no_space());
+                }
+                // The write already failed when the receiver is gone
+                if sender.send(Ok(chunk)).await.is_err() {
+                    return None;
+                }
+            };
+
+            // A length mismatch points at a truncated or padded transfer
+            let failure = failure.or_else(|| match declared_length {
+                Some(declared_length) if enforce_content_length && declared_length != received => {
+                    Some(
+                        FinderResponse::BadRequest().json(ErrorResponse::new(format!(
+                            "Upload size mismatch: declared {} bytes, received {}",
+                            declared_length, received
+                        ))),
+                    )
+                }
+                _ if received == 0 => Some(
+                    FinderResponse::BadRequest()
+                        .json(ErrorResponse::new("Missing file or filename")),
+                ),
+                _ => None,
+            });
+            if failure.is_some() {
+                let aborted = std::io::Error::other("upload aborted");
+                let _ = sender.send(Err(StorageError::Io(aborted))).await;
+            }
+            failure
+        };
+
+        let staged = data.staging_path(storage.as_ref(), &resolved.path);
+        let (failure, written) = futures_util::join!(pump, storage.write_stream(&staged, chunks));
+        if failure.is_some() || written.is_err() {
+            data.discard_staged(storage.as_ref(), &staged, &resolved.path)
+                .await;
+        }
+        if let Some(response) = failure {
+            return response;
+        }
+        if let Err(e) = written {
+            return FinderError::from(e).error_response();
+        }
+        if let Err(response) = data
+            .scan_written(storage.as_ref(), &staged, &resolved.path)
+            .await
+        {
+            return response;
+        }
+        data.hook_after_write(storage.as_ref(), &resolved.path)
+            .await;
+
+        Self::index(data, query).await
+    }
+
+    // Download `url` into the `path` folder on the server's side, streamed
+    // like an upload and held to the same checks
+    pub async fn fetch_url(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: FetchUrlRequest,
+    ) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+        if !data.fetcher.enabled() {
+            return FinderResponse::BadRequest().json(ErrorResponse::new(
+                "Fetching URLs needs the `fetch` feature",
+            ));
+        }
+
+        let config = &data.config.fetch_url;
+        let url = match url::Url::parse(payload.url.trim()) {
+            Ok(url) => url,
+            Err(e) => {
+                return FinderResponse::BadRequest()
+                    .json(ErrorResponse::new(format!("Invalid URL: {}", e)))
+            }
+        };
+        if let Err(message) = fetch_url::check_url(&url, config) {
+            return FinderResponse::BadRequest().json(ErrorResponse::new(message));
+        }
+        let download = match data.fetcher.fetch(&url, config).await {
+            Ok(download) => download,
+            Err(message) => {
+                return FinderResponse::BadGateway()
+                    .json(ErrorResponse::new(format!("Download failed: {}", message)))
+            }
+        };
+
+        let Some(filename) = payload
+            .name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .or_else(|| fetch_url::file_name(&url, &download))
+        else {
+            return FinderResponse::BadRequest().json(ErrorResponse::new("Missing file name"));
+        };
+        let filename = match data.clean_name(&filename) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+        if let Err(e) = file_types::check_name(&data.config, &filename) {
+            return e.error_response();
+        }
+
+        let max_size = config.max_size.or(data.config.max_upload_size);
+        let available = if data.config.check_disk_space {
+            storage
+                .usage()
+                .await
+                .ok()
+                .flatten()
+                .map(|usage| usage.available)
+        } else {
+            None
+        };
+        let no_space = || {
+            FinderResponse::InsufficientStorage().json(ErrorResponse::new(
+                "Not enough storage space for this download.",
+            ))
+        };
+        if let (Some(max), Some(length)) = (max_size, download.length) {
+            if length > max {
+                return upload_too_large(max);
+            }
+        }
+        if let (Some(available), Some(length)) = (available, download.length) {
+            if available < length {
+                return no_space();
+            }
+        }
+
+        let filepath = format!("{}/{}", query.path.clone().unwrap_or_default(), filename);
+        let policy = query.conflict.unwrap_or(CollisionPolicy::Overwrite);
+        let resolved = match resolve_collision(storage.as_ref(), &filepath, policy).await {
+            Ok(resolved) => resolved,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        if resolved.skip {
+            return Self::index(data, query).await;
+        }
+        if let Err(response) = data
+            .hook_before_write(storage.as_ref(), &resolved.path)
+            .await
+        {
+            return response;
+        }
+        if let Err(response) = data.snapshot(storage.as_ref(), &resolved.path).await {
+            return response;
+        }
+
+        // Checked as it arrives, the storage write runs alongside and is
+        // aborted by a failure
+        let file_config = data.config.clone();
+        let mut body = download.body;
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Result<Bytes, StorageError>>(8);
+        let chunks = Box::pin(async_stream::stream! {
+            while let Some(chunk) = receiver.recv().await {
+                yield chunk;
+            }
+        });
+        let pump = async move {
+            let mut received = 0u64;
+            let failure = loop {
+                let chunk = match body.next().await {
+                    Some(Ok(chunk)) => chunk,
+                    None => break None,
+                    Some(Err(message)) => {
+                        break Some(
+                            FinderResponse::BadGateway()
+                                .json(ErrorResponse::new(format!("Download failed: {}", message))),
+                        )
+                    }
+                };
+                if received == 0 {
+                    if let Err(e) = file_types::check_contents(&file_config, &chunk) {
+                        break Some(e.error_response());
+                    }
+                }
+                received += chunk.len() as u64;
+                if let Some(max) = max_size.filter(|max| received > *max) {
+                    break Some(upload_too_large(max));
+                }
+                if available.is_some_and(|available| received > available) {
+                    break Some(no_space());
+                }
+                if sender.send(Ok(chunk)).await.is_err() {
+                    return None;
+                }
+            };
+            if failure.is_some() {
+                let aborted = std::io::Error::other("download aborted");
+                let _ = sender.send(Err(StorageError::Io(aborted))).await;
+            }
+            failure
+        };
+
+        let staged = data.staging_path(storage.as_ref(), &resolved.path);
+        let (failure, written) = futures_util::join!(pump, storage.write_stream(&staged, chunks));
+        if failure.is_some() || written.is_err() {
+            data.discard_staged(storage.as_ref(), &staged, &resolved.path)
+                .await;
+        }
+        if let Some(response) = failure {
+            return response;
+        }
+        if let Err(e) = written {
+            return FinderError::from(e).error_response();
+        }
+        if let Err(response) = data
+            .scan_written(storage.as_ref(), &staged, &resolved.path)
+            .await
+        {
+            return response;
+        }
+        data.hook_after_write(storage.as_ref(), &resolved.path)
+            .await;
+
+        Self::index(data, query).await
+    }
+
+    // Open a resumable upload into `path`, chunks are then sent with `upload-chunk`
+    pub async fn upload_init(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: UploadInitRequest,
+    ) -> FinderResponse {
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let storage = match data.storages.get(&adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let name = match data.clean_name(&payload.name) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+        if let Err(e) = file_types::check_name(&data.config, &name) {
+            return e.error_response();
+        }
+
+        if let (Some(max), Some(size)) = (data.config.max_upload_size, payload.size) {
+            if size > max {
+                return upload_too_large(max);
+            }
+        }
+
+        if data.config.check_disk_space {
+            if let (Some(size), Ok(Some(usage))) = (payload.size, storage.usage().await) {
+                if usage.available < size {
+                    return FinderResponse::InsufficientStorage().json(ErrorResponse::new(
+                        "Not enough storage space for this upload.",
+                    ));
+                }
+            }
+        }
+
+        data.upload_sessions
+            .prune(Duration::from_secs(data.config.upload_session_ttl_secs))
+            .await;
+
+        let path = query.path.clone().unwrap_or_default();
+        match data
+            .upload_sessions
+            .create(
+                data.principal.as_deref(),
+                &adapter,
+                &path,
+                &name,
+                payload.size,
+            )
+            .await
+        {
+            Ok(id) => FinderResponse::Ok().json(UploadSessionResponse {
+                status: true,
+                id,
+                offset: 0,
+                name: None,
+                size: None,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Append the `file` field to the session `id` at `offset`
+    pub async fn upload_chunk(
+        data: Arc<VueFinder>,
+        query: Query,
+        mut payload: multer::Multipart<'static>,
+    ) -> FinderResponse {
+        let (id, offset) = match (&query.id, query.offset) {
+            (Some(id), Some(offset)) => (id, offset),
+            _ => {
+                return FinderResponse::BadRequest()
+                    .json(ErrorResponse::new("Missing upload id or offset"))
+            }
+        };
+
+        let max_upload_size = data.config.max_upload_size;
+        let too_large = |len: usize| max_upload_size.filter(|max| offset + len as u64 > *max);
+        let mut chunk = Vec::new();
+        let received: Result<(), multer::Error> = async {
+            while let Some(mut field) = payload.next_field().await? {
+                if field.name() == Some("file") {
+                    while let Some(bytes) = field.try_next().await? {
+                        chunk.extend_from_slice(&bytes);
+                        // Stop reading, the chunk is refused anyway
+                        if too_large(chunk.len()).is_some() {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        .await;
+        if let Some(max) = too_large(chunk.len()) {
+            return upload_too_large(max);
+        }
+
+        // Nothing is appended, the client resends the chunk from the same offset
+        if let Err(e) = received {
+            return FinderResponse::BadRequest()
+                .json(ErrorResponse::new(format!("Upload interrupted: {}", e)));
+        }
+
+        match data
+            .upload_sessions
+            .append(id, data.principal.as_deref(), offset, &chunk)
+            .await
+        {
+            Ok(offset) => FinderResponse::Ok().json(UploadSessionResponse {
+                status: true,
+                id: id.to_string(),
+                offset,
+                name: None,
+                size: None,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Where a resumed upload should continue from
+    pub async fn upload_status(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let id = query.id.clone().unwrap_or_default();
+        match data
+            .upload_sessions
+            .status(&id, data.principal.as_deref())
+            .await
+        {
+            Ok(session) => FinderResponse::Ok().json(UploadSessionResponse {
+                status: true,
+                id,
+                offset: session.offset,
+                name: Some(session.name),
+                size: session.size,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Write the assembled file through its adapter and close the session
+    pub async fn upload_finalize(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: UploadSessionRequest,
+    ) -> FinderResponse {
+        let staged = match data
+            .upload_sessions
+            .finish(&payload.id, data.principal.as_deref())
+            .await
+        {
+            Ok(staged) => staged,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        let session = &staged.session;
+        // Streamed from the staging file, never held in memory whole
+        let contents = || async {
+            staged
+                .contents()
+                .await
+                .map(|contents| contents.map_err(StorageError::from).boxed())
+                .map_err(|e| FinderError::from(UploadSessionError::from(e)).error_response())
+        };
+        let storage = match data.storages.get(&session.adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        // The name was checked by `upload-init`, the contents only arrive now
+        let sniffed = match contents().await {
+            Ok(mut contents) => match contents.try_next().await {
+                Ok(head) => file_types::check_contents(&data.config, &head.unwrap_or_default())
+                    .map_err(|e| e.error_response()),
+                Err(e) => Err(FinderError::from(e).error_response()),
+            },
+            Err(response) => Err(response),
+        };
+        if let Err(response) = sniffed {
+            return response;
+        }
+
+        let filepath = join_path(&session.path, &session.name);
+        let policy = query.conflict.unwrap_or(CollisionPolicy::Overwrite);
+        let resolved = match resolve_collision(storage.as_ref(), &filepath, policy).await {
+            Ok(resolved) => resolved,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        if !resolved.skip {
+            if let Err(response) = data
+                .hook_before_write(storage.as_ref(), &resolved.path)
+                .await
+            {
+                return response;
+            }
+            let scanned = match contents().await {
+                Ok(contents) => data.scan(storage.as_ref(), &resolved.path, contents).await,
+                Err(response) => Err(response),
+            };
+            if let Err(response) = scanned {
+                return response;
+            }
+            if let Err(response) = data.snapshot(storage.as_ref(), &resolved.path).await {
+                return response;
+            }
+            let written = match contents().await {
+                Ok(contents) => storage
+                    .write_stream(&resolved.path, contents)
+                    .await
+                    .map_err(|e| FinderError::from(e).error_response()),
+                Err(response) => Err(response),
+            };
+            if let Err(response) = written {
+                return response;
+            }
+            data.hook_after_write(storage.as_ref(), &resolved.path)
+                .await;
+        }
+
+        Self::index(data, query).await
+    }
+
+    pub async fn upload_abort(
+        data: Arc<VueFinder>,
+        payload: UploadSessionRequest,
+    ) -> FinderResponse {
+        let removed = data
+            .upload_sessions
+            .remove(&payload.id, data.principal.as_deref())
+            .await;
+        FinderResponse::Ok().json(StatusResponse {
+            status: removed,
+            message: None,
+        })
+    }
+
+    pub async fn share_create(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: ShareCreateRequest,
+    ) -> FinderResponse {
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let storage = match data.storages.get(&adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        match storage.exists(&payload.path).await {
+            Ok(true) => {}
+            Ok(false) => return FinderResponse::NotFound().finish(),
+            Err(e) => return FinderError::from(e).error_response(),
+        }
+
+        let options = NewShareLink {
+            password: payload.password,
+            expires_in_secs: payload.expires_in_secs,
+            max_downloads: payload.max_downloads,
+        };
+        // Links outlive the request, they keep the path every user resolves the same
+        let path = storage.shared_path(&payload.path);
+        match data
+            .shares
+            .create(&adapter, &path, data.principal.as_deref(), options)
+            .await
+        {
+            Ok(mut link) => {
+                link.path = payload.path;
+                FinderResponse::Ok().json(ShareLinkResponse::from(link))
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Links of the queried adapter, of every adapter without one
+    pub async fn share_list(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        match data.shares.list(query.adapter.as_deref()).await {
+            Ok(links) => FinderResponse::Ok().json(ShareListResponse {
+                links: links
+                    .into_iter()
+                    .filter_map(|link| data.visible_link(link))
+                    .map(ShareLinkResponse::from)
+                    .collect(),
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn share_revoke(data: Arc<VueFinder>, payload: ShareRevokeRequest) -> FinderResponse {
+        // Their own links, or those whose path they see
+        let allowed = |link: &ShareLink| {
+            (link.owner.is_some() && link.owner == data.principal)
+                || data.visible_link(link.clone()).is_some()
+        };
+        match data.shares.revoke(&payload.id, allowed).await {
+            Ok(revoked) => FinderResponse::Ok().json(StatusResponse {
+                status: revoked,
+                message: None,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Paths the request's user recently opened or modified on the queried
+    // adapter, of every adapter without one, most recent first. Those since
+    // moved away are left out.
+    pub async fn recent(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let entries = match data
+            .recent
+            .list(data.principal.as_deref(), query.adapter.as_deref())
+            .await
+        {
+            Ok(entries) => entries,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+
+        let mut recent = Vec::new();
+        for entry in entries {
+            let Some(path) = data.visible_path(&entry.adapter, &entry.path) else {
+                continue;
+            };
+            if !data.visible(&query, &path) {
+                continue;
+            }
+            let Some(storage) = data.storages.get(&entry.adapter) else {
+                continue;
+            };
+            let Ok(item) = storage.metadata(&path).await else {
+                continue;
+            };
+            recent.push(RecentItem {
+                item,
+                adapter: entry.adapter,
+                action: entry.action,
+                at: entry.at,
+            });
+        }
+        FinderResponse::Ok().json(RecentResponse { recent })
+    }
+
+    // Favorites of the request's user on the queried adapter, of every adapter
+    // without one. Those that no longer exist are listed without a type.
+    pub async fn favorites_list(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let favorites = match data
+            .favorites
+            .list(data.principal.as_deref(), query.adapter.as_deref())
+            .await
+        {
+            Ok(favorites) => favorites,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+
+        let mut list = Vec::new();
+        for favorite in favorites {
+            let Some(path) = data.visible_path(&favorite.adapter, &favorite.path) else {
+                continue;
+            };
+            if !data.visible(&query, &path) {
+                continue;
+            }
+            let item = match data.storages.get(&favorite.adapter) {
+                Some(storage) => storage.metadata(&path).await.ok(),
+                None => None,
+            };
+            let basename = match &item {
+                Some(item) => item.basename.clone(),
+                None => Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            list.push(FavoriteResponse {
+                adapter: favorite.adapter,
+                path,
+                basename,
+                node_type: item.map(|item| item.node_type),
+                created_at: favorite.created_at,
+            });
+        }
+        FinderResponse::Ok().json(FavoritesResponse { favorites: list })
+    }
+
+    // Bookmark a file or folder for the request's user, then list their favorites
+    pub async fn favorites_add(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: FavoriteRequest,
+    ) -> FinderResponse {
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let storage = match data.storages.get(&adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        match storage.exists(&payload.path).await {
+            Ok(true) => {}
+            Ok(false) => return FinderResponse::NotFound().finish(),
+            Err(e) => return FinderError::from(e).error_response(),
+        }
+        // Kept as every user resolves the path, like share links
+        let path = storage.shared_path(&payload.path);
+        if let Err(e) = data
+            .favorites
+            .add(data.principal.as_deref(), &adapter, &path)
+            .await
+        {
+            return FinderError::from(e).error_response();
+        }
+        Self::favorites_list(data, query).await
+    }
+
+    pub async fn favorites_remove(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: FavoriteRequest,
+    ) -> FinderResponse {
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let storage = match data.storages.get(&adapter) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = storage.shared_path(&payload.path);
+        if let Err(e) = data
+            .favorites
+            .remove(data.principal.as_deref(), &adapter, &path)
+            .await
+        {
+            return FinderError::from(e).error_response();
+        }
+        Self::favorites_list(data, query).await
+    }
+
+    // Take or renew the lock on a file. Until it's released or expires, other
+    // users get 423 from commands changing the file.
+    pub async fn lock(data: Arc<VueFinder>, query: Query, payload: LockRequest) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        match storage.metadata(&payload.item).await {
+            Ok(item) if item.node_type == "dir" => {
+                return FinderError::BadRequest("Only files can be locked.".to_string())
+                    .error_response()
+            }
+            Ok(_) => {}
+            Err(e) => return FinderError::from(e).error_response(),
+        }
+        let max_ttl = data.config.lock_ttl_secs;
+        let ttl = payload.ttl_secs.unwrap_or(max_ttl).min(max_ttl);
+        let locked = data
+            .locks
+            .acquire(
+                &storage.name(),
+                &storage.shared_path(&payload.item),
+                data.lock_owner(),
+                Duration::from_secs(ttl),
+            )
+            .await;
+        match locked {
+            Ok(mut lock) => {
+                lock.path = payload.item;
+                FinderResponse::Ok().json(lock)
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn unlock(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: UnlockRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = storage.shared_path(&payload.item);
+        match data
+            .locks
+            .release(&storage.name(), &path, data.lock_owner())
+            .await
+        {
+            Ok(()) => FinderResponse::Ok().json(StatusResponse {
+                status: true,
+                message: None,
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Locks in force on the queried adapter
+    pub async fn locks(data: Arc<VueFinder>, query: Query) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        match data.locks.list(&storage.name()).await {
+            Ok(locks) => FinderResponse::Ok().json(LocksResponse {
+                locks: locks
+                    .into_iter()
+                    .filter_map(|mut lock: FileLock| {
+                        lock.path = data.visible_path(&lock.adapter, &lock.path)?;
+                        Some(lock)
+                    })
+                    .collect(),
+            }),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    // Download what a share link points at, a ZIP for folders. Needs no token.
+    pub async fn share_download(
+        data: Arc<VueFinder>,
+        mut query: Query,
+        range: Option<ByteRange>,
+    ) -> FinderResponse {
+        let id = query.id.clone().unwrap_or_default();
+        let link = match data.shares.open(&id, query.password.as_deref()).await {
+            Ok(link) => link,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+        let storage = match data.storages.get(&link.adapter) {
+            Some(s) => s.clone(),
+            None => return FinderResponse::NotFound().finish(),
+        };
+
+        query.adapter = Some(link.adapter);
+        query.path = Some(link.path.clone());
+        query.items = None;
+        if storage.is_dir(&link.path).await.unwrap_or(false) {
+            Self::download_archive(data, query).await
+        } else {
+            Self::download(data, query, range).await
+        }
+    }
+
+    pub async fn archive(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: ArchiveRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let name = match data.clean_name(&payload.name) {
+            Ok(name) => name,
+            Err(message) => return FinderResponse::BadRequest().json(ErrorResponse::new(message)),
+        };
+
+        let archive_path = format!(
+            "{}/{}.{}",
+            query.path.clone().unwrap_or_default(),
+            name,
+            payload.format.extension()
+        );
+        if let Err(e) = file_types::check_name(&data.config, &archive_path) {
+            return e.error_response();
+        }
+
+        // Check if file already exists
+        if storage.exists(&archive_path).await.unwrap_or(false) {
+            return FinderError::Conflict(
+                "Archive already exists. Please use a different name.".to_string(),
+            )
+            .error_response();
+        }
+        if let Err(response) = data
+            .hook_before_write(storage.as_ref(), &archive_path)
+            .await
+        {
+            return response;
+        }
+
+        let mut archive = ArchiveWriter::new(payload.format);
+        for item in &payload.items {
+            let file_name = Path::new(&item.path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+
+            // Directories are added with all their descendants, files on their own
+            let entries = if storage.is_dir(&item.path).await.unwrap_or(false) {
+                match walk(storage.as_ref(), &item.path, data.config.walk_limit).await {
+                    Ok(walked) => {
+                        let mut entries = vec![(file_name.to_string(), None)];
+                        entries.extend(
+                            walked
+                                .into_iter()
+                                .filter(|entry| !data.walk_skips(&query, &entry.item.path))
+                                .map(|entry| {
+                                    let name = format!("{}/{}", file_name, entry.relative_path);
+                                    let is_dir = entry.item.node_type == "dir";
+                                    (name, (!is_dir).then_some(entry.item.path))
+                                }),
+                        );
+                        entries
+                    }
+                    Err(e) => {
+                        return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(
+                            format!("Failed to read source directory: {}", e),
+                        ));
+                    }
+                }
+            } else {
+                vec![(file_name.to_string(), Some(item.path.clone()))]
+            };
+
+            for (name, source) in entries {
+                let source = match source {
+                    Some(source) => source,
+                    None => {
+                        if let Err(e) = archive.add_directory(&name) {
+                            return FinderResponse::InternalServerError().json(ErrorResponse::new(
+                                format!("Failed to add directory to archive: {}", e),
+                            ));
+                        }
+                        continue;
+                    }
+                };
+
+                let contents = match storage.read(&source).await {
+                    Ok(contents) => contents,
+                    Err(e) => {
+                        return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(
+                            format!("Failed to read source file: {}", e),
+                        ));
+                    }
+                };
+
+                if let Err(e) = archive.add_file(&name, &contents) {
+                    return FinderResponse::InternalServerError().json(ErrorResponse::new(
+                        format!("Failed to add file to archive: {}", e),
+                    ));
+                }
+            }
+        }
+
+        let archive = match archive.finish() {
+            Ok(archive) => archive,
+            Err(e) => {
+                return FinderResponse::InternalServerError().json(ErrorResponse::new(format!(
+                    "Failed to finalize archive: {}",
+                    e
+                )));
+            }
+        };
+
+        if let Err(e) = storage.write(&archive_path, archive).await {
+            return FinderResponse::build(storage_status(&e))
+                .json(ErrorResponse::new(format!("Failed to save archive: {}", e)));
+        }
+        data.hook_after_write(storage.as_ref(), &archive_path).await;
+
+        Self::index(data, query).await
+    }
+
+    pub async fn unarchive(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: UnarchiveRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let contents = match storage.read(&payload.item).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                return FinderResponse::build(storage_status(&e))
+                    .json(ErrorResponse::new(format!("Failed to read archive: {}", e)));
+            }
+        };
+
+        let Some(format) = ArchiveFormat::detect(&payload.item, &contents) else {
+            return FinderResponse::BadRequest()
+                .json(ErrorResponse::new("Unsupported archive format."));
+        };
+        let mut archive = match ArchiveReader::open(format, contents, data.config.max_extract_size)
+        {
+            Ok(archive) => archive,
+            Err(e) => return archive_error(e, StatusCode::BAD_REQUEST, "Failed to open archive"),
+        };
+
+        let extract_path = format!(
+            "{}/{}",
+            query.path.clone().unwrap_or_default(),
+            archive_stem(
+                Path::new(&payload.item)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default()
+            )
+        );
+
+        // Report every entry that would land outside the extraction folder, that
+        // the target can't store, that the access rules don't let write or whose
+        // type isn't allowed, before writing anything
+        let mut invalid = Vec::new();
+        for i in 0..archive.len() {
+            let Ok(name) = archive.name(i) else {
+                continue;
+            };
+            let name = sanitize_name(&name);
+            let Some(path) = entry_path(&name) else {
+                invalid.push(InvalidEntry {
+                    name,
+                    message: "Absolute and parent paths aren't allowed.".to_string(),
+                });
+                continue;
+            };
+            if data.config.validate_archive_entries {
+                if let Err(e) = storage.validate_key(&path) {
+                    invalid.push(InvalidEntry {
+                        name,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            }
+            let outpath = format!("{}/{}", extract_path, path);
+            if !acl::allowed(&data.config.access_rules, &outpath, Permission::Write) {
+                invalid.push(InvalidEntry {
+                    name,
+                    message: format!("Permission denied: {}", outpath),
+                });
+                continue;
+            }
+            if !name.ends_with('/') {
+                let checked = file_types::check_name(&data.config, &path).and_then(|_| {
+                    let head = archive.head(i, 8192).unwrap_or_default();
+                    file_types::check_contents(&data.config, &head)
+                });
+                if let Err(e) = checked {
+                    invalid.push(InvalidEntry {
+                        name,
+                        message: e.to_string(),
+                    });
+                }
+            }
+        }
+        if !invalid.is_empty() {
+            return FinderResponse::BadRequest().json(ErrorResponse {
+                invalid,
+                ..ErrorResponse::new("Some archive entries can't be extracted.")
+            });
+        }
+
+        // Existing files are replaced unless the request or query says otherwise
+        let policy = payload
+            .conflict
+            .or(query.conflict)
+            .unwrap_or(CollisionPolicy::Overwrite);
+
+        if let Err(response) = data
+            .hook_before_write(storage.as_ref(), &extract_path)
+            .await
+        {
+            return response;
+        }
+
+        // Create extraction target directory
+        if let Err(e) = storage.create_dir(&extract_path).await {
+            return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(format!(
+                "Failed to create extraction directory: {}",
+                e
+            )));
+        }
+
+        for i in 0..archive.len() {
+            let entry = match archive.entry(i) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    return archive_error(
+                        e,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to read archive entry",
+                    )
+                }
+            };
+
+            let path = entry_path(&sanitize_name(&entry.name)).unwrap_or_default();
+            if path.is_empty() {
+                continue;
+            }
+            let outpath = format!("{}/{}", extract_path, path);
+
+            match entry.contents {
+                None => {
+                    // Create directory
+                    if let Err(e) = storage.create_dir(&outpath).await {
+                        return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(
+                            format!("Failed to create directory: {}", e),
+                        ));
+                    }
+                }
+                Some(contents) => {
+                    // Ensure parent directory exists
+                    if let Some(p) = Path::new(&outpath).parent() {
+                        if let Some(parent_path) = p.to_str() {
+                            if let Err(e) = storage.create_dir(parent_path).await {
+                                return FinderResponse::build(storage_status(&e)).json(
+                                    ErrorResponse::new(format!(
+                                        "Failed to create parent directory: {}",
+                                        e
+                                    )),
+                                );
+                            }
+                        }
+                    }
+
+                    let resolved = match resolve_collision(storage.as_ref(), &outpath, policy).await
+                    {
+                        Ok(resolved) => resolved,
+                        Err(e) => return FinderError::from(e).error_response(),
+                    };
+                    if resolved.skip {
+                        continue;
+                    }
+                    // Renamed on conflict into a name that wasn't checked above
+                    if !acl::allowed(&data.config.access_rules, &resolved.path, Permission::Write) {
+                        return FinderError::Forbidden(format!(
+                            "Permission denied: {}",
+                            resolved.path
+                        ))
+                        .error_response();
+                    }
+                    if let Err(e) = storage.write(&resolved.path, contents).await {
+                        return FinderResponse::build(storage_status(&e)).json(ErrorResponse::new(
+                            format!("Failed to write extracted file: {}", e),
+                        ));
+                    }
+                }
+            }
+        }
+        data.hook_after_write(storage.as_ref(), &extract_path).await;
+
+        Self::index(data, query).await
+    }
+
+    pub async fn save(data: Arc<VueFinder>, query: Query, payload: SaveRequest) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        let checked = file_types::check_name(&data.config, &path)
+            .and_then(|_| file_types::check_contents(&data.config, payload.content.as_bytes()));
+        if let Err(e) = checked {
+            return e.error_response();
+        }
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &path).await {
+            return response;
+        }
+        if let Err(response) = data
+            .scan_contents(storage.as_ref(), &path, payload.content.as_bytes())
+            .await
+        {
+            return response;
+        }
+        if let Err(response) = data.snapshot(storage.as_ref(), &path).await {
+            return response;
+        }
+
+        let contents = payload.content.as_bytes().to_vec();
+        let written = match payload.if_match.as_deref().map(str::trim) {
+            None => storage.write(&path, contents).await.map(|_| true),
+            Some("*") => match storage.exists(&path).await {
+                Ok(true) => storage.write(&path, contents).await.map(|_| true),
+                Ok(false) => Ok(false),
+                Err(e) => Err(e),
+            },
+            Some(etag) => {
+                let expected = etag.trim_start_matches("W/").trim_matches('"');
+                storage.write_if_match(&path, expected, contents).await
+            }
+        };
+        match written {
+            Ok(true) => {
+                data.hook_after_write(storage.as_ref(), &path).await;
+                Self::preview_file(&data, &query, None).await
+            }
+            Ok(false) => FinderError::PreconditionFailed(
+                "The file was changed since it was opened.".to_string(),
+            )
+            .error_response(),
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn append(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: AppendRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let contents = if payload.base64 {
+            match BASE64_STANDARD.decode(&payload.content) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return FinderResponse::BadRequest()
+                        .json(ErrorResponse::new(format!("Invalid base64 content: {}", e)))
+                }
+            }
+        } else {
+            payload.content.as_bytes().to_vec()
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        let mut checked = file_types::check_name(&data.config, &path);
+        // Only the start of a file shows what it is
+        if checked.is_ok() && !storage.exists(&path).await.unwrap_or(false) {
+            checked = file_types::check_contents(&data.config, &contents);
+        }
+        if let Err(e) = checked {
+            return e.error_response();
+        }
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &path).await {
+            return response;
+        }
+
+        match storage.append(&path, contents).await {
+            Ok(_) => {
+                data.hook_after_write(storage.as_ref(), &path).await;
+                FinderResponse::Ok().json(StatusResponse {
+                    status: true,
+                    message: Some("Content appended.".to_string()),
+                })
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn cas_save(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: CasSaveRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let path = query.path.clone().unwrap_or_default();
+        let checked = file_types::check_name(&data.config, &path)
+            .and_then(|_| file_types::check_contents(&data.config, payload.content.as_bytes()));
+        if let Err(e) = checked {
+            return e.error_response();
+        }
+        if let Err(response) = data.hook_before_write(storage.as_ref(), &path).await {
+            return response;
+        }
+
+        if let Err(response) = data.snapshot(storage.as_ref(), &path).await {
+            return response;
+        }
+
+        let contents = payload.content.as_bytes().to_vec();
+        let hash = content_hash(&contents);
+
+        match storage
+            .write_if_match(&path, &payload.expected_hash, contents)
+            .await
+        {
+            Ok(swapped) => {
+                if swapped {
+                    data.hook_after_write(storage.as_ref(), &path).await;
+                }
+                FinderResponse::Ok().json(CasSaveResponse {
+                    status: swapped,
+                    hash: swapped.then_some(hash),
+                })
+            }
+            Err(e) => FinderError::from(e).error_response(),
+        }
+    }
+
+    pub async fn reorder(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: ReorderRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let dirname = query.path.clone().unwrap_or_default();
+        let list_contents = match storage.list_contents(&dirname).await {
+            Ok(contents) => contents,
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+
+        // Start from the current effective order
+        let mut files: Vec<FileNode> = list_contents
+            .into_iter()
+            .filter(|item| item.basename != ORDER_FILE)
+            .map(|item| FileNode {
+                storage_item: item,
+                url: None,
+                dir: None,
+            })
+            .collect();
+        let order = read_manual_order(storage.as_ref(), &dirname).await;
+        sort_manually(&mut files, &order);
+
+        let mut names: Vec<String> = files
+            .into_iter()
+            .map(|node| node.storage_item.basename)
+            .collect();
+        let basename = Path::new(&payload.item)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let index = match names.iter().position(|name| *name == basename) {
+            Some(index) => index,
+            None => {
+                return FinderResponse::NotFound()
+                    .json(ErrorResponse::new("Item not found in directory."))
+            }
+        };
+
+        match payload.direction {
+            ReorderDirection::Up if index > 0 => names.swap(index, index - 1),
+            ReorderDirection::Down if index + 1 < names.len() => names.swap(index, index + 1),
+            _ => {}
+        }
+
+        let contents = serde_json::to_vec_pretty(&names).unwrap_or_default();
+        if let Err(e) = storage.write(&order_file_path(&dirname), contents).await {
+            return FinderError::from(e).error_response();
+        }
+
+        let query = Query {
+            order: Some("manual".to_string()),
+            ..query
+        };
+        Self::index(data, query).await
+    }
+
+    // Unified diff between two files of the queried adapter, either of them
+    // possibly one of the file's versions
+    pub async fn diff(data: Arc<VueFinder>, query: Query, payload: DiffRequest) -> FinderResponse {
+        let storage = match data.get_storage(query.adapter.clone()) {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+        for path in [&payload.left, &payload.right] {
+            if let Err(response) = data.hook_before_download(storage.as_ref(), path).await {
+                return response;
+            }
+        }
+
+        Self::text_diff(
+            storage.as_ref(),
+            (&payload.left, payload.left_version.as_deref()),
+            (&payload.right, payload.right_version.as_deref()),
+        )
+        .await
+    }
+
+    // Diff of two sides, each a file and optionally the id of one of its
+    // versions to compare instead of the current contents
+    async fn text_diff(
+        storage: &dyn StorageAdapter,
+        (left, left_version): (&str, Option<&str>),
+        (right, right_version): (&str, Option<&str>),
+    ) -> FinderResponse {
+        let read = |path: &str, version: Option<&str>| {
+            let (path, version) = (path.to_string(), version.map(str::to_string));
+            async move {
+                let contents = match &version {
+                    Some(id) => versions::read(storage, &path, id).await?,
+                    None => storage.read(&path).await?,
+                };
+                let label = match &version {
+                    Some(id) => format!("{}@{}", path, id),
+                    None => path,
+                };
+                Ok::<_, StorageError>((label, contents))
+            }
+        };
+        let ((old_label, old), (new_label, new)) =
+            match tokio::try_join!(read(left, left_version), read(right, right_version)) {
+                Ok(sides) => sides,
+                Err(e) => return FinderError::from(e).error_response(),
+            };
+        let (Ok(old), Ok(new)) = (String::from_utf8(old), String::from_utf8(new)) else {
+            return FinderError::BadRequest("Only text files can be compared.".to_string())
+                .error_response();
+        };
+        FinderResponse::Ok().json(text_diff::unified_diff(&old_label, &old, &new_label, &new))
+    }
+
+    pub async fn diff_dirs(
+        data: Arc<VueFinder>,
+        query: Query,
+        payload: DiffDirsRequest,
+    ) -> FinderResponse {
+        let storage = match data
+            .storages
+            .get(&query.adapter.clone().unwrap_or_default())
+        {
+            Some(s) => s,
+            None => return FinderResponse::BadRequest().finish(),
+        };
+
+        let limit = data.config.walk_limit;
+        let files = |entries: Vec<WalkEntry>| -> std::collections::BTreeMap<String, StorageItem> {
+            entries
+                .into_iter()
+                .filter(|entry| {
+                    entry.item.node_type == "file" && !data.walk_skips(&query, &entry.item.path)
+                })
+                .map(|entry| (entry.relative_path, entry.item))
+                .collect()
+        };
+        let (left, right) = match tokio::try_join!(
+            walk(storage.as_ref(), &payload.left, limit),
+            walk(storage.as_ref(), &payload.right, limit)
+        ) {
+            Ok((left, right)) => (files(left), files(right)),
+            Err(e) => return FinderError::from(e).error_response(),
+        };
+
+        let added: Vec<_> = right
+            .keys()
+            .filter(|path| !left.contains_key(*path))
+            .cloned()
+            .collect();
+        let removed: Vec<_> = left
+            .keys()
+            .filter(|path| !right.contains_key(*path))
+            .cloned()
+            .collect();
+
+        let mut changed = Vec::new();
+        for (path, left_item) in &left {
+            let right_item = match right.get(path) {
+                Some(item) => item,
+                None => continue,
+            };
+
+            let differs = if left_item.size != right_item.size {
+                true
+            } else {
+                match payload.compare {
+                    DiffCompare::Meta => left_item.last_modified != right_item.last_modified,
+                    DiffCompare::Hash => {
+                        match tokio::try_join!(
+                            storage.read(&left_item.path),
+                            storage.read(&right_item.path)
+                        ) {
+                            Ok((a, b)) => content_hash(&a) != content_hash(&b),
+                            Err(e) => {
+                                return FinderResponse::InternalServerError()
+                                    .json(ErrorResponse::new(e.to_string()))
+                            }
+                        }
+                    }
+                }
+            };
+
+            if differs {
+                changed.push(path.clone());
+            }
+        }
+
+        FinderResponse::Ok().json(DiffDirsResponse {
+            added,
+            removed,
+            changed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::local::LocalStorage;
+    use crate::storages::memory::MemoryStorage;
+    use crate::storages::read_only::ReadOnlyStorage;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn finder(root: &TempDir) -> Arc<VueFinder> {
+        finder_with_config(root, VueFinderConfig::default())
+    }
+
+    fn finder_with_config(root: &TempDir, config: VueFinderConfig) -> Arc<VueFinder> {
+        let storage = Arc::new(LocalStorage::new(root.path().to_str().unwrap()));
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(storage.name(), storage);
+        Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            config: Arc::new(config),
+            ..VueFinder::default()
+        })
+    }
+
+    fn query(query: &str) -> Query {
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    async fn body_json(response: FinderResponse) -> serde_json::Value {
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_new_file_with_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        let payload = NewFileRequest {
+            name: "hello.txt".to_string(),
+            content: Some(BASE64_STANDARD.encode("Hello")),
+            base64: true,
+        };
+        let response = VueFinder::new_file(
+            data.clone(),
+            query("q=newfile&adapter=local&path=local://"),
+            payload,
+        )
+        .await;
+        assert!(response.status().is_success());
+
+        let body = body_json(response).await;
+        assert_eq!(body["item"]["basename"], "hello.txt");
+        assert_eq!(body["item"]["file_size"], 5);
+
+        let storage = &data.storages["local"];
+        assert!(storage.exists("hello.txt").await.unwrap());
+        assert_eq!(storage.read("hello.txt").await.unwrap(), b"Hello");
+
+        // Creating the same file again must not overwrite it
+        let payload = NewFileRequest {
+            name: "hello.txt".to_string(),
+            content: Some("Bye".to_string()),
+            base64: false,
+        };
+        let response = VueFinder::new_file(
+            data.clone(),
+            query("q=newfile&adapter=local&path=local://"),
+            payload,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(storage.read("hello.txt").await.unwrap(), b"Hello");
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_upload() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                partial.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"partial.txt\"\r\n\
+                Content-Type: text/plain\r\n\r\n\
+                first half of the ",
+            )),
+            Err(std::io::ErrorKind::UnexpectedEof.into()),
+        ];
+        let multipart = multer::Multipart::new(futures_util::stream::iter(chunks), "BOUNDARY");
+
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!data.storages["local"].exists("partial.txt").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_html_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a&b.txt"), "hello").unwrap();
+
+        let response = VueFinder::html_index(
+            data.clone(),
+            query("adapter=local&path=local://docs"),
+            "/api",
+            "/browse",
+        )
+        .await;
+        assert!(response.status().is_success());
+
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        let html = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(html.contains(
+            "<a href=\"/api?q=download&amp;adapter=local&amp;path=local%3A%2F%2Fdocs%2Fa%26b.txt\">a&amp;b.txt</a>"
+        ));
+        assert!(html.contains(
+            "<a href=\"/api?q=preview&amp;adapter=local&amp;path=local%3A%2F%2Fdocs%2Fa%26b.txt\">preview</a>"
+        ));
+        assert!(html.contains("<a href=\"/browse?adapter=local&amp;path=local%3A%2F%2F\">..</a>"));
+    }
+
+    #[tokio::test]
+    async fn test_save_if_match() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("note.txt"), "v1").unwrap();
+        let data = finder(&temp_dir);
+        let save = |if_match: &str, content: &str| {
+            VueFinder::save(
+                data.clone(),
+                query("q=save&adapter=local&path=local://note.txt"),
+                SaveRequest {
+                    content: content.to_string(),
+                    if_match: Some(if_match.to_string()),
+                },
+            )
+        };
+        let etag = |response: &FinderResponse| {
+            response
+                .headers()
+                .get(header::ETAG)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let opened = VueFinder::preview(
+            data.clone(),
+            query("q=preview&adapter=local&path=local://note.txt"),
+            None,
+        )
+        .await;
+        let opened = etag(&opened);
+        assert_eq!(opened, format!("\"{}\"", content_hash(b"v1")));
+
+        let response = save(&opened, "mine").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(etag(&response), format!("\"{}\"", content_hash(b"mine")));
+
+        // Someone else saved meanwhile
+        let response = save(&opened, "theirs").await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("note.txt")).unwrap(),
+            "mine"
+        );
+        assert!(save("*", "any").await.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_cas_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        std::fs::write(temp_dir.path().join("note.txt"), "v1").unwrap();
+
+        let cas_save = |expected_hash: String, content: &str| {
+            VueFinder::cas_save(
+                data.clone(),
+                query("q=cas-save&adapter=local&path=local://note.txt"),
+                CasSaveRequest {
+                    content: content.to_string(),
+                    expected_hash,
+                },
+            )
+        };
+
+        // Stale hash is rejected and leaves the file untouched
+        let body = body_json(cas_save(content_hash(b"v0"), "v2").await).await;
+        assert_eq!(body["status"], false);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("note.txt")).unwrap(),
+            b"v1"
+        );
+
+        // Fresh hash swaps the contents
+        let body = body_json(cas_save(content_hash(b"v1"), "v2").await).await;
+        assert_eq!(body["status"], true);
+        assert_eq!(body["hash"], content_hash(b"v2"));
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("note.txt")).unwrap(),
+            b"v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_unicode_normalization() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        // "café" with a combining acute accent (NFD), as created on macOS
+        std::fs::write(temp_dir.path().join("Cafe\u{301} Menu.txt"), "").unwrap();
+
+        // Query with the precomposed (NFC) form
+        let response = VueFinder::search(
+            data.clone(),
+            query("q=search&adapter=local&path=&filter=caf%C3%A9"),
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["files"].as_array().unwrap().len(), 1);
+        assert_eq!(body["files"][0]["basename"], "Cafe\u{301} Menu.txt");
+    }
+
+    #[tokio::test]
+    async fn test_search_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("reports/2024")).unwrap();
+        std::fs::write(temp_dir.path().join("reports/final.pdf"), "").unwrap();
+        std::fs::write(temp_dir.path().join("reports/2024/Final.PDF"), "").unwrap();
+        std::fs::write(temp_dir.path().join("final.txt"), "").unwrap();
+        let data = finder(&temp_dir);
+        let search = |params: &str| {
+            let query = query(&format!("q=search&adapter=local&path=local://&{}", params));
+            let data = data.clone();
+            async move {
+                let body = body_json(VueFinder::search(data, query).await).await;
+                let mut names: Vec<String> = body["files"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|file| file["basename"].as_str().unwrap().to_string())
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(
+            search("mode=glob&filter=*.pdf").await,
+            vec!["Final.PDF", "final.pdf"]
+        );
+        assert_eq!(
+            search("mode=glob&filter=*.pdf&case_sensitive=true").await,
+            vec!["final.pdf"]
+        );
+        assert_eq!(
+            search("mode=glob&filter=reports/**/final*").await,
+            vec!["Final.PDF", "final.pdf"]
+        );
+        assert_eq!(
+            search("mode=regex&filter=%5Efinal%5C.(txt%7Cpdf)%24&case_sensitive=true").await,
+            vec!["final.pdf", "final.txt"]
+        );
+
+        let response = VueFinder::search(
+            data.clone(),
+            query("q=search&adapter=local&mode=regex&filter=("),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("photos")).unwrap();
+        std::fs::write(temp_dir.path().join("photos/big.jpg"), vec![0; 2048]).unwrap();
+        std::fs::write(temp_dir.path().join("photos/small.jpg"), vec![0; 16]).unwrap();
+        std::fs::write(temp_dir.path().join("report.pdf"), vec![0; 4096]).unwrap();
+        let data = finder(&temp_dir);
+        let search = |params: &str| {
+            let query = query(&format!("q=search&adapter=local&path=&{}", params));
+            let data = data.clone();
+            async move {
+                let body = body_json(VueFinder::search(data, query).await).await;
+                let mut names: Vec<String> = body["files"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|file| file["basename"].as_str().unwrap().to_string())
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(search("min_size=1024").await, vec!["big.jpg", "report.pdf"]);
+        assert_eq!(search("min_size=1024&type=image").await, vec!["big.jpg"]);
+        assert_eq!(search("max_size=100").await, vec!["small.jpg"]);
+        assert_eq!(search("type=document").await, vec!["report.pdf"]);
+        assert_eq!(search("type=dir&filter=photo").await, vec!["photos"]);
+        assert!(search("modified_after=99999999999").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let body = body_json(VueFinder::version(finder(&temp_dir)).await).await;
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["adapters"], json!(["local"]));
+    }
+
+    #[tokio::test]
+    async fn test_stat() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a.md"), "# A").unwrap();
+        let data = finder(&temp_dir);
+
+        let body =
+            body_json(VueFinder::stat(data.clone(), query("q=stat&path=local://docs/a.md")).await)
+                .await;
+        assert_eq!(body["type"], "file");
+        assert_eq!(body["path"], "local://docs/a.md");
+        assert_eq!(body["file_size"], 3);
+        assert_eq!(body["mime_type"], "text/markdown");
+        assert!(body["last_modified"].as_u64().is_some());
+
+        let body =
+            body_json(VueFinder::stat(data.clone(), query("q=stat&path=local://docs")).await).await;
+        assert_eq!(body["type"], "dir");
+        assert_eq!(body["basename"], "docs");
+
+        let response = VueFinder::stat(data, query("q=stat&path=local://missing.txt")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "hello world").unwrap();
+        let data = finder(&temp_dir);
+
+        let body =
+            body_json(VueFinder::hash(data.clone(), query("q=hash&path=local://a.txt")).await)
+                .await;
+        assert_eq!(
+            body,
+            json!({
+                "algorithm": "sha256",
+                "hash": "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+            })
+        );
+        let body = body_json(
+            VueFinder::hash(
+                data.clone(),
+                query("q=hash&path=local://a.txt&algorithm=md5"),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(body["hash"], "5eb63bbbe01eeed093cb22bb8f5acdc3");
+
+        let response = VueFinder::hash(data, query("q=hash&path=local://")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_size() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a.md"), "# A").unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                folder_size_cache_secs: 60,
+                ..VueFinderConfig::default()
+            },
+        );
+
+        let size = || async {
+            body_json(VueFinder::size(data.clone(), query("q=size&path=local://docs")).await).await
+        };
+        assert_eq!(
+            size().await,
+            json!({ "size": 3, "files": 1, "dirs": 0, "complete": true })
+        );
+
+        // Served from the cache until it expires
+        std::fs::write(temp_dir.path().join("docs/b.md"), "# B").unwrap();
+        assert_eq!(size().await["files"], 1);
+    }
+
+    // Poll `job-status` until the job is no longer running
+    async fn finished_job(data: &Arc<VueFinder>, id: &str) -> serde_json::Value {
+        let status_query = format!("q=job-status&id={}", id);
+        let mut status = json!(null);
+        for _ in 0..100 {
+            status =
+                body_json(VueFinder::job_status(data.clone(), query(&status_query)).await).await;
+            if status["state"] != "running" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        status
+    }
+
+    #[tokio::test]
+    async fn test_duplicates() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("a.md"), "# A").unwrap();
+        std::fs::write(temp_dir.path().join("docs/copy.md"), "# A").unwrap();
+        let data = finder_with_config(&temp_dir, VueFinderConfig::default());
+
+        let response = VueFinder::duplicates(data.clone(), query("q=duplicates")).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let id = body_json(response).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let status = finished_job(&data, &id).await;
+        assert_eq!(status["state"], "done");
+        assert_eq!(
+            status["result"]["sets"][0]["paths"],
+            json!(["local://a.md", "local://docs/copy.md"])
+        );
+
+        let response = VueFinder::job_status(data.clone(), query("q=job-status&id=unknown")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_storages() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                public_links: Some(HashMap::from([(
+                    "local://public".to_string(),
+                    "https://cdn.example.com".to_string(),
+                )])),
+                ..VueFinderConfig::default()
+            },
+        );
+        let mut storages = (*data.storages).clone();
+        storages.insert(
+            "demo".to_string(),
+            Arc::new(ReadOnlyStorage::new(Arc::new(MemoryStorage::new("demo")))),
+        );
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..data.as_ref().clone()
+        });
+
+        let body = body_json(VueFinder::storages(data).await).await;
+        let storages = body["storages"].as_array().unwrap();
+        assert_eq!(storages[0]["name"], "demo");
+        assert_eq!(storages[0]["read_only"], true);
+        assert_eq!(storages[0]["capabilities"]["rename"], false);
+        assert_eq!(storages[1]["name"], "local");
+        assert_eq!(storages[1]["read_only"], false);
+        assert_eq!(storages[1]["capabilities"]["streaming"], true);
+        assert_eq!(storages[1]["capabilities"]["public_urls"], true);
+        assert!(storages[1]["usage"]["total"].as_u64().is_some());
+    }
+
+    // Nested tree shared by the recursive archive/move tests
+    fn nested_tree(root: &std::path::Path) {
+        std::fs::create_dir_all(root.join("photos/2023/raw")).unwrap();
+        std::fs::write(root.join("photos/2023/a.jpg"), "a").unwrap();
+        std::fs::write(root.join("photos/b.jpg"), "b").unwrap();
+        std::fs::write(root.join("loose.txt"), "loose").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_nested_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::archive(
+            data.clone(),
+            query("q=archive&adapter=local&path=local://"),
+            ArchiveRequest {
+                name: "bundle".to_string(),
+                items: vec![
+                    FileItem {
+                        path: "local://photos".to_string(),
+                    },
+                    FileItem {
+                        path: "local://loose.txt".to_string(),
+                    },
+                ],
+                format: ArchiveFormat::Zip,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = VueFinder::unarchive(
+            data.clone(),
+            query("q=unarchive&adapter=local&path=local://"),
+            UnarchiveRequest {
+                item: "local://bundle.zip".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let out = temp_dir.path().join("bundle");
+        let read = |path: &str| std::fs::read_to_string(out.join(path)).unwrap();
+        assert_eq!(read("photos/2023/a.jpg"), "a");
+        assert_eq!(read("photos/b.jpg"), "b");
+        assert_eq!(read("loose.txt"), "loose");
+        assert!(out.join("photos/2023/raw").is_dir());
+
+        // Existing zip is still refused
+        let response = VueFinder::archive(
+            data,
+            query("q=archive&adapter=local&path=local://"),
+            ArchiveRequest {
+                name: "bundle".to_string(),
+                items: vec![FileItem {
+                    path: "local://loose.txt".to_string(),
+                }],
+                format: ArchiveFormat::Zip,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_archive_tar_gz() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::archive(
+            data.clone(),
+            query("q=archive&adapter=local&path=local://"),
+            ArchiveRequest {
+                name: "bundle".to_string(),
+                items: vec![FileItem {
+                    path: "local://photos".to_string(),
+                }],
+                format: ArchiveFormat::TarGz,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Detected from the contents, not the name
+        std::fs::rename(
+            temp_dir.path().join("bundle.tar.gz"),
+            temp_dir.path().join("bundle.tgz"),
+        )
+        .unwrap();
+        let response = VueFinder::unarchive(
+            data.clone(),
+            query("q=unarchive&adapter=local&path=local://"),
+            UnarchiveRequest {
+                item: "local://bundle.tgz".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let out = temp_dir.path().join("bundle");
+        assert_eq!(
+            std::fs::read_to_string(out.join("photos/2023/a.jpg")).unwrap(),
+            "a"
+        );
+        assert!(out.join("photos/2023/raw").is_dir());
+
+        let response = VueFinder::unarchive(
+            data,
+            query("q=unarchive&adapter=local&path=local://"),
+            UnarchiveRequest {
+                item: "local://loose.txt".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_move_nested_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        std::fs::create_dir(temp_dir.path().join("dest")).unwrap();
+        let data = finder(&temp_dir);
+
+        let move_items = |target: &str, items: &[&str]| {
+            VueFinder::r#move(
+                data.clone(),
+                query("q=move&adapter=local&path=local://"),
+                MoveRequest {
+                    from_adapter: None,
+                    to_adapter: None,
+                    conflict: None,
+                    item: target.to_string(),
+                    items: items
+                        .iter()
+                        .map(|path| FileItem {
+                            path: path.to_string(),
+                        })
+                        .collect(),
+                },
+            )
+        };
+
+        let response = move_items("local://dest", &["local://photos", "local://loose.txt"]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let dest = temp_dir.path().join("dest");
+        assert_eq!(
+            std::fs::read_to_string(dest.join("photos/2023/a.jpg")).unwrap(),
+            "a"
+        );
+        assert!(dest.join("photos/b.jpg").is_file());
+        assert!(dest.join("photos/2023/raw").is_dir());
+        assert!(dest.join("loose.txt").is_file());
+        assert!(!temp_dir.path().join("photos").exists());
+
+        // Conflicts and moving a folder into itself are refused
+        std::fs::write(temp_dir.path().join("loose.txt"), "again").unwrap();
+        let response = move_items("local://dest", &["local://loose.txt"]).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = move_items("local://dest/photos/2023", &["local://dest/photos"]).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_copy_nested_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        std::fs::create_dir(temp_dir.path().join("dest")).unwrap();
+        let data = finder(&temp_dir);
+
+        let copy_items = |conflict: &str, target: &str, items: &[&str]| {
+            VueFinder::copy(
+                data.clone(),
+                query(&format!(
+                    "q=copy&adapter=local&path=local://&conflict={}",
+                    conflict
+                )),
+                CopyRequest {
+                    from_adapter: None,
+                    to_adapter: None,
+                    conflict: None,
+                    item: target.to_string(),
+                    items: items
+                        .iter()
+                        .map(|path| FileItem {
+                            path: path.to_string(),
+                        })
+                        .collect(),
+                },
+            )
+        };
+
+        let response = copy_items(
+            "fail",
+            "local://dest",
+            &["local://photos", "local://loose.txt"],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let dest = temp_dir.path().join("dest");
+        assert_eq!(
+            std::fs::read_to_string(dest.join("photos/2023/a.jpg")).unwrap(),
+            "a"
+        );
+        assert!(dest.join("photos/2023/raw").is_dir());
+        assert!(dest.join("loose.txt").is_file());
+        // Sources stay in place
+        assert!(temp_dir.path().join("photos/2023/a.jpg").is_file());
+        assert!(temp_dir.path().join("loose.txt").is_file());
+
+        let response = copy_items("fail", "local://dest", &["local://loose.txt"]).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = copy_items("rename", "local://dest", &["local://loose.txt"]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(dest.join("loose (1).txt").is_file());
+
+        let response = copy_items("fail", "local://photos/2023", &["local://photos"]).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_partial_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        std::fs::create_dir(temp_dir.path().join("dest")).unwrap();
+        std::fs::write(temp_dir.path().join("dest/loose.txt"), "taken").unwrap();
+        let data = finder(&temp_dir);
+        let items = |paths: &[&str]| {
+            paths
+                .iter()
+                .map(|path| FileItem {
+                    path: path.to_string(),
+                })
+                .collect()
+        };
+
+        let response = VueFinder::r#move(
+            data.clone(),
+            query("q=move&adapter=local&path=local://"),
+            MoveRequest {
+                from_adapter: None,
+                to_adapter: None,
+                conflict: None,
+                item: "local://dest".to_string(),
+                items: items(&["local://loose.txt", "local://photos"]),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body = body_json(response).await;
+        assert_eq!(
+            (&body["succeeded"], &body["failed"]),
+            (&json!(1), &json!(1))
+        );
+        assert_eq!(body["results"][0]["status"], false);
+        assert_eq!(body["results"][1]["target"], "local://dest/photos");
+        assert!(temp_dir.path().join("dest/photos/2023/a.jpg").is_file());
+        assert!(temp_dir.path().join("loose.txt").is_file());
+
+        let response = VueFinder::delete(
+            data.clone(),
+            query("q=delete&adapter=local&path=local://"),
+            DeleteRequest {
+                items: items(&["local://missing.txt", "local://loose.txt"]),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        assert!(!temp_dir.path().join("loose.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_move_between_adapters() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+        let memory = Arc::new(MemoryStorage::new("mem").with_files([("loose.txt", "taken")]));
+        let mut storages = (*data.storages).clone();
+        storages.insert("mem".to_string(), memory.clone());
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..data.as_ref().clone()
+        });
+
+        let response = VueFinder::r#move(
+            data.clone(),
+            query("q=move&adapter=local&path=local://"),
+            MoveRequest {
+                from_adapter: None,
+                to_adapter: Some("mem".to_string()),
+                conflict: None,
+                item: "mem://".to_string(),
+                items: ["local://photos", "local://loose.txt"]
+                    .iter()
+                    .map(|path| FileItem {
+                        path: path.to_string(),
+                    })
+                    .collect(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let id = body_json(response).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let status = finished_job(&data, &id).await;
+        assert_eq!(
+            (&status["kind"], &status["state"]),
+            (&json!("move"), &json!("done"))
+        );
+        assert_eq!(status["phase"], "copying");
+        // The conflicting file counts towards the total but isn't copied
+        assert_eq!((&status["done"], &status["total"]), (&json!(2), &json!(7)));
+        let report = &status["result"];
+        assert_eq!(
+            (&report["succeeded"], &report["failed"]),
+            (&json!(1), &json!(1))
+        );
+        assert_eq!(memory.read("mem://photos/2023/a.jpg").await.unwrap(), b"a");
+        assert!(!temp_dir.path().join("photos").exists());
+        assert!(temp_dir.path().join("loose.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+        let memory = Arc::new(
+            MemoryStorage::new("mem").with_files([("site/b.jpg", "stale"), ("site/old.txt", "x")]),
+        );
+        let mut storages = (*data.storages).clone();
+        storages.insert("mem".to_string(), memory.clone());
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..data.as_ref().clone()
+        });
+        let sync = |source: &str, target: &str, dry_run: bool| {
+            VueFinder::sync(
+                data.clone(),
+                query("q=sync&adapter=local"),
+                SyncRequest {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    from_adapter: None,
+                    to_adapter: Some(target.split("://").next().unwrap().to_string()),
+                    compare: DiffCompare::Hash,
+                    delete: true,
+                    dry_run,
+                },
+            )
+        };
+        let result = |response: FinderResponse| {
+            let data = data.clone();
+            async move {
+                assert_eq!(response.status(), StatusCode::ACCEPTED);
+                let id = body_json(response).await["id"]
+                    .as_str()
+                    .unwrap()
+                    .to_string();
+                let status = finished_job(&data, &id).await;
+                assert_eq!(status["state"], "done");
+                status["result"].clone()
+            }
+        };
+
+        // A dry run only lists the changes
+        let planned = result(sync("local://photos", "mem://site", true).await).await;
+        assert_eq!(
+            planned,
+            json!({
+                "status": true,
+                "copied": ["2023/a.jpg", "b.jpg"],
+                "created": ["2023", "2023/raw"],
+                "deleted": ["old.txt"],
+                "unchanged": 0,
+                "failed": [],
+            })
+        );
+        assert!(memory.exists("mem://site/old.txt").await.unwrap());
+
+        let synced = result(sync("local://photos", "mem://site", false).await).await;
+        assert_eq!(synced, planned);
+        assert_eq!(memory.read("mem://site/2023/a.jpg").await.unwrap(), b"a");
+        assert_eq!(memory.read("mem://site/b.jpg").await.unwrap(), b"b");
+        assert!(memory.is_dir("mem://site/2023/raw").await.unwrap());
+        assert!(!memory.exists("mem://site/old.txt").await.unwrap());
+
+        let again = result(sync("local://photos", "mem://site", false).await).await;
+        assert_eq!(
+            (&again["copied"], &again["unchanged"]),
+            (&json!([]), &json!(2))
+        );
+
+        let response = sync("local://photos", "local://photos/2023", false).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_download_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::download_archive(
+            data.clone(),
+            query(
+                "q=download-archive&adapter=local&items=%5B%22local%3A%2F%2Fphotos%22%2C%22local%3A%2F%2Floose.txt%22%5D",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"archive.zip\""
+        );
+        let body = response.into_body().to_bytes().await.unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "loose.txt",
+                "photos/",
+                "photos/2023/",
+                "photos/2023/a.jpg",
+                "photos/2023/raw/",
+                "photos/b.jpg",
+            ]
+        );
+        let mut contents = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("photos/2023/a.jpg").unwrap(),
+            &mut contents,
+        )
+        .unwrap();
+        assert_eq!(contents, "a");
+
+        let response = VueFinder::download_archive(
+            data,
+            query("q=download-archive&adapter=local&path=local://missing"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_download_archive_enforced_hidden() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("app/.git")).unwrap();
+        std::fs::write(temp_dir.path().join("app/.git/config"), "secret").unwrap();
+        std::fs::write(temp_dir.path().join("app/.env"), "secret").unwrap();
+        std::fs::write(temp_dir.path().join("app/main.rs"), "fn main() {}").unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                hide_dotfiles: true,
+                enforce_hidden: true,
+                ..VueFinderConfig::default()
+            },
+        );
+
+        let response = VueFinder::download_archive(
+            data,
+            query("q=download-archive&adapter=local&path=local://app&show_hidden=true"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().to_bytes().await.unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(names, vec!["app/", "app/main.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_archive_preview() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                archive_preview: true,
+                ..VueFinderConfig::default()
+            },
+        );
+
+        let mut zip_buffer = Vec::new();
+        {
+            let mut zip = ZipWriter::new(Cursor::new(&mut zip_buffer));
+            zip.add_directory("docs/", FileOptions::default()).unwrap();
+            zip.start_file("docs/readme.txt", FileOptions::default())
+                .unwrap();
+            zip.write_all(&[b'a'; 100]).unwrap();
+            zip.finish().unwrap();
+        }
+        std::fs::write(temp_dir.path().join("bundle.zip"), &zip_buffer).unwrap();
+
+        let response = VueFinder::preview(
+            data.clone(),
+            query("q=preview&adapter=local&path=local://bundle.zip"),
+            None,
+        )
+        .await;
+        let body = body_json(response).await;
+        let entries = body["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["name"], "docs/");
+        assert_eq!(entries[0]["type"], "dir");
+        assert_eq!(entries[1]["name"], "docs/readme.txt");
+        assert_eq!(entries[1]["size"], 100);
+        assert!(entries[1]["compressed_size"].as_u64().unwrap() < 100);
+    }
+
+    #[tokio::test]
+    async fn test_manual_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        for name in ["a.mp3", "b.mp3", "c.mp3", "d.mp3"] {
+            std::fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+        std::fs::write(temp_dir.path().join(ORDER_FILE), r#"["c.mp3", "a.mp3"]"#).unwrap();
+
+        let names = |body: serde_json::Value| -> Vec<String> {
+            body["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|file| file["basename"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // Sidecar entries first, the rest alphabetically, sidecar itself hidden
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://&order=manual"),
+        )
+        .await;
+        assert_eq!(
+            names(body_json(response).await),
+            vec!["c.mp3", "a.mp3", "b.mp3", "d.mp3"]
+        );
+
+        // Move d.mp3 up one slot
+        let response = VueFinder::reorder(
+            data.clone(),
+            query("q=reorder&adapter=local&path=local://"),
+            ReorderRequest {
+                item: "local://d.mp3".to_string(),
+                direction: ReorderDirection::Up,
+            },
+        )
+        .await;
+        assert_eq!(
+            names(body_json(response).await),
+            vec!["c.mp3", "a.mp3", "d.mp3", "b.mp3"]
+        );
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://&order=manual"),
+        )
+        .await;
+        assert_eq!(
+            names(body_json(response).await),
+            vec!["c.mp3", "a.mp3", "d.mp3", "b.mp3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_control_characters() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        for name in ["bad\nname.txt", "bad\0name.txt"] {
+            let response = VueFinder::new_file(
+                data.clone(),
+                query("q=newfile&adapter=local&path=local://"),
+                NewFileRequest {
+                    name: name.to_string(),
+                    content: None,
+                    base64: false,
+                },
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let response = VueFinder::new_folder(
+                data.clone(),
+                query("q=newfolder&adapter=local&path=local://"),
+                NewFolderRequest {
+                    name: name.to_string(),
+                },
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        assert_eq!(sanitize_name("a\nb\x7f.txt"), "a_b_.txt");
+    }
+
+    fn multipart(body: &'static [u8]) -> multer::Multipart<'static> {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![Ok(Bytes::from_static(body))];
+        multer::Multipart::new(futures_util::stream::iter(chunks), "BOUNDARY")
+    }
+
+    #[tokio::test]
+    async fn test_resumable_upload() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::upload_init(
+            data.clone(),
+            query("q=upload-init&adapter=local&path=local://"),
+            UploadInitRequest {
+                name: "big.txt".to_string(),
+                size: Some(11),
+            },
+        )
+        .await;
+        let body = body_json(response).await;
+        let id = body["id"].as_str().unwrap().to_string();
+        assert_eq!(body["offset"], 0);
+
+        let chunk = |offset: u64, body: &'static [u8]| {
+            VueFinder::upload_chunk(
+                data.clone(),
+                query(&format!(
+                    "q=upload-chunk&adapter=local&id={}&offset={}",
+                    id, offset
+                )),
+                multipart(body),
+            )
+        };
+        let response = chunk(
+            0,
+            b"--BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"blob\"\r\n\r\n\
+            Hello \r\n\
+            --BOUNDARY--\r\n",
+        )
+        .await;
+        assert_eq!(body_json(response).await["offset"], 6);
+
+        // A resent chunk is refused with the offset to resume from
+        let response = chunk(
+            0,
+            b"--BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"blob\"\r\n\r\n\
+            Hello \r\n\
+            --BOUNDARY--\r\n",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body_json(response).await["offset"], 6);
+
+        let response =
+            VueFinder::upload_status(data.clone(), query(&format!("q=upload-status&id={}", id)))
+                .await;
+        assert_eq!(body_json(response).await["offset"], 6);
+
+        let response = chunk(
+            6,
+            b"--BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"blob\"\r\n\r\n\
+            world\r\n\
+            --BOUNDARY--\r\n",
+        )
+        .await;
+        assert_eq!(body_json(response).await["offset"], 11);
+        assert!(!temp_dir.path().join("big.txt").exists());
+
+        let response = VueFinder::upload_finalize(
+            data.clone(),
+            query("q=upload-finalize&adapter=local&path=local://"),
+            UploadSessionRequest { id: id.clone() },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("big.txt")).unwrap(),
+            "Hello world"
+        );
+
+        // The session is gone once finalized
+        let response = VueFinder::upload_finalize(
+            data,
+            query("q=upload-finalize&adapter=local&path=local://"),
+            UploadSessionRequest { id },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_upload_content_length_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                short.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"short.txt\"\r\n\
+                Content-Length: 100\r\n\r\n\
+                Hello\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(!data.storages["local"].exists("short.txt").await.unwrap());
+
+        // Matching length is accepted
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                exact.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"exact.txt\"\r\n\
+                Content-Length: 5\r\n\r\n\
+                Hello\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert!(response.status().is_success());
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("exact.txt")).unwrap(),
+            b"Hello"
+        );
+    }
+
+    #[test]
+    fn test_config_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+
+        let json = write("a.json", r#"{ "read_only": true, "walk_limit": 5 }"#);
+        let toml = write(
+            "a.toml",
+            "read_only = true\nwalk_limit = 5\n[jwt]\nsecret = \"s\"\n",
+        );
+        let yaml = write("a.yml", "read_only: true\nwalk_limit: 5\n");
+        for path in [json, toml.clone(), yaml] {
+            let config = VueFinderConfig::from_file(&path).unwrap();
+            assert!(config.read_only, "{}", path);
+            assert_eq!(config.walk_limit, 5);
+            assert!(config.trash);
+        }
+        assert!(VueFinderConfig::from_file(
+            &temp_dir.path().join("missing.json").to_string_lossy()
+        )
+        .is_err());
+
+        // Environment variables win over the file
+        std::env::set_var("VUEFINDER__WALK_LIMIT", "7");
+        std::env::set_var("VUEFINDER__JWT__SECRET", "from-env");
+        let config = VueFinderConfig::from_file(&toml).unwrap();
+        std::env::remove_var("VUEFINDER__WALK_LIMIT");
+        std::env::remove_var("VUEFINDER__JWT__SECRET");
+        assert_eq!(config.walk_limit, 7);
+        assert_eq!(config.jwt.unwrap().secret, "from-env");
+    }
+
+    // Flags contents containing `EICAR`
+    struct FakeScanner;
+
+    #[async_trait::async_trait]
+    impl Scanner for FakeScanner {
+        async fn scan(
+            &self,
+            contents: BoxStream<'static, Result<Bytes, StorageError>>,
+        ) -> Result<ScanVerdict, String> {
+            let contents: Vec<Bytes> = contents.try_collect().await.unwrap();
+            Ok(if contents.concat().windows(5).any(|w| w == b"EICAR") {
+                ScanVerdict::Infected("Eicar-Test-Signature".to_string())
+            } else {
+                ScanVerdict::Clean
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_uploads() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(LocalStorage::new(temp_dir.path().to_str().unwrap()));
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(storage.name(), storage);
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            scanner: Some(Arc::new(FakeScanner)),
+            ..VueFinder::default()
+        });
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"bad.txt\"\r\n\r\n\
+                X5O!P%@AP EICAR\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(!temp_dir.path().join("bad.txt").exists());
+
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"good.txt\"\r\n\r\n\
+                hello\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert!(response.status().is_success());
+        assert!(temp_dir.path().join("good.txt").exists());
+
+        // A refused upload never replaces the file it was meant for
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"good.txt\"\r\n\r\n\
+                X5O!P%@AP EICAR\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("good.txt")).unwrap(),
+            b"hello"
+        );
+        let staged = temp_dir.path().join(UPLOADS_DIR);
+        assert!(!staged.exists() || std::fs::read_dir(staged).unwrap().count() == 0);
+
+        let response = VueFinder::save(
+            data.clone(),
+            query("q=save&adapter=local&path=local://good.txt"),
+            SaveRequest {
+                content: "EICAR".to_string(),
+                if_match: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("good.txt")).unwrap(),
+            b"hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_too_large() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                max_upload_size: Some(4),
+                ..VueFinderConfig::default()
+            },
+        );
+
+        // Refused while streaming, without a declared length
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                big.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"big.txt\"\r\n\r\n\
+                Hello\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!temp_dir.path().join("big.txt").exists());
+
+        let response = VueFinder::upload_init(
+            data.clone(),
+            query("q=upload-init&adapter=local&path=local://"),
+            UploadInitRequest {
+                name: "big.txt".to_string(),
+                size: Some(5),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    // Serves every URL with the same body, in chunks of 4 bytes
+    struct FakeTransport(&'static [u8]);
+
+    #[async_trait::async_trait]
+    impl fetch_url::FetchTransport for FakeTransport {
+        async fn get(
+            &self,
+            _url: &url::Url,
+            _config: &FetchUrlConfig,
+        ) -> Result<fetch_url::Download, String> {
+            let chunks: Vec<_> = self
+                .0
+                .chunks(4)
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect();
+            Ok(fetch_url::Download {
+                filename: None,
+                length: None,
+                body: Box::pin(futures_util::stream::iter(chunks)),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = Arc::new(VueFinder {
+            fetcher: Arc::new(UrlFetcher::new(FakeTransport(b"Hello, world"))),
+            config: Arc::new(VueFinderConfig {
+                fetch_url: FetchUrlConfig {
+                    max_size: Some(12),
+                    ..FetchUrlConfig::default()
+                },
+                ..VueFinderConfig::default()
+            }),
+            ..finder(&temp_dir).as_ref().clone()
+        });
+        let fetch = |url: &str, name: Option<&str>| {
+            VueFinder::fetch_url(
+                data.clone(),
+                query("q=fetch-url&adapter=local&path=local://"),
+                FetchUrlRequest {
+                    url: url.to_string(),
+                    name: name.map(str::to_string),
+                },
+            )
+        };
+
+        // Named after the URL unless told otherwise
+        let response = fetch("https://example.com/files/hello%20world.txt?x=1", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("hello world.txt")).unwrap(),
+            b"Hello, world"
+        );
+        let response = fetch("https://example.com/download", Some("greeting.txt")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(temp_dir.path().join("greeting.txt").exists());
+
+        for url in [
+            "http://example.com/a.txt",
+            "https://127.0.0.1/a.txt",
+            "nonsense",
+        ] {
+            let response = fetch(url, None).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", url);
+        }
+        assert!(!temp_dir.path().join("a.txt").exists());
+
+        let data = Arc::new(VueFinder {
+            fetcher: Arc::new(UrlFetcher::new(FakeTransport(b"Hello, world!"))),
+            ..data.as_ref().clone()
+        });
+        let response = VueFinder::fetch_url(
+            data,
+            query("q=fetch-url&adapter=local&path=local://"),
+            FetchUrlRequest {
+                url: "https://example.com/big.txt".to_string(),
+                name: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!temp_dir.path().join("big.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_diff_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        let write = |path: &str, contents: &str| {
+            let full_path = temp_dir.path().join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(&full_path, contents).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&full_path)
+                .unwrap()
+                .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000))
+                .unwrap();
+        };
+        write("staging/same.txt", "same");
+        write("staging/assets/app.js", "v2");
+        write("staging/assets/new.css", "new");
+        write("staging/config.json", "{\"a\":2}");
+        write("prod/same.txt", "same");
+        write("prod/assets/app.js", "v1");
+        write("prod/old.txt", "old");
+        write("prod/config.json", "{\"a\":1}");
+
+        let response = VueFinder::diff_dirs(
+            data.clone(),
+            query("q=diff-dirs&adapter=local"),
+            DiffDirsRequest {
+                left: "local://prod".to_string(),
+                right: "local://staging".to_string(),
+                compare: DiffCompare::Hash,
+            },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["added"], json!(["assets/new.css"]));
+        assert_eq!(body["removed"], json!(["old.txt"]));
+        assert_eq!(body["changed"], json!(["assets/app.js", "config.json"]));
+
+        // Same size and mtime look unchanged without hashing
+        let response = VueFinder::diff_dirs(
+            data.clone(),
+            query("q=diff-dirs&adapter=local"),
+            DiffDirsRequest {
+                left: "local://prod".to_string(),
+                right: "local://staging".to_string(),
+                compare: DiffCompare::Meta,
+            },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["changed"], json!([]));
+    }
+
+    // Local storage that reports a nearly full volume
+    struct FullStorage(LocalStorage);
+
+    #[async_trait::async_trait]
+    impl StorageAdapter for FullStorage {
+        fn name(&self) -> String {
+            self.0.name()
+        }
+        async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+            self.0.list_contents(path).await
+        }
+        async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+            self.0.read(path).await
+        }
+        async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+            self.0.write(path, contents).await
+        }
+        async fn delete(&self, path: &str) -> Result<(), StorageError> {
+            self.0.delete(path).await
+        }
+        async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+            self.0.create_dir(path).await
+        }
+        async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+            self.0.exists(path).await
+        }
+        async fn usage(&self) -> Result<Option<crate::storages::StorageUsage>, StorageError> {
+            Ok(Some(crate::storages::StorageUsage {
+                total: 1024,
+                available: 4,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rename_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+
+        // Native rename on local storage, copy and delete on an adapter without one
+        let native: Arc<dyn StorageAdapter> =
+            Arc::new(LocalStorage::new(temp_dir.path().to_str().unwrap()));
+        let fallback: Arc<dyn StorageAdapter> = Arc::new(FullStorage(LocalStorage::new(
+            temp_dir.path().to_str().unwrap(),
+        )));
+
+        for (storage, from, to) in [
+            (native, "photos", "pictures"),
+            (fallback, "pictures", "images"),
+        ] {
+            let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+            storages.insert(storage.name(), storage);
+            let data = Arc::new(VueFinder {
+                storages: Arc::new(storages),
+                ..VueFinder::default()
+            });
+
+            let response = VueFinder::rename(
+                data,
+                query("q=rename&adapter=local&path=local://"),
+                RenameRequest {
+                    name: to.to_string(),
+                    item: format!("local://{}", from),
+                },
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!temp_dir.path().join(from).exists());
+            assert_eq!(
+                std::fs::read_to_string(temp_dir.path().join(to).join("2023/a.jpg")).unwrap(),
+                "a"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["IMG_1.JPG", "IMG_2.JPG", "a.txt", "b.txt"] {
+            std::fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        let data = finder(&temp_dir);
+        let bulk_rename = |paths: &[&str], find: &str, replace: &str, regex: bool, dry_run| {
+            VueFinder::bulk_rename(
+                data.clone(),
+                query("q=bulk-rename&adapter=local&path=local://"),
+                BulkRenameRequest {
+                    items: paths
+                        .iter()
+                        .map(|path| FileItem {
+                            path: format!("local://{}", path),
+                        })
+                        .collect(),
+                    find: find.to_string(),
+                    replace: replace.to_string(),
+                    regex,
+                    start: None,
+                    dry_run,
+                },
+            )
+        };
+
+        // A dry run only proposes the names
+        let photos = ["IMG_1.JPG", "IMG_2.JPG"];
+        let response = bulk_rename(&photos, "", "photo_{n:2}{ext}", false, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        assert_eq!(body["status"], true);
+        assert_eq!(body["results"][1]["new_path"], "local://photo_02.JPG");
+        assert!(temp_dir.path().join("IMG_1.JPG").exists());
+
+        let response = bulk_rename(&photos, r"^IMG_(\d)", "photo_$1", true, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("photo_2.JPG")).unwrap(),
+            "IMG_2.JPG"
+        );
+
+        let response = bulk_rename(&["a.txt", "b.txt"], r"^(a|b)", "${1}x", true, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = bulk_rename(&["ax.txt", "bx.txt"], r"^a", "b", true, false).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = bulk_rename(&["ax.txt", "bx.txt"], "", "{n}{ext}", false, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Names other items are leaving can be taken, e.g. to swap them
+        let response = bulk_rename(&["2.txt", "1.txt"], "", "{n}{ext}", false, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("1.txt")).unwrap(),
+            "b.txt"
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("2.txt")).unwrap(),
+            "a.txt"
+        );
+
+        // Nothing is renamed when one of the names is taken
+        let response = bulk_rename(&["1.txt", "2.txt"], "", "photo_1.JPG", false, false).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = body_json(response).await;
+        assert_eq!(body["status"], false);
+        assert!(body["results"][1]["message"].is_string());
+        assert!(temp_dir.path().join("1.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_move_folder_keeps_structure() {
+        let storage = Arc::new(MemoryStorage::new("mem"));
+        storage
+            .write("mem://docs/guide/intro.md", b"# Intro".to_vec())
+            .await
+            .unwrap();
+        storage.create_dir("mem://docs/empty").await.unwrap();
+        storage.create_dir("mem://archive/docs").await.unwrap();
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(storage.name(), storage.clone());
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..VueFinder::default()
+        });
+
+        let rename = |name: &str, item: &str| {
+            VueFinder::rename(
+                data.clone(),
+                query("q=rename&adapter=mem&path=mem://"),
+                RenameRequest {
+                    name: name.to_string(),
+                    item: item.to_string(),
+                },
+            )
+        };
+
+        // Renaming onto an existing folder is refused instead of merging
+        let response = rename("archive", "mem://docs").await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(storage.exists("mem://docs/guide/intro.md").await.unwrap());
+
+        // Renaming to the same name leaves everything in place
+        let response = rename("docs", "mem://docs").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(storage.exists("mem://docs/guide/intro.md").await.unwrap());
+
+        let response = rename("manual", "mem://docs").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            storage.read("mem://manual/guide/intro.md").await.unwrap(),
+            b"# Intro"
+        );
+        assert!(storage.is_dir("mem://manual/empty").await.unwrap());
+        assert!(!storage.exists("mem://docs").await.unwrap());
+
+        let response = VueFinder::r#move(
+            data.clone(),
+            query("q=move&adapter=mem&path=mem://"),
+            MoveRequest {
+                from_adapter: None,
+                to_adapter: None,
+                conflict: None,
+                item: "mem://archive".to_string(),
+                items: vec![FileItem {
+                    path: "mem://manual".to_string(),
+                }],
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(storage.is_dir("mem://archive/manual/empty").await.unwrap());
+        assert!(storage.exists("mem://archive/docs").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_insufficient_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(FullStorage(LocalStorage::new(
+            temp_dir.path().to_str().unwrap(),
+        )));
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(storage.name(), storage);
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..VueFinder::default()
+        });
+
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                big.txt\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"big.txt\"\r\n\r\n\
+                Hello\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+        assert!(!temp_dir.path().join("big.txt").exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_trailing_dot_names() {
+        assert!(validate_windows_name("report.txt").is_ok());
+        assert!(validate_windows_name("foo.").is_err());
+        assert!(validate_windows_name("bar ").is_err());
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_windows_reserved_names() {
+        assert!(validate_windows_name("console.txt").is_ok());
+        assert!(validate_windows_name("CON").is_err());
+        assert!(validate_windows_name("nul.txt").is_err());
+        assert!(validate_windows_name("Com1").is_err());
+
+        let temp_dir = TempDir::new().unwrap();
+        let response = VueFinder::new_folder(
+            finder(&temp_dir),
+            query("q=newfolder&adapter=local&path=local://"),
+            NewFolderRequest {
+                name: "LPT1".to_string(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_group_by_type() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("b_dir")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("a_dir")).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "x").unwrap();
+        std::fs::write(
+            temp_dir.path().join(ORDER_FILE),
+            r#"["b_dir", "file.txt", "a_dir"]"#,
+        )
+        .unwrap();
+        let data = finder(&temp_dir);
+
+        let body = body_json(
+            VueFinder::index(
+                data.clone(),
+                query("q=index&adapter=local&path=local://&group_by=type&order=manual"),
+            )
+            .await,
+        )
+        .await;
+        let names = |key: &str| -> Vec<String> {
+            body[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f["basename"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names("dirs"), vec!["b_dir", "a_dir"]);
+        assert_eq!(names("files"), vec!["file.txt"]);
+
+        // Flat listing stays the default
+        let body =
+            body_json(VueFinder::index(data, query("q=index&adapter=local&path=local://")).await)
+                .await;
+        assert!(body.get("dirs").is_none());
+        assert_eq!(body["files"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_read_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        zip.start_file("a.txt", options).unwrap();
+        zip.write_all(b"first").unwrap();
+        zip.add_directory("docs/", options).unwrap();
+        zip.start_file("docs/b.json", options).unwrap();
+        zip.write_all(b"{\"second\": true}").unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        std::fs::write(temp_dir.path().join("bundle.zip"), bytes).unwrap();
+        let data = finder(&temp_dir);
+
+        let read = |entry: &str| {
+            VueFinder::read_entry(
+                data.clone(),
+                query("q=read-entry&adapter=local&path=local://"),
+                ReadEntryRequest {
+                    archive: "local://bundle.zip".to_string(),
+                    entry: entry.to_string(),
+                },
+            )
+        };
+
+        let response = read("docs/b.json").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        let body = response.into_body().to_bytes().await.unwrap();
+        assert_eq!(&body[..], b"{\"second\": true}");
+
+        assert_eq!(read("missing.txt").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read("../a.txt").await.status(), StatusCode::BAD_REQUEST);
+
+        // Entries are only decompressed up to `max_extract_size`
+        let response = VueFinder::read_entry(
+            finder_with_config(
+                &temp_dir,
+                VueFinderConfig {
+                    max_extract_size: 4,
+                    ..VueFinderConfig::default()
+                },
+            ),
+            query("q=read-entry&adapter=local&path=local://"),
+            ReadEntryRequest {
+                archive: "local://bundle.zip".to_string(),
+                entry: "a.txt".to_string(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_mime() {
+        let temp_dir = TempDir::new().unwrap();
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        std::fs::write(temp_dir.path().join("photo.bin"), png).unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "héllo").unwrap();
+        let data = finder(&temp_dir);
+
+        // Generic extension: sniffed from the content
+        let body = body_json(
+            VueFinder::mime(
+                data.clone(),
+                query("q=mime&adapter=local&path=local://photo.bin"),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(body["mime"], "image/png");
+        assert_eq!(body["sniffed"], true);
+        assert!(body["charset"].is_null());
+
+        // Known extension: trusted as-is
+        let body = body_json(
+            VueFinder::mime(
+                data.clone(),
+                query("q=mime&adapter=local&path=local://notes.txt"),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(body["mime"], "text/plain");
+        assert_eq!(body["sniffed"], false);
+        assert_eq!(body["charset"], "utf-8");
+    }
+
+    #[tokio::test]
+    async fn test_unarchive_validates_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for name in ["ok.txt", "docs/CON.txt", "trailing."] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+        std::fs::write(temp_dir.path().join("bundle.zip"), bytes).unwrap();
+
+        // Local storage with Windows naming rules, whatever the host platform
+        let storage =
+            Arc::new(LocalStorage::new(temp_dir.path().to_str().unwrap()).with_windows_names(true));
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(storage.name(), storage);
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            ..VueFinder::default()
+        });
+
+        let response = VueFinder::unarchive(
+            data,
+            query("q=unarchive&adapter=local&path=local://"),
+            UnarchiveRequest {
+                item: "local://bundle.zip".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        let names: Vec<_> = body["invalid"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["docs/CON.txt", "trailing."]);
+
+        // Nothing was extracted
+        assert!(!temp_dir.path().join("bundle").exists());
+    }
+
+    #[tokio::test]
+    async fn test_file_types_on_every_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                denied_extensions: vec!["exe".to_string()],
+                denied_mime_types: vec!["image/png".to_string()],
+                ..VueFinderConfig::default()
+            },
+        );
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, contents) in [
+            ("ok.txt", &b"x"[..]),
+            ("tool.exe", b"x"),
+            ("renamed.txt", png),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+        std::fs::write(temp_dir.path().join("bundle.zip"), bytes).unwrap();
+        let response = VueFinder::unarchive(
+            data.clone(),
+            query("q=unarchive&adapter=local&path=local://"),
+            UnarchiveRequest {
+                item: "local://bundle.zip".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        let names: Vec<_> = body["invalid"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["tool.exe", "renamed.txt"]);
+        assert!(!temp_dir.path().join("bundle").exists());
+
+        let response = VueFinder::append(
+            data.clone(),
+            query("q=append&adapter=local&path=local://tool.exe"),
+            AppendRequest {
+                content: "x".to_string(),
+                base64: false,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = VueFinder::append(
+            data.clone(),
+            query("q=append&adapter=local&path=local://image.txt"),
+            AppendRequest {
+                content: BASE64_STANDARD.encode(png),
+                base64: true,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = VueFinder::cas_save(
+            data,
+            query("q=cas-save&adapter=local&path=local://tool.exe"),
+            CasSaveRequest {
+                content: "x".to_string(),
+                expected_hash: String::new(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_access_rules_on_write_targets() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                access_rules: vec![AccessRule {
+                    adapter: None,
+                    path: "**/*.bin".to_string(),
+                    permissions: vec![Permission::Read],
+                }],
+                ..VueFinderConfig::default()
+            },
+        );
+
+        std::fs::create_dir(temp_dir.path().join("in")).unwrap();
+
+        // The folder is writable, the uploaded name isn't
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://in"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                x.bin\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"x.bin\"\r\n\r\n\
+                x\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!temp_dir.path().join("in/x.bin").exists());
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for name in ["ok.txt", "x.bin"] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+        std::fs::write(temp_dir.path().join("in/bundle.zip"), bytes).unwrap();
+        let response = VueFinder::unarchive(
+            data.clone(),
+            query("q=unarchive&adapter=local&path=local://in"),
+            UnarchiveRequest {
+                item: "local://in/bundle.zip".to_string(),
+                conflict: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        let names: Vec<_> = body["invalid"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["x.bin"]);
+        assert!(!temp_dir.path().join("in/bundle").exists());
+
+        // A folder is copied only when everything ending up below the target is writable
+        std::fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        std::fs::write(temp_dir.path().join("src/a.bin"), "a").unwrap();
+        std::fs::create_dir(temp_dir.path().join("dest")).unwrap();
+        let response = VueFinder::copy(
+            data,
+            query("q=copy&adapter=local&path=local://"),
+            CopyRequest {
+                from_adapter: None,
+                to_adapter: None,
+                conflict: None,
+                item: "local://dest".to_string(),
+                items: vec![FileItem {
+                    path: "local://src".to_string(),
+                }],
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!temp_dir.path().join("dest/src").exists());
+    }
+
+    #[tokio::test]
+    async fn test_unarchive_traversal_and_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+        let write_zip = |name: &str, entries: &[&str]| {
+            let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            for entry in entries {
+                zip.start_file(*entry, zip::write::FileOptions::default())
+                    .unwrap();
+                zip.write_all(b"new").unwrap();
+            }
+            let bytes = zip.finish().unwrap().into_inner();
+            std::fs::write(temp_dir.path().join(name), bytes).unwrap();
+        };
+        let unarchive = |item: &str, conflict: Option<CollisionPolicy>| {
+            VueFinder::unarchive(
+                data.clone(),
+                query("q=unarchive&adapter=local&path=local://"),
+                UnarchiveRequest {
+                    item: format!("local://{}", item),
+                    conflict,
+                },
+            )
+        };
+
+        write_zip("evil.zip", &["ok.txt", "../escaped.txt", "/abs.txt"]);
+        let response = unarchive("evil.zip", None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        assert_eq!(body["invalid"].as_array().unwrap().len(), 2);
+        assert!(!temp_dir.path().join("escaped.txt").exists());
+        assert!(!temp_dir.path().join("evil").exists());
+
+        write_zip("bundle.zip", &["a.txt", "./b.txt"]);
+        std::fs::create_dir(temp_dir.path().join("bundle")).unwrap();
+        std::fs::write(temp_dir.path().join("bundle/a.txt"), "old").unwrap();
+        let read = |name: &str| std::fs::read_to_string(temp_dir.path().join(name)).unwrap();
+
+        let response = unarchive("bundle.zip", Some(CollisionPolicy::Skip)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read("bundle/a.txt"), "old");
+        assert_eq!(read("bundle/b.txt"), "new");
+
+        let response = unarchive("bundle.zip", Some(CollisionPolicy::Rename)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read("bundle/a.txt"), "old");
+        assert_eq!(read("bundle/a (1).txt"), "new");
+
+        let response = unarchive("bundle.zip", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read("bundle/a.txt"), "new");
+    }
+
+    #[tokio::test]
+    async fn test_share_links() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "shared").unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::share_create(
+            data.clone(),
+            query("q=share/create&adapter=local"),
+            ShareCreateRequest {
+                path: "local://a.txt".to_string(),
+                password: Some("secret".to_string()),
+                expires_in_secs: None,
+                max_downloads: Some(1),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let link = body_json(response).await;
+        assert_eq!(link["protected"], true);
+        assert!(link.get("password_hash").is_none());
+        let id = link["id"].as_str().unwrap();
+
+        let download = |password: &str| {
+            let mut query = query(&format!("q=share/download&id={}", id));
+            query.password = Some(password.to_string());
+            VueFinder::share_download(data.clone(), query, None)
+        };
+        let response = download("wrong").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = download("secret").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.into_body().to_bytes().await.unwrap(), "shared");
+        let response = download("secret").await;
+        assert_eq!(response.status(), StatusCode::GONE);
+
+        let body =
+            body_json(VueFinder::share_list(data.clone(), query("q=share/list")).await).await;
+        assert_eq!(body["links"][0]["downloads"], 1);
+
+        let response =
+            VueFinder::share_revoke(data.clone(), ShareRevokeRequest { id: id.to_string() }).await;
+        assert_eq!(body_json(response).await["status"], true);
+        let response = download("secret").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_favorites() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+        let data = finder(&temp_dir);
+        let add = |path: &str| {
+            VueFinder::favorites_add(
+                data.clone(),
+                query("q=favorites/add&adapter=local"),
+                FavoriteRequest {
+                    path: path.to_string(),
+                },
+            )
+        };
+
+        let response = add("local://missing").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        add("local://docs").await;
+        let body = body_json(add("local://a.txt").await).await;
+        assert_eq!(body["favorites"][0]["basename"], "docs");
+        assert_eq!(body["favorites"][0]["type"], "dir");
+        assert_eq!(body["favorites"][1]["path"], "local://a.txt");
+
+        // Other users have their own, and deleted paths stay listed without a type
+        let bob = Arc::new(VueFinder {
+            principal: Some("bob".to_string()),
+            ..data.as_ref().clone()
+        });
+        let body = body_json(VueFinder::favorites_list(bob, query("q=favorites/list")).await).await;
+        assert_eq!(body["favorites"].as_array().unwrap().len(), 0);
+        std::fs::remove_file(temp_dir.path().join("a.txt")).unwrap();
+        let body =
+            body_json(VueFinder::favorites_list(data.clone(), query("q=favorites/list")).await)
+                .await;
+        assert!(body["favorites"][1]["type"].is_null());
+
+        let response = VueFinder::favorites_remove(
+            data.clone(),
+            query("q=favorites/remove&adapter=local"),
+            FavoriteRequest {
+                path: "local://docs/".to_string(),
+            },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["favorites"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recent() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.md"), "v1").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::download(
+            data.clone(),
+            query("q=download&adapter=local&path=local://b.txt"),
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        VueFinder::save(
+            data.clone(),
+            query("q=save&adapter=local&path=local://a.md"),
+            SaveRequest {
+                content: "v2".to_string(),
+                if_match: None,
+            },
+        )
+        .await;
+
+        let recent = || VueFinder::recent(data.clone(), query("q=recent"));
+        let body = body_json(recent().await).await;
+        assert_eq!(body["recent"][0]["path"], "local://a.md");
+        assert_eq!(body["recent"][0]["action"], "modified");
+        assert_eq!(body["recent"][1]["basename"], "b.txt");
+        assert_eq!(body["recent"][1]["action"], "opened");
+
+        // Paths no longer there are left out
+        std::fs::remove_file(temp_dir.path().join("b.txt")).unwrap();
+        let body = body_json(recent().await).await;
+        assert_eq!(body["recent"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_locks() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a.md"), "v1").unwrap();
+        let data = finder(&temp_dir);
+        let as_user = |user: &str| {
+            Arc::new(VueFinder {
+                principal: Some(user.to_string()),
+                ..data.as_ref().clone()
+            })
+        };
+        let (alice, bob) = (as_user("alice"), as_user("bob"));
+        let save = |data: Arc<VueFinder>| {
+            VueFinder::save(
+                data,
+                query("q=save&adapter=local&path=local://docs/a.md"),
+                SaveRequest {
+                    content: "v2".to_string(),
+                    if_match: None,
+                },
+            )
+        };
+
+        let response = VueFinder::lock(
+            alice.clone(),
+            query("q=lock&adapter=local"),
+            LockRequest {
+                item: "local://docs/a.md".to_string(),
+                ttl_secs: None,
+            },
+        )
+        .await;
+        assert_eq!(body_json(response).await["owner"], "alice");
+
+        assert_eq!(save(bob.clone()).await.status(), StatusCode::LOCKED);
+        let response = VueFinder::rename(
+            bob.clone(),
+            query("q=rename&adapter=local&path=local://"),
+            RenameRequest {
+                name: "papers".to_string(),
+                item: "local://docs".to_string(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::LOCKED);
+        let response = VueFinder::delete(
+            bob.clone(),
+            query("q=delete&adapter=local&path=local://docs"),
+            DeleteRequest {
+                items: vec![FileItem {
+                    path: "local://docs/a.md".to_string(),
+                }],
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::LOCKED);
+        assert_eq!(save(alice.clone()).await.status(), StatusCode::OK);
+
+        let body =
+            body_json(VueFinder::locks(bob.clone(), query("q=locks&adapter=local")).await).await;
+        assert_eq!(body["locks"][0]["path"], "local://docs/a.md");
+
+        let unlock = |data: Arc<VueFinder>| {
+            VueFinder::unlock(
+                data,
+                query("q=unlock&adapter=local"),
+                UnlockRequest {
+                    item: "local://docs/a.md".to_string(),
+                },
+            )
+        };
+        assert_eq!(unlock(bob.clone()).await.status(), StatusCode::LOCKED);
+        assert_eq!(unlock(alice).await.status(), StatusCode::OK);
+        assert_eq!(save(bob).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("note.txt"), "one\ntwo\n").unwrap();
+        let config = VueFinderConfig {
+            versioning: true,
+            max_versions: Some(2),
+            ..VueFinderConfig::default()
+        };
+        let data = finder_with_config(&temp_dir, config);
+        for content in ["one\n2\n", "one\nthree\n", "four\n"] {
+            let response = VueFinder::save(
+                data.clone(),
+                query("q=save&adapter=local&path=local://note.txt"),
+                SaveRequest {
+                    content: content.to_string(),
+                    if_match: None,
+                },
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // The oldest contents are dropped past `max_versions`
+        let list = || {
+            VueFinder::versions_list(
+                data.clone(),
+                query("q=versions/list&adapter=local&path=local://note.txt"),
+            )
+        };
+        let body = body_json(list().await).await;
+        let versions = body["versions"].as_array().unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0]["hash"], content_hash(b"one\nthree\n"));
+        let id = versions[1]["id"].as_str().unwrap().to_string();
+
+        let response = VueFinder::versions_diff(
+            data.clone(),
+            query(&format!(
+                "q=versions/diff&adapter=local&path=local://note.txt&id={}",
+                id
+            )),
+        )
+        .await;
+        let diff = body_json(response).await;
+        assert_eq!(
+            (diff["added"].as_u64(), diff["removed"].as_u64()),
+            (Some(1), Some(2))
+        );
+        assert!(diff["diff"].as_str().unwrap().contains("-one\n-2\n+four\n"));
+
+        let response = VueFinder::versions_restore(
+            data.clone(),
+            query("q=versions/restore&adapter=local&path=local://note.txt"),
+            VersionRestoreRequest { id },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["versions"][0]["hash"], content_hash(b"four\n"));
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("note.txt")).unwrap(),
+            "one\n2\n"
+        );
+
+        // Versions are kept out of listings, searches and folder totals
+        let body = body_json(
+            VueFinder::index(data.clone(), query("q=index&adapter=local&path=local://")).await,
+        )
+        .await;
+        assert_eq!(body["files"].as_array().unwrap().len(), 1);
+        let body = body_json(
+            VueFinder::sub_folders(
+                data.clone(),
+                query("q=subfolders&adapter=local&path=local://"),
+            )
+            .await,
+        )
+        .await;
+        assert!(body["folders"].as_array().unwrap().is_empty());
+        let body = body_json(
+            VueFinder::search(data.clone(), query("q=search&adapter=local&path=local://")).await,
+        )
+        .await;
+        assert_eq!(body["files"].as_array().unwrap().len(), 1);
+        let body =
+            body_json(VueFinder::size(data, query("q=size&adapter=local&path=local://")).await)
+                .await;
+        assert_eq!(
+            (body["files"].as_u64(), body["dirs"].as_u64()),
+            (Some(1), Some(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "x\ny\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "x\nz\n").unwrap();
+        std::fs::write(temp_dir.path().join("c.bin"), [0xff, 0xfe]).unwrap();
+        let data = finder(&temp_dir);
+        let diff = |left: &str, left_version: Option<String>, right: &str| {
+            VueFinder::diff(
+                data.clone(),
+                query("q=diff&adapter=local"),
+                DiffRequest {
+                    left: left.to_string(),
+                    left_version,
+                    right: right.to_string(),
+                    right_version: None,
+                },
+            )
+        };
+
+        let body = body_json(diff("local://a.txt", None, "local://b.txt").await).await;
+        assert_eq!(
+            body["diff"],
+            "--- local://a.txt\n+++ local://b.txt\n@@ -1,2 +1,2 @@\n x\n-y\n+z\n"
+        );
+        assert_eq!(
+            (body["added"].as_u64(), body["removed"].as_u64()),
+            (Some(1), Some(1))
+        );
+
+        let storage = data.storages.get("local").unwrap().clone();
+        let version = versions::snapshot(storage.as_ref(), "local://a.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "x\ny\nw\n").unwrap();
+        let body =
+            body_json(diff("local://a.txt", Some(version.id.clone()), "local://a.txt").await).await;
+        assert!(body["diff"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("--- local://a.txt@{}\n", version.id)));
+        assert_eq!(body["added"], 1);
+
+        let response = diff("local://a.txt", None, "local://c.bin").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = diff(
+            "local://a.txt",
+            Some("missing".to_string()),
+            "local://a.txt",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_search_content() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        std::fs::write(temp_dir.path().join("docs/a.md"), "Meeting notes").unwrap();
+        let config = VueFinderConfig {
+            content_index: Some(ContentIndexConfig::default()),
+            ..VueFinderConfig::default()
+        };
+        let index: Arc<dyn ContentIndex> = Arc::new(content_index::MemoryContentIndex::default());
+        let data = Arc::new(VueFinder {
+            content_index: Some(index.clone()),
+            ..finder_with_config(&temp_dir, config).as_ref().clone()
+        });
+        let storage = data.storages.get("local").unwrap().clone();
+        content_index::reindex(
+            index.as_ref(),
+            storage.as_ref(),
+            "local://",
+            1024,
+            100,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let search = |text: &str| {
+            let data = data.clone();
+            let query = query(&format!("q=search-content&adapter=local&filter={}", text));
+            async move {
+                body_json(VueFinder::search_content(data, query).await).await["matches"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|found| found["path"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(search("meeting").await, vec!["local://docs/a.md"]);
+
+        // Saves, renames and deletes keep the index up to date
+        let response = VueFinder::save(
+            data.clone(),
+            query("q=save&adapter=local&path=local://docs/b.txt"),
+            SaveRequest {
+                content: "Budget meeting".to_string(),
+                if_match: None,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(search("budget").await, vec!["local://docs/b.txt"]);
+
+        let response = VueFinder::rename(
+            data.clone(),
+            query("q=rename&adapter=local&path=local://docs"),
+            RenameRequest {
+                name: "c.txt".to_string(),
+                item: "local://docs/b.txt".to_string(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(search("budget").await, vec!["local://docs/c.txt"]);
+
+        VueFinder::delete(
+            data.clone(),
+            query("q=delete&adapter=local"),
+            DeleteRequest {
+                items: vec![FileItem {
+                    path: "local://docs".to_string(),
+                }],
+            },
+        )
+        .await;
+        assert!(search("meeting").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_copy_between_adapters() {
+        let source = Arc::new(MemoryStorage::new("src"));
+        source
+            .write("src://tree/a.txt", b"a".to_vec())
+            .await
+            .unwrap();
+        source
+            .write("src://tree/nested/deep/b.txt", b"b".to_vec())
+            .await
+            .unwrap();
+        source.create_dir("src://tree/empty").await.unwrap();
+        source
+            .write("src://loose.txt", b"loose".to_vec())
+            .await
+            .unwrap();
+        source.write("src://id.key", b"key".to_vec()).await.unwrap();
+        let destination = Arc::new(MemoryStorage::new("dst"));
+        destination
+            .write("dst://backup/loose.txt", b"old".to_vec())
+            .await
+            .unwrap();
+
+        let mut storages: HashMap<String, Arc<dyn StorageAdapter>> = HashMap::new();
+        storages.insert(source.name(), source.clone());
+        storages.insert(destination.name(), destination.clone());
+        let data = Arc::new(VueFinder {
+            storages: Arc::new(storages),
+            config: Arc::new(VueFinderConfig {
+                access_rules: vec![AccessRule {
+                    adapter: Some("dst".to_string()),
+                    path: "**/*.key".to_string(),
+                    permissions: vec![Permission::Read],
+                }],
+                ..VueFinderConfig::default()
+            }),
+            ..VueFinder::default()
+        });
+
+        let copy = |to_adapter: &str| {
+            VueFinder::copy_between_adapters(
+                data.clone(),
+                query("q=copy-between-adapters"),
+                CopyBetweenAdaptersRequest {
+                    from_adapter: "src".to_string(),
+                    to_adapter: to_adapter.to_string(),
+                    items: vec![
+                        FileItem {
+                            path: "src://tree".to_string(),
+                        },
+                        FileItem {
+                            path: "src://loose.txt".to_string(),
+                        },
+                        FileItem {
+                            path: "src://id.key".to_string(),
+                        },
+                    ],
+                    target: format!("{}://backup", to_adapter),
+                },
+            )
+        };
+
+        let body = body_json(copy("dst").await).await;
+        assert_eq!(body["succeeded"], 1);
+        assert_eq!(body["failed"], 2);
+        assert_eq!(body["results"][0]["status"], true);
+        assert_eq!(body["results"][0]["target"], "dst://backup/tree");
+        // Existing files are reported, not overwritten
+        assert_eq!(body["results"][1]["status"], false);
+        assert_eq!(
+            destination.read("dst://backup/loose.txt").await.unwrap(),
+            b"old"
+        );
+
+        assert_eq!(
+            destination
+                .read("dst://backup/tree/nested/deep/b.txt")
+                .await
+                .unwrap(),
+            b"b"
+        );
+        assert!(destination.is_dir("dst://backup/tree/empty").await.unwrap());
+        // Source is left untouched
+        assert!(source.exists("src://tree/a.txt").await.unwrap());
+        // Written through the same checks as any copy
+        assert_eq!(body["results"][2]["status"], false);
+        assert!(!destination.exists("dst://backup/id.key").await.unwrap());
+
+        let response = copy("missing").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_index_missing_vs_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("empty")).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "x").unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://empty"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        assert_eq!(body["files"], json!([]));
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://missing"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://file.txt"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_collision_policies() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("dest")).unwrap();
+        std::fs::write(temp_dir.path().join("dest/a.txt"), "old").unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "new").unwrap();
+        let data = finder(&temp_dir);
+        let read = |path: &str| std::fs::read_to_string(temp_dir.path().join(path)).unwrap();
+
+        let upload = |policy: &str| {
+            VueFinder::upload(
+                data.clone(),
+                query(&format!(
+                    "q=upload&adapter=local&path=local://dest&conflict={}",
+                    policy
+                )),
+                multipart(
+                    b"--BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                    a.txt\r\n\
+                    --BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n\
+                    upload\r\n\
+                    --BOUNDARY--\r\n",
+                ),
+            )
+        };
+
+        // Upload: rename, skip, fail, overwrite
+        assert!(upload("rename").await.status().is_success());
+        assert_eq!(read("dest/a (1).txt"), "upload");
+        assert!(upload("skip").await.status().is_success());
+        assert_eq!(read("dest/a.txt"), "old");
+        assert_eq!(upload("fail").await.status(), StatusCode::CONFLICT);
+        assert!(upload("overwrite").await.status().is_success());
+        assert_eq!(read("dest/a.txt"), "upload");
+
+        // Move: skip leaves both files, rename picks the next free suffix
+        let move_a = |policy: &str| {
+            VueFinder::r#move(
+                data.clone(),
+                query(&format!(
+                    "q=move&adapter=local&path=local://&conflict={}",
+                    policy
+                )),
+                MoveRequest {
+                    from_adapter: None,
+                    to_adapter: None,
+                    conflict: None,
+                    item: "local://dest".to_string(),
+                    items: vec![FileItem {
+                        path: "local://a.txt".to_string(),
+                    }],
+                },
+            )
+        };
+        assert!(move_a("skip").await.status().is_success());
+        assert_eq!(read("a.txt"), "new");
+        assert!(move_a("rename").await.status().is_success());
+        assert_eq!(read("dest/a (2).txt"), "new");
+        assert!(!temp_dir.path().join("a.txt").exists());
+
+        // Rename: fail refuses to clobber
+        let response = VueFinder::rename(
+            data.clone(),
+            query("q=rename&adapter=local&path=local://dest&conflict=fail"),
+            RenameRequest {
+                item: "local://dest/a (1).txt".to_string(),
+                name: "a.txt".to_string(),
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(read("dest/a (1).txt"), "upload");
+
+        // `on_conflict` in the upload form and the body of `copy`
+        let response = VueFinder::upload(
+            data.clone(),
+            query("q=upload&adapter=local&path=local://dest"),
+            multipart(
+                b"--BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"on_conflict\"\r\n\r\n\
+                rename\r\n\
+                --BOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n\
+                form\r\n\
+                --BOUNDARY--\r\n",
+            ),
+        )
+        .await;
+        assert!(response.status().is_success());
+        assert_eq!(read("dest/a (3).txt"), "form");
+
+        let request: CopyRequest = serde_json::from_value(json!({
+            "item": "local://dest",
+            "items": [{ "path": "local://dest/a.txt" }],
+            "on_conflict": "rename",
+        }))
+        .unwrap();
+        let response = VueFinder::copy(
+            data.clone(),
+            query("q=copy&adapter=local&path=local://"),
+            request,
+        )
+        .await;
+        assert!(response.status().is_success());
+        assert_eq!(read("dest/a (4).txt"), "upload");
+    }
+
+    #[tokio::test]
+    async fn test_preview_text_headers() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "héllo").unwrap();
+        std::fs::write(temp_dir.path().join("image.png"), b"\x89PNG\r\n").unwrap();
+
+        let response = VueFinder::preview(
+            finder(&temp_dir),
+            query("q=preview&adapter=local&path=local://notes.txt"),
+            None,
+        )
+        .await;
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers().get("x-content-type-options").unwrap(),
+            "nosniff"
+        );
+
+        let response = VueFinder::preview(
+            finder(&temp_dir),
+            query("q=preview&adapter=local&path=local://image.png"),
+            None,
+        )
+        .await;
+        assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+
+        let response = VueFinder::download(
+            finder_with_config(
+                &temp_dir,
+                VueFinderConfig {
+                    nosniff: false,
+                    ..VueFinderConfig::default()
+                },
+            ),
+            query("q=download&adapter=local&path=local://notes.txt"),
+            None,
+        )
+        .await;
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert!(response.headers().get("x-content-type-options").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_download_streams_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let contents: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        std::fs::write(temp_dir.path().join("large.bin"), &contents).unwrap();
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::download(
+            data.clone(),
+            query("q=download&adapter=local&path=local://large.bin"),
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"large.bin\""
+        );
+        let body = response.into_body().to_bytes().await.unwrap();
+        assert_eq!(&body[..], &contents[..]);
+
+        let response = VueFinder::preview(
+            data,
+            query("q=preview&adapter=local&path=local://missing.bin"),
+            None,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_preview_range() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("clip.mp4"), b"0123456789").unwrap();
+        let data = finder(&temp_dir);
+        let preview = |range: Option<ByteRange>| {
+            VueFinder::preview(
+                data.clone(),
+                query("q=preview&adapter=local&path=local://clip.mp4"),
+                range,
+            )
+        };
+
+        let response = preview(None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("accept-ranges").unwrap(), "bytes");
+        assert_eq!(response.headers().get("content-length").unwrap(), "10");
+
+        let response = preview(Some(ByteRange::From(2, Some(5)))).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes 2-5/10"
+        );
+        let body = response.into_body().to_bytes().await.unwrap();
+        assert_eq!(&body[..], b"2345");
+
+        // Open-ended and suffix ranges
+        let response = preview(Some("bytes=7-".parse().unwrap())).await;
+        assert_eq!(response.into_body().to_bytes().await.unwrap(), "789");
+        let response = preview(Some("bytes=-3".parse().unwrap())).await;
+        assert_eq!(response.into_body().to_bytes().await.unwrap(), "789");
+
+        let response = preview(Some(ByteRange::From(20, Some(30)))).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get("content-range").unwrap(),
+            "bytes */10"
+        );
+
+        // Multiple ranges aren't parsed, so they get the whole file
+        assert_eq!("bytes=0-1,4-5".parse::<ByteRange>(), Err(()));
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail() {
+        let temp_dir = TempDir::new().unwrap();
+        image::RgbImage::new(600, 300)
+            .save(temp_dir.path().join("wide.png"))
+            .unwrap();
+        std::fs::write(temp_dir.path().join("notes.png"), "not an image").unwrap();
+        let data = finder(&temp_dir);
+
+        let thumbnail = |path: &str| {
+            VueFinder::thumbnail(
+                data.clone(),
+                query(&format!(
+                    "q=thumbnail&adapter=local&path=local://{}&format=png",
+                    path
+                )),
+            )
+        };
+
+        let response = thumbnail("wide.png").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+        let body = response.into_body().to_bytes().await.unwrap();
+        let image = image::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (256, 128));
+
+        // Cached under `.thumbs`, which the listing hides, and served from there
+        let cached: Vec<_> = std::fs::read_dir(temp_dir.path().join(THUMBS_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(cached.len(), 1);
+        std::fs::write(&cached[0], "cached").unwrap();
+        let response = thumbnail("wide.png").await;
+        assert_eq!(response.into_body().to_bytes().await.unwrap(), "cached");
+
+        let body =
+            body_json(VueFinder::index(data.clone(), query("q=index&adapter=local")).await).await;
+        let names: Vec<_> = body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["basename"].as_str().unwrap())
+            .collect();
+        assert!(!names.contains(&THUMBS_DIR));
+
+        let response = thumbnail("notes.png").await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let response = thumbnail("missing.png").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_append() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        for line in ["first\n", "second\n"] {
+            let response = VueFinder::append(
+                data.clone(),
+                query("q=append&adapter=local&path=local://app.log"),
+                AppendRequest {
+                    content: line.to_string(),
+                    base64: false,
+                },
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("app.log")).unwrap(),
+            "first\nsecond\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_qr() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("public")).unwrap();
+        std::fs::write(temp_dir.path().join("public/a.txt"), "a").unwrap();
+        std::fs::write(temp_dir.path().join("private.txt"), "b").unwrap();
+        let data = finder_with_config(
+            &temp_dir,
+            VueFinderConfig {
+                public_links: Some(HashMap::from([(
+                    "local://public".to_string(),
+                    "https://files.example.com".to_string(),
+                )])),
+                ..VueFinderConfig::default()
+            },
+        );
+
+        let response = VueFinder::qr(
+            data.clone(),
+            query("q=qr&adapter=local&path=local://public/a.txt"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let response = VueFinder::qr(
+            data.clone(),
+            query("q=qr&adapter=local&path=local://public/a.txt&format=svg"),
+        )
+        .await;
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("<svg"));
+
+        let response = VueFinder::qr(
+            data.clone(),
+            query("q=qr&adapter=local&path=local://private.txt"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_modified_time_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let data = finder(&temp_dir);
+
+        let touch = |path: &str, modified: u64| {
+            let full_path = temp_dir.path().join(path);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            let file = std::fs::File::create(&full_path).unwrap();
+            file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(modified))
+                .unwrap();
+        };
+        touch("old.txt", 1_000);
+        touch("recent.txt", 2_000);
+        touch("nested/old.txt", 1_000);
+        touch("nested/deep/recent.txt", 2_500);
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("q=index&adapter=local&path=local://&modified_after=1500&modified_before=3000"),
+        )
+        .await;
+        let body = body_json(response).await;
+        let names: Vec<_> = body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["basename"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["recent.txt"]);
+
+        // Subtree-wide query through the recursive search
+        let response = VueFinder::search(
+            data.clone(),
+            query("q=search&adapter=local&path=&filter=&modified_after=1500"),
+        )
+        .await;
+        let body = body_json(response).await;
+        let mut paths: Vec<_> = body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["path"].as_str().unwrap().to_string())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["local://nested/deep/recent.txt", "local://recent.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trash() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let delete = |path: &str| {
+            VueFinder::delete(
+                data.clone(),
+                query("q=delete&adapter=local&path=local://"),
+                DeleteRequest {
+                    items: vec![FileItem {
+                        path: path.to_string(),
+                    }],
+                },
+            )
+        };
+
+        let body = body_json(delete("local://photos").await).await;
+        let names: Vec<_> = body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["basename"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["loose.txt"]);
+
+        // Trashed items are left out of commands reading whole folders
+        let body = body_json(
+            VueFinder::search(
+                data.clone(),
+                query("q=search&adapter=local&path=local://&filter=jpg"),
+            )
+            .await,
+        )
+        .await;
+        assert!(body["files"].as_array().unwrap().is_empty());
+        let body = body_json(
+            VueFinder::size(data.clone(), query("q=size&adapter=local&path=local://")).await,
+        )
+        .await;
+        assert_eq!(
+            body,
+            json!({ "size": 5, "files": 1, "dirs": 0, "complete": true })
+        );
+
+        let body =
+            body_json(VueFinder::trash_list(data.clone(), query("adapter=local")).await).await;
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["path"], "local://photos");
+        assert_eq!(items[0]["type"], "dir");
+        let id = items[0]["id"].as_str().unwrap().to_string();
+
+        let response = VueFinder::trash_restore(
+            data.clone(),
+            query("adapter=local"),
+            TrashRequest {
+                items: vec![id.clone(), "../loose.txt".to_string()],
+            },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert_eq!(body["restored"], 1);
+        assert_eq!(body["results"][0]["path"], "local://photos");
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("photos/2023/a.jpg")).unwrap(),
+            "a"
+        );
+
+        delete("local://loose.txt").await;
+        let response = VueFinder::trash_empty(
+            data.clone(),
+            query("adapter=local"),
+            TrashRequest { items: vec![] },
+        )
+        .await;
+        let body = body_json(response).await;
+        assert!(body["items"].as_array().unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_dir(temp_dir.path().join(TRASH_DIR))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_paging_and_sorting() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("b.txt"), "bbb").unwrap();
+        std::fs::write(temp_dir.path().join("C.txt"), "c").unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "aa").unwrap();
+        std::fs::create_dir(temp_dir.path().join("z")).unwrap();
+        let data = finder(&temp_dir);
+
+        let names = |body: &serde_json::Value| -> Vec<String> {
+            body["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f["basename"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let index = |q: &str| VueFinder::index(data.clone(), query(q));
+
+        let body = body_json(index("adapter=local&path=local://&sort=name").await).await;
+        assert_eq!(names(&body), ["a.txt", "b.txt", "C.txt", "z"]);
+        assert!(body.get("pagination").is_none());
+
+        let body = body_json(
+            index("adapter=local&path=local://&sort=size&order=desc&dirs_first=true").await,
+        )
+        .await;
+        assert_eq!(names(&body), ["z", "b.txt", "a.txt", "C.txt"]);
+
+        let body =
+            body_json(index("adapter=local&path=local://&sort=name&page=2&per_page=3").await).await;
+        assert_eq!(names(&body), ["z"]);
+        assert_eq!(
+            body["pagination"],
+            json!({ "page": 2, "per_page": 3, "total": 4, "total_pages": 2 })
+        );
+
+        let response = index("adapter=local&path=local://&sort=color").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = index("adapter=local&path=local://&per_page=0").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_typed_responses() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::index(
+            data.clone(),
+            query("adapter=local&path=local://&group_by=type"),
+        )
+        .await;
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        let body: IndexResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body.dirname, "local://");
+        assert_eq!(body.files[0].storage_item.basename, "loose.txt");
+        assert_eq!(body.dirs.unwrap()[0].storage_item.node_type, "dir");
+
+        let response = VueFinder::index(data, query("adapter=local&path=local://missing")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let bytes = response.into_body().to_bytes().await.unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(!body.status);
+        assert_eq!(body.message, "Directory not found.");
+    }
+
+    #[tokio::test]
+    async fn test_error_statuses() {
+        let temp_dir = TempDir::new().unwrap();
+        nested_tree(temp_dir.path());
+        let data = finder(&temp_dir);
+
+        let response = VueFinder::delete(
+            data.clone(),
+            query("q=delete&adapter=local&path=local://"),
+            DeleteRequest {
+                items: vec![FileItem {
+                    path: "local://missing.txt".to_string(),
+                }],
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response =
+            VueFinder::sub_folders(data.clone(), query("adapter=local&path=local://missing")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = VueFinder::new_file(
+            data,
+            query("q=newfile&adapter=local&path=local://"),
+            NewFileRequest {
+                name: "loose.txt".to_string(),
+                content: None,
+                base64: false,
+            },
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
     }
 }
@@ -0,0 +1,672 @@
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, TryStreamExt};
+use http::header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::OwnedSemaphorePermit;
+use tracing::{field, Instrument};
+
+use crate::acl;
+use crate::auth::Claims;
+use crate::error::FinderError;
+use crate::finder::{ByteRange, VueFinder};
+use crate::payload::{
+    AppendRequest, ArchiveRequest, BulkRenameRequest, CasSaveRequest, CopyBetweenAdaptersRequest,
+    CopyRequest, DeleteRequest, DiffDirsRequest, DiffRequest, FavoriteRequest, FetchUrlRequest,
+    LockRequest, MoveRequest, NewFileRequest, NewFolderRequest, Query, ReadEntryRequest,
+    RenameRequest, ReorderRequest, SaveRequest, ShareCreateRequest, ShareRevokeRequest,
+    SyncRequest, TrashRequest, UnarchiveRequest, UnlockRequest, UploadInitRequest,
+    UploadSessionRequest, VersionRestoreRequest,
+};
+use crate::response::ErrorResponse;
+use crate::storages::content_hash;
+use crate::webhook::Webhooks;
+
+// Command dispatch shared by the web framework integrations. Each one turns
+// its request into a `FinderRequest` and sends back the `FinderResponse`.
+// Neither holds types of a framework, so commands run on whichever runtime
+// serves the request.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FinderMethod {
+    Get,
+    Post,
+    Other,
+}
+
+pub enum FinderPayload {
+    None,
+    Json(Value),
+    Multipart(multer::Multipart<'static>),
+}
+
+impl FinderPayload {
+    // Multipart body from its `Content-Type` header and raw bytes, `None`
+    // without a boundary
+    pub fn multipart<S>(content_type: &str, body: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    {
+        match multer::parse_boundary(content_type) {
+            Ok(boundary) => FinderPayload::Multipart(multer::Multipart::new(body, boundary)),
+            Err(_) => FinderPayload::None,
+        }
+    }
+
+    fn json(&self) -> Option<&Value> {
+        match self {
+            FinderPayload::Json(json) => Some(json),
+            _ => None,
+        }
+    }
+}
+
+// Body of a `FinderResponse`
+pub enum FinderBody {
+    Empty,
+    Bytes(Bytes),
+    // Sent as it is produced, with a `Content-Length` header when the length
+    // is known up front
+    Stream(BoxStream<'static, Result<Bytes, std::io::Error>>),
+}
+
+impl FinderBody {
+    // The whole body in memory, for small responses and tests
+    pub async fn to_bytes(self) -> Result<Bytes, std::io::Error> {
+        match self {
+            FinderBody::Empty => Ok(Bytes::new()),
+            FinderBody::Bytes(bytes) => Ok(bytes),
+            FinderBody::Stream(stream) => {
+                let chunks: Vec<Bytes> = stream.try_collect().await?;
+                Ok(chunks.concat().into())
+            }
+        }
+    }
+}
+
+// What a command answers, turned into a response of their own by the web
+// framework integrations. Built like actix-web's `HttpResponse`, e.g.
+// `FinderResponse::Ok().json(body)`.
+pub struct FinderResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: FinderBody,
+}
+
+macro_rules! status_builders {
+    ($($name:ident => $status:ident,)*) => {
+        $(
+            #[allow(non_snake_case)]
+            pub fn $name() -> FinderResponseBuilder {
+                FinderResponseBuilder::new(StatusCode::$status)
+            }
+        )*
+    };
+}
+
+impl FinderResponse {
+    pub fn build(status: StatusCode) -> FinderResponseBuilder {
+        FinderResponseBuilder::new(status)
+    }
+
+    status_builders! {
+        Ok => OK,
+        Accepted => ACCEPTED,
+        BadRequest => BAD_REQUEST,
+        Unauthorized => UNAUTHORIZED,
+        Forbidden => FORBIDDEN,
+        NotFound => NOT_FOUND,
+        MethodNotAllowed => METHOD_NOT_ALLOWED,
+        Conflict => CONFLICT,
+        PayloadTooLarge => PAYLOAD_TOO_LARGE,
+        UnsupportedMediaType => UNSUPPORTED_MEDIA_TYPE,
+        RangeNotSatisfiable => RANGE_NOT_SATISFIABLE,
+        TooManyRequests => TOO_MANY_REQUESTS,
+        InternalServerError => INTERNAL_SERVER_ERROR,
+        BadGateway => BAD_GATEWAY,
+        InsufficientStorage => INSUFFICIENT_STORAGE,
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    pub fn into_body(self) -> FinderBody {
+        self.body
+    }
+
+    pub fn into_parts(self) -> (StatusCode, HeaderMap, FinderBody) {
+        (self.status, self.headers, self.body)
+    }
+}
+
+pub struct FinderResponseBuilder {
+    status: StatusCode,
+    headers: HeaderMap,
+}
+
+impl FinderResponseBuilder {
+    fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    pub fn status(&mut self, status: StatusCode) -> &mut Self {
+        self.status = status;
+        self
+    }
+
+    // Headers that aren't valid are left out
+    pub fn insert_header<K, V>(&mut self, (name, value): (K, V)) -> &mut Self
+    where
+        K: TryInto<HeaderName>,
+        V: TryInto<HeaderValue>,
+    {
+        if let (Ok(name), Ok(value)) = (name.try_into(), value.try_into()) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    pub fn content_type<V: TryInto<HeaderValue>>(&mut self, value: V) -> &mut Self {
+        self.insert_header((CONTENT_TYPE, value))
+    }
+
+    // Length of the body passed to `streaming`, so it isn't sent chunked
+    pub fn no_chunking(&mut self, length: u64) -> &mut Self {
+        self.insert_header((CONTENT_LENGTH, length))
+    }
+
+    fn with_body(&mut self, body: FinderBody) -> FinderResponse {
+        FinderResponse {
+            status: self.status,
+            headers: std::mem::take(&mut self.headers),
+            body,
+        }
+    }
+
+    pub fn finish(&mut self) -> FinderResponse {
+        self.with_body(FinderBody::Empty)
+    }
+
+    pub fn body(&mut self, body: impl Into<Bytes>) -> FinderResponse {
+        self.with_body(FinderBody::Bytes(body.into()))
+    }
+
+    pub fn json(&mut self, value: impl Serialize) -> FinderResponse {
+        match serde_json::to_vec(&value) {
+            Ok(json) => {
+                if !self.headers.contains_key(CONTENT_TYPE) {
+                    self.content_type("application/json");
+                }
+                self.body(json)
+            }
+            Err(e) => FinderResponse::InternalServerError()
+                .json(ErrorResponse::new(format!("Invalid response: {}", e))),
+        }
+    }
+
+    pub fn streaming<S, E>(&mut self, stream: S) -> FinderResponse
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        self.with_body(FinderBody::Stream(Box::pin(
+            stream.map_err(std::io::Error::other),
+        )))
+    }
+}
+
+pub struct FinderRequest {
+    pub method: FinderMethod,
+    pub query: Query,
+    pub payload: FinderPayload,
+    // `Range` header of downloads and previews
+    pub range: Option<String>,
+    // `If-Match` header of `save`
+    pub if_match: Option<String>,
+    // Peer address of the request, `None` when the server doesn't pass it on
+    pub client: Option<String>,
+    // Claims of the request's bearer token, selecting its folder under `user_roots`
+    pub claims: Option<Claims>,
+    // Who holds the request's file locks and upload slots, see
+    // `auth::principal`. Requests without one are told apart by `client`.
+    pub principal: Option<String>,
+}
+
+// Take one of the client's upload slots, `Err` when it has none left. The slot
+// is released when the permit is dropped. Requests from an unknown client
+// aren't limited, rather than all sharing one client's slots.
+async fn upload_permit(
+    client: Option<&str>,
+    data: &VueFinder,
+) -> Result<Option<OwnedSemaphorePermit>, ()> {
+    match (client, data.config.max_concurrent_uploads) {
+        (Some(client), Some(limit)) => data
+            .upload_limiter
+            .acquire(client, limit, data.config.upload_limit_mode)
+            .await
+            .map(Some)
+            .ok_or(()),
+        _ => Ok(None),
+    }
+}
+
+fn too_many_uploads() -> FinderResponse {
+    FinderResponse::TooManyRequests().json(ErrorResponse::new("Too many concurrent uploads."))
+}
+
+// Run `request` through the middlewares, the access rules and its command,
+// in a `finder_router` span that ends with the response status
+pub async fn handle(data: Arc<VueFinder>, request: FinderRequest) -> FinderResponse {
+    let span = tracing::info_span!(
+        "finder_router",
+        otel.name = %format_args!("vuefinder {}", request.query.q),
+        command = %request.query.q,
+        adapter = request.query.adapter.as_deref().unwrap_or_default(),
+        path = request.query.path.as_deref().unwrap_or_default(),
+        status = field::Empty,
+    );
+    let response = run(VueFinder::refreshed(data), request)
+        .instrument(span.clone())
+        .await;
+    span.record("status", response.status().as_u16());
+    response
+}
+
+async fn run(data: Arc<VueFinder>, request: FinderRequest) -> FinderResponse {
+    let FinderRequest {
+        method,
+        mut query,
+        mut payload,
+        range,
+        if_match,
+        client,
+        claims,
+        principal,
+    } = request;
+    let data = match (&data.config.user_roots, claims) {
+        (Some(user_roots), Some(claims)) => match user_roots.root(&claims) {
+            Some(root) => Arc::new(data.for_user(user_roots, &root)),
+            None => {
+                return FinderResponse::Forbidden().json(ErrorResponse::new(format!(
+                    "Token has no usable `{}` claim.",
+                    user_roots.claim
+                )))
+            }
+        },
+        _ => data,
+    };
+    let anonymous = |client: &str| format!("client:{}", &content_hash(client.as_bytes())[..12]);
+    let known = principal.or_else(|| client.as_deref().map(anonymous));
+    let principal = known.clone().unwrap_or_else(|| anonymous(""));
+    let data = Arc::new(VueFinder {
+        principal: Some(principal.clone()),
+        changed_paths: Arc::default(),
+        ..data.as_ref().clone()
+    });
+    let command = query.q.clone();
+
+    for middleware in data.middlewares.iter() {
+        let json = match &mut payload {
+            FinderPayload::Json(json) => Some(json),
+            _ => None,
+        };
+        if let Some(response) = middleware.before(&command, &mut query, json) {
+            return response;
+        }
+    }
+
+    if let Some(path) = data.denied_path(&command, &query, payload.json()) {
+        return FinderResponse::Forbidden()
+            .json(ErrorResponse::new(format!("Permission denied: {}", path)));
+    }
+
+    let event = if data.config.webhooks.is_empty() {
+        None
+    } else {
+        // The paths named by the request, never its contents
+        let adapter = data.get_default_adapter(query.adapter.clone());
+        let paths = acl::command_access(&command, &query, payload.json(), &adapter)
+            .into_iter()
+            .flatten()
+            .map(|access| access.path)
+            .collect();
+        Webhooks::event(
+            &command,
+            query.adapter.clone(),
+            query.path.clone(),
+            payload.json(),
+            paths,
+        )
+    };
+
+    let range = range.and_then(|range| range.parse::<ByteRange>().ok());
+    // The command's handler, without the middlewares and access checks around it
+    let handler = tracing::info_span!(
+        "handler",
+        otel.name = %format_args!("VueFinder::{}", command),
+        command = %command,
+    );
+    let response = match dispatch(
+        data.clone(),
+        method,
+        query,
+        payload,
+        range,
+        if_match,
+        // Only once authenticated, so made up tokens don't get slots of their own
+        known.as_deref(),
+    )
+    .instrument(handler)
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => return e.error_response(),
+    };
+
+    if let Some(mut event) = event.filter(|_| response.status().is_success()) {
+        // Such as the names of uploaded files
+        event.add_paths(data.changed_paths.lock().unwrap().drain(..));
+        data.webhooks.spawn_notify(&data.config.webhooks, event);
+    }
+
+    data.middlewares
+        .iter()
+        .rev()
+        .fold(response, |response, middleware| {
+            middleware.after(&command, response)
+        })
+}
+
+fn invalid_payload(error: serde_json::Error) -> FinderError {
+    FinderError::BadRequest(format!("Invalid payload: {}", error))
+}
+
+async fn dispatch(
+    data: Arc<VueFinder>,
+    method: FinderMethod,
+    query: Query,
+    payload: FinderPayload,
+    range: Option<ByteRange>,
+    if_match: Option<String>,
+    client: Option<&str>,
+) -> Result<FinderResponse, FinderError> {
+    match method {
+        FinderMethod::Get => match query.q.as_str() {
+            "index" => Ok(VueFinder::index(data, query).await),
+            "subfolders" => Ok(VueFinder::sub_folders(data, query).await),
+            "download" => Ok(VueFinder::download(data, query, range).await),
+            "download-archive" => Ok(VueFinder::download_archive(data, query).await),
+            "preview" => Ok(VueFinder::preview(data, query, range).await),
+            "qr" => Ok(VueFinder::qr(data, query).await),
+            "thumbnail" => Ok(VueFinder::thumbnail(data, query).await),
+            "mime" => Ok(VueFinder::mime(data, query).await),
+            "stat" => Ok(VueFinder::stat(data, query).await),
+            "size" => Ok(VueFinder::size(data, query).await),
+            "hash" => Ok(VueFinder::hash(data, query).await),
+            "duplicates" => Ok(VueFinder::duplicates(data, query).await),
+            "job-status" => Ok(VueFinder::job_status(data, query).await),
+            "locks" => Ok(VueFinder::locks(data, query).await),
+            "watch" => Ok(VueFinder::watch(data, query).await),
+            "search" => Ok(VueFinder::search(data, query).await),
+            "search-content" => Ok(VueFinder::search_content(data, query).await),
+            "content-reindex" => Ok(VueFinder::content_reindex(data, query).await),
+            "upload-status" => Ok(VueFinder::upload_status(data, query).await),
+            "trash/list" => Ok(VueFinder::trash_list(data, query).await),
+            "versions/list" => Ok(VueFinder::versions_list(data, query).await),
+            "versions/diff" => Ok(VueFinder::versions_diff(data, query).await),
+            "share/list" => Ok(VueFinder::share_list(data, query).await),
+            "favorites/list" => Ok(VueFinder::favorites_list(data, query).await),
+            "recent" => Ok(VueFinder::recent(data, query).await),
+            "share/download" => Ok(VueFinder::share_download(data, query, range).await),
+            "version" => Ok(VueFinder::version(data).await),
+            "storages" => Ok(VueFinder::storages(data).await),
+            _ => Ok(FinderResponse::BadRequest().finish()),
+        },
+        FinderMethod::Post => {
+            if matches!(payload, FinderPayload::None) {
+                return Err(FinderError::BadRequest("Missing request payload".into()));
+            }
+
+            match query.q.as_str() {
+                "upload" => match payload {
+                    FinderPayload::Multipart(multipart) => {
+                        // Held for the whole upload, released when dropped
+                        let Ok(_permit) = upload_permit(client, &data).await else {
+                            return Ok(too_many_uploads());
+                        };
+                        Ok(VueFinder::upload(data, query, multipart).await)
+                    }
+                    _ => Err(FinderError::BadRequest(
+                        "Upload requests should use multipart/form-data".into(),
+                    )),
+                },
+                "upload-chunk" => match payload {
+                    FinderPayload::Multipart(multipart) => {
+                        let Ok(_permit) = upload_permit(client, &data).await else {
+                            return Ok(too_many_uploads());
+                        };
+                        Ok(VueFinder::upload_chunk(data, query, multipart).await)
+                    }
+                    _ => Err(FinderError::BadRequest(
+                        "Upload requests should use multipart/form-data".into(),
+                    )),
+                },
+                "fetch-url" => match payload {
+                    FinderPayload::Json(json) => {
+                        let payload: FetchUrlRequest =
+                            serde_json::from_value(json).map_err(invalid_payload)?;
+                        // Counts as an upload while the download runs
+                        let Ok(_permit) = upload_permit(client, &data).await else {
+                            return Ok(too_many_uploads());
+                        };
+                        Ok(VueFinder::fetch_url(data, query, payload).await)
+                    }
+                    _ => Err(FinderError::BadRequest("Expected JSON payload".into())),
+                },
+                cmd @ ("newfolder"
+                | "newfile"
+                | "rename"
+                | "bulk-rename"
+                | "move"
+                | "copy"
+                | "delete"
+                | "save"
+                | "cas-save"
+                | "append"
+                | "archive"
+                | "unarchive"
+                | "reorder"
+                | "diff-dirs"
+                | "diff"
+                | "read-entry"
+                | "copy-between-adapters"
+                | "sync"
+                | "upload-init"
+                | "upload-finalize"
+                | "upload-abort"
+                | "trash/restore"
+                | "trash/empty"
+                | "versions/restore"
+                | "share/create"
+                | "share/revoke"
+                | "favorites/add"
+                | "favorites/remove"
+                | "lock"
+                | "unlock") => match payload {
+                    FinderPayload::Json(json) => match cmd {
+                        "newfolder" => {
+                            let payload: NewFolderRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::new_folder(data, query, payload).await)
+                        }
+                        "newfile" => {
+                            let payload: NewFileRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::new_file(data, query, payload).await)
+                        }
+                        "rename" => {
+                            let payload: RenameRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::rename(data, query, payload).await)
+                        }
+                        "bulk-rename" => {
+                            let payload: BulkRenameRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::bulk_rename(data, query, payload).await)
+                        }
+                        "move" => {
+                            let payload: MoveRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::r#move(data, query, payload).await)
+                        }
+                        "copy" => {
+                            let payload: CopyRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::copy(data, query, payload).await)
+                        }
+                        "delete" => {
+                            let payload: DeleteRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::delete(data, query, payload).await)
+                        }
+                        "save" => {
+                            let mut payload: SaveRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            payload.if_match = if_match.or(payload.if_match);
+                            Ok(VueFinder::save(data, query, payload).await)
+                        }
+                        "append" => {
+                            let payload: AppendRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::append(data, query, payload).await)
+                        }
+                        "read-entry" => {
+                            let payload: ReadEntryRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::read_entry(data, query, payload).await)
+                        }
+                        "copy-between-adapters" => {
+                            let payload: CopyBetweenAdaptersRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::copy_between_adapters(data, query, payload).await)
+                        }
+                        "sync" => {
+                            let payload: SyncRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::sync(data, query, payload).await)
+                        }
+                        "upload-init" => {
+                            let payload: UploadInitRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::upload_init(data, query, payload).await)
+                        }
+                        "upload-finalize" => {
+                            let payload: UploadSessionRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::upload_finalize(data, query, payload).await)
+                        }
+                        "upload-abort" => {
+                            let payload: UploadSessionRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::upload_abort(data, payload).await)
+                        }
+                        "trash/restore" => {
+                            let payload: TrashRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::trash_restore(data, query, payload).await)
+                        }
+                        "trash/empty" => {
+                            let payload: TrashRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::trash_empty(data, query, payload).await)
+                        }
+                        "versions/restore" => {
+                            let payload: VersionRestoreRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::versions_restore(data, query, payload).await)
+                        }
+                        "share/create" => {
+                            let payload: ShareCreateRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::share_create(data, query, payload).await)
+                        }
+                        "favorites/add" => {
+                            let payload: FavoriteRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::favorites_add(data, query, payload).await)
+                        }
+                        "favorites/remove" => {
+                            let payload: FavoriteRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::favorites_remove(data, query, payload).await)
+                        }
+                        "share/revoke" => {
+                            let payload: ShareRevokeRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::share_revoke(data, payload).await)
+                        }
+                        "lock" => {
+                            let payload: LockRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::lock(data, query, payload).await)
+                        }
+                        "unlock" => {
+                            let payload: UnlockRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::unlock(data, query, payload).await)
+                        }
+                        "cas-save" => {
+                            let payload: CasSaveRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::cas_save(data, query, payload).await)
+                        }
+                        "archive" => {
+                            let payload: ArchiveRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::archive(data, query, payload).await)
+                        }
+                        "unarchive" => {
+                            let payload: UnarchiveRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::unarchive(data, query, payload).await)
+                        }
+                        "reorder" => {
+                            let payload: ReorderRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::reorder(data, query, payload).await)
+                        }
+                        "diff-dirs" => {
+                            let payload: DiffDirsRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::diff_dirs(data, query, payload).await)
+                        }
+                        "diff" => {
+                            let payload: DiffRequest =
+                                serde_json::from_value(json).map_err(invalid_payload)?;
+                            Ok(VueFinder::diff(data, query, payload).await)
+                        }
+                        _ => unreachable!(),
+                    },
+                    _ => Err(FinderError::BadRequest("Expected JSON payload".into())),
+                },
+                _ => Ok(FinderResponse::BadRequest().finish()),
+            }
+        }
+        FinderMethod::Other => Ok(FinderResponse::MethodNotAllowed().finish()),
+    }
+}
@@ -0,0 +1,116 @@
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::response::FolderSizeResponse;
+use crate::storages::{StorageAdapter, StorageError};
+
+// Add up the files and folders below `path`. Stops after `limit` entries
+// with `complete: false`, the totals are then a lower bound. Entries `skip`
+// accepts are left out along with their contents.
+pub async fn folder_size(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    limit: usize,
+    skip: &(dyn Fn(&str) -> bool + Sync),
+) -> Result<FolderSizeResponse, StorageError> {
+    let mut total = FolderSizeResponse {
+        size: 0,
+        files: 0,
+        dirs: 0,
+        complete: true,
+    };
+    let mut visited = 0;
+    let mut pending = vec![path.to_string()];
+
+    while let Some(dir) = pending.pop() {
+        let mut contents = storage.list_stream(&dir);
+        while let Some(item) = contents.try_next().await? {
+            if skip(&item.path) {
+                continue;
+            }
+            if visited >= limit {
+                total.complete = false;
+                return Ok(total);
+            }
+            visited += 1;
+
+            if item.node_type == "dir" {
+                total.dirs += 1;
+                pending.push(item.path);
+            } else {
+                total.files += 1;
+                total.size += item.size.unwrap_or_default();
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+// Recently computed folder sizes, keyed by the folder's shared path, so
+// reopening a properties dialog doesn't walk the folder again. Entries aren't
+// invalidated by changes and may be stale for up to their time to live.
+#[derive(Default)]
+pub struct FolderSizes {
+    entries: Mutex<HashMap<String, (FolderSizeResponse, Instant)>>,
+}
+
+impl FolderSizes {
+    pub fn get(&self, path: &str, ttl: Duration) -> Option<FolderSizeResponse> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(path)
+            .filter(|(_, computed)| computed.elapsed() < ttl)
+            .map(|(size, _)| size.clone())
+    }
+
+    // Also drops the entries that have expired
+    pub fn insert(&self, path: String, size: FolderSizeResponse, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, computed)| computed.elapsed() < ttl);
+        entries.insert(path, (size, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_folder_size() {
+        let storage = MemoryStorage::new("mem").with_files([
+            ("docs/a.txt", "aaa"),
+            ("docs/drafts/b.txt", "bb"),
+            ("docs/empty/", ""),
+            ("c.txt", "c"),
+        ]);
+
+        let none = |_: &str| false;
+        let size = folder_size(&storage, "mem://docs", 100, &none)
+            .await
+            .unwrap();
+        assert_eq!((size.size, size.files, size.dirs), (5, 2, 2));
+        assert!(size.complete);
+
+        let drafts = |path: &str| path == "mem://docs/drafts";
+        let skipped = folder_size(&storage, "mem://docs", 100, &drafts)
+            .await
+            .unwrap();
+        assert_eq!((skipped.size, skipped.files, skipped.dirs), (3, 1, 1));
+
+        let size = folder_size(&storage, "mem://", 2, &none).await.unwrap();
+        assert!(!size.complete);
+        assert!(folder_size(&storage, "mem://missing", 100, &none)
+            .await
+            .is_err());
+
+        let sizes = FolderSizes::default();
+        let ttl = Duration::from_secs(60);
+        sizes.insert("mem://docs".to_string(), size.clone(), ttl);
+        assert_eq!(sizes.get("mem://docs", ttl), Some(size));
+        assert_eq!(sizes.get("mem://docs", Duration::ZERO), None);
+    }
+}
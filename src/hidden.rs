@@ -0,0 +1,62 @@
+use crate::acl::glob_match;
+use crate::finder::VueFinderConfig;
+use crate::trash::TRASH_DIR;
+
+// Whether `name` matches one of the `hidden_patterns`, globs where `*` and `?`
+// stand for any characters
+fn matches_pattern(patterns: &[String], name: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    patterns.iter().any(|pattern| {
+        let pattern: Vec<char> = pattern.chars().collect();
+        glob_match(&pattern, &name)
+    })
+}
+
+// Whether `path` is hidden, because it or a folder it's in is a dotfile or
+// matches `hidden_patterns`. `show_hidden` is the request's `show_hidden`
+// parameter: `true` shows everything unless `enforce_hidden` is set, `false`
+// also hides dotfiles when `hide_dotfiles` is off.
+pub fn is_hidden(config: &VueFinderConfig, show_hidden: Option<bool>, path: &str) -> bool {
+    if show_hidden == Some(true) && !config.enforce_hidden {
+        return false;
+    }
+    let dotfiles = config.hide_dotfiles || show_hidden == Some(false);
+
+    let key = path.split_once("://").map_or(path, |(_, key)| key);
+    key.split('/').filter(|name| !name.is_empty()).any(|name| {
+        // The trash has its own commands
+        (dotfiles && name.starts_with('.') && name != TRASH_DIR)
+            || matches_pattern(&config.hidden_patterns, name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_hidden() {
+        let config = VueFinderConfig {
+            hide_dotfiles: true,
+            hidden_patterns: vec!["node_modules".to_string(), "*.db".to_string()],
+            ..VueFinderConfig::default()
+        };
+        assert!(is_hidden(&config, None, "local://.git"));
+        assert!(is_hidden(&config, None, "local://app/.git/config"));
+        assert!(is_hidden(&config, None, "local://app/node_modules/a.js"));
+        assert!(is_hidden(&config, None, "local://photos/Thumbs.db"));
+        assert!(!is_hidden(&config, None, "local://app/src/main.rs"));
+        assert!(!is_hidden(&config, None, "local://.trash"));
+        assert!(!is_hidden(&config, Some(true), "local://.git"));
+
+        let config = VueFinderConfig {
+            enforce_hidden: true,
+            ..config
+        };
+        assert!(is_hidden(&config, Some(true), "local://.git"));
+
+        let config = VueFinderConfig::default();
+        assert!(!is_hidden(&config, None, "local://.env"));
+        assert!(is_hidden(&config, Some(false), "local://.env"));
+    }
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+
+use crate::error::FinderError;
+
+// Hooks around the file operations of the finder commands, for validation,
+// auditing or refusing operations from library code.
+//
+// Hooks are registered in order on `VueFinder::hooks`. `before_*` hooks run
+// before anything is changed or sent; the first `Err` stops the operation and
+// is answered with its status and message, e.g. `FinderError::Forbidden` for
+// a 403. `after_*` hooks run once the change succeeded. `adapter` is the
+// storage name and `path` the full `adapter://` path of the item.
+#[async_trait]
+pub trait FinderHooks: Send + Sync {
+    // A file or folder is about to be created or replaced at `path`: uploads,
+    // new files and folders, saves, copy and move targets, renames and archives
+    async fn before_write(&self, _adapter: &str, _path: &str) -> Result<(), FinderError> {
+        Ok(())
+    }
+
+    async fn after_write(&self, _adapter: &str, _path: &str) {}
+
+    // `path` is about to be deleted or moved to the trash
+    async fn before_delete(&self, _adapter: &str, _path: &str) -> Result<(), FinderError> {
+        Ok(())
+    }
+
+    async fn after_delete(&self, _adapter: &str, _path: &str) {}
+
+    // The contents of `path` are about to be sent by `download`, `preview`,
+    // `download-archive`, `thumbnail`, `read-entry`, the diffs or as
+    // `search-content` snippets
+    async fn before_download(&self, _adapter: &str, _path: &str) -> Result<(), FinderError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::{VueFinderAppConfig, VueFinderAppExt};
+    use crate::storages::local::LocalStorage;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    // Keeps `private*` files out of reach and records every change
+    #[derive(Default)]
+    struct Guard(Mutex<Vec<String>>);
+
+    fn name(path: &str) -> &str {
+        path.rsplit('/').next().unwrap_or_default()
+    }
+
+    fn check(path: &str) -> Result<(), FinderError> {
+        if name(path).starts_with("private") {
+            return Err(FinderError::Forbidden(
+                "Private files are off limits.".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[async_trait]
+    impl FinderHooks for Arc<Guard> {
+        async fn before_write(&self, _adapter: &str, path: &str) -> Result<(), FinderError> {
+            check(path)
+        }
+
+        async fn after_write(&self, adapter: &str, path: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("write {} {}", adapter, name(path)));
+        }
+
+        async fn before_delete(&self, _adapter: &str, path: &str) -> Result<(), FinderError> {
+            check(path)
+        }
+
+        async fn after_delete(&self, adapter: &str, path: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("delete {} {}", adapter, name(path)));
+        }
+
+        async fn before_download(&self, _adapter: &str, path: &str) -> Result<(), FinderError> {
+            check(path)
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("private.txt"), "secret").unwrap();
+        std::fs::write(temp_dir.path().join("public.txt"), "hello").unwrap();
+        let guard = Arc::new(Guard::default());
+
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            hooks: Arc::new(vec![Arc::new(guard.clone()) as Arc<dyn FinderHooks>]),
+            finder_config: Arc::new(crate::VueFinderConfig {
+                trash: false,
+                ..Default::default()
+            }),
+            ..VueFinderAppConfig::default()
+        };
+        let app = test::init_service(App::new().configure_vuefinder(config)).await;
+        let post = |command: &str, body: serde_json::Value| {
+            test::TestRequest::post()
+                .uri(&format!("/api?q={}&adapter=local&path=local://", command))
+                .set_json(body)
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, post("newfile", json!({"name": "private.md"}))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(!temp_dir.path().join("private.md").exists());
+        let resp = test::call_service(&app, post("newfile", json!({"name": "notes.md"}))).await;
+        assert!(resp.status().is_success());
+
+        let resp = test::call_service(
+            &app,
+            post(
+                "delete",
+                json!({"items": [
+                    {"path": "local://public.txt", "type": "file"},
+                    {"path": "local://private.txt", "type": "file"}
+                ]}),
+            ),
+        )
+        .await;
+        // Nothing is deleted when one of the items is refused
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(temp_dir.path().join("public.txt").exists());
+        let resp = test::call_service(
+            &app,
+            post(
+                "delete",
+                json!({"items": [{"path": "local://public.txt", "type": "file"}]}),
+            ),
+        )
+        .await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::get()
+            .uri("/api?q=download&adapter=local&path=local://private.txt")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        // Anything else showing the contents asks the same hooks
+        let resp = test::call_service(
+            &app,
+            post(
+                "diff",
+                json!({"left": "local://notes.md", "right": "local://private.txt"}),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        assert_eq!(
+            *guard.0.lock().unwrap(),
+            vec!["write local notes.md", "delete local public.txt"]
+        );
+    }
+}
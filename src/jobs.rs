@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::storages::transfer::CopyProgress;
+
+// How long results stay around once a job has finished
+const FINISHED_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+}
+
+// Body of `job-status`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JobStatus {
+    pub id: String,
+    // The command that started the job, e.g. `duplicates`
+    pub kind: String,
+    pub state: JobState,
+    // What the job is busy with, each kind has its own phases
+    pub phase: String,
+    // Units of work done in the current phase, out of `total` when known
+    pub done: u64,
+    pub total: Option<u64>,
+    // The command's response, once the job is done
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+struct Job {
+    status: JobStatus,
+    finished: Option<Instant>,
+}
+
+// Handed to a running job to report how far it got
+#[derive(Clone)]
+pub struct JobProgress {
+    job: Arc<Mutex<Job>>,
+}
+
+impl JobProgress {
+    // Start a new phase, with `total` units of work when known
+    pub fn phase(&self, phase: &str, total: Option<u64>) {
+        let status = &mut self.job.lock().unwrap().status;
+        status.phase = phase.to_string();
+        status.done = 0;
+        status.total = total;
+    }
+
+    pub fn advance(&self, done: u64) {
+        self.job.lock().unwrap().status.done += done;
+    }
+}
+
+impl CopyProgress for JobProgress {
+    fn copied(&self, bytes: u64) {
+        self.advance(bytes);
+    }
+}
+
+// Commands too slow to answer in one request run here, clients poll
+// `job-status` with the returned id. Jobs are lost on restart.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Mutex<HashMap<String, Arc<Mutex<Job>>>>,
+    counter: AtomicU64,
+}
+
+impl Jobs {
+    fn new_id(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(self.counter.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        hasher.update(std::process::id().to_le_bytes());
+        hex::encode(&hasher.finalize()[..16])
+    }
+
+    // Run `task` on the runtime and return the job's id right away. Also
+    // drops the jobs that finished a while ago.
+    pub fn spawn<F, Fut, T, E>(&self, kind: &str, task: F) -> String
+    where
+        F: FnOnce(JobProgress) -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: Display,
+    {
+        let id = self.new_id();
+        let job = Arc::new(Mutex::new(Job {
+            status: JobStatus {
+                id: id.clone(),
+                kind: kind.to_string(),
+                state: JobState::Running,
+                phase: String::new(),
+                done: 0,
+                total: None,
+                result: None,
+                error: None,
+            },
+            finished: None,
+        }));
+
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.retain(|_, job| {
+                job.lock()
+                    .unwrap()
+                    .finished
+                    .is_none_or(|finished| finished.elapsed() < FINISHED_TTL)
+            });
+            jobs.insert(id.clone(), job.clone());
+        }
+
+        let task = task(JobProgress { job: job.clone() });
+        tokio::spawn(async move {
+            let outcome = match task.await {
+                Ok(result) => serde_json::to_value(result).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            let mut job = job.lock().unwrap();
+            match outcome {
+                Ok(result) => {
+                    job.status.state = JobState::Done;
+                    job.status.result = Some(result);
+                }
+                Err(e) => {
+                    job.status.state = JobState::Failed;
+                    job.status.error = Some(e);
+                }
+            }
+            job.finished = Some(Instant::now());
+        });
+
+        id
+    }
+
+    pub fn status(&self, id: &str) -> Option<JobStatus> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(id).map(|job| job.lock().unwrap().status.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::StorageError;
+
+    #[tokio::test]
+    async fn test_jobs() {
+        let jobs = Jobs::default();
+        let (release, wait) = tokio::sync::oneshot::channel::<()>();
+        let id = jobs.spawn("count", |progress| async move {
+            progress.phase("counting", Some(2));
+            progress.advance(1);
+            wait.await.ok();
+            Ok::<_, StorageError>(42)
+        });
+        let failed = jobs.spawn("fail", |_| async {
+            Err::<(), _>(StorageError::NotFound("mem://missing".to_string()))
+        });
+
+        tokio::task::yield_now().await;
+        let status = jobs.status(&id).unwrap();
+        assert_eq!(status.state, JobState::Running);
+        assert_eq!(
+            (status.phase.as_str(), status.done, status.total),
+            ("counting", 1, Some(2))
+        );
+
+        release.send(()).unwrap();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        let status = jobs.status(&id).unwrap();
+        assert_eq!(status.state, JobState::Done);
+        assert_eq!(status.result, Some(serde_json::json!(42)));
+        assert_eq!(jobs.status(&failed).unwrap().state, JobState::Failed);
+        assert!(jobs.status("unknown").is_none());
+    }
+}
@@ -1,9 +1,53 @@
+pub mod acl;
+pub mod api_key;
 pub mod app_config;
+pub mod archive;
+pub mod auth;
+#[cfg(feature = "axum")]
+pub mod axum_router;
+pub mod batch;
+pub mod bulk_rename;
+pub mod content_index;
+pub mod duplicates;
+pub mod error;
+pub mod favorites;
+pub mod fetch_url;
+pub mod file_types;
 pub mod finder;
+pub mod finder_core;
+pub mod folder_size;
+pub mod hidden;
+pub mod hooks;
+pub mod jobs;
+pub mod limiter;
+pub mod list_store;
+pub mod locks;
+pub mod middleware;
 pub mod payload;
+pub mod recent;
+pub mod reload;
+pub mod response;
 pub mod router;
+pub mod sanitize;
+pub mod scan;
+pub mod search;
+pub mod share;
 pub mod storages;
+pub mod sync;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod text_diff;
+pub mod trash;
+pub mod upload_session;
+pub mod versions;
+pub mod watch;
+pub mod webhook;
+pub mod zip_stream;
 
+pub use error::FinderError;
 pub use finder::{VueFinder, VueFinderConfig};
+pub use hooks::FinderHooks;
+pub use middleware::FinderMiddleware;
+pub use response::{ErrorResponse, IndexResponse, SearchResponse};
 pub use router::finder_router;
 pub use storages::{StorageAdapter, StorageItem};
@@ -0,0 +1,100 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// What to do with an upload once its client already has the maximum in flight
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadLimitMode {
+    // Answer 429 right away
+    #[default]
+    Reject,
+    // Wait for one of the client's uploads to finish
+    Queue,
+}
+
+// Per-client cap on concurrent uploads, keyed by principal or IP
+#[derive(Debug, Default)]
+pub struct UploadLimiter {
+    clients: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl UploadLimiter {
+    // Take an upload slot for `client`, `None` when rejected. The slot is held
+    // until the returned permit is dropped.
+    pub async fn acquire(
+        &self,
+        client: &str,
+        limit: usize,
+        mode: UploadLimitMode,
+    ) -> Option<OwnedSemaphorePermit> {
+        let semaphore = {
+            let mut clients = self.clients.lock().unwrap();
+            // Permits keep their semaphore alive, so a lone reference is an idle client
+            clients.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            clients
+                .entry(client.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone()
+        };
+
+        match mode {
+            UploadLimitMode::Reject => semaphore.try_acquire_owned().ok(),
+            UploadLimitMode::Queue => semaphore.acquire_owned().await.ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_reject_over_limit() {
+        let limiter = Arc::new(UploadLimiter::default());
+
+        let uploads = (0..5).map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                let permit = limiter
+                    .acquire("10.0.0.1", 2, UploadLimitMode::Reject)
+                    .await;
+                let accepted = permit.is_some();
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                accepted
+            })
+        });
+        let results = futures_util::future::join_all(uploads).await;
+        let accepted = results.into_iter().filter(|r| *r.as_ref().unwrap()).count();
+        assert_eq!(accepted, 2);
+
+        // Other clients and finished uploads are unaffected
+        assert!(limiter
+            .acquire("10.0.0.2", 2, UploadLimitMode::Reject)
+            .await
+            .is_some());
+        assert!(limiter
+            .acquire("10.0.0.1", 2, UploadLimitMode::Reject)
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_queue_over_limit() {
+        let limiter = UploadLimiter::default();
+
+        let first = limiter.acquire("client", 1, UploadLimitMode::Queue).await;
+        assert!(first.is_some());
+
+        let queued = limiter.acquire("client", 1, UploadLimitMode::Queue);
+        tokio::pin!(queued);
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut queued)
+            .await
+            .is_err());
+
+        drop(first);
+        assert!(queued.await.is_some());
+    }
+}
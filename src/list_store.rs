@@ -0,0 +1,190 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ListStoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid store: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// Where a `StoredList` keeps its items. The whole list is loaded once and
+// saved after every change.
+#[async_trait]
+pub trait ListStore<T>: Send + Sync {
+    async fn load(&self) -> Result<Vec<T>, ListStoreError>;
+    async fn save(&self, items: &[T]) -> Result<(), ListStoreError>;
+}
+
+// Items lost on restart
+pub struct MemoryListStore<T> {
+    items: Mutex<Vec<T>>,
+}
+
+impl<T> Default for MemoryListStore<T> {
+    fn default() -> Self {
+        Self {
+            items: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Clone + Send + Sync> ListStore<T> for MemoryListStore<T> {
+    async fn load(&self) -> Result<Vec<T>, ListStoreError> {
+        Ok(self.items.lock().unwrap().clone())
+    }
+
+    async fn save(&self, items: &[T]) -> Result<(), ListStoreError> {
+        *self.items.lock().unwrap() = items.to_vec();
+        Ok(())
+    }
+}
+
+// Items kept in a JSON file, replaced atomically on every save
+pub struct JsonListStore<T> {
+    file: PathBuf,
+    items: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonListStore<T> {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            items: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Serialize + DeserializeOwned + Sync> ListStore<T> for JsonListStore<T> {
+    async fn load(&self) -> Result<Vec<T>, ListStoreError> {
+        match tokio::fs::read(&self.file).await {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, items: &[T]) -> Result<(), ListStoreError> {
+        if let Some(dir) = self.file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let part = self.file.with_extension("json.part");
+        tokio::fs::write(&part, serde_json::to_vec_pretty(items)?).await?;
+        tokio::fs::rename(&part, &self.file).await?;
+        Ok(())
+    }
+}
+
+// A list loaded from its store on first use and kept in memory, changed one
+// caller at a time
+pub struct StoredList<T> {
+    store: Arc<dyn ListStore<T>>,
+    // `None` until loaded
+    items: Arc<tokio::sync::Mutex<Option<Vec<T>>>>,
+    // A save of changes made with `update_later` is scheduled
+    pending: Arc<AtomicBool>,
+}
+
+impl<T: Clone + Send + Sync + 'static> Default for StoredList<T> {
+    fn default() -> Self {
+        Self::new(MemoryListStore::default())
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> StoredList<T> {
+    pub fn new(store: impl ListStore<T> + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            items: Arc::new(tokio::sync::Mutex::new(None)),
+            pending: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub async fn items(&self) -> Result<Vec<T>, ListStoreError> {
+        let mut items = self.items.lock().await;
+        Ok(self.loaded(&mut items).await?.clone())
+    }
+
+    // Apply `change` and save the list when it returns `true`
+    pub async fn update(
+        &self,
+        change: impl FnOnce(&mut Vec<T>) -> bool,
+    ) -> Result<(), ListStoreError> {
+        let mut items = self.items.lock().await;
+        let list = self.loaded(&mut items).await?;
+        let mut changed = list.clone();
+        if !change(&mut changed) {
+            return Ok(());
+        }
+        // Kept in memory only once saved, so a failed save changes nothing
+        self.store.save(&changed).await?;
+        *list = changed;
+        Ok(())
+    }
+
+    // Apply `change` now and save the list within `delay`, together with the
+    // other changes made meanwhile. Frequent changes that may be lost when the
+    // process exits first go through here.
+    pub async fn update_later(
+        &self,
+        change: impl FnOnce(&mut Vec<T>) -> bool,
+        delay: Duration,
+    ) -> Result<(), ListStoreError> {
+        let mut items = self.items.lock().await;
+        if !change(self.loaded(&mut items).await?) {
+            return Ok(());
+        }
+        if self.pending.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let (store, items, pending) =
+            (self.store.clone(), self.items.clone(), self.pending.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(e) = Self::save_pending(store.as_ref(), &items, &pending).await {
+                log::warn!("Failed to save a list: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    // Save what `update_later` hasn't yet
+    pub async fn flush(&self) -> Result<(), ListStoreError> {
+        Self::save_pending(self.store.as_ref(), &self.items, &self.pending).await
+    }
+
+    async fn save_pending(
+        store: &dyn ListStore<T>,
+        items: &tokio::sync::Mutex<Option<Vec<T>>>,
+        pending: &AtomicBool,
+    ) -> Result<(), ListStoreError> {
+        let items = items.lock().await;
+        if !pending.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        match items.as_ref() {
+            Some(items) => store.save(items).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn loaded<'a>(
+        &self,
+        items: &'a mut Option<Vec<T>>,
+    ) -> Result<&'a mut Vec<T>, ListStoreError> {
+        if items.is_none() {
+            *items = Some(self.store.load().await?);
+        }
+        Ok(items.as_mut().unwrap())
+    }
+}
@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("Locked by {}", .0.owner)]
+    Locked(FileLock),
+    #[error("Not locked: {0}")]
+    NotFound(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// An advisory lock on a file, held by whoever is editing it. Paths are as
+// the adapter shares them, see `StorageAdapter::shared_path`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FileLock {
+    pub adapter: String,
+    pub path: String,
+    // The token's `sub` or the API key's name, see `VueFinder::lock_owner`
+    pub owner: String,
+    // Unix seconds
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl FileLock {
+    fn expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    // Whether changing `path` of `adapter` touches the locked file
+    fn covers(&self, adapter: &str, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.adapter == adapter
+            && (self.path == path || self.path.starts_with(&format!("{}/", path)))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Where locks are kept. Every change loads and saves the whole set, like
+// `ShareStore`, so stores only need to persist a list.
+#[async_trait]
+pub trait LockStore: Send + Sync {
+    async fn load(&self) -> Result<Vec<FileLock>, LockError>;
+    async fn save(&self, locks: &[FileLock]) -> Result<(), LockError>;
+}
+
+// Locks lost on restart, the default
+#[derive(Default)]
+pub struct MemoryLockStore {
+    locks: Mutex<Vec<FileLock>>,
+}
+
+#[async_trait]
+impl LockStore for MemoryLockStore {
+    async fn load(&self) -> Result<Vec<FileLock>, LockError> {
+        Ok(self.locks.lock().unwrap().clone())
+    }
+
+    async fn save(&self, locks: &[FileLock]) -> Result<(), LockError> {
+        *self.locks.lock().unwrap() = locks.to_vec();
+        Ok(())
+    }
+}
+
+// File locks of every adapter, shared by all workers. Expired locks are
+// ignored and dropped on the next change.
+pub struct FileLocks {
+    store: Box<dyn LockStore>,
+    // Serializes load-modify-save cycles
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl Default for FileLocks {
+    fn default() -> Self {
+        Self::new(MemoryLockStore::default())
+    }
+}
+
+impl FileLocks {
+    pub fn new(store: impl LockStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    // Take the lock on `path` for `ttl`, or extend it when `owner` holds it already
+    pub async fn acquire(
+        &self,
+        adapter: &str,
+        path: &str,
+        owner: &str,
+        ttl: Duration,
+    ) -> Result<FileLock, LockError> {
+        let _guard = self.lock.lock().await;
+        let now = now();
+        let mut locks = self.store.load().await?;
+        locks.retain(|lock| !lock.expired(now));
+
+        let created_at = match locks
+            .iter()
+            .find(|lock| lock.adapter == adapter && lock.path == path)
+        {
+            Some(lock) if lock.owner != owner => return Err(LockError::Locked(lock.clone())),
+            Some(lock) => lock.created_at,
+            None => now,
+        };
+        locks.retain(|lock| !(lock.adapter == adapter && lock.path == path));
+        let lock = FileLock {
+            adapter: adapter.to_string(),
+            path: path.to_string(),
+            owner: owner.to_string(),
+            created_at,
+            expires_at: now + ttl.as_secs(),
+        };
+        locks.push(lock.clone());
+        self.store.save(&locks).await?;
+        Ok(lock)
+    }
+
+    pub async fn release(&self, adapter: &str, path: &str, owner: &str) -> Result<(), LockError> {
+        let _guard = self.lock.lock().await;
+        let now = now();
+        let mut locks = self.store.load().await?;
+        locks.retain(|lock| !lock.expired(now));
+
+        match locks
+            .iter()
+            .position(|lock| lock.adapter == adapter && lock.path == path)
+        {
+            Some(index) if locks[index].owner != owner => {
+                Err(LockError::Locked(locks[index].clone()))
+            }
+            Some(index) => {
+                locks.remove(index);
+                self.store.save(&locks).await
+            }
+            None => Err(LockError::NotFound(path.to_string())),
+        }
+    }
+
+    // `Err(Locked)` when someone other than `owner` holds a lock on `path`
+    // or, for folders, on a file below it
+    pub async fn check(&self, adapter: &str, path: &str, owner: &str) -> Result<(), LockError> {
+        let now = now();
+        let locks = self.store.load().await?;
+        match locks
+            .into_iter()
+            .find(|lock| !lock.expired(now) && lock.owner != owner && lock.covers(adapter, path))
+        {
+            Some(lock) => Err(LockError::Locked(lock)),
+            None => Ok(()),
+        }
+    }
+
+    // Locks in force on `adapter`
+    pub async fn list(&self, adapter: &str) -> Result<Vec<FileLock>, LockError> {
+        let now = now();
+        let mut locks = self.store.load().await?;
+        locks.retain(|lock| lock.adapter == adapter && !lock.expired(now));
+        Ok(locks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_locks() {
+        let locks = FileLocks::default();
+        let ttl = Duration::from_secs(60);
+        let lock = locks
+            .acquire("local", "local://docs/a.md", "alice", ttl)
+            .await
+            .unwrap();
+        assert_eq!(lock.owner, "alice");
+
+        assert!(matches!(
+            locks
+                .acquire("local", "local://docs/a.md", "bob", ttl)
+                .await,
+            Err(LockError::Locked(_))
+        ));
+        assert!(matches!(
+            locks.check("local", "local://docs", "bob").await,
+            Err(LockError::Locked(_))
+        ));
+        locks
+            .check("local", "local://docs/a.md", "alice")
+            .await
+            .unwrap();
+        locks.check("local", "local://docs2", "bob").await.unwrap();
+        assert!(matches!(
+            locks.release("local", "local://docs/a.md", "bob").await,
+            Err(LockError::Locked(_))
+        ));
+
+        locks
+            .release("local", "local://docs/a.md", "alice")
+            .await
+            .unwrap();
+        assert!(locks.list("local").await.unwrap().is_empty());
+
+        // Expired locks don't count
+        locks
+            .acquire("local", "local://b.md", "alice", Duration::ZERO)
+            .await
+            .unwrap();
+        locks.check("local", "local://b.md", "bob").await.unwrap();
+    }
+}
@@ -1,34 +1,381 @@
 use actix_cors::Cors;
+use actix_web::dev::ServerHandle;
 use actix_web::middleware::Logger;
 use actix_web::{App, HttpServer};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use env_logger::Env;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "fulltext")]
+use vuefinder::content_index::TantivyIndex;
+#[cfg(feature = "clamav")]
+use vuefinder::scan::ClamdScanner;
+#[cfg(feature = "gcs")]
+use vuefinder::storages::gcs::GcsStorage;
+#[cfg(feature = "otel")]
+use vuefinder::telemetry::{init_otlp, OtlpGuard};
 use vuefinder::{
     app_config::{VueFinderAppConfig, VueFinderAppExt},
+    content_index::{self, ContentIndex},
+    favorites::Favorites,
     finder::VueFinderConfig,
-    storages::local::LocalStorage,
+    list_store::JsonListStore,
+    recent::RecentFiles,
+    reload::{watch_config, ConfigCell},
+    scan::Scanner,
+    share::{JsonShareStore, ShareLinks},
+    storages::{
+        config::{build_storages, StorageConfig},
+        local::LocalStorage,
+        read_only::ReadOnlyStorage,
+        traced::TracedStorage,
+    },
+    StorageAdapter,
 };
 
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Server listening port
-    #[arg(short, long, default_value = "8080")]
+    #[arg(short, long, global = true, default_value = "8080")]
     port: u16,
 
     /// Server binding address
-    #[arg(short = 'b', long, default_value = "127.0.0.1")]
+    #[arg(short = 'b', long, global = true, default_value = "127.0.0.1")]
     host: String,
 
-    /// Local storage path
-    #[arg(short = 'l', long, default_value = "./storage")]
+    /// Local storage path, unused when the config file lists `storages`
+    #[arg(short = 'l', long, global = true, default_value = "./storage")]
     local_storage: String,
 
     /// Finder config file path
-    #[arg(short, long, default_value = "./vuefinder.json")]
+    #[arg(short, long, global = true, default_value = "./vuefinder.json")]
     config: String,
+
+    /// PEM certificate chain, serves HTTPS together with `--tls-key`
+    #[arg(long, global = true)]
+    tls_cert: Option<String>,
+
+    /// PEM private key of the certificate
+    #[arg(long, global = true)]
+    tls_key: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the server, the default
+    Serve,
+    /// Validate the config and reach every storage, exits non-zero on failure
+    CheckConfig,
+    /// Print the mounted storages and what they support
+    ListStorages,
+}
+
+// Buckets listed in the config file
+#[cfg(feature = "gcs")]
+fn gcs_storages(config: &VueFinderConfig) -> Vec<(String, Arc<dyn StorageAdapter>)> {
+    config
+        .gcs
+        .iter()
+        .filter_map(|gcs| match GcsStorage::new(gcs) {
+            Ok(storage) => Some((
+                gcs.name.clone(),
+                Arc::new(storage) as Arc<dyn StorageAdapter>,
+            )),
+            Err(e) => {
+                log::error!("Cannot mount GCS bucket {}: {}", gcs.bucket, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "gcs"))]
+fn gcs_storages(config: &VueFinderConfig) -> Vec<(String, Arc<dyn StorageAdapter>)> {
+    if !config.gcs.is_empty() {
+        log::warn!("Ignoring `gcs` adapters, this build doesn't have the `gcs` feature");
+    }
+    Vec::new()
+}
+
+// Span export, kept running until the returned guard is dropped
+#[cfg(feature = "otel")]
+fn init_tracing(config: &VueFinderConfig) -> Option<OtlpGuard> {
+    let endpoint = config.otlp_endpoint.as_deref()?;
+    match init_otlp(endpoint, "vuefinder") {
+        Ok(guard) => Some(guard),
+        Err(e) => {
+            log::error!("Cannot export spans to {}: {}", endpoint, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn init_tracing(config: &VueFinderConfig) -> Option<()> {
+    if config.otlp_endpoint.is_some() {
+        log::warn!("Ignoring `otlp_endpoint`, this build doesn't have the `otel` feature");
+    }
+    None
+}
+
+#[cfg(feature = "tls")]
+type TlsConfig = rustls::ServerConfig;
+#[cfg(not(feature = "tls"))]
+type TlsConfig = ();
+
+// Certificate chain and key from PEM files
+#[cfg(feature = "tls")]
+fn tls_config(cert: &str, key: &str) -> std::io::Result<TlsConfig> {
+    use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+
+    let unreadable = |path: &str, e: rustls::pki_types::pem::Error| {
+        std::io::Error::other(format!("Cannot read {}: {}", path, e))
+    };
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| unreadable(cert, e))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| unreadable(key, e))?;
+
+    rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_config(_cert: &str, _key: &str) -> std::io::Result<TlsConfig> {
+    Err(std::io::Error::other(
+        "TLS needs a build with the `tls` feature",
+    ))
+}
+
+#[cfg(feature = "clamav")]
+fn scanner(config: &VueFinderConfig) -> Option<Arc<dyn Scanner>> {
+    let address = config.clamd.as_deref()?;
+    Some(Arc::new(ClamdScanner::new(address)))
+}
+
+#[cfg(not(feature = "clamav"))]
+fn scanner(config: &VueFinderConfig) -> Option<Arc<dyn Scanner>> {
+    if config.clamd.is_some() {
+        log::warn!("Ignoring `clamd`, this build doesn't have the `clamav` feature");
+    }
+    None
+}
+
+// Index for `search-content`, and whether it needs building as it starts empty
+#[cfg(feature = "fulltext")]
+fn content_index(
+    config: &VueFinderConfig,
+) -> std::io::Result<Option<(Arc<dyn ContentIndex>, bool)>> {
+    let Some(index_config) = &config.content_index else {
+        return Ok(None);
+    };
+    let dir = index_config.path.as_deref().map(std::path::Path::new);
+    let index = TantivyIndex::open(dir).map_err(std::io::Error::other)?;
+    Ok(Some((Arc::new(index), dir.is_none())))
+}
+
+#[cfg(not(feature = "fulltext"))]
+fn content_index(
+    config: &VueFinderConfig,
+) -> std::io::Result<Option<(Arc<dyn ContentIndex>, bool)>> {
+    let Some(index_config) = &config.content_index else {
+        return Ok(None);
+    };
+    if index_config.path.is_some() {
+        log::warn!(
+            "Keeping the content index in memory, this build doesn't have the `fulltext` feature"
+        );
+    }
+    Ok(Some((
+        Arc::new(content_index::MemoryContentIndex::default()),
+        true,
+    )))
+}
+
+// Fill an index that starts empty from every adapter, in the background
+fn build_content_index(
+    index: Arc<dyn ContentIndex>,
+    storages: &HashMap<String, Arc<dyn StorageAdapter>>,
+    config: &VueFinderConfig,
+) {
+    let max_file_size = config
+        .content_index
+        .as_ref()
+        .map_or(0, |index| index.max_file_size);
+    let limit = config.walk_limit;
+    for storage in storages.values() {
+        let storage = storage.clone();
+        let index = index.clone();
+        actix_web::rt::spawn(async move {
+            let root = format!("{}://", storage.name());
+            let indexed = content_index::reindex(
+                index.as_ref(),
+                storage.as_ref(),
+                &root,
+                max_file_size,
+                limit,
+                None,
+            )
+            .await;
+            match indexed {
+                Ok(indexed) if !indexed.complete => log::warn!(
+                    "Indexed the first {} files of {}, the rest is past `walk_limit`",
+                    indexed.indexed,
+                    storage.name()
+                ),
+                Ok(indexed) => {
+                    log::info!("Indexed {} files of {}", indexed.indexed, storage.name())
+                }
+                Err(e) => log::warn!("Failed to index {}: {}", storage.name(), e),
+            }
+        });
+    }
+}
+
+// The config file, or the defaults with `VUEFINDER__*` overrides when there is none
+fn load_config(args: &Args) -> std::io::Result<(VueFinderConfig, bool)> {
+    let exists = std::path::Path::new(&args.config).exists();
+    let config = if exists {
+        VueFinderConfig::from_file(&args.config)
+    } else {
+        VueFinderConfig::from_env()
+    }
+    .map_err(|e| std::io::Error::other(format!("Invalid config {}: {}", args.config, e)))?;
+    Ok((config, exists))
+}
+
+fn check_tls(args: &Args, config: &VueFinderConfig) -> std::io::Result<Option<TlsConfig>> {
+    // Refuse to fall back to plain HTTP when TLS was asked for
+    match (
+        args.tls_cert.as_ref().or(config.tls_cert.as_ref()),
+        args.tls_key.as_ref().or(config.tls_key.as_ref()),
+    ) {
+        (Some(cert), Some(key)) => Ok(Some(tls_config(cert, key)?)),
+        (None, None) => Ok(None),
+        _ => Err(std::io::Error::other(
+            "TLS needs both a certificate and a key",
+        )),
+    }
+}
+
+// The configured storages by name, before the server's own wrappers
+async fn mount_storages(
+    args: &Args,
+    config: &VueFinderConfig,
+) -> std::io::Result<HashMap<String, Arc<dyn StorageAdapter>>> {
+    let mut storages = if config.storages.is_empty() {
+        // Ensure storage directory exists
+        tokio::fs::create_dir_all(&args.local_storage).await?;
+        (*LocalStorage::setup(&args.local_storage)).clone()
+    } else {
+        build_storages(&config.storages).map_err(std::io::Error::other)?
+    };
+    storages.extend(gcs_storages(config));
+    Ok(storages)
+}
+
+// Sorted so scripts get a stable order
+fn sorted(
+    storages: &HashMap<String, Arc<dyn StorageAdapter>>,
+) -> Vec<(&String, &Arc<dyn StorageAdapter>)> {
+    let mut storages: Vec<_> = storages.iter().collect();
+    storages.sort_by_key(|(name, _)| *name);
+    storages
+}
+
+async fn check_config(args: &Args) -> std::io::Result<()> {
+    let (config, _) = load_config(args)?;
+    check_tls(args, &config)?;
+
+    // Report every mount rather than stopping at the first one that fails
+    let mut checks = Vec::new();
+    let storages = if config.storages.is_empty() {
+        mount_storages(args, &config).await?
+    } else {
+        let mut storages = HashMap::new();
+        for (name, mount) in &config.storages {
+            match mount.build(name) {
+                Ok(storage) => {
+                    storages.insert(name.clone(), storage);
+                }
+                Err(e) => checks.push((name.clone(), Err(e.to_string()))),
+            }
+        }
+        storages.extend(gcs_storages(&config));
+        storages
+    };
+    for (name, storage) in storages {
+        let listed = storage.list_contents(&format!("{}://", name)).await;
+        checks.push((name, listed.map(|_| ()).map_err(|e| e.to_string())));
+    }
+    checks.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut failed = 0;
+    for (name, check) in &checks {
+        match check {
+            Ok(()) => println!("ok     {}", name),
+            Err(e) => {
+                println!("failed {}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{} of {} storages unreachable",
+            failed,
+            checks.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn list_storages(args: &Args) -> std::io::Result<()> {
+    let (config, _) = load_config(args)?;
+    let storages = mount_storages(args, &config).await?;
+
+    for (name, storage) in sorted(&storages) {
+        let mount = config.storages.get(name);
+        let kind = match mount.map(|mount| &mount.storage) {
+            Some(StorageConfig::Local { .. }) => "local",
+            Some(StorageConfig::Memory { .. }) => "memory",
+            Some(StorageConfig::Gcs { .. }) => "gcs",
+            Some(StorageConfig::Dropbox { .. }) => "dropbox",
+            Some(StorageConfig::Gdrive { .. }) => "gdrive",
+            Some(StorageConfig::Sqlite { .. }) => "sqlite",
+            Some(StorageConfig::Opendal { .. }) => "opendal",
+            Some(StorageConfig::Union { .. }) => "union",
+            // Buckets of the `gcs` section or the `--local-storage` folder
+            None if config.gcs.iter().any(|gcs| &gcs.name == name) => "gcs",
+            None => "local",
+        };
+        let mut capabilities = vec![if config.read_only
+            || mount.is_some_and(|mount| mount.read_only)
+        {
+            "read-only"
+        } else {
+            "read-write"
+        }];
+        if mount.is_some_and(|mount| mount.encryption.is_some()) {
+            capabilities.push("encrypted");
+        }
+        if mount.is_some_and(|mount| mount.cache.is_some()) {
+            capabilities.push("cached");
+        }
+        if matches!(storage.usage().await, Ok(Some(_))) {
+            capabilities.push("usage");
+        }
+        println!("{}://\t{}\t{}", name, kind, capabilities.join(","));
+    }
+    Ok(())
 }
 
 #[actix_web::main]
@@ -37,22 +384,83 @@ async fn main() -> std::io::Result<()> {
 
     env_logger::init_from_env(Env::default().default_filter_or("info"));
 
-    // Ensure storage directory exists
-    tokio::fs::create_dir_all(&args.local_storage).await?;
+    match args.command {
+        Some(Command::CheckConfig) => check_config(&args).await,
+        Some(Command::ListStorages) => list_storages(&args).await,
+        Some(Command::Serve) | None => serve(args).await,
+    }
+}
 
-    let config = VueFinderConfig::from_file(&args.config).unwrap_or_default();
+async fn serve(args: Args) -> std::io::Result<()> {
+    let (config, config_exists) = load_config(&args)?;
+    let tls = check_tls(&args, &config)?;
+    let _tracing = init_tracing(&config);
 
+    let mut storages = mount_storages(&args, &config).await?;
+    for storage in storages.values_mut() {
+        if config.read_only {
+            *storage = Arc::new(ReadOnlyStorage::new(storage.clone()));
+        }
+        *storage = Arc::new(TracedStorage::new(storage.clone()));
+    }
+
+    if !config.webhooks.is_empty() && !cfg!(feature = "webhooks") {
+        log::warn!("Ignoring `webhooks`, this build doesn't have the `webhooks` feature");
+    }
+
+    if config.user_roots.is_some() && config.jwt.is_none() {
+        log::warn!("`user_roots` has no effect without `jwt`, every request sees all files");
+    }
+
+    let shares = match &config.share_store {
+        Some(file) => ShareLinks::new(JsonShareStore::new(file)),
+        None => ShareLinks::default(),
+    };
+    let favorites = match &config.favorites_store {
+        Some(file) => Favorites::new(JsonListStore::new(file)),
+        None => Favorites::default(),
+    };
+    let recent = match &config.recent_store {
+        Some(file) => RecentFiles::new(JsonListStore::new(file)),
+        None => RecentFiles::default(),
+    };
+
+    let scanner = scanner(&config);
+    let content_index = content_index(&config)?;
+    if let Some((index, true)) = &content_index {
+        build_content_index(index.clone(), &storages, &config);
+    }
+    let config = Arc::new(config);
+    // Access rules, links and limits follow the file, mounts and listeners need a restart
+    let live_config = config_exists.then(|| {
+        let cell = Arc::new(ConfigCell::new(config.clone()));
+        watch_config(args.config.clone().into(), cell.clone());
+        cell
+    });
+
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
     let app_config = VueFinderAppConfig {
-        storages: LocalStorage::setup(&args.local_storage),
-        finder_config: Arc::new(config),
+        storages: Arc::new(storages),
+        shares: Arc::new(shares),
+        favorites: Arc::new(favorites),
+        recent: Arc::new(recent),
+        scanner,
+        content_index: content_index.map(|(index, _)| index),
+        live_config,
+        finder_config: config,
         ..VueFinderAppConfig::default()
     };
+    let webhooks = app_config.webhooks.clone();
+    let recent = app_config.recent.clone();
 
-    HttpServer::new(move || {
+    let address = format!("{}:{}", args.host, args.port);
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header()
+            // Read by editors for `save`'s `If-Match`
+            .expose_headers(["ETag"])
             .max_age(3600);
 
         App::new()
@@ -60,7 +468,68 @@ async fn main() -> std::io::Result<()> {
             .wrap(cors)
             .configure_vuefinder(app_config.clone())
     })
-    .bind(format!("{}:{}", args.host, args.port))?
-    .run()
-    .await
+    .shutdown_timeout(shutdown_timeout.as_secs())
+    .disable_signals();
+    #[cfg(feature = "tls")]
+    let server = match tls {
+        Some(tls) => server.bind_rustls_0_23(address, tls)?,
+        None => server.bind(address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = {
+        let _ = tls;
+        server.bind(address)?
+    };
+
+    let server = server.run();
+    let signalled = Arc::new(OnceLock::new());
+    actix_web::rt::spawn(stop_on_signal(server.handle(), signalled.clone()));
+    server.await?;
+
+    // Requests are done, give webhooks what's left of the timeout
+    let stopped = signalled.get().copied().unwrap_or_else(Instant::now);
+    let left = (stopped + shutdown_timeout).saturating_duration_since(Instant::now());
+    if !webhooks.drain(left).await {
+        log::warn!("Stopped with webhook deliveries still running");
+    }
+    if let Err(e) = recent.flush().await {
+        log::warn!("Failed to save recent files: {}", e);
+    }
+    log::logger().flush();
+    Ok(())
+}
+
+// Resolves on SIGTERM or Ctrl-C
+async fn stop_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+// Stop accepting connections on the first signal and let the requests in
+// flight finish, a second signal stops at once
+async fn stop_on_signal(server: ServerHandle, signalled: Arc<OnceLock<Instant>>) {
+    stop_signal().await;
+    let _ = signalled.set(Instant::now());
+    log::info!("Shutting down, waiting for requests in flight");
+    let graceful = actix_web::rt::spawn({
+        let server = server.clone();
+        async move { server.stop(true).await }
+    });
+    tokio::select! {
+        _ = graceful => {}
+        _ = stop_signal() => {
+            log::warn!("Stopping without waiting for requests in flight");
+            server.stop(false).await;
+        }
+    }
 }
@@ -0,0 +1,128 @@
+use serde_json::Value;
+
+use crate::finder_core::FinderResponse;
+use crate::payload::Query;
+
+// Hooks run around every command dispatched by `finder_router`.
+//
+// Middlewares are registered as an ordered chain in `VueFinderAppConfig`:
+// `before` runs in registration order and `after` runs in reverse, so the
+// first middleware wraps all the others. Returning a response from `before`
+// short-circuits the chain and the command; that response is sent as-is
+// without running any `after` hook.
+pub trait FinderMiddleware: Send + Sync {
+    // Inspect or rewrite the request. `payload` is the JSON body for JSON
+    // commands and `None` for GET and upload requests.
+    fn before(
+        &self,
+        _command: &str,
+        _query: &mut Query,
+        _payload: Option<&mut Value>,
+    ) -> Option<FinderResponse> {
+        None
+    }
+
+    // Inspect or replace the command's response.
+    fn after(&self, _command: &str, response: FinderResponse) -> FinderResponse {
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_config::{VueFinderAppConfig, VueFinderAppExt};
+    use crate::storages::local::LocalStorage;
+    use actix_web::{test, App};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    struct DenyDelete;
+
+    impl FinderMiddleware for DenyDelete {
+        fn before(
+            &self,
+            command: &str,
+            _query: &mut Query,
+            _payload: Option<&mut Value>,
+        ) -> Option<FinderResponse> {
+            if command == "delete" {
+                return Some(FinderResponse::Forbidden().json(json!({
+                    "status": false,
+                    "message": "Deleting is disabled."
+                })));
+            }
+            None
+        }
+    }
+
+    struct Record(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl FinderMiddleware for Record {
+        fn before(
+            &self,
+            command: &str,
+            _query: &mut Query,
+            _payload: Option<&mut Value>,
+        ) -> Option<FinderResponse> {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("before {} {}", self.0, command));
+            None
+        }
+
+        fn after(&self, command: &str, response: FinderResponse) -> FinderResponse {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("after {} {}", self.0, command));
+            response
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("keep.txt"), "keep").unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let config = VueFinderAppConfig {
+            storages: LocalStorage::setup(temp_dir.path().to_str().unwrap()),
+            middlewares: Arc::new(vec![
+                Arc::new(Record("outer", log.clone())) as Arc<dyn FinderMiddleware>,
+                Arc::new(DenyDelete),
+                Arc::new(Record("inner", log.clone())),
+            ]),
+            ..VueFinderAppConfig::default()
+        };
+        let app = test::init_service(App::new().configure_vuefinder(config)).await;
+
+        // Rejected in `before`: the command never runs
+        let req = test::TestRequest::post()
+            .uri("/api?q=delete&adapter=local&path=local://")
+            .set_json(json!({"items": [{"path": "local://keep.txt", "type": "file"}]}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+        assert!(temp_dir.path().join("keep.txt").exists());
+        assert_eq!(*log.lock().unwrap(), vec!["before outer delete"]);
+
+        log.lock().unwrap().clear();
+        let req = test::TestRequest::get()
+            .uri("/api?q=index&adapter=local&path=local://")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "before outer index",
+                "before inner index",
+                "after inner index",
+                "after outer index",
+            ]
+        );
+    }
+}
@@ -1,11 +1,60 @@
 use serde::Deserialize;
 
+use crate::archive::ArchiveFormat;
+use crate::search::{EntryType, SearchMode};
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::collision::CollisionPolicy;
+
 #[derive(Deserialize)]
 pub struct Query {
+    #[serde(default)]
     pub q: String,
     pub adapter: Option<String>,
     pub path: Option<String>,
     pub filter: Option<String>,
+    // How `search` matches `filter`, and whether case matters (it doesn't by default)
+    pub mode: Option<SearchMode>,
+    pub case_sensitive: Option<bool>,
+    // Unix seconds bounds on `last_modified`
+    pub modified_after: Option<u64>,
+    pub modified_before: Option<u64>,
+    // Bounds on the size of files found by `search`, in bytes
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    // Kind of entries `search` finds, files of any kind when unset
+    #[serde(rename = "type")]
+    pub entry_type: Option<EntryType>,
+    // `asc` or `desc` for `sort`; `manual` sorts by the directory's `.order.json` sidecar
+    pub order: Option<String>,
+    // `name`, `size` or `mtime`
+    pub sort: Option<String>,
+    // List folders before files
+    pub dirs_first: Option<bool>,
+    // 1-based page of the listing and its size, the whole listing when both are unset
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    // Output format for rendered responses such as `qr` (`png` or `svg`)
+    // and `thumbnail` (`jpeg`, `webp` or `png`)
+    pub format: Option<String>,
+    // `type` splits listings into separate `dirs` and `files` arrays
+    pub group_by: Option<String>,
+    // How to handle an existing target, each command has its own default
+    #[serde(alias = "on_conflict")]
+    pub conflict: Option<CollisionPolicy>,
+    // Resumable upload session and the byte offset of the chunk being sent,
+    // the share link of `share/download` or the version of `versions/diff`
+    pub id: Option<String>,
+    pub offset: Option<u64>,
+    // JSON array of paths for commands acting on a selection, such as `download-archive`
+    pub items: Option<String>,
+    // Password of a protected share link, only taken from the
+    // `X-Share-Password` header
+    #[serde(skip)]
+    pub password: Option<String>,
+    // `true` lists hidden files, `false` also hides dotfiles, see `VueFinderConfig::hide_dotfiles`
+    pub show_hidden: Option<bool>,
+    // Digest computed by `hash`, `sha256` when unset
+    pub algorithm: Option<ChecksumAlgorithm>,
 }
 
 #[derive(Deserialize)]
@@ -16,6 +65,10 @@ pub struct NewFolderRequest {
 #[derive(Deserialize)]
 pub struct NewFileRequest {
     pub name: String,
+    // Optional initial content, written on creation
+    pub content: Option<String>,
+    #[serde(default)]
+    pub base64: bool,
 }
 
 #[derive(Deserialize)]
@@ -24,10 +77,80 @@ pub struct RenameRequest {
     pub item: String,
 }
 
+// Body of `move` and `copy`, which put `items` into the folder `item`
 #[derive(Deserialize)]
-pub struct MoveRequest {
+pub struct TransferRequest {
     pub item: String,
     pub items: Vec<FileItem>,
+    // Adapters of `items` and of the destination `item`, both the query's
+    // `adapter` when unset. Between two adapters the command runs as a job.
+    pub from_adapter: Option<String>,
+    pub to_adapter: Option<String>,
+    // How to handle items that already exist in the destination, `fail`
+    // unless set here or in the query
+    #[serde(default, alias = "on_conflict")]
+    pub conflict: Option<CollisionPolicy>,
+}
+
+pub type MoveRequest = TransferRequest;
+pub type CopyRequest = TransferRequest;
+
+#[derive(Deserialize)]
+pub struct UploadInitRequest {
+    pub name: String,
+    // Total size in bytes, checked on every chunk and on finalize when given
+    pub size: Option<u64>,
+}
+
+// Body of `fetch-url`, the file goes into the `path` folder
+#[derive(Deserialize)]
+pub struct FetchUrlRequest {
+    pub url: String,
+    // Taken from the response or the URL when unset
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UploadSessionRequest {
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareCreateRequest {
+    // File or folder to share
+    pub path: String,
+    pub password: Option<String>,
+    pub expires_in_secs: Option<u64>,
+    pub max_downloads: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ShareRevokeRequest {
+    pub id: String,
+}
+
+// Body of `favorites/add` and `favorites/remove`
+#[derive(Deserialize)]
+pub struct FavoriteRequest {
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+pub struct LockRequest {
+    pub item: String,
+    // Shorter than `VueFinderConfig::lock_ttl_secs`, which is also the longest
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct UnlockRequest {
+    pub item: String,
+}
+
+#[derive(Deserialize)]
+pub struct VersionRestoreRequest {
+    // Id from `versions/list`
+    pub id: String,
 }
 
 #[derive(Deserialize)]
@@ -35,23 +158,151 @@ pub struct DeleteRequest {
     pub items: Vec<FileItem>,
 }
 
+// Body of `bulk-rename`, see `bulk_rename::Renamer` for `replace`
+#[derive(Deserialize)]
+pub struct BulkRenameRequest {
+    pub items: Vec<FileItem>,
+    // Text replaced in each name, the whole name when empty
+    #[serde(default)]
+    pub find: String,
+    pub replace: String,
+    // `find` is a regular expression
+    #[serde(default)]
+    pub regex: bool,
+    // First value of `{n}`, 1 when unset
+    pub start: Option<u64>,
+    // Only answer with the new names
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TrashRequest {
+    // Ids from `trash/list`, `trash/empty` removes everything when empty
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CopyBetweenAdaptersRequest {
+    pub from_adapter: String,
+    pub to_adapter: String,
+    pub items: Vec<FileItem>,
+    // Destination directory on `to_adapter`
+    pub target: String,
+}
+
 #[derive(Deserialize)]
 pub struct ArchiveRequest {
     pub name: String,
     pub items: Vec<FileItem>,
+    // `zip`, `tar` or `tar.gz`, also the extension added to `name`
+    #[serde(default)]
+    pub format: ArchiveFormat,
+}
+
+#[derive(Deserialize)]
+pub struct ReadEntryRequest {
+    pub archive: String,
+    // Path of the entry inside the archive
+    pub entry: String,
 }
 
 #[derive(Deserialize)]
 pub struct UnarchiveRequest {
     pub item: String,
+    // How to handle files that already exist in the extraction folder,
+    // `overwrite` unless set here or in the query
+    #[serde(default, alias = "on_conflict")]
+    pub conflict: Option<CollisionPolicy>,
 }
 
 #[derive(Deserialize)]
 pub struct SaveRequest {
     pub content: String,
+    // ETag the file must still have, from `preview`. Taken from the `If-Match`
+    // header when set there.
+    #[serde(default)]
+    pub if_match: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AppendRequest {
+    pub content: String,
+    #[serde(default)]
+    pub base64: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CasSaveRequest {
+    pub content: String,
+    // SHA-256 of the contents the client last saw, empty when the file should not exist yet
+    pub expected_hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReorderDirection {
+    Up,
+    Down,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderRequest {
+    pub item: String,
+    pub direction: ReorderDirection,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffCompare {
+    // Size and modification time
+    #[default]
+    Meta,
+    // Size, then SHA-256 of the contents
+    Hash,
 }
 
+// Body of `diff`, comparing `left` with `right`. Either side can be one of
+// its file's versions, by id from `versions/list`, instead of the current contents.
 #[derive(Deserialize)]
+pub struct DiffRequest {
+    pub left: String,
+    #[serde(default)]
+    pub left_version: Option<String>,
+    pub right: String,
+    #[serde(default)]
+    pub right_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DiffDirsRequest {
+    pub left: String,
+    pub right: String,
+    #[serde(default)]
+    pub compare: DiffCompare,
+}
+
+// Body of `sync`, mirroring the folder `source` into `target`
+#[derive(Deserialize)]
+pub struct SyncRequest {
+    pub source: String,
+    pub target: String,
+    // Adapters of `source` and `target`, both the query's `adapter` when unset
+    pub from_adapter: Option<String>,
+    pub to_adapter: Option<String>,
+    // How to tell files that are already up to date
+    #[serde(default)]
+    pub compare: DiffCompare,
+    // Also remove what `source` doesn't have from `target`
+    #[serde(default)]
+    pub delete: bool,
+    // Only answer with the changes
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct FileItem {
     pub path: String,
 }
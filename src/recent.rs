@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+use crate::list_store::{ListStore, ListStoreError, StoredList};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecentAction {
+    // Downloaded or previewed
+    Opened,
+    // Written by any command
+    Modified,
+}
+
+// A path someone recently opened or changed
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecentEntry {
+    // Whose entry it is, `None` when there's no authentication
+    pub owner: Option<String>,
+    pub adapter: String,
+    pub path: String,
+    // What happened last
+    pub action: RecentAction,
+    // Unix seconds
+    pub at: u64,
+}
+
+// Opening files mustn't write the store each time, changes are saved together
+// at most this often
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Recently opened and modified paths of every user, most recent first
+#[derive(Default)]
+pub struct RecentFiles {
+    list: StoredList<RecentEntry>,
+}
+
+impl RecentFiles {
+    pub fn new(store: impl ListStore<RecentEntry> + 'static) -> Self {
+        Self {
+            list: StoredList::new(store),
+        }
+    }
+
+    // Move `path` to the front of `owner`'s entries, dropping their oldest
+    // beyond `limit`
+    pub async fn record(
+        &self,
+        owner: Option<&str>,
+        adapter: &str,
+        path: &str,
+        action: RecentAction,
+        limit: usize,
+    ) -> Result<(), ListStoreError> {
+        self.list
+            .update_later(
+                |entries| {
+                    entries.retain(|entry| {
+                        entry.owner.as_deref() != owner
+                            || entry.adapter != adapter
+                            || entry.path != path
+                    });
+                    entries.insert(
+                        0,
+                        RecentEntry {
+                            owner: owner.map(str::to_string),
+                            adapter: adapter.to_string(),
+                            path: path.to_string(),
+                            action,
+                            at: now(),
+                        },
+                    );
+                    let mut kept = 0;
+                    entries.retain(|entry| {
+                        if entry.owner.as_deref() != owner {
+                            return true;
+                        }
+                        kept += 1;
+                        kept <= limit
+                    });
+                    true
+                },
+                SAVE_DELAY,
+            )
+            .await
+    }
+
+    // Drop the entries of every user at or below a deleted `path`
+    pub async fn forget(&self, adapter: &str, path: &str) -> Result<(), ListStoreError> {
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        self.list
+            .update_later(
+                |entries| {
+                    let count = entries.len();
+                    entries.retain(|entry| {
+                        entry.adapter != adapter
+                            || (entry.path != path && !entry.path.starts_with(&dir))
+                    });
+                    entries.len() != count
+                },
+                SAVE_DELAY,
+            )
+            .await
+    }
+
+    // Save the changes that are still waiting, before shutting down
+    pub async fn flush(&self) -> Result<(), ListStoreError> {
+        self.list.flush().await
+    }
+
+    // Entries of `owner` on `adapter`, or on every adapter when `None`, most
+    // recent first
+    pub async fn list(
+        &self,
+        owner: Option<&str>,
+        adapter: Option<&str>,
+    ) -> Result<Vec<RecentEntry>, ListStoreError> {
+        let mut entries = self.list.items().await?;
+        entries.retain(|entry| {
+            entry.owner.as_deref() == owner
+                && adapter.is_none_or(|adapter| entry.adapter == adapter)
+        });
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_store::JsonListStore;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_recent_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("recent.json");
+        let recent = RecentFiles::new(JsonListStore::new(&file));
+        for path in ["local://a", "local://b", "local://docs/c", "local://d"] {
+            recent
+                .record(Some("ann"), "local", path, RecentAction::Opened, 3)
+                .await
+                .unwrap();
+        }
+        recent
+            .record(Some("ann"), "local", "local://b", RecentAction::Modified, 3)
+            .await
+            .unwrap();
+        recent
+            .record(Some("bob"), "local", "local://a", RecentAction::Opened, 3)
+            .await
+            .unwrap();
+
+        // Persisted across instances, bounded per user and most recent first
+        recent.flush().await.unwrap();
+        let recent = RecentFiles::new(JsonListStore::new(&file));
+        let paths = |entries: Vec<RecentEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.path).collect()
+        };
+        let ann = recent.list(Some("ann"), None).await.unwrap();
+        assert_eq!(ann[0].action, RecentAction::Modified);
+        assert_eq!(paths(ann), ["local://b", "local://d", "local://docs/c"]);
+        assert_eq!(
+            paths(recent.list(Some("bob"), Some("local")).await.unwrap()),
+            ["local://a"]
+        );
+        assert!(recent
+            .list(Some("bob"), Some("media"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        recent.forget("local", "local://docs").await.unwrap();
+        recent.forget("local", "local://a").await.unwrap();
+        assert_eq!(
+            paths(recent.list(Some("ann"), None).await.unwrap()),
+            ["local://b", "local://d"]
+        );
+        assert!(recent.list(Some("bob"), None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use notify::{RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::finder::VueFinderConfig;
+
+// The config requests start with, replaced as a whole when it's reloaded so a
+// request never sees half of an update
+pub struct ConfigCell(RwLock<Arc<VueFinderConfig>>);
+
+impl ConfigCell {
+    pub fn new(config: Arc<VueFinderConfig>) -> Self {
+        Self(RwLock::new(config))
+    }
+
+    pub fn get(&self) -> Arc<VueFinderConfig> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set(&self, config: VueFinderConfig) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+}
+
+// Load `path` into `cell`, keeping the current config when it's invalid
+pub fn reload(cell: &ConfigCell, path: &Path) {
+    match VueFinderConfig::from_file(&path.to_string_lossy()) {
+        Ok(config) => {
+            cell.set(config);
+            log::info!("Reloaded {}", path.display());
+        }
+        Err(e) => log::error!(
+            "Keeping the current config, {} is invalid: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+// Reload `path` into `cell` whenever the file changes and, on Unix, on
+// `SIGHUP`. Runs until the returned task is aborted.
+pub fn watch_config(path: PathBuf, cell: Arc<ConfigCell>) -> tokio::task::JoinHandle<()> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    // Editors often replace the file instead of writing it, so its folder is watched
+    let name = path.file_name().map(|name| name.to_os_string());
+    let changes = sender.clone();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let ours = event.paths.iter().any(|p| p.file_name() == name.as_deref());
+            if ours && !event.kind.is_access() {
+                let _ = changes.send(());
+            }
+        }
+    })
+    .and_then(|mut watcher| {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let watcher = match watcher {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("Not watching {}: {}", path.display(), e);
+            None
+        }
+    };
+
+    #[cfg(unix)]
+    if let Ok(mut hangups) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+    {
+        tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                if sender.send(()).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        while receiver.recv().await.is_some() {
+            // Let the writer finish and merge the events of one save
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            while receiver.try_recv().is_ok() {}
+            reload(&cell, &path);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_watch_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("vuefinder.json");
+        std::fs::write(&path, r#"{ "thumbnail_max_width": 100 }"#).unwrap();
+        let cell = Arc::new(ConfigCell::new(Arc::new(
+            VueFinderConfig::from_file(&path.to_string_lossy()).unwrap(),
+        )));
+        let task = watch_config(path.clone(), cell.clone());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        std::fs::write(&path, r#"{ "thumbnail_max_width": 200 }"#).unwrap();
+        let mut reloaded = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if cell.get().thumbnail_max_width == 200 {
+                reloaded = true;
+                break;
+            }
+        }
+        assert!(reloaded);
+
+        // A broken file leaves the config as it was
+        std::fs::write(&path, "{ broken").unwrap();
+        reload(&cell, &path);
+        assert_eq!(cell.get().thumbnail_max_width, 200);
+        task.abort();
+    }
+}
@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+
+use crate::content_index::ContentMatch;
+use crate::locks::FileLock;
+use crate::recent::RecentAction;
+use crate::share::ShareLink;
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::{StorageCapabilities, StorageItem, StorageUsage};
+use crate::trash::TrashEntry;
+use crate::versions::FileVersion;
+
+// Response bodies of the finder commands. Library users can deserialize them
+// to check responses with types.
+
+// Body of every failed command
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    // Always `false`
+    pub status: bool,
+    pub message: String,
+    // Where a resumable upload should continue, on a chunk at the wrong offset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    // Archive entries the target adapter can't store
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid: Vec<InvalidEntry>,
+}
+
+impl ErrorResponse {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            status: false,
+            message: message.into(),
+            offset: None,
+            invalid: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InvalidEntry {
+    pub name: String,
+    pub message: String,
+}
+
+// Outcome of commands that don't return a listing
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StatusResponse {
+    pub status: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileNode {
+    #[serde(flatten)]
+    pub storage_item: StorageItem,
+    pub url: Option<String>,
+    // search result supported
+    pub dir: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Pagination {
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+    pub total_pages: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IndexResponse {
+    pub adapter: String,
+    pub storages: Vec<String>,
+    pub dirname: String,
+    // Only files when `group_by=type` moved the folders to `dirs`
+    pub files: Vec<FileNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirs: Option<Vec<FileNode>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+    // The created item, for `newfile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<FileNode>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchResponse {
+    pub adapter: String,
+    pub storages: Vec<String>,
+    pub dirname: Option<String>,
+    pub files: Vec<FileNode>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContentSearchResponse {
+    pub adapter: String,
+    pub matches: Vec<ContentMatch>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContentReindexResponse {
+    // Text files now in the index
+    pub indexed: usize,
+    // `false` when the walk stopped at `walk_limit`
+    pub complete: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Folder {
+    pub adapter: String,
+    pub path: String,
+    pub basename: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubfoldersResponse {
+    pub folders: Vec<Folder>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub git_hash: Option<String>,
+    pub adapters: Vec<String>,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChecksumResponse {
+    pub algorithm: ChecksumAlgorithm,
+    // Lowercase hex
+    pub hash: String,
+}
+
+// Totals below a folder, see `folder_size`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FolderSizeResponse {
+    // Bytes of all files
+    pub size: u64,
+    pub files: u64,
+    pub dirs: u64,
+    // `false` when the walk stopped at `walk_limit` entries
+    pub complete: bool,
+}
+
+// Files with the same contents, see `find_duplicates`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DuplicateSet {
+    // Of each file
+    pub size: u64,
+    // SHA-256, in hex
+    pub hash: String,
+    pub paths: Vec<String>,
+}
+
+// Result of a `duplicates` job
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DuplicatesResponse {
+    pub sets: Vec<DuplicateSet>,
+    // `false` when the walk stopped at `walk_limit` entries
+    pub complete: bool,
+}
+
+// A background job was started, poll `job-status` with its id
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JobResponse {
+    pub id: String,
+}
+
+// One adapter of the `storages` command
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StorageInfo {
+    pub name: String,
+    pub read_only: bool,
+    pub capabilities: StorageCapabilities,
+    // `None` when the backend doesn't report it or can't be reached
+    pub usage: Option<StorageUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StoragesResponse {
+    pub storages: Vec<StorageInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MimeResponse {
+    pub mime: String,
+    pub charset: Option<String>,
+    // Detected from the contents because the extension was unknown
+    pub sniffed: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArchiveListingResponse {
+    pub entries: Vec<ArchiveEntry>,
+}
+
+// Outcome of one item of `delete`, `move` or `copy`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchResult {
+    pub path: String,
+    pub status: bool,
+    // Where the item was moved or copied to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    // The target existed and the conflict policy left it alone
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// Result of a `move` or `copy` job between adapters and of `copy-between-adapters`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchReport {
+    // Whether every item was done
+    pub status: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchResult>,
+}
+
+// Body of `delete`, `move` and `copy` when some items failed, see `Batch`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchResponse {
+    // Always `false`
+    pub status: bool,
+    // Why the first item failed
+    pub message: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrashListResponse {
+    pub adapter: String,
+    pub items: Vec<TrashEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VersionsListResponse {
+    pub path: String,
+    pub versions: Vec<FileVersion>,
+}
+
+// A share link without its password hash
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ShareLinkResponse {
+    pub id: String,
+    pub adapter: String,
+    pub path: String,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub protected: bool,
+    pub max_downloads: Option<u64>,
+    pub downloads: u64,
+}
+
+impl From<ShareLink> for ShareLinkResponse {
+    fn from(link: ShareLink) -> Self {
+        Self {
+            protected: link.password_hash.is_some(),
+            id: link.id,
+            adapter: link.adapter,
+            path: link.path,
+            created_at: link.created_at,
+            expires_at: link.expires_at,
+            max_downloads: link.max_downloads,
+            downloads: link.downloads,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ShareListResponse {
+    pub links: Vec<ShareLinkResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FavoriteResponse {
+    pub adapter: String,
+    pub path: String,
+    pub basename: String,
+    // `dir` or `file`, `None` once it no longer exists
+    #[serde(rename = "type")]
+    pub node_type: Option<String>,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FavoritesResponse {
+    pub favorites: Vec<FavoriteResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecentItem {
+    #[serde(flatten)]
+    pub item: StorageItem,
+    pub adapter: String,
+    pub action: RecentAction,
+    // Unix seconds
+    pub at: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecentResponse {
+    pub recent: Vec<RecentItem>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LocksResponse {
+    pub locks: Vec<FileLock>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkRenameResult {
+    pub path: String,
+    pub new_path: String,
+    // Why the item can't be renamed so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// Answer of a `bulk-rename` dry run, or of one refused because of `message`s
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BulkRenameResponse {
+    pub status: bool,
+    pub results: Vec<BulkRenameResult>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RestoreResult {
+    pub id: String,
+    pub status: bool,
+    // Where the item was restored to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrashRestoreResponse {
+    // Whether every item was restored
+    pub status: bool,
+    pub restored: usize,
+    pub total: usize,
+    pub results: Vec<RestoreResult>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadSessionResponse {
+    pub status: bool,
+    pub id: String,
+    // Bytes received so far, where the next chunk starts
+    pub offset: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CasSaveResponse {
+    // `false` when the file changed since the client read it
+    pub status: bool,
+    // Hash of the new contents when saved
+    pub hash: Option<String>,
+}
+
+// Result of a `sync` job, paths relative to the synced folders. A dry run
+// lists what would be done.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SyncResponse {
+    // `false` when some entries failed
+    pub status: bool,
+    pub copied: Vec<String>,
+    // Folders created
+    pub created: Vec<String>,
+    pub deleted: Vec<String>,
+    // Files that were already up to date
+    pub unchanged: usize,
+    pub failed: Vec<SyncFailure>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SyncFailure {
+    pub path: String,
+    pub message: String,
+}
+
+// Paths relative to the compared folders
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DiffDirsResponse {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
@@ -1,93 +1,107 @@
-use actix_multipart::Multipart;
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::{HeaderName, CONTENT_TYPE, IF_MATCH, RANGE};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
 
-use crate::payload::{
-    ArchiveRequest, DeleteRequest, MoveRequest, NewFileRequest, NewFolderRequest, Query,
-    RenameRequest, SaveRequest, UnarchiveRequest,
+use crate::api_key::ApiKey;
+use crate::auth::{self, Claims};
+use crate::finder::VueFinder;
+use crate::finder_core::{
+    self, FinderBody, FinderMethod, FinderPayload, FinderRequest, FinderResponse,
 };
+use crate::payload::Query;
+use crate::share::SHARE_PASSWORD_HEADER;
 
-use crate::finder::VueFinder;
+impl From<FinderResponse> for HttpResponse {
+    fn from(response: FinderResponse) -> Self {
+        let (status, headers, body) = response.into_parts();
+        let status =
+            StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = HttpResponse::build(status);
+        let mut length = None;
+        for (name, value) in headers.iter() {
+            // actix-web sets the length from the body
+            if name == http::header::CONTENT_LENGTH {
+                length = value.to_str().ok().and_then(|value| value.parse().ok());
+                continue;
+            }
+            response.append_header((name.as_str(), value.as_bytes()));
+        }
+        match body {
+            FinderBody::Empty => response.finish(),
+            FinderBody::Bytes(bytes) => response.body(bytes),
+            FinderBody::Stream(stream) => {
+                if let Some(length) = length {
+                    response.no_chunking(length);
+                }
+                response.streaming(stream)
+            }
+        }
+    }
+}
+
+// The request body can't leave the worker it arrived on, so it is read there
+// and handed to the command through a channel
+fn forward_payload(mut payload: web::Payload) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(8);
+    actix_web::rt::spawn(async move {
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(|e| std::io::Error::other(e.to_string()));
+            // Stops once the command is done with the body
+            if sender.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+    futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
 
 pub async fn finder_router(
     req: HttpRequest,
     data: web::Data<VueFinder>,
     query: web::Query<Query>,
-    payload: Option<web::Either<web::Json<serde_json::Value>, Multipart>>,
+    payload: Option<web::Either<web::Json<serde_json::Value>, web::Payload>>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    match *req.method() {
-        actix_web::http::Method::GET => match query.q.as_str() {
-            "index" => Ok(VueFinder::index(data, query).await),
-            "subfolders" => Ok(VueFinder::sub_folders(data, query).await),
-            "download" => Ok(VueFinder::download(data, query).await),
-            "preview" => Ok(VueFinder::preview(data, query).await),
-            "search" => Ok(VueFinder::search(data, query).await),
-            _ => Ok(HttpResponse::BadRequest().finish()),
+    let method = match *req.method() {
+        Method::GET => FinderMethod::Get,
+        Method::POST => FinderMethod::Post,
+        _ => FinderMethod::Other,
+    };
+    let header = |name: HeaderName| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let payload = match payload {
+        Some(web::Either::Left(json)) => FinderPayload::Json(json.into_inner()),
+        Some(web::Either::Right(body)) => match header(CONTENT_TYPE) {
+            Some(content_type) if content_type.starts_with("multipart/form-data") => {
+                FinderPayload::multipart(&content_type, forward_payload(body))
+            }
+            _ => FinderPayload::None,
         },
-        actix_web::http::Method::POST => {
-            let payload = payload
-                .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing request payload"))?;
+        None => FinderPayload::None,
+    };
 
-            match query.q.as_str() {
-                "upload" => match payload {
-                    web::Either::Right(multipart) => {
-                        Ok(VueFinder::upload(data, query, multipart).await)
-                    }
-                    _ => Err(actix_web::error::ErrorBadRequest(
-                        "Upload requests should use multipart/form-data",
-                    )),
-                },
-                cmd @ ("newfolder" | "newfile" | "rename" | "move" | "delete" | "save"
-                | "archive" | "unarchive") => match payload {
-                    web::Either::Left(json) => match cmd {
-                        "newfolder" => {
-                            let payload: NewFolderRequest =
-                                serde_json::from_value(json.into_inner())
-                                    .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::new_folder(data, query, web::Json(payload)).await)
-                        }
-                        "newfile" => {
-                            let payload: NewFileRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::new_file(data, query, web::Json(payload)).await)
-                        }
-                        "rename" => {
-                            let payload: RenameRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::rename(data, query, web::Json(payload)).await)
-                        }
-                        "move" => {
-                            let payload: MoveRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::r#move(data, query, web::Json(payload)).await)
-                        }
-                        "delete" => {
-                            let payload: DeleteRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::delete(data, query, web::Json(payload)).await)
-                        }
-                        "save" => {
-                            let payload: SaveRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::save(data, query, web::Json(payload)).await)
-                        }
-                        "archive" => {
-                            let payload: ArchiveRequest = serde_json::from_value(json.into_inner())
-                                .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::archive(data, query, web::Json(payload)).await)
-                        }
-                        "unarchive" => {
-                            let payload: UnarchiveRequest =
-                                serde_json::from_value(json.into_inner())
-                                    .map_err(actix_web::error::ErrorBadRequest)?;
-                            Ok(VueFinder::unarchive(data, query, web::Json(payload)).await)
-                        }
-                        _ => unreachable!(),
-                    },
-                    _ => Err(actix_web::error::ErrorBadRequest("Expected JSON payload")),
-                },
-                _ => Ok(HttpResponse::BadRequest().finish()),
-            }
-        }
-        _ => Ok(HttpResponse::MethodNotAllowed().finish()),
-    }
+    let mut query = query.into_inner();
+    query.password = header(HeaderName::from_static(SHARE_PASSWORD_HEADER));
+
+    let request = FinderRequest {
+        method,
+        query,
+        payload,
+        range: header(RANGE),
+        if_match: header(IF_MATCH),
+        client: req.connection_info().peer_addr().map(str::to_string),
+        principal: auth::principal(
+            req.extensions().get::<Claims>(),
+            req.extensions().get::<ApiKey>(),
+        ),
+        claims: req.extensions().get::<Claims>().cloned(),
+    };
+    Ok(finder_core::handle(data.into_inner(), request).await.into())
 }
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::storages::validate_windows_name;
+
+// Extra checks on the names users give to new files and folders. Whatever the
+// policy, names that would leave the current folder (`/`, `.`, `..`) or
+// contain control characters are always refused.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NameRule {
+    // Also refuse backslashes and leading or trailing whitespace
+    Strict,
+    // Also refuse names Windows can't store: reserved device names, trailing
+    // dots and spaces and the characters `<>:"|?*`
+    WindowsSafe,
+    // Store names composed to Unicode NFC, so `é` typed on macOS and Windows is the same file
+    UnicodeNormalize,
+}
+
+// The name to store for the user-supplied `name`, `Err` explains the refusal
+pub fn sanitize(rules: &[NameRule], name: &str) -> Result<String, String> {
+    let name = if rules.contains(&NameRule::UnicodeNormalize) {
+        name.nfc().collect::<String>()
+    } else {
+        name.to_string()
+    };
+
+    if name.is_empty() || name == "." || name == ".." {
+        return Err("Name must not be empty, \".\" or \"..\".".to_string());
+    }
+    if name.contains('/') {
+        return Err("Name must not contain \"/\".".to_string());
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Name must not contain control characters.".to_string());
+    }
+
+    if rules.contains(&NameRule::Strict) {
+        if name.contains('\\') {
+            return Err("Name must not contain \"\\\".".to_string());
+        }
+        if name.trim() != name {
+            return Err("Name must not start or end with whitespace.".to_string());
+        }
+    }
+    if rules.contains(&NameRule::WindowsSafe) {
+        if let Some(c) = name.chars().find(|c| "<>:\"|?*\\".contains(*c)) {
+            return Err(format!("Name must not contain \"{}\".", c));
+        }
+        validate_windows_name(&name)?;
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        for name in ["", ".", "..", "../etc", "a/b", "a\nb", "a\u{85}b"] {
+            assert!(sanitize(&[], name).is_err(), "{:?}", name);
+        }
+        assert_eq!(sanitize(&[], " notes. ").unwrap(), " notes. ");
+
+        let strict = [NameRule::Strict];
+        assert!(sanitize(&strict, " notes").is_err());
+        assert!(sanitize(&strict, "a\\b").is_err());
+        assert_eq!(sanitize(&strict, "notes.txt").unwrap(), "notes.txt");
+
+        let windows = [NameRule::WindowsSafe];
+        assert!(sanitize(&windows, "notes.").is_err());
+        assert!(sanitize(&windows, "CON.txt").is_err());
+        assert!(sanitize(&windows, "a:b").is_err());
+
+        let normalize = [NameRule::UnicodeNormalize];
+        assert_eq!(sanitize(&normalize, "cafe\u{301}").unwrap(), "caf\u{e9}");
+    }
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+
+use crate::storages::StorageError;
+
+// Outcome of scanning a file
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScanVerdict {
+    Clean,
+    // Name of the signature that matched
+    Infected(String),
+}
+
+// Checks uploaded and saved contents for malware before they're kept
+#[async_trait]
+pub trait Scanner: Send + Sync {
+    async fn scan(
+        &self,
+        contents: BoxStream<'static, Result<Bytes, StorageError>>,
+    ) -> Result<ScanVerdict, String>;
+}
+
+// Scans with a clamd daemon through its `INSTREAM` command. `address` is
+// `host:port` or, on Unix, the path of its socket.
+#[cfg(feature = "clamav")]
+pub struct ClamdScanner {
+    address: String,
+}
+
+#[cfg(feature = "clamav")]
+impl ClamdScanner {
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+        }
+    }
+
+    async fn instream<S>(
+        mut socket: S,
+        mut contents: BoxStream<'static, Result<Bytes, StorageError>>,
+    ) -> Result<ScanVerdict, String>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        use futures_util::StreamExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let io = |e: std::io::Error| format!("clamd: {}", e);
+        socket.write_all(b"zINSTREAM\0").await.map_err(io)?;
+        while let Some(chunk) = contents.next().await {
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            // clamd refuses chunks over its `StreamMaxLength` anyway
+            for part in chunk.chunks(u32::MAX as usize) {
+                socket
+                    .write_all(&(part.len() as u32).to_be_bytes())
+                    .await
+                    .map_err(io)?;
+                socket.write_all(part).await.map_err(io)?;
+            }
+        }
+        socket.write_all(&0u32.to_be_bytes()).await.map_err(io)?;
+
+        let mut reply = Vec::new();
+        socket.read_to_end(&mut reply).await.map_err(io)?;
+        let reply = String::from_utf8_lossy(&reply);
+        let reply = reply.trim_end_matches(['\0', '\n']);
+        // `stream: OK`, `stream: <signature> FOUND` or `<reason> ERROR`
+        let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+        if result == "OK" {
+            Ok(ScanVerdict::Clean)
+        } else if let Some(signature) = result.strip_suffix(" FOUND") {
+            Ok(ScanVerdict::Infected(signature.to_string()))
+        } else {
+            Err(format!("clamd: {}", reply))
+        }
+    }
+}
+
+#[cfg(feature = "clamav")]
+#[async_trait]
+impl Scanner for ClamdScanner {
+    async fn scan(
+        &self,
+        contents: BoxStream<'static, Result<Bytes, StorageError>>,
+    ) -> Result<ScanVerdict, String> {
+        #[cfg(unix)]
+        if self.address.starts_with('/') {
+            let socket = tokio::net::UnixStream::connect(&self.address)
+                .await
+                .map_err(|e| format!("clamd: {}", e))?;
+            return Self::instream(socket, contents).await;
+        }
+        let socket = tokio::net::TcpStream::connect(&self.address)
+            .await
+            .map_err(|e| format!("clamd: {}", e))?;
+        Self::instream(socket, contents).await
+    }
+}
+
+#[cfg(all(test, feature = "clamav"))]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Answers like clamd, flagging streams containing `EICAR`
+    async fn fake_clamd() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut command = [0u8; 10];
+                socket.read_exact(&mut command).await.unwrap();
+                assert_eq!(&command, b"zINSTREAM\0");
+                let mut contents = Vec::new();
+                loop {
+                    let length = socket.read_u32().await.unwrap() as usize;
+                    if length == 0 {
+                        break;
+                    }
+                    let mut chunk = vec![0; length];
+                    socket.read_exact(&mut chunk).await.unwrap();
+                    contents.extend(chunk);
+                }
+                let reply: &[u8] = if contents.windows(5).any(|w| w == b"EICAR") {
+                    b"stream: Eicar-Test-Signature FOUND\0"
+                } else {
+                    b"stream: OK\0"
+                };
+                socket.write_all(reply).await.unwrap();
+            }
+        });
+        address
+    }
+
+    fn stream(chunks: &[&'static str]) -> BoxStream<'static, Result<Bytes, StorageError>> {
+        let chunks: Vec<_> = chunks.iter().map(|c| Ok(Bytes::from(*c))).collect();
+        futures_util::stream::iter(chunks).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_clamd_scanner() {
+        let scanner = ClamdScanner::new(&fake_clamd().await);
+        assert_eq!(
+            scanner.scan(stream(&["hello ", "world"])).await.unwrap(),
+            ScanVerdict::Clean
+        );
+        assert_eq!(
+            scanner
+                .scan(stream(&["X5O!P%@AP", "EICAR-TEST"]))
+                .await
+                .unwrap(),
+            ScanVerdict::Infected("Eicar-Test-Signature".to_string())
+        );
+        assert!(ClamdScanner::new("127.0.0.1:1")
+            .scan(stream(&["a"]))
+            .await
+            .is_err());
+    }
+}
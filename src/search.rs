@@ -0,0 +1,308 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::storages::StorageItem;
+
+// Largest compiled `regex` pattern, bounding what a query can make the server build
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+// How `search` matches `filter` against names
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    // Names containing `filter`
+    #[default]
+    Substring,
+    // `*` and `?` within a name, `**` across folders. Patterns with a `/` are
+    // matched against the path below the searched folder, others the name.
+    Glob,
+    // Names the regular expression finds a match in
+    Regex,
+}
+
+// Kinds of entries `search` can be limited to with `type`. Without it only
+// files are found.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    File,
+    Dir,
+    Image,
+    Video,
+    // PDFs, office documents, e-books and plain text
+    Document,
+}
+
+impl EntryType {
+    pub fn matches(self, item: &StorageItem) -> bool {
+        if item.node_type == "dir" {
+            return self == EntryType::Dir;
+        }
+        let mime = match &item.mime_type {
+            Some(mime) => mime.clone(),
+            None => mime_guess::from_path(&item.basename)
+                .first_or_octet_stream()
+                .to_string(),
+        };
+        let (kind, subtype) = mime.split_once('/').unwrap_or((&mime, ""));
+        match self {
+            EntryType::File => true,
+            EntryType::Dir => false,
+            EntryType::Image => kind == "image",
+            EntryType::Video => kind == "video",
+            EntryType::Document => {
+                matches!(mime.as_str(), "text/plain" | "text/markdown" | "text/csv")
+                    || matches!(
+                        subtype,
+                        "pdf"
+                            | "msword"
+                            | "rtf"
+                            | "epub+zip"
+                            | "vnd.ms-excel"
+                            | "vnd.ms-powerpoint"
+                    )
+                    || subtype.starts_with("vnd.openxmlformats-officedocument.")
+                    || subtype.starts_with("vnd.oasis.opendocument.")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Char(char),
+    // `?`
+    One,
+    // `*`
+    Star,
+    // `**`
+    Any,
+    // `**/`, which may also stand for no folder at all
+    AnyDirs,
+}
+
+enum Matcher {
+    Substring(String),
+    Glob { tokens: Vec<Token>, paths: bool },
+    Regex(Regex),
+}
+
+// Checks names against one `search` query
+pub struct NameFilter {
+    matcher: Matcher,
+    case_sensitive: bool,
+    // Compare in NFC, see `VueFinderConfig::search_normalization`
+    normalize: bool,
+}
+
+impl NameFilter {
+    // Errors when `pattern` isn't a valid regular expression
+    pub fn new(
+        mode: SearchMode,
+        pattern: &str,
+        case_sensitive: bool,
+        normalize: bool,
+    ) -> Result<Self, String> {
+        let matcher = match mode {
+            SearchMode::Substring => Matcher::Substring(key(pattern, case_sensitive, normalize)),
+            SearchMode::Glob => Matcher::Glob {
+                paths: pattern.contains('/'),
+                tokens: tokens(key(pattern, case_sensitive, normalize).trim_start_matches('/')),
+            },
+            // Lowercasing would turn classes such as `\D` into their opposite
+            SearchMode::Regex => Matcher::Regex(
+                RegexBuilder::new(&key(pattern, true, normalize))
+                    .case_insensitive(!case_sensitive)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            ),
+        };
+        Ok(Self {
+            matcher,
+            case_sensitive,
+            normalize,
+        })
+    }
+
+    // Whether the entry called `name` matches, `relative` being its path
+    // below the searched folder
+    pub fn matches(&self, name: &str, relative: &str) -> bool {
+        match &self.matcher {
+            Matcher::Substring(pattern) => self.key(name).contains(pattern.as_str()),
+            Matcher::Glob { tokens, paths } => {
+                let subject = if *paths { relative } else { name };
+                let subject: Vec<char> = self.key(subject).chars().collect();
+                glob_match(tokens, &subject)
+            }
+            Matcher::Regex(regex) => regex.is_match(&key(name, true, self.normalize)),
+        }
+    }
+
+    fn key(&self, value: &str) -> String {
+        key(value, self.case_sensitive, self.normalize)
+    }
+}
+
+// `path` below the folder `base`, either of them with or without the scheme
+pub fn relative_path<'a>(base: &str, path: &'a str) -> &'a str {
+    fn key(path: &str) -> &str {
+        path.split_once("://").map_or(path, |(_, key)| key)
+    }
+    let base = key(base).trim_matches('/');
+    let path = key(path).trim_start_matches('/');
+    if base.is_empty() {
+        return path;
+    }
+    path.strip_prefix(base)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(path)
+}
+
+// Fold a name or pattern for matching. Leading BOMs are dropped and, when
+// enabled, composed/decomposed forms (NFC vs NFD) compare equal.
+fn key(value: &str, case_sensitive: bool, normalize: bool) -> String {
+    let value = value.trim_start_matches('\u{feff}');
+    let value = if normalize {
+        value.nfc().collect::<String>()
+    } else {
+        value.to_string()
+    };
+    if case_sensitive {
+        value
+    } else {
+        value.to_lowercase()
+    }
+}
+
+fn tokens(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (token, len) = match chars[i..] {
+            ['*', '*', '/', ..] => (Token::AnyDirs, 3),
+            ['*', '*', ..] => (Token::Any, 2),
+            ['*', ..] => (Token::Star, 1),
+            ['?', ..] => (Token::One, 1),
+            [c, ..] => (Token::Char(c), 1),
+            [] => unreachable!(),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    tokens
+}
+
+// Matched over every pair of positions rather than by backtracking, so
+// patterns full of stars take no longer than others
+fn glob_match(tokens: &[Token], path: &[char]) -> bool {
+    let m = path.len();
+    // `next[j]`: whether the tokens after the current one match `path[j..]`
+    let mut next = vec![false; m + 1];
+    next[m] = true;
+    for token in tokens.iter().rev() {
+        let mut current = vec![false; m + 1];
+        // Whether a `/` at `j` or later is followed by a match of `next`
+        let mut later_dir = false;
+        for j in (0..=m).rev() {
+            let here = path.get(j).copied();
+            current[j] = match *token {
+                Token::Char(c) => here == Some(c) && next[j + 1],
+                Token::One => here.is_some_and(|c| c != '/') && next[j + 1],
+                Token::Star => next[j] || (here.is_some_and(|c| c != '/') && current[j + 1]),
+                Token::Any => next[j] || (here.is_some() && current[j + 1]),
+                Token::AnyDirs => {
+                    later_dir = later_dir || (here == Some('/') && next[j + 1]);
+                    next[j] || later_dir
+                }
+            };
+        }
+        next = current;
+    }
+    next[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(mode: SearchMode, pattern: &str, case_sensitive: bool, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap();
+        NameFilter::new(mode, pattern, case_sensitive, true)
+            .unwrap()
+            .matches(name, path)
+    }
+
+    #[test]
+    fn test_glob() {
+        let glob = |pattern: &str, path: &str| matches(SearchMode::Glob, pattern, false, path);
+        assert!(glob("*.pdf", "docs/Report.PDF"));
+        assert!(!matches(SearchMode::Glob, "*.pdf", true, "docs/Report.PDF"));
+        assert!(glob("report-?.txt", "report-1.txt"));
+        assert!(!glob("report-?.txt", "report-10.txt"));
+
+        assert!(glob("reports/**/final*", "reports/final.pdf"));
+        assert!(glob("reports/**/final*", "reports/2024/q1/final-v2.pdf"));
+        assert!(!glob("reports/**/final*", "old/reports/final.pdf"));
+        assert!(!glob("reports/*", "reports/2024/a.pdf"));
+        assert!(glob("**/a*", "x/y/abc"));
+
+        // Long runs of stars don't backtrack
+        assert!(!glob(&format!("{}b", "*a".repeat(30)), &"a".repeat(200)));
+    }
+
+    #[test]
+    fn test_regex_and_substring() {
+        let pattern = r"^IMG_\d{4}\.jpe?g$";
+        assert!(matches(SearchMode::Regex, pattern, false, "img_0042.JPG"));
+        assert!(!matches(SearchMode::Regex, pattern, true, "img_0042.JPG"));
+        assert!(!matches(SearchMode::Regex, r"\D", false, "2024"));
+        assert!(NameFilter::new(SearchMode::Regex, "(", false, true).is_err());
+
+        assert!(matches(
+            SearchMode::Substring,
+            "port",
+            false,
+            "a/Report.pdf"
+        ));
+        assert!(!matches(
+            SearchMode::Substring,
+            "Port",
+            true,
+            "a/Report.pdf"
+        ));
+        // Folders on the way aren't part of the name
+        assert!(!matches(SearchMode::Substring, "a/", false, "a/Report.pdf"));
+
+        assert_eq!(
+            relative_path("local://docs/", "local://docs/a/b.md"),
+            "a/b.md"
+        );
+        assert_eq!(relative_path("", "local://a.md"), "a.md");
+    }
+
+    #[test]
+    fn test_entry_type() {
+        let file = |name: &str| StorageItem {
+            node_type: "file".to_string(),
+            path: format!("local://{}", name),
+            basename: name.to_string(),
+            extension: None,
+            mime_type: None,
+            last_modified: None,
+            size: Some(1),
+        };
+        assert!(EntryType::Image.matches(&file("a.JPG")));
+        assert!(EntryType::Video.matches(&file("a.mp4")));
+        assert!(EntryType::Document.matches(&file("a.pdf")));
+        assert!(EntryType::Document.matches(&file("a.docx")));
+        assert!(!EntryType::Document.matches(&file("a.zip")));
+        assert!(EntryType::File.matches(&file("a.zip")));
+
+        let dir = StorageItem::dir("local://a".to_string(), "a".to_string());
+        assert!(EntryType::Dir.matches(&dir));
+        assert!(!EntryType::File.matches(&dir) && !EntryType::Dir.matches(&file("a")));
+    }
+}
@@ -0,0 +1,417 @@
+use async_trait::async_trait;
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use thiserror::Error;
+
+use crate::storages::random_id;
+
+// Header carrying the password of `share/download`, kept out of URLs and
+// access logs
+pub const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+
+#[derive(Error, Debug)]
+pub enum ShareError {
+    #[error("Unknown share link: {0}")]
+    NotFound(String),
+    #[error("Share link has expired: {0}")]
+    Expired(String),
+    #[error("Share link reached its download limit: {0}")]
+    LimitReached(String),
+    #[error("Wrong or missing password for share link: {0}")]
+    Password(String),
+    #[error("Expiry too far in the future: {0} seconds")]
+    InvalidExpiry(u64),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid share store: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// A persistent link to one file or folder of an adapter
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub adapter: String,
+    pub path: String,
+    // Who created it, `None` for links created without one
+    #[serde(default)]
+    pub owner: Option<String>,
+    // Unix seconds
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    // See `password_hash`, `None` for links without a password
+    pub password_hash: Option<String>,
+    pub max_downloads: Option<u64>,
+    pub downloads: u64,
+}
+
+// Options of a new share link
+#[derive(Clone, Debug, Default)]
+pub struct NewShareLink {
+    pub password: Option<String>,
+    pub expires_in_secs: Option<u64>,
+    pub max_downloads: Option<u64>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// PBKDF2 rounds of new password hashes, lowered in tests to keep them fast
+#[cfg(not(test))]
+const PASSWORD_ROUNDS: u32 = 600_000;
+#[cfg(test)]
+const PASSWORD_ROUNDS: u32 = 1_000;
+
+// `pbkdf2-sha256$<rounds>$<salt>$<hash>`, salt and hash hex-encoded
+fn password_hash(password: &str) -> String {
+    let salt = random_id();
+    let mut hash = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(
+        password.as_bytes(),
+        salt.as_bytes(),
+        PASSWORD_ROUNDS,
+        &mut hash,
+    );
+    format!(
+        "pbkdf2-sha256${}${}${}",
+        PASSWORD_ROUNDS,
+        salt,
+        hex::encode(hash)
+    )
+}
+
+// Also accepts the salted SHA-256 of links created before PBKDF2 was used
+fn password_matches(id: &str, stored: &str, password: &str) -> bool {
+    let mut parts = stored.split('$');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("pbkdf2-sha256"), Some(rounds), Some(salt), Some(expected)) => {
+            let Ok(rounds) = rounds.parse() else {
+                return false;
+            };
+            let mut hash = [0u8; 32];
+            pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), rounds, &mut hash);
+            hex::encode(hash) == expected
+        }
+        _ => {
+            let mut hasher = Sha256::new();
+            hasher.update(id.as_bytes());
+            hasher.update([0]);
+            hasher.update(password.as_bytes());
+            hex::encode(hasher.finalize()) == stored
+        }
+    }
+}
+
+// Where share links are kept. Every change loads and saves the whole set, so
+// stores only need to persist a list.
+#[async_trait]
+pub trait ShareStore: Send + Sync {
+    async fn load(&self) -> Result<Vec<ShareLink>, ShareError>;
+    async fn save(&self, links: &[ShareLink]) -> Result<(), ShareError>;
+}
+
+// Links lost on restart, the default
+#[derive(Default)]
+pub struct MemoryShareStore {
+    links: Mutex<Vec<ShareLink>>,
+}
+
+#[async_trait]
+impl ShareStore for MemoryShareStore {
+    async fn load(&self) -> Result<Vec<ShareLink>, ShareError> {
+        Ok(self.links.lock().unwrap().clone())
+    }
+
+    async fn save(&self, links: &[ShareLink]) -> Result<(), ShareError> {
+        *self.links.lock().unwrap() = links.to_vec();
+        Ok(())
+    }
+}
+
+// Links kept in a JSON file, replaced atomically on every change
+pub struct JsonShareStore {
+    file: PathBuf,
+}
+
+impl JsonShareStore {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self { file: file.into() }
+    }
+}
+
+#[async_trait]
+impl ShareStore for JsonShareStore {
+    async fn load(&self) -> Result<Vec<ShareLink>, ShareError> {
+        match tokio::fs::read(&self.file).await {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, links: &[ShareLink]) -> Result<(), ShareError> {
+        if let Some(dir) = self.file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let part = self.file.with_extension("json.part");
+        tokio::fs::write(&part, serde_json::to_vec_pretty(links)?).await?;
+        tokio::fs::rename(&part, &self.file).await?;
+        Ok(())
+    }
+}
+
+// Share links of every adapter, shared by all workers
+pub struct ShareLinks {
+    store: Box<dyn ShareStore>,
+    // Serializes load-modify-save cycles
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl Default for ShareLinks {
+    fn default() -> Self {
+        Self::new(MemoryShareStore::default())
+    }
+}
+
+impl ShareLinks {
+    pub fn new(store: impl ShareStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub async fn create(
+        &self,
+        adapter: &str,
+        path: &str,
+        owner: Option<&str>,
+        options: NewShareLink,
+    ) -> Result<ShareLink, ShareError> {
+        let created_at = now();
+        let expires_at = match options.expires_in_secs {
+            Some(secs) => Some(
+                created_at
+                    .checked_add(secs)
+                    .ok_or(ShareError::InvalidExpiry(secs))?,
+            ),
+            None => None,
+        };
+        // Hashed off the async workers, the KDF is slow on purpose
+        let password_hash = match options.password.filter(|password| !password.is_empty()) {
+            Some(password) => Some(
+                tokio::task::spawn_blocking(move || password_hash(&password))
+                    .await
+                    .map_err(std::io::Error::other)?,
+            ),
+            None => None,
+        };
+        let link = ShareLink {
+            id: random_id(),
+            adapter: adapter.to_string(),
+            path: path.to_string(),
+            owner: owner.map(str::to_string),
+            created_at,
+            expires_at,
+            password_hash,
+            max_downloads: options.max_downloads,
+            downloads: 0,
+        };
+
+        let _guard = self.lock.lock().await;
+        let mut links = self.store.load().await?;
+        links.push(link.clone());
+        self.store.save(&links).await?;
+        Ok(link)
+    }
+
+    // Links of `adapter`, or of every adapter when `None`
+    pub async fn list(&self, adapter: Option<&str>) -> Result<Vec<ShareLink>, ShareError> {
+        let mut links = self.store.load().await?;
+        links.retain(|link| adapter.is_none_or(|adapter| link.adapter == adapter));
+        Ok(links)
+    }
+
+    // Remove the link when `allowed` accepts it. Returns whether it was
+    // removed, links refused are reported as missing.
+    pub async fn revoke(
+        &self,
+        id: &str,
+        allowed: impl FnOnce(&ShareLink) -> bool,
+    ) -> Result<bool, ShareError> {
+        let _guard = self.lock.lock().await;
+        let mut links = self.store.load().await?;
+        let Some(index) = links.iter().position(|link| link.id == id) else {
+            return Ok(false);
+        };
+        if !allowed(&links[index]) {
+            return Ok(false);
+        }
+        links.remove(index);
+        self.store.save(&links).await?;
+        Ok(true)
+    }
+
+    // Check a link for a download and count it
+    pub async fn open(&self, id: &str, password: Option<&str>) -> Result<ShareLink, ShareError> {
+        let link = self
+            .store
+            .load()
+            .await?
+            .into_iter()
+            .find(|link| link.id == id)
+            .ok_or_else(|| ShareError::NotFound(id.to_string()))?;
+        if link
+            .expires_at
+            .is_some_and(|expires_at| now() >= expires_at)
+        {
+            return Err(ShareError::Expired(id.to_string()));
+        }
+        // Checked without holding the lock, so slow guesses don't hold up
+        // other links
+        if let Some(hash) = link.password_hash {
+            let (link_id, password) = (id.to_string(), password.unwrap_or_default().to_string());
+            let matches =
+                tokio::task::spawn_blocking(move || password_matches(&link_id, &hash, &password))
+                    .await
+                    .unwrap_or(false);
+            if !matches {
+                return Err(ShareError::Password(id.to_string()));
+            }
+        }
+
+        let _guard = self.lock.lock().await;
+        let mut links = self.store.load().await?;
+        // Revoked meanwhile
+        let link = links
+            .iter_mut()
+            .find(|link| link.id == id)
+            .ok_or_else(|| ShareError::NotFound(id.to_string()))?;
+        if link
+            .max_downloads
+            .is_some_and(|max_downloads| link.downloads >= max_downloads)
+        {
+            return Err(ShareError::LimitReached(id.to_string()));
+        }
+
+        link.downloads += 1;
+        let link = link.clone();
+        self.store.save(&links).await?;
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_share_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("shares.json");
+        let shares = ShareLinks::new(JsonShareStore::new(&file));
+
+        let link = shares
+            .create(
+                "local",
+                "local://a.txt",
+                Some("ann"),
+                NewShareLink {
+                    password: Some("secret".to_string()),
+                    max_downloads: Some(1),
+                    ..NewShareLink::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            shares.open(&link.id, None).await,
+            Err(ShareError::Password(_))
+        ));
+        assert_eq!(
+            shares
+                .open(&link.id, Some("secret"))
+                .await
+                .unwrap()
+                .downloads,
+            1
+        );
+        assert!(matches!(
+            shares.open(&link.id, Some("secret")).await,
+            Err(ShareError::LimitReached(_))
+        ));
+
+        // Persisted across instances
+        let shares = ShareLinks::new(JsonShareStore::new(&file));
+        let links = shares.list(Some("local")).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].downloads, 1);
+        assert!(shares.list(Some("other")).await.unwrap().is_empty());
+
+        let expired = shares
+            .create(
+                "local",
+                "local://a.txt",
+                None,
+                NewShareLink {
+                    expires_in_secs: Some(0),
+                    ..NewShareLink::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            shares.open(&expired.id, None).await,
+            Err(ShareError::Expired(_))
+        ));
+
+        assert!(matches!(
+            shares
+                .create(
+                    "local",
+                    "local://a.txt",
+                    None,
+                    NewShareLink {
+                        expires_in_secs: Some(u64::MAX),
+                        ..NewShareLink::default()
+                    },
+                )
+                .await,
+            Err(ShareError::InvalidExpiry(_))
+        ));
+
+        assert!(!shares
+            .revoke(&link.id, |link| link.owner.as_deref() == Some("bob"))
+            .await
+            .unwrap());
+        assert!(shares.revoke(&link.id, |_| true).await.unwrap());
+        assert!(!shares.revoke(&link.id, |_| true).await.unwrap());
+        assert!(matches!(
+            shares.open(&link.id, Some("secret")).await,
+            Err(ShareError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_password_hash() {
+        let hash = password_hash("secret");
+        assert!(hash.starts_with("pbkdf2-sha256$"));
+        assert_ne!(hash, password_hash("secret"));
+        assert!(password_matches("id", &hash, "secret"));
+        assert!(!password_matches("id", &hash, "wrong"));
+
+        // Links hashed before PBKDF2 keep working
+        let legacy = hex::encode(Sha256::digest(b"id\0secret"));
+        assert!(password_matches("id", &legacy, "secret"));
+        assert!(!password_matches("other", &legacy, "secret"));
+    }
+}
@@ -0,0 +1,355 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::checksum::ChecksumAlgorithm;
+use super::{
+    parent_path, StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage,
+};
+
+struct Cached<T> {
+    value: T,
+    stored: Instant,
+}
+
+// Least recently used entries of one kind, each valid for `ttl`
+struct Cache<T> {
+    entries: Mutex<LruCache<String, Cached<T>>>,
+    ttl: Duration,
+}
+
+impl<T: Clone> Cache<T> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            ttl,
+        }
+    }
+
+    fn get(&self, path: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(path) {
+            Some(cached) if cached.stored.elapsed() < self.ttl => Some(cached.value.clone()),
+            Some(_) => {
+                entries.pop(path);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, path: &str, value: T) {
+        self.entries.lock().unwrap().put(
+            path.to_string(),
+            Cached {
+                value,
+                stored: Instant::now(),
+            },
+        );
+    }
+
+    // Drop `path` and everything below it
+    fn remove_tree(&self, path: &str) {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut entries = self.entries.lock().unwrap();
+        let stale: Vec<String> = entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| *key == path || key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in stale {
+            entries.pop(&key);
+        }
+    }
+
+    fn remove(&self, path: &str) {
+        self.entries.lock().unwrap().pop(path);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+// Keeps listings and small files of a slow adapter in memory. Entries expire
+// after the TTL and are dropped as soon as something is changed through this
+// adapter; changes made behind its back show up once the TTL runs out.
+pub struct CachedStorage {
+    inner: Arc<dyn StorageAdapter>,
+    listings: Cache<Vec<StorageItem>>,
+    files: Cache<Arc<Vec<u8>>>,
+    // Larger files are always read from the wrapped adapter
+    max_file_size: usize,
+}
+
+impl CachedStorage {
+    pub fn new(inner: Arc<dyn StorageAdapter>) -> Self {
+        Self::with_options(inner, Duration::from_secs(30), 1024 * 1024)
+    }
+
+    pub fn with_options(
+        inner: Arc<dyn StorageAdapter>,
+        ttl: Duration,
+        max_file_size: usize,
+    ) -> Self {
+        Self {
+            inner,
+            listings: Cache::new(1024, ttl),
+            files: Cache::new(256, ttl),
+            max_file_size,
+        }
+    }
+
+    // Forget what is cached for `path`, everything below it and the listings of
+    // its ancestors, which writes may have created
+    pub fn invalidate(&self, path: &str) {
+        self.listings.remove_tree(path);
+        self.files.remove_tree(path);
+        let mut parent = parent_path(path);
+        while !parent.is_empty() {
+            self.listings.remove(&parent);
+            let next = parent_path(&parent);
+            if next == parent {
+                break;
+            }
+            parent = next;
+        }
+    }
+
+    pub fn clear(&self) {
+        self.listings.clear();
+        self.files.clear();
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for CachedStorage {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        if let Some(items) = self.listings.get(path) {
+            return Ok(items);
+        }
+        let items = self.inner.list_contents(path).await?;
+        self.listings.put(path, items.clone());
+        Ok(items)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        if let Some(contents) = self.files.get(path) {
+            return Ok(contents.to_vec());
+        }
+        let contents = self.inner.read(path).await?;
+        if contents.len() <= self.max_file_size {
+            self.files.put(path, Arc::new(contents.clone()));
+        }
+        Ok(contents)
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let result = self.inner.write(path, contents).await;
+        self.invalidate(path);
+        result
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        let result = self.inner.write_stream(path, stream).await;
+        self.invalidate(path);
+        result
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let result = self.inner.delete(path).await;
+        self.invalidate(path);
+        result
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        let result = self.inner.create_dir(path).await;
+        self.invalidate(path);
+        result
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.exists(path).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let result = self.inner.rename(from, to).await;
+        self.invalidate(from);
+        self.invalidate(to);
+        result
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        self.inner.validate_key(name)
+    }
+
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Box::pin(futures_util::stream::once(async move {
+                Ok(Bytes::from(contents.to_vec()))
+            }))),
+            None => self.inner.read_stream(path).await,
+        }
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.inner.read_stream_range(path, offset, length).await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        match self.files.get(path) {
+            Some(contents) => Ok(contents.len() as u64),
+            None => self.inner.file_size(path).await,
+        }
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.inner.read_range(path, offset, length).await
+    }
+
+    fn local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.local_path(path)
+    }
+
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        self.inner.checksum(path, algorithm).await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        self.inner.metadata(path).await
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.is_dir(path).await
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.inner.usage().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let result = self.inner.append(path, contents).await;
+        self.invalidate(path);
+        result
+    }
+
+    async fn write_if_match(
+        &self,
+        path: &str,
+        expected_hash: &str,
+        contents: Vec<u8>,
+    ) -> Result<bool, StorageError> {
+        let result = self
+            .inner
+            .write_if_match(path, expected_hash, contents)
+            .await;
+        self.invalidate(path);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_cached_storage() {
+        let inner = Arc::new(MemoryStorage::new("remote"));
+        inner
+            .write("remote://docs/a.txt", b"a".to_vec())
+            .await
+            .unwrap();
+        let storage = CachedStorage::with_options(inner.clone(), Duration::from_secs(60), 4);
+
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            1
+        );
+        assert_eq!(storage.read("remote://docs/a.txt").await.unwrap(), b"a");
+
+        // Changes behind the cache's back aren't seen until invalidated
+        inner
+            .write("remote://docs/b.txt", b"b".to_vec())
+            .await
+            .unwrap();
+        inner
+            .write("remote://docs/a.txt", b"changed".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            1
+        );
+        assert_eq!(storage.read("remote://docs/a.txt").await.unwrap(), b"a");
+        storage.invalidate("remote://docs");
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            2
+        );
+        assert_eq!(
+            storage.read("remote://docs/a.txt").await.unwrap(),
+            b"changed"
+        );
+
+        // Writing through the cache drops the stale entries
+        storage
+            .write("remote://docs/c.txt", b"c".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            3
+        );
+        storage.delete("remote://docs/a.txt").await.unwrap();
+        assert!(storage.read("remote://docs/a.txt").await.is_err());
+
+        // Expired entries are fetched again
+        let storage = CachedStorage::with_options(inner.clone(), Duration::ZERO, 4);
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            2
+        );
+        inner.delete("remote://docs/b.txt").await.unwrap();
+        assert_eq!(
+            storage.list_contents("remote://docs").await.unwrap().len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::digest::DynDigest;
+use sha2::{Digest, Sha256};
+
+use super::StorageError;
+
+// Digest of the `hash` command and `StorageAdapter::checksum`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha1,
+    #[default]
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    fn hasher(self) -> Box<dyn DynDigest + Send> {
+        match self {
+            ChecksumAlgorithm::Md5 => Box::new(Md5::new()),
+            ChecksumAlgorithm::Sha1 => Box::new(Sha1::new()),
+            ChecksumAlgorithm::Sha256 => Box::new(Sha256::new()),
+        }
+    }
+}
+
+// Hex digest of everything `stream` yields, one chunk in memory at a time
+pub async fn digest_stream(
+    mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    algorithm: ChecksumAlgorithm,
+) -> Result<String, StorageError> {
+    let mut hasher = algorithm.hasher();
+    while let Some(chunk) = stream.try_next().await? {
+        hasher.update(&chunk);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// A stored MD5 in hex, from an S3-style ETag. Multipart uploads have ETags
+// that aren't an MD5 of the contents, those are `None`.
+pub fn etag_md5(etag: &str) -> Option<String> {
+    let etag = etag.trim_start_matches("W/").trim_matches('"');
+    (etag.len() == 32 && etag.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| etag.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_digest_stream() {
+        let chunks = || -> BoxStream<'static, Result<Bytes, StorageError>> {
+            Box::pin(futures_util::stream::iter(vec![
+                Ok(Bytes::from("hello ")),
+                Ok(Bytes::from("world")),
+            ]))
+        };
+
+        assert_eq!(
+            digest_stream(chunks(), ChecksumAlgorithm::Md5)
+                .await
+                .unwrap(),
+            "5eb63bbbe01eeed093cb22bb8f5acdc3"
+        );
+        assert_eq!(
+            digest_stream(chunks(), ChecksumAlgorithm::Sha1)
+                .await
+                .unwrap(),
+            "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"
+        );
+        assert_eq!(
+            digest_stream(chunks(), ChecksumAlgorithm::Sha256)
+                .await
+                .unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+
+        assert_eq!(
+            etag_md5("\"5EB63BBBE01EEED093CB22BB8F5ACDC3\"").as_deref(),
+            Some("5eb63bbbe01eeed093cb22bb8f5acdc3")
+        );
+        assert_eq!(etag_md5("\"d41d8cd98f00b204e9800998ecf8427e-2\""), None);
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use tokio::sync::OwnedMutexGuard;
+
+use super::{lock, StorageAdapter, StorageError};
+
+// Highest ` (n)` suffix tried before giving up on finding a free name
+const MAX_SUFFIX: usize = 10_000;
+
+// What to do when a command's target path is already taken
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    // Fail with `AlreadyExists`
+    Fail,
+    // Replace the existing entry
+    Overwrite,
+    // Pick the first free `name (n).ext`
+    Rename,
+    // Leave the existing entry alone and don't write
+    Skip,
+}
+
+// For policies sent outside of JSON, such as the `on_conflict` field of uploads
+impl std::str::FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "fail" => Ok(CollisionPolicy::Fail),
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            "rename" => Ok(CollisionPolicy::Rename),
+            "skip" => Ok(CollisionPolicy::Skip),
+            _ => Err(format!("Unknown conflict policy: {}", policy)),
+        }
+    }
+}
+
+// Where a command should write, decided under a lock on the parent directory.
+// Keep it alive until the write is done so concurrent commands can't pick the same name.
+#[derive(Debug)]
+pub struct ResolvedPath {
+    pub path: String,
+    // The target exists and the policy says to leave it alone
+    pub skip: bool,
+    _guard: OwnedMutexGuard<()>,
+}
+
+// `photo (2).jpg` for `photo.jpg`, `folder (2)` for `folder`
+fn with_suffix(path: &str, n: usize) -> String {
+    let (dir, name) = match path.rfind('/') {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    };
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}{} ({}){}", dir, &name[..dot], n, &name[dot..]),
+        _ => format!("{}{} ({})", dir, name, n),
+    }
+}
+
+pub async fn resolve_collision(
+    storage: &dyn StorageAdapter,
+    desired_path: &str,
+    policy: CollisionPolicy,
+) -> Result<ResolvedPath, StorageError> {
+    let parent = desired_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let guard = lock::lock_path(&format!("collision:{}:{}", storage.name(), parent)).await;
+
+    let resolved = |path: String, skip: bool| ResolvedPath {
+        path,
+        skip,
+        _guard: guard,
+    };
+
+    if !storage.exists(desired_path).await? {
+        return Ok(resolved(desired_path.to_string(), false));
+    }
+
+    match policy {
+        CollisionPolicy::Fail => Err(StorageError::AlreadyExists(desired_path.to_string())),
+        CollisionPolicy::Overwrite => Ok(resolved(desired_path.to_string(), false)),
+        CollisionPolicy::Skip => Ok(resolved(desired_path.to_string(), true)),
+        CollisionPolicy::Rename => {
+            for n in 1..=MAX_SUFFIX {
+                let candidate = with_suffix(desired_path, n);
+                if !storage.exists(&candidate).await? {
+                    return Ok(resolved(candidate, false));
+                }
+            }
+            Err(StorageError::LimitExceeded(MAX_SUFFIX))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[test]
+    fn test_with_suffix() {
+        assert_eq!(with_suffix("mem://a/photo.jpg", 1), "mem://a/photo (1).jpg");
+        assert_eq!(
+            with_suffix("mem://a/archive.tar.gz", 2),
+            "mem://a/archive.tar (2).gz"
+        );
+        assert_eq!(with_suffix("mem://folder", 3), "mem://folder (3)");
+        assert_eq!(with_suffix("mem://.env", 1), "mem://.env (1)");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_collision() {
+        let storage = MemoryStorage::new("mem");
+        storage.write("mem://a.txt", vec![]).await.unwrap();
+        storage.write("mem://a (1).txt", vec![]).await.unwrap();
+
+        let free = resolve_collision(&storage, "mem://b.txt", CollisionPolicy::Fail)
+            .await
+            .unwrap();
+        assert_eq!(free.path, "mem://b.txt");
+        assert!(!free.skip);
+        drop(free);
+
+        assert!(matches!(
+            resolve_collision(&storage, "mem://a.txt", CollisionPolicy::Fail).await,
+            Err(StorageError::AlreadyExists(_))
+        ));
+
+        let overwrite = resolve_collision(&storage, "mem://a.txt", CollisionPolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(overwrite.path, "mem://a.txt");
+        assert!(!overwrite.skip);
+        drop(overwrite);
+
+        let skip = resolve_collision(&storage, "mem://a.txt", CollisionPolicy::Skip)
+            .await
+            .unwrap();
+        assert!(skip.skip);
+        drop(skip);
+
+        let rename = resolve_collision(&storage, "mem://a.txt", CollisionPolicy::Rename)
+            .await
+            .unwrap();
+        assert_eq!(rename.path, "mem://a (2).txt");
+    }
+}
@@ -0,0 +1,392 @@
+use base64::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::cached::CachedStorage;
+use super::dropbox::DropboxConfig;
+use super::encrypted::EncryptedStorage;
+use super::gcs::GcsConfig;
+use super::gdrive::GoogleDriveConfig;
+use super::local::LocalStorage;
+use super::memory::MemoryStorage;
+use super::read_only::ReadOnlyStorage;
+use super::union::{UnionStorage, WritePolicy};
+use super::{StorageAdapter, StorageError};
+
+// One entry of the `storages` config section, keyed by the name it is mounted
+// under, e.g. `{ "media": { "type": "local", "root": "/srv/media" } }`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageConfig {
+    Local {
+        root: String,
+        // Create the root folder when missing
+        #[serde(default = "default_create")]
+        create: bool,
+    },
+    Memory {
+        // Local folder whose files the mount starts with, e.g. for a demo
+        #[serde(default)]
+        seed: Option<String>,
+    },
+    // Needs the `gcs` feature
+    Gcs {
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        credentials: String,
+    },
+    // Needs the `dropbox` feature
+    Dropbox {
+        token: String,
+        #[serde(default)]
+        root: String,
+    },
+    // A single database file holding every file, needs the `sqlite` feature
+    Sqlite {
+        path: String,
+    },
+    // Needs the `gdrive` feature
+    Gdrive {
+        credentials: String,
+        // Folder ID, the account's My Drive when unset
+        #[serde(default)]
+        root: Option<String>,
+    },
+    // Any Apache OpenDAL service by its scheme, e.g. `s3`, `azblob` or `webdav`,
+    // with that service's options. Needs the `opendal` feature
+    Opendal {
+        scheme: String,
+        #[serde(default)]
+        options: HashMap<String, String>,
+    },
+    // Other mounts merged into one, e.g. a writable folder over read-only
+    // templates. Earlier layers shadow later ones
+    Union {
+        layers: Vec<MountConfig>,
+        #[serde(default)]
+        write: WritePolicy,
+    },
+}
+
+fn default_create() -> bool {
+    true
+}
+
+// A `storages` entry: the adapter plus the wrappers applied on top of it
+#[derive(Clone, Debug, Deserialize)]
+pub struct MountConfig {
+    #[serde(flatten)]
+    pub storage: StorageConfig,
+    // Encrypt what the mount stores, see `EncryptedStorage`
+    #[serde(default)]
+    pub encryption: Option<EncryptionConfig>,
+    // Keep listings and small files in memory, see `CachedStorage`
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    // Refuse every change to the mount
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EncryptionConfig {
+    // Base64 of a 32-byte key
+    pub key: String,
+    // Encrypt file and folder names too
+    #[serde(default)]
+    pub names: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub ttl_secs: u64,
+    // Files larger than this are never cached
+    pub max_file_size: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 30,
+            max_file_size: 1024 * 1024,
+        }
+    }
+}
+
+// Mount names become path schemes, `<name>://`
+fn validate_name(name: &str) -> Result<(), StorageError> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !valid {
+        return Err(StorageError::InvalidPath(format!(
+            "Invalid storage name \"{}\"",
+            name
+        )));
+    }
+    Ok(())
+}
+
+impl StorageConfig {
+    pub fn build(&self, name: &str) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+        validate_name(name)?;
+        Ok(match self {
+            StorageConfig::Local { root, create } => {
+                if *create {
+                    std::fs::create_dir_all(root)?;
+                } else if !std::path::Path::new(root).is_dir() {
+                    return Err(StorageError::NotFound(root.clone()));
+                }
+                Arc::new(LocalStorage::new(root).with_name(name))
+            }
+            StorageConfig::Memory { seed } => {
+                let storage = MemoryStorage::new(name);
+                Arc::new(match seed {
+                    Some(seed) => storage.with_dir(seed)?,
+                    None => storage,
+                })
+            }
+            StorageConfig::Gcs {
+                bucket,
+                prefix,
+                credentials,
+            } => gcs(GcsConfig {
+                name: name.to_string(),
+                bucket: bucket.clone(),
+                prefix: prefix.clone(),
+                credentials: credentials.clone(),
+            })?,
+            StorageConfig::Dropbox { token, root } => dropbox(DropboxConfig {
+                name: name.to_string(),
+                token: token.clone(),
+                root: root.clone(),
+            })?,
+            StorageConfig::Sqlite { path } => sqlite(name, path)?,
+            StorageConfig::Gdrive { credentials, root } => gdrive(GoogleDriveConfig {
+                name: name.to_string(),
+                credentials: credentials.clone(),
+                root: root.clone().unwrap_or_else(|| "root".to_string()),
+            })?,
+            StorageConfig::Opendal { scheme, options } => opendal(name, scheme, options)?,
+            StorageConfig::Union { layers, write } => {
+                if layers.is_empty() {
+                    return Err(StorageError::InvalidPath(format!(
+                        "{}: a union needs at least one layer",
+                        name
+                    )));
+                }
+                // Layers are only reachable through the union, under `<name>-<index>://`
+                let layers = layers
+                    .iter()
+                    .enumerate()
+                    .map(|(index, layer)| layer.build(&format!("{}-{}", name, index)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Arc::new(UnionStorage::new(name, layers).with_write_policy(*write))
+            }
+        })
+    }
+}
+
+impl MountConfig {
+    pub fn build(&self, name: &str) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+        let storage = match &self.encryption {
+            Some(encryption) => Self::encrypted(name, self.storage.build(name)?, encryption)?,
+            None => self.storage.build(name)?,
+        };
+        let storage = match &self.cache {
+            Some(cache) => Arc::new(CachedStorage::with_options(
+                storage,
+                Duration::from_secs(cache.ttl_secs),
+                cache.max_file_size,
+            )),
+            None => storage,
+        };
+        Ok(if self.read_only {
+            Arc::new(ReadOnlyStorage::new(storage))
+        } else {
+            storage
+        })
+    }
+
+    fn encrypted(
+        name: &str,
+        storage: Arc<dyn StorageAdapter>,
+        encryption: &EncryptionConfig,
+    ) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+        let key: [u8; 32] = BASE64_STANDARD
+            .decode(&encryption.key)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| {
+                StorageError::InvalidPath(format!(
+                    "{}: the encryption key must be 32 bytes of base64",
+                    name
+                ))
+            })?;
+        let encrypted = EncryptedStorage::new(storage, &key);
+        Ok(Arc::new(if encryption.names {
+            encrypted.with_encrypted_names(&key)
+        } else {
+            encrypted
+        }))
+    }
+}
+
+#[cfg(feature = "gcs")]
+fn gcs(config: GcsConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Ok(Arc::new(super::gcs::GcsStorage::new(&config)?))
+}
+
+#[cfg(not(feature = "gcs"))]
+fn gcs(config: GcsConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Err(StorageError::Unsupported(format!(
+        "{}: this build doesn't have the `gcs` feature",
+        config.name
+    )))
+}
+
+#[cfg(feature = "dropbox")]
+fn dropbox(config: DropboxConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Ok(Arc::new(super::dropbox::DropboxStorage::new(&config)))
+}
+
+#[cfg(not(feature = "dropbox"))]
+fn dropbox(config: DropboxConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Err(StorageError::Unsupported(format!(
+        "{}: this build doesn't have the `dropbox` feature",
+        config.name
+    )))
+}
+
+#[cfg(feature = "gdrive")]
+fn gdrive(config: GoogleDriveConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Ok(Arc::new(super::gdrive::GoogleDriveStorage::new(&config)?))
+}
+
+#[cfg(not(feature = "gdrive"))]
+fn gdrive(config: GoogleDriveConfig) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Err(StorageError::Unsupported(format!(
+        "{}: this build doesn't have the `gdrive` feature",
+        config.name
+    )))
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite(name: &str, path: &str) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Ok(Arc::new(super::sqlite::SqliteStorage::open(name, path)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite(name: &str, _path: &str) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Err(StorageError::Unsupported(format!(
+        "{}: this build doesn't have the `sqlite` feature",
+        name
+    )))
+}
+
+#[cfg(feature = "opendal")]
+fn opendal(
+    name: &str,
+    scheme: &str,
+    options: &HashMap<String, String>,
+) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Ok(Arc::new(super::opendal::OpendalStorage::from_options(
+        name, scheme, options,
+    )?))
+}
+
+#[cfg(not(feature = "opendal"))]
+fn opendal(
+    name: &str,
+    _scheme: &str,
+    _options: &HashMap<String, String>,
+) -> Result<Arc<dyn StorageAdapter>, StorageError> {
+    Err(StorageError::Unsupported(format!(
+        "{}: this build doesn't have the `opendal` feature",
+        name
+    )))
+}
+
+// Build the adapters of a `storages` config section. Fails on the first one
+// that can't be mounted, naming it in the error.
+pub fn build_storages(
+    configs: &HashMap<String, MountConfig>,
+) -> Result<HashMap<String, Arc<dyn StorageAdapter>>, StorageError> {
+    configs
+        .iter()
+        .map(|(name, config)| Ok((name.clone(), config.build(name)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_build_storages() {
+        let temp_dir = TempDir::new().unwrap();
+        let media = temp_dir.path().join("media");
+        let configs: HashMap<String, MountConfig> = serde_json::from_value(serde_json::json!({
+            "local": { "type": "local", "root": temp_dir.path().join("files") },
+            "media": { "type": "local", "root": media },
+            "scratch": { "type": "memory" },
+            "demo": { "type": "memory", "read_only": true }
+        }))
+        .unwrap();
+
+        let storages = build_storages(&configs).unwrap();
+        assert_eq!(storages.len(), 4);
+        assert!(media.is_dir());
+
+        let storage = &storages["media"];
+        assert_eq!(storage.name(), "media");
+        storage
+            .write("media://a/b.txt", b"b".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(media.join("a/b.txt")).unwrap(), b"b");
+        let items = storage.list_contents("media://a").await.unwrap();
+        assert_eq!(items[0].path, "media://a/b.txt");
+        assert_eq!(storages["scratch"].name(), "scratch");
+        assert!(matches!(
+            storages["demo"].write("demo://a.txt", Vec::new()).await,
+            Err(StorageError::ReadOnly(_))
+        ));
+
+        let configs: HashMap<String, MountConfig> = serde_json::from_value(serde_json::json!({
+            "vault": {
+                "type": "memory",
+                "encryption": { "key": BASE64_STANDARD.encode([1u8; 32]), "names": true },
+                "cache": { "ttl_secs": 60 }
+            }
+        }))
+        .unwrap();
+        let storages = build_storages(&configs).unwrap();
+        let vault = &storages["vault"];
+        vault.write("vault://a.txt", b"a".to_vec()).await.unwrap();
+        assert_eq!(vault.read("vault://a.txt").await.unwrap(), b"a");
+
+        let configs: HashMap<String, MountConfig> = serde_json::from_value(serde_json::json!({
+            "team": {
+                "type": "union",
+                "layers": [{ "type": "memory" }, { "type": "memory", "read_only": true }]
+            }
+        }))
+        .unwrap();
+        let team = &build_storages(&configs).unwrap()["team"];
+        team.write("team://a.txt", b"a".to_vec()).await.unwrap();
+        assert_eq!(
+            team.list_contents("team://").await.unwrap()[0].path,
+            "team://a.txt"
+        );
+
+        let invalid: HashMap<String, MountConfig> =
+            serde_json::from_value(serde_json::json!({ "a b": { "type": "memory" } })).unwrap();
+        assert!(build_storages(&invalid).is_err());
+    }
+}
@@ -0,0 +1,609 @@
+use serde::Deserialize;
+
+// A Dropbox account or app folder mounted from the `storages` config section
+#[derive(Clone, Debug, Deserialize)]
+pub struct DropboxConfig {
+    // Adapter name and path scheme
+    #[serde(default = "default_name")]
+    pub name: String,
+    // OAuth access token of the account
+    pub token: String,
+    // Folder the adapter is rooted at, e.g. `/Team/files`, the whole account when empty
+    #[serde(default)]
+    pub root: String,
+}
+
+fn default_name() -> String {
+    "dropbox".to_string()
+}
+
+#[cfg(feature = "dropbox")]
+pub use adapter::DropboxStorage;
+
+#[cfg(feature = "dropbox")]
+mod adapter {
+    use super::DropboxConfig;
+    use crate::storages::{
+        join_path, StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage,
+    };
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use futures_util::stream::BoxStream;
+    use futures_util::TryStreamExt;
+    use mime_guess::from_path;
+    use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
+    use serde::de::DeserializeOwned;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::path::{Component, Path};
+
+    const API: &str = "https://api.dropboxapi.com/2";
+    const CONTENT_API: &str = "https://content.dropboxapi.com/2";
+    // Header carrying the JSON arguments of content endpoints
+    const API_ARG: &str = "Dropbox-API-Arg";
+    // Files up to this size are sent in one request, larger ones through an
+    // upload session in chunks of this size. Dropbox takes at most 150 MB a request.
+    const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+    #[derive(Deserialize)]
+    struct Entry {
+        #[serde(rename = ".tag")]
+        tag: String,
+        name: String,
+        size: Option<u64>,
+        server_modified: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct FolderList {
+        entries: Vec<Entry>,
+        cursor: String,
+        has_more: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct UploadSession {
+        session_id: String,
+    }
+
+    #[derive(Deserialize)]
+    struct SpaceUsage {
+        used: u64,
+        allocation: Allocation,
+    }
+
+    #[derive(Deserialize)]
+    struct Allocation {
+        // Missing for allocations Dropbox doesn't describe
+        allocated: Option<u64>,
+    }
+
+    // Body of 409 responses, e.g. `path/not_found/..`
+    #[derive(Deserialize)]
+    struct ApiError {
+        error_summary: String,
+    }
+
+    fn io_error(error: impl std::fmt::Display) -> StorageError {
+        StorageError::Io(std::io::Error::other(error.to_string()))
+    }
+
+    fn parse_time(value: &str) -> Option<u64> {
+        time::OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339)
+            .ok()
+            .and_then(|time| u64::try_from(time.unix_timestamp()).ok())
+    }
+
+    // The `Dropbox-API-Arg` header only takes ASCII, so other characters are
+    // escaped the way JSON allows
+    fn api_arg(arg: &Value) -> String {
+        let mut header = String::new();
+        for c in arg.to_string().chars() {
+            if c.is_ascii() && c != '\x7f' {
+                header.push(c);
+            } else {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    header.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+        header
+    }
+
+    // Storage error for an `error_summary`, naming `path`
+    fn api_error(path: &str, summary: &str) -> StorageError {
+        if summary.contains("not_found") {
+            StorageError::NotFound(path.to_string())
+        } else if summary.contains("not_folder") {
+            StorageError::NotADirectory(path.to_string())
+        } else if summary.contains("conflict") {
+            StorageError::AlreadyExists(path.to_string())
+        } else if summary.contains("insufficient_space") {
+            StorageError::InsufficientStorage(path.to_string())
+        } else {
+            io_error(format!("Dropbox: {}", summary.trim_end_matches('.')))
+        }
+    }
+
+    // Dropbox account storage through its HTTP API. Folders are real, so empty
+    // ones survive and `rename` moves without copying.
+    pub struct DropboxStorage {
+        name: String,
+        token: String,
+        root: String,
+        client: Client,
+    }
+
+    impl DropboxStorage {
+        pub fn new(config: &DropboxConfig) -> Self {
+            let root = config.root.trim_matches('/');
+            Self {
+                name: config.name.clone(),
+                token: config.token.clone(),
+                root: if root.is_empty() {
+                    String::new()
+                } else {
+                    format!("/{}", root)
+                },
+                client: Client::new(),
+            }
+        }
+
+        // Dropbox path for a storage path, rejecting anything but plain
+        // relative paths. The root is `""`, everything else starts with `/`.
+        fn api_path(&self, path: &str) -> Result<String, StorageError> {
+            let clean_path = path
+                .strip_prefix(&format!("{}://", self.name))
+                .unwrap_or(path)
+                .trim_matches('/');
+
+            if Path::new(clean_path)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+            {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+
+            Ok(if clean_path.is_empty() {
+                self.root.clone()
+            } else {
+                format!("{}/{}", self.root, clean_path)
+            })
+        }
+
+        fn is_root(&self, path: &str) -> Result<bool, StorageError> {
+            Ok(self.api_path(path)? == self.root)
+        }
+
+        // Send a request, turning 409 answers into the matching storage error
+        async fn send(
+            &self,
+            request: RequestBuilder,
+            path: &str,
+        ) -> Result<Response, StorageError> {
+            let response = request
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .map_err(io_error)?;
+            if response.status() == StatusCode::CONFLICT {
+                let error: ApiError = response.json().await.map_err(io_error)?;
+                return Err(api_error(path, &error.error_summary));
+            }
+            response.error_for_status().map_err(io_error)
+        }
+
+        // Call an RPC endpoint taking and returning JSON
+        async fn rpc<T: DeserializeOwned>(
+            &self,
+            endpoint: &str,
+            arg: Value,
+            path: &str,
+        ) -> Result<T, StorageError> {
+            let request = self.client.post(format!("{}/{}", API, endpoint)).json(&arg);
+            self.send(request, path)
+                .await?
+                .json()
+                .await
+                .map_err(io_error)
+        }
+
+        // Call a content endpoint, `arg` going in the `Dropbox-API-Arg` header
+        async fn content(
+            &self,
+            endpoint: &str,
+            arg: Value,
+            body: Vec<u8>,
+            path: &str,
+        ) -> Result<Response, StorageError> {
+            let request = self
+                .client
+                .post(format!("{}/{}", CONTENT_API, endpoint))
+                .header(API_ARG, api_arg(&arg))
+                .header(header::CONTENT_TYPE, "application/octet-stream")
+                .body(body);
+            self.send(request, path).await
+        }
+
+        async fn metadata(&self, path: &str) -> Result<Option<Entry>, StorageError> {
+            let arg = json!({ "path": self.api_path(path)? });
+            match self.rpc("files/get_metadata", arg, path).await {
+                Ok(entry) => Ok(Some(entry)),
+                Err(StorageError::NotFound(_)) => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+
+        async fn download(
+            &self,
+            path: &str,
+            range: Option<String>,
+        ) -> Result<Vec<u8>, StorageError> {
+            let mut request = self
+                .client
+                .post(format!("{}/files/download", CONTENT_API))
+                .header(API_ARG, api_arg(&json!({ "path": self.api_path(path)? })));
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+
+            let response = request
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .map_err(io_error)?;
+            match response.status() {
+                StatusCode::CONFLICT => {
+                    let error: ApiError = response.json().await.map_err(io_error)?;
+                    Err(api_error(path, &error.error_summary))
+                }
+                // The range starts past the end of the file
+                StatusCode::RANGE_NOT_SATISFIABLE => Ok(Vec::new()),
+                _ => Ok(response
+                    .error_for_status()
+                    .map_err(io_error)?
+                    .bytes()
+                    .await
+                    .map_err(io_error)?
+                    .to_vec()),
+            }
+        }
+
+        // Where and how uploads are stored: replacing the file, without
+        // notifying the account's devices of every save
+        fn commit(&self, path: &str) -> Result<Value, StorageError> {
+            Ok(json!({
+                "path": self.api_path(path)?,
+                "mode": "overwrite",
+                "mute": true,
+            }))
+        }
+
+        fn to_item(&self, dir: &str, entry: &Entry) -> StorageItem {
+            let path = join_path(dir, &entry.name);
+            if entry.tag == "folder" {
+                return StorageItem {
+                    node_type: "dir".to_string(),
+                    path,
+                    basename: entry.name.clone(),
+                    extension: None,
+                    mime_type: None,
+                    last_modified: None,
+                    size: None,
+                };
+            }
+
+            StorageItem {
+                node_type: "file".to_string(),
+                path,
+                extension: Path::new(&entry.name)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned()),
+                mime_type: Some(
+                    from_path(&entry.name)
+                        .first_or_octet_stream()
+                        .essence_str()
+                        .to_owned(),
+                ),
+                basename: entry.name.clone(),
+                last_modified: entry.server_modified.as_deref().and_then(parse_time),
+                size: entry.size,
+            }
+        }
+
+        // Storage path of a folder, as listed children are joined onto it
+        fn dir_path(&self, path: &str) -> String {
+            let clean_path = path
+                .strip_prefix(&format!("{}://", self.name))
+                .unwrap_or(path)
+                .trim_matches('/');
+            format!("{}://{}", self.name, clean_path)
+        }
+    }
+
+    #[async_trait]
+    impl StorageAdapter for DropboxStorage {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+            let arg = json!({ "path": self.api_path(path)?, "include_deleted": false });
+            let mut page: FolderList = self.rpc("files/list_folder", arg, path).await?;
+            let dir = self.dir_path(path);
+
+            let mut items = Vec::new();
+            loop {
+                items.extend(page.entries.iter().map(|entry| self.to_item(&dir, entry)));
+                if !page.has_more {
+                    return Ok(items);
+                }
+                let arg = json!({ "cursor": page.cursor });
+                page = self.rpc("files/list_folder/continue", arg, path).await?;
+            }
+        }
+
+        async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+            self.download(path, None).await
+        }
+
+        async fn read_range(
+            &self,
+            path: &str,
+            offset: u64,
+            length: u64,
+        ) -> Result<Vec<u8>, StorageError> {
+            if length == 0 {
+                return Ok(Vec::new());
+            }
+            let end = offset.saturating_add(length - 1);
+            self.download(path, Some(format!("bytes={}-{}", offset, end)))
+                .await
+        }
+
+        async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+            match self.metadata(path).await? {
+                Some(entry) if entry.tag == "file" => Ok(entry.size.unwrap_or_default()),
+                Some(_) => Err(StorageError::InvalidPath(path.to_string())),
+                None => Err(StorageError::NotFound(path.to_string())),
+            }
+        }
+
+        async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+            if self.is_root(path)? {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            if contents.len() <= CHUNK_SIZE {
+                self.content("files/upload", self.commit(path)?, contents, path)
+                    .await?;
+                return Ok(());
+            }
+
+            let chunks: Vec<_> = contents
+                .chunks(CHUNK_SIZE)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect();
+            self.write_stream(path, Box::pin(futures_util::stream::iter(chunks)))
+                .await
+        }
+
+        async fn write_stream(
+            &self,
+            path: &str,
+            mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+        ) -> Result<(), StorageError> {
+            if self.is_root(path)? {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+
+            // Small files go in one request, so the first chunk is gathered first
+            let mut chunk = Vec::new();
+            while chunk.len() <= CHUNK_SIZE {
+                match stream.try_next().await? {
+                    Some(bytes) => chunk.extend_from_slice(&bytes),
+                    None => return self.write(path, chunk).await,
+                }
+            }
+
+            let mut offset = 0;
+            let mut session = None;
+            loop {
+                let done = match stream.try_next().await? {
+                    Some(bytes) => {
+                        chunk.extend_from_slice(&bytes);
+                        false
+                    }
+                    None => true,
+                };
+                while chunk.len() >= CHUNK_SIZE || (done && !chunk.is_empty()) {
+                    let part: Vec<u8> = chunk.drain(..chunk.len().min(CHUNK_SIZE)).collect();
+                    let length = part.len() as u64;
+                    session = Some(match session {
+                        None => {
+                            let response = self
+                                .content(
+                                    "files/upload_session/start",
+                                    json!({ "close": false }),
+                                    part,
+                                    path,
+                                )
+                                .await?;
+                            response
+                                .json::<UploadSession>()
+                                .await
+                                .map_err(io_error)?
+                                .session_id
+                        }
+                        Some(id) => {
+                            let cursor = json!({ "session_id": id, "offset": offset });
+                            self.content(
+                                "files/upload_session/append_v2",
+                                json!({ "cursor": cursor, "close": false }),
+                                part,
+                                path,
+                            )
+                            .await?;
+                            id
+                        }
+                    });
+                    offset += length;
+                }
+                if done {
+                    break;
+                }
+            }
+
+            let cursor = json!({ "session_id": session, "offset": offset });
+            self.content(
+                "files/upload_session/finish",
+                json!({ "cursor": cursor, "commit": self.commit(path)? }),
+                Vec::new(),
+                path,
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), StorageError> {
+            if self.is_root(path)? {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            let arg = json!({ "path": self.api_path(path)? });
+            self.rpc::<Value>("files/delete_v2", arg, path).await?;
+            Ok(())
+        }
+
+        async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+            if self.is_root(path)? {
+                return Ok(());
+            }
+            let arg = json!({ "path": self.api_path(path)?, "autorename": false });
+            match self.rpc::<Value>("files/create_folder_v2", arg, path).await {
+                // Already there, like `create_dir_all`
+                Ok(_) | Err(StorageError::AlreadyExists(_)) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+
+        async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+            if self.is_root(path)? {
+                return Ok(true);
+            }
+            Ok(self.metadata(path).await?.is_some())
+        }
+
+        async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+            if self.is_root(path)? {
+                return Ok(true);
+            }
+            Ok(self
+                .metadata(path)
+                .await?
+                .is_some_and(|entry| entry.tag == "folder"))
+        }
+
+        async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+            if self.is_root(from)? || self.is_root(to)? {
+                return Err(StorageError::InvalidPath(from.to_string()));
+            }
+            let arg = json!({
+                "from_path": self.api_path(from)?,
+                "to_path": self.api_path(to)?,
+                "autorename": false,
+            });
+            self.rpc::<Value>("files/move_v2", arg, from).await?;
+            Ok(())
+        }
+
+        async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+            // Takes no arguments, which Dropbox wants as a `null` body
+            let usage: SpaceUsage = self
+                .rpc("users/get_space_usage", Value::Null, &self.dir_path(""))
+                .await?;
+            Ok(usage.allocation.allocated.map(|total| StorageUsage {
+                total,
+                available: total.saturating_sub(usage.used),
+            }))
+        }
+
+        fn capabilities(&self) -> StorageCapabilities {
+            StorageCapabilities {
+                streaming: true,
+                rename: true,
+                range_read: true,
+                quota: true,
+                ..StorageCapabilities::default()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn storage(root: &str) -> DropboxStorage {
+            DropboxStorage::new(&DropboxConfig {
+                name: "dropbox".to_string(),
+                token: String::new(),
+                root: root.to_string(),
+            })
+        }
+
+        #[test]
+        fn test_api_paths() {
+            let team = storage("/Team/files/");
+            assert_eq!(
+                team.api_path("dropbox://a/b.txt").unwrap(),
+                "/Team/files/a/b.txt"
+            );
+            assert_eq!(team.api_path("dropbox://").unwrap(), "/Team/files");
+            assert!(team.api_path("dropbox://../secret").is_err());
+            assert_eq!(storage("").api_path("dropbox://").unwrap(), "");
+            assert_eq!(storage("").api_path("dropbox://a").unwrap(), "/a");
+
+            assert_eq!(
+                api_arg(&json!({ "path": "/café 🎉" })),
+                r#"{"path":"/caf\u00e9 \ud83c\udf89"}"#
+            );
+            assert!(matches!(
+                api_error("dropbox://a", "path/not_found/."),
+                StorageError::NotFound(_)
+            ));
+            assert!(matches!(
+                api_error("dropbox://a", "path/conflict/folder/.."),
+                StorageError::AlreadyExists(_)
+            ));
+        }
+
+        #[test]
+        fn test_listing() {
+            let storage = storage("");
+            let list: FolderList = serde_json::from_str(
+                r#"{
+                    "entries": [
+                        {".tag": "folder", "name": "guide", "path_display": "/Docs/guide"},
+                        {".tag": "file", "name": "a.md", "size": 42,
+                         "server_modified": "2024-02-29T13:45:30Z"}
+                    ],
+                    "cursor": "c",
+                    "has_more": false
+                }"#,
+            )
+            .unwrap();
+
+            let dir = storage.dir_path("dropbox://docs/");
+            let items: Vec<_> = list
+                .entries
+                .iter()
+                .map(|entry| storage.to_item(&dir, entry))
+                .collect();
+            assert_eq!(items[0].node_type, "dir");
+            assert_eq!(items[0].path, "dropbox://docs/guide");
+            assert_eq!(items[1].path, "dropbox://docs/a.md");
+            assert_eq!(items[1].size, Some(42));
+            assert_eq!(items[1].last_modified, Some(1_709_214_330));
+            assert_eq!(items[1].mime_type.as_deref(), Some("text/markdown"));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use super::{
+    relative_key, StorageAdapter, StorageCapabilities, StorageError, StorageItem, Storages,
+};
+use async_trait::async_trait;
+use include_dir::{Dir, DirEntry};
+use mime_guess::from_path;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Read-only storage over a directory embedded at compile time with `include_dir!`
+#[derive(Debug)]
+pub struct EmbeddedStorage {
+    scheme: String,
+    dir: &'static Dir<'static>,
+}
+
+impl EmbeddedStorage {
+    pub fn new(scheme: &str, dir: &'static Dir<'static>) -> Self {
+        Self {
+            scheme: scheme.to_string(),
+            dir,
+        }
+    }
+
+    pub fn setup(scheme: &str, dir: &'static Dir<'static>) -> Storages {
+        let mut storages = HashMap::new();
+        let storage = Arc::new(Self::new(scheme, dir)) as Arc<dyn StorageAdapter>;
+        storages.insert(storage.name(), storage);
+        Arc::new(storages)
+    }
+
+    fn get_entry(&self, path: &str) -> Result<Option<&'static DirEntry<'static>>, StorageError> {
+        let clean_path = relative_key(&self.scheme, path)?;
+        Ok(self.dir.get_entry(clean_path))
+    }
+
+    fn to_storage_item(&self, entry: &DirEntry<'static>) -> StorageItem {
+        let entry_path = entry.path();
+        let file = entry.as_file();
+
+        StorageItem {
+            node_type: if file.is_some() { "file" } else { "dir" }.to_string(),
+            path: format!("{}://{}", self.scheme, entry_path.to_string_lossy()),
+            basename: entry_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            extension: file.and_then(|_| {
+                entry_path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned())
+            }),
+            mime_type: file.map(|_| {
+                from_path(entry_path)
+                    .first_or_octet_stream()
+                    .essence_str()
+                    .to_owned()
+            }),
+            last_modified: None,
+            size: file.map(|file| file.contents().len() as u64),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for EmbeddedStorage {
+    fn name(&self) -> String {
+        self.scheme.clone()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let entries = if relative_key(&self.scheme, path)?.is_empty() {
+            self.dir.entries()
+        } else {
+            match self.get_entry(path)? {
+                Some(DirEntry::Dir(dir)) => dir.entries(),
+                Some(DirEntry::File(_)) => {
+                    return Err(StorageError::NotADirectory(path.to_string()))
+                }
+                None => return Err(StorageError::NotFound(path.to_string())),
+            }
+        };
+
+        Ok(entries
+            .iter()
+            .map(|entry| self.to_storage_item(entry))
+            .collect())
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        match self.get_entry(path)? {
+            Some(DirEntry::File(file)) => Ok(file.contents().to_vec()),
+            _ => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn write(&self, path: &str, _contents: Vec<u8>) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        Ok(relative_key(&self.scheme, path)?.is_empty() || self.get_entry(path)?.is_some())
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        Ok(relative_key(&self.scheme, path)?.is_empty()
+            || matches!(self.get_entry(path)?, Some(DirEntry::Dir(_))))
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            read_only: true,
+            ..StorageCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use include_dir::File;
+
+    static ASSETS: Dir<'static> = Dir::new(
+        "",
+        &[
+            DirEntry::Dir(Dir::new(
+                "docs",
+                &[DirEntry::File(File::new("docs/readme.md", b"# Demo"))],
+            )),
+            DirEntry::File(File::new("hello.txt", b"Hello")),
+        ],
+    );
+
+    #[tokio::test]
+    async fn test_embedded_storage() {
+        let storage = EmbeddedStorage::new("demo", &ASSETS);
+
+        // Test list contents
+        let entries = storage.list_contents("demo://").await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "demo://docs");
+        assert_eq!(entries[0].node_type, "dir");
+        assert_eq!(entries[1].path, "demo://hello.txt");
+        assert_eq!(entries[1].size, Some(5));
+        assert_eq!(entries[1].mime_type.as_deref(), Some("text/plain"));
+
+        let entries = storage.list_contents("demo://docs").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].basename, "readme.md");
+
+        // Test read and exists
+        assert_eq!(
+            storage.read("demo://docs/readme.md").await.unwrap(),
+            b"# Demo"
+        );
+        assert!(storage.exists("demo://hello.txt").await.unwrap());
+        assert!(!storage.exists("demo://missing.txt").await.unwrap());
+        assert!(storage.read("demo://../hello.txt").await.is_err());
+
+        // Test metadata, looked up in the parent's listing
+        let item = storage.metadata("demo://docs/readme.md").await.unwrap();
+        assert_eq!(item.path, "demo://docs/readme.md");
+        assert_eq!(item.size, Some(6));
+        assert_eq!(storage.metadata("demo://").await.unwrap().node_type, "dir");
+        assert!(matches!(
+            storage.metadata("demo://docs/missing.md").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        // Test writes are rejected
+        assert!(matches!(
+            storage.write("demo://new.txt", vec![]).await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.delete("demo://hello.txt").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use base64::prelude::*;
+use mime_guess::from_path;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{
+    join_path, StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage,
+};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+fn decrypt_error(path: &str) -> StorageError {
+    StorageError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Failed to decrypt {}", path),
+    ))
+}
+
+// Encrypts contents with AES-256-GCM before they reach the wrapped adapter,
+// stored as a random nonce followed by the ciphertext. With `with_encrypted_names`
+// every path segment is encrypted too, using a nonce derived from the segment so
+// the same name always maps to the same stored name.
+pub struct EncryptedStorage {
+    inner: Arc<dyn StorageAdapter>,
+    contents: Aes256Gcm,
+    names: Option<Aes256Gcm>,
+}
+
+impl EncryptedStorage {
+    pub fn new(inner: Arc<dyn StorageAdapter>, key: &[u8; 32]) -> Self {
+        Self {
+            inner,
+            contents: Aes256Gcm::new(key.into()),
+            names: None,
+        }
+    }
+
+    // Encrypted names are base64, about 1.4 times longer than the plain ones plus 38 bytes
+    pub fn with_encrypted_names(mut self, key: &[u8; 32]) -> Self {
+        // Separate key so name nonces can't collide with content nonces
+        let mut hasher = Sha256::new();
+        hasher.update(b"vuefinder-names");
+        hasher.update(key);
+        let name_key: [u8; 32] = hasher.finalize().into();
+        self.names = Some(Aes256Gcm::new(&name_key.into()));
+        self
+    }
+
+    fn encrypt_name(names: &Aes256Gcm, name: &str) -> String {
+        let digest = Sha256::digest(name.as_bytes());
+        let nonce = Nonce::from_slice(&digest[..NONCE_LEN]);
+        let mut stored = nonce.to_vec();
+        stored.extend(
+            names
+                .encrypt(nonce, name.as_bytes())
+                .expect("AES-GCM encryption of a short buffer"),
+        );
+        BASE64_URL_SAFE_NO_PAD.encode(stored)
+    }
+
+    fn decrypt_name(names: &Aes256Gcm, stored: &str) -> Option<String> {
+        let stored = BASE64_URL_SAFE_NO_PAD.decode(stored).ok()?;
+        if stored.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = stored.split_at(NONCE_LEN);
+        let name = names.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(name).ok()
+    }
+
+    // Path on the wrapped adapter, `scheme://` is kept as-is
+    fn inner_path(&self, path: &str) -> String {
+        let Some(names) = &self.names else {
+            return path.to_string();
+        };
+        let (scheme, key) = match path.split_once("://") {
+            Some((scheme, key)) => (format!("{}://", scheme), key),
+            None => (String::new(), path),
+        };
+        let key = key
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| Self::encrypt_name(names, segment))
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("{}{}", scheme, key)
+    }
+
+    fn encrypt(&self, contents: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut stored = nonce.to_vec();
+        stored.extend(
+            self.contents
+                .encrypt(&nonce, contents)
+                .expect("AES-GCM encryption of an in-memory buffer"),
+        );
+        stored
+    }
+
+    fn decrypt(&self, path: &str, stored: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if stored.len() < NONCE_LEN + TAG_LEN {
+            return Err(decrypt_error(path));
+        }
+        let (nonce, ciphertext) = stored.split_at(NONCE_LEN);
+        self.contents
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| decrypt_error(path))
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for EncryptedStorage {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let items = self.inner.list_contents(&self.inner_path(path)).await?;
+        let mut decrypted = Vec::with_capacity(items.len());
+        for mut item in items {
+            if let Some(names) = &self.names {
+                // Entries that weren't written through this adapter are left out
+                let Some(basename) = Self::decrypt_name(names, &item.basename) else {
+                    continue;
+                };
+                item.path = join_path(path, &basename);
+                item.basename = basename;
+                if item.node_type == "file" {
+                    item.extension = Path::new(&item.basename)
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().into_owned());
+                    item.mime_type = Some(
+                        from_path(&item.basename)
+                            .first_or_octet_stream()
+                            .essence_str()
+                            .to_owned(),
+                    );
+                }
+            }
+            if item.node_type == "file" {
+                item.size = item
+                    .size
+                    .map(|size| size.saturating_sub((NONCE_LEN + TAG_LEN) as u64));
+            }
+            decrypted.push(item);
+        }
+        Ok(decrypted)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let stored = self.inner.read(&self.inner_path(path)).await?;
+        self.decrypt(path, &stored)
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        self.inner
+            .write(&self.inner_path(path), self.encrypt(&contents))
+            .await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        self.inner.delete(&self.inner_path(path)).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        self.inner.create_dir(&self.inner_path(path)).await
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.exists(&self.inner_path(path)).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.inner
+            .rename(&self.inner_path(from), &self.inner_path(to))
+            .await
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        match &self.names {
+            Some(_) => self.inner.validate_key(&self.inner_path(name)),
+            None => self.inner.validate_key(name),
+        }
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let size = self.inner.file_size(&self.inner_path(path)).await?;
+        Ok(size.saturating_sub((NONCE_LEN + TAG_LEN) as u64))
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.is_dir(&self.inner_path(path)).await
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.inner.usage().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        // Contents are sealed whole, so neither streamed nor read in parts
+        StorageCapabilities {
+            streaming: false,
+            range_read: false,
+            ..self.inner.capabilities()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_encrypted_storage() {
+        let key = [7u8; 32];
+        let inner = Arc::new(MemoryStorage::new("vault"));
+        let storage = EncryptedStorage::new(inner.clone(), &key).with_encrypted_names(&key);
+
+        storage
+            .write("vault://docs/secret.txt", b"top secret".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.read("vault://docs/secret.txt").await.unwrap(),
+            b"top secret"
+        );
+        assert_eq!(
+            storage.file_size("vault://docs/secret.txt").await.unwrap(),
+            10
+        );
+
+        let items = storage.list_contents("vault://docs").await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].path, "vault://docs/secret.txt");
+        assert_eq!(items[0].mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(items[0].size, Some(10));
+
+        // Neither names nor contents reach the wrapped adapter in the clear
+        let stored = inner.list_contents("vault://").await.unwrap();
+        assert_ne!(stored[0].basename, "docs");
+        let stored = inner.list_contents(&stored[0].path).await.unwrap();
+        let ciphertext = inner.read(&stored[0].path).await.unwrap();
+        assert!(!ciphertext.windows(10).any(|w| w == b"top secret"));
+
+        // A different key can't read it back
+        let other = EncryptedStorage::new(inner.clone(), &[8u8; 32]);
+        assert!(other.read(&stored[0].path).await.is_err());
+    }
+}
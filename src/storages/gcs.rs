@@ -0,0 +1,545 @@
+use serde::Deserialize;
+
+// A bucket registered from the `gcs` list of the JSON config file
+#[derive(Clone, Debug, Deserialize)]
+pub struct GcsConfig {
+    // Adapter name and path scheme
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub bucket: String,
+    // Object name prefix the adapter is rooted at, e.g. `team/files/`
+    #[serde(default)]
+    pub prefix: String,
+    // Path to a service account key or an `authorized_user` credentials file
+    pub credentials: String,
+}
+
+fn default_name() -> String {
+    "gcs".to_string()
+}
+
+#[cfg(feature = "gcs")]
+pub use adapter::GcsStorage;
+
+#[cfg(feature = "gcs")]
+mod adapter {
+    use super::GcsConfig;
+    use crate::storages::checksum::{digest_stream, ChecksumAlgorithm};
+    use crate::storages::google_auth::GoogleAuth;
+    use crate::storages::{
+        StorageAdapter, StorageCapabilities, StorageError, StorageItem, Storages,
+    };
+    use async_trait::async_trait;
+    use base64::prelude::*;
+    use mime_guess::from_path;
+    use reqwest::{header, Client, StatusCode, Url};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::path::{Component, Path};
+    use std::sync::Arc;
+
+    const API: &str = "https://storage.googleapis.com/storage/v1";
+    const UPLOAD_API: &str = "https://storage.googleapis.com/upload/storage/v1";
+    const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+
+    #[derive(Deserialize)]
+    struct Object {
+        name: String,
+        // GCS sends 64-bit integers as strings
+        size: Option<String>,
+        updated: Option<String>,
+        // Base64, missing for composite objects
+        #[serde(rename = "md5Hash")]
+        md5_hash: Option<String>,
+    }
+
+    #[derive(Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ObjectList {
+        #[serde(default)]
+        items: Vec<Object>,
+        #[serde(default)]
+        prefixes: Vec<String>,
+        next_page_token: Option<String>,
+    }
+
+    fn io_error(error: impl std::fmt::Display) -> StorageError {
+        StorageError::Io(std::io::Error::other(error.to_string()))
+    }
+
+    fn parse_time(value: &str) -> Option<u64> {
+        time::OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339)
+            .ok()
+            .and_then(|time| u64::try_from(time.unix_timestamp()).ok())
+    }
+
+    // Google Cloud Storage bucket. Directories are the `/`-separated prefixes of
+    // object names, `create_dir` stores an empty `name/` placeholder object.
+    pub struct GcsStorage {
+        name: String,
+        bucket: String,
+        prefix: String,
+        auth: GoogleAuth,
+        client: Client,
+    }
+
+    impl GcsStorage {
+        pub fn new(config: &GcsConfig) -> Result<Self, StorageError> {
+            let mut prefix = config.prefix.trim_matches('/').to_string();
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+
+            Ok(Self {
+                name: config.name.clone(),
+                bucket: config.bucket.clone(),
+                prefix,
+                auth: GoogleAuth::from_file(&config.credentials, SCOPE)?,
+                client: Client::new(),
+            })
+        }
+
+        pub fn setup(config: &GcsConfig) -> Result<Storages, StorageError> {
+            let mut storages = HashMap::new();
+            let storage = Arc::new(Self::new(config)?) as Arc<dyn StorageAdapter>;
+            storages.insert(storage.name(), storage);
+            Ok(Arc::new(storages))
+        }
+
+        // Object name for a storage path, rejecting anything but plain relative paths
+        fn object_name(&self, path: &str) -> Result<String, StorageError> {
+            let clean_path = path
+                .strip_prefix(&format!("{}://", self.name))
+                .unwrap_or(path)
+                .trim_matches('/');
+
+            if Path::new(clean_path)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+            {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+
+            Ok(format!("{}{}", self.prefix, clean_path))
+        }
+
+        // Prefix listing the children of a directory object name
+        fn dir_prefix(object: &str) -> String {
+            if object.is_empty() || object.ends_with('/') {
+                object.to_string()
+            } else {
+                format!("{}/", object)
+            }
+        }
+
+        fn storage_path(&self, object: &str) -> String {
+            format!(
+                "{}://{}",
+                self.name,
+                object
+                    .strip_prefix(&self.prefix)
+                    .unwrap_or(object)
+                    .trim_end_matches('/')
+            )
+        }
+
+        fn object_url(&self, api: &str, object: &str) -> Result<Url, StorageError> {
+            let mut url = Url::parse(api).map_err(io_error)?;
+            url.path_segments_mut()
+                .map_err(|_| io_error("Invalid API URL"))?
+                .extend(["b", &self.bucket, "o", object]);
+            Ok(url)
+        }
+
+        fn bucket_url(&self, api: &str) -> Result<Url, StorageError> {
+            let mut url = Url::parse(api).map_err(io_error)?;
+            url.path_segments_mut()
+                .map_err(|_| io_error("Invalid API URL"))?
+                .extend(["b", &self.bucket, "o"]);
+            Ok(url)
+        }
+
+        // One page of objects below `prefix`, grouped by `/` unless `recursive`
+        async fn list_page(
+            &self,
+            prefix: &str,
+            recursive: bool,
+            max_results: Option<u32>,
+            page_token: Option<&str>,
+        ) -> Result<ObjectList, StorageError> {
+            let mut query = vec![("prefix", prefix.to_string())];
+            if !recursive {
+                query.push(("delimiter", "/".to_string()));
+            }
+            if let Some(max_results) = max_results {
+                query.push(("maxResults", max_results.to_string()));
+            }
+            if let Some(page_token) = page_token {
+                query.push(("pageToken", page_token.to_string()));
+            }
+
+            self.client
+                .get(self.bucket_url(API)?)
+                .bearer_auth(self.auth.access_token().await?)
+                .query(&query)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(io_error)?
+                .json()
+                .await
+                .map_err(io_error)
+        }
+
+        async fn list_all(
+            &self,
+            prefix: &str,
+            recursive: bool,
+        ) -> Result<ObjectList, StorageError> {
+            let mut all = ObjectList::default();
+            let mut page_token = None;
+            loop {
+                let page = self
+                    .list_page(prefix, recursive, None, page_token.as_deref())
+                    .await?;
+                all.items.extend(page.items);
+                all.prefixes.extend(page.prefixes);
+                match page.next_page_token {
+                    Some(token) => page_token = Some(token),
+                    None => return Ok(all),
+                }
+            }
+        }
+
+        async fn get_object(&self, object: &str) -> Result<Option<Object>, StorageError> {
+            let response = self
+                .client
+                .get(self.object_url(API, object)?)
+                .bearer_auth(self.auth.access_token().await?)
+                .send()
+                .await
+                .map_err(io_error)?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            response
+                .error_for_status()
+                .map_err(io_error)?
+                .json()
+                .await
+                .map(Some)
+                .map_err(io_error)
+        }
+
+        async fn upload(&self, object: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+            let mime = from_path(object).first_or_octet_stream();
+            self.client
+                .post(self.bucket_url(UPLOAD_API)?)
+                .bearer_auth(self.auth.access_token().await?)
+                .query(&[("uploadType", "media"), ("name", object)])
+                .header(header::CONTENT_TYPE, mime.essence_str())
+                .body(contents)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(io_error)?;
+            Ok(())
+        }
+
+        async fn delete_object(&self, object: &str) -> Result<bool, StorageError> {
+            let response = self
+                .client
+                .delete(self.object_url(API, object)?)
+                .bearer_auth(self.auth.access_token().await?)
+                .send()
+                .await
+                .map_err(io_error)?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(false);
+            }
+            response.error_for_status().map_err(io_error)?;
+            Ok(true)
+        }
+
+        async fn download(
+            &self,
+            path: &str,
+            range: Option<String>,
+        ) -> Result<Vec<u8>, StorageError> {
+            let object = self.object_name(path)?;
+            let mut request = self
+                .client
+                .get(self.object_url(API, &object)?)
+                .bearer_auth(self.auth.access_token().await?)
+                .query(&[("alt", "media")]);
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+
+            let response = request.send().await.map_err(io_error)?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Err(StorageError::NotFound(path.to_string())),
+                // The range starts past the end of the object
+                StatusCode::RANGE_NOT_SATISFIABLE => Ok(Vec::new()),
+                _ => Ok(response
+                    .error_for_status()
+                    .map_err(io_error)?
+                    .bytes()
+                    .await
+                    .map_err(io_error)?
+                    .to_vec()),
+            }
+        }
+
+        fn to_storage_item(&self, object: &Object) -> StorageItem {
+            let basename = object
+                .name
+                .rsplit('/')
+                .next()
+                .unwrap_or(&object.name)
+                .to_string();
+
+            StorageItem {
+                node_type: "file".to_string(),
+                path: self.storage_path(&object.name),
+                extension: Path::new(&basename)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned()),
+                mime_type: Some(
+                    from_path(&basename)
+                        .first_or_octet_stream()
+                        .essence_str()
+                        .to_owned(),
+                ),
+                basename,
+                last_modified: object.updated.as_deref().and_then(parse_time),
+                size: object.size.as_deref().and_then(|size| size.parse().ok()),
+            }
+        }
+
+        fn to_dir_item(&self, prefix: &str) -> StorageItem {
+            let name = prefix.trim_end_matches('/');
+            StorageItem {
+                node_type: "dir".to_string(),
+                path: self.storage_path(name),
+                basename: name.rsplit('/').next().unwrap_or(name).to_string(),
+                extension: None,
+                mime_type: None,
+                last_modified: None,
+                size: None,
+            }
+        }
+
+        // Entries of one listing page, leaving out the directory's own placeholder
+        fn to_items(&self, prefix: &str, list: &ObjectList) -> Vec<StorageItem> {
+            list.prefixes
+                .iter()
+                .map(|dir| self.to_dir_item(dir))
+                .chain(
+                    list.items
+                        .iter()
+                        .filter(|object| object.name != prefix)
+                        .map(|object| self.to_storage_item(object)),
+                )
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl StorageAdapter for GcsStorage {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+            let object = self.object_name(path)?;
+            let prefix = Self::dir_prefix(&object);
+            if !self.is_dir(path).await? {
+                return Err(match self.get_object(&object).await? {
+                    Some(_) => StorageError::NotADirectory(path.to_string()),
+                    None => StorageError::NotFound(path.to_string()),
+                });
+            }
+
+            let list = self.list_all(&prefix, false).await?;
+            Ok(self.to_items(&prefix, &list))
+        }
+
+        async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+            self.download(path, None).await
+        }
+
+        async fn read_range(
+            &self,
+            path: &str,
+            offset: u64,
+            length: u64,
+        ) -> Result<Vec<u8>, StorageError> {
+            if length == 0 {
+                return Ok(Vec::new());
+            }
+            let end = offset.saturating_add(length - 1);
+            self.download(path, Some(format!("bytes={}-{}", offset, end)))
+                .await
+        }
+
+        async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+            match self.get_object(&self.object_name(path)?).await? {
+                Some(object) => Ok(object
+                    .size
+                    .as_deref()
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or_default()),
+                None => Err(StorageError::NotFound(path.to_string())),
+            }
+        }
+
+        // GCS keeps the MD5 of every object that isn't composed from others
+        async fn checksum(
+            &self,
+            path: &str,
+            algorithm: ChecksumAlgorithm,
+        ) -> Result<String, StorageError> {
+            if algorithm == ChecksumAlgorithm::Md5 {
+                let object = self
+                    .get_object(&self.object_name(path)?)
+                    .await?
+                    .ok_or_else(|| StorageError::NotFound(path.to_string()))?;
+                if let Some(md5) = object
+                    .md5_hash
+                    .and_then(|md5| BASE64_STANDARD.decode(md5).ok())
+                {
+                    return Ok(hex::encode(md5));
+                }
+            }
+            digest_stream(self.read_stream(path).await?, algorithm).await
+        }
+
+        async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+            let object = self.object_name(path)?;
+            if object == self.prefix {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+
+            self.upload(&object, contents).await
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), StorageError> {
+            let object = self.object_name(path)?;
+            if object == self.prefix {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+
+            // A file, or every object below a directory including its placeholder
+            let mut deleted = self.delete_object(&object).await?;
+            let prefix = Self::dir_prefix(&object);
+            for child in self.list_all(&prefix, true).await?.items {
+                deleted |= self.delete_object(&child.name).await?;
+            }
+
+            if deleted {
+                Ok(())
+            } else {
+                Err(StorageError::NotFound(path.to_string()))
+            }
+        }
+
+        async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+            let object = self.object_name(path)?;
+            if object == self.prefix {
+                return Ok(());
+            }
+            self.upload(&Self::dir_prefix(&object), Vec::new()).await
+        }
+
+        async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+            let object = self.object_name(path)?;
+            Ok(self.get_object(&object).await?.is_some() || self.is_dir(path).await?)
+        }
+
+        async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+            let object = self.object_name(path)?;
+            if object == self.prefix {
+                return Ok(true);
+            }
+            let page = self
+                .list_page(&Self::dir_prefix(&object), true, Some(1), None)
+                .await?;
+            Ok(!page.items.is_empty())
+        }
+
+        fn capabilities(&self) -> StorageCapabilities {
+            StorageCapabilities {
+                range_read: true,
+                ..StorageCapabilities::default()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::storages::google_auth::Credentials;
+
+        fn storage(prefix: &str) -> GcsStorage {
+            GcsStorage {
+                name: "gcs".to_string(),
+                bucket: "bucket".to_string(),
+                prefix: prefix.to_string(),
+                auth: GoogleAuth::new(
+                    Credentials::AuthorizedUser {
+                        client_id: String::new(),
+                        client_secret: String::new(),
+                        refresh_token: String::new(),
+                    },
+                    SCOPE,
+                ),
+                client: Client::new(),
+            }
+        }
+
+        #[test]
+        fn test_object_names() {
+            let storage = storage("team/");
+            assert_eq!(
+                storage.object_name("gcs://a/b.txt").unwrap(),
+                "team/a/b.txt"
+            );
+            assert_eq!(storage.object_name("gcs://").unwrap(), "team/");
+            assert!(storage.object_name("gcs://../secret").is_err());
+            assert_eq!(storage.storage_path("team/a/b.txt"), "gcs://a/b.txt");
+            assert_eq!(storage.storage_path("team/a/"), "gcs://a");
+
+            let url = storage.object_url(API, "team/a b.txt").unwrap();
+            assert_eq!(
+                url.as_str(),
+                "https://storage.googleapis.com/storage/v1/b/bucket/o/team%2Fa%20b.txt"
+            );
+        }
+
+        #[test]
+        fn test_listing() {
+            let storage = storage("");
+            let list: ObjectList = serde_json::from_str(
+                r#"{
+                    "prefixes": ["docs/guide/"],
+                    "items": [
+                        {"name": "docs/", "size": "0"},
+                        {"name": "docs/a.md", "size": "42", "updated": "2024-02-29T13:45:30.123Z"}
+                    ]
+                }"#,
+            )
+            .unwrap();
+
+            let items = storage.to_items("docs/", &list);
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[0].node_type, "dir");
+            assert_eq!(items[0].path, "gcs://docs/guide");
+            assert_eq!(items[0].basename, "guide");
+            assert_eq!(items[1].path, "gcs://docs/a.md");
+            assert_eq!(items[1].size, Some(42));
+            assert_eq!(items[1].last_modified, Some(1_709_214_330));
+            assert_eq!(items[1].mime_type.as_deref(), Some("text/markdown"));
+        }
+    }
+}
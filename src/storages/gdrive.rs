@@ -0,0 +1,803 @@
+use serde::Deserialize;
+
+// A Google Drive folder mounted from the `storages` config section
+#[derive(Clone, Debug, Deserialize)]
+pub struct GoogleDriveConfig {
+    // Adapter name and path scheme
+    #[serde(default = "default_name")]
+    pub name: String,
+    // Path to a service account key or an `authorized_user` credentials file
+    pub credentials: String,
+    // ID of the folder the adapter is rooted at, `root` being the account's My Drive
+    #[serde(default = "default_root")]
+    pub root: String,
+}
+
+fn default_name() -> String {
+    "gdrive".to_string()
+}
+
+fn default_root() -> String {
+    "root".to_string()
+}
+
+#[cfg(feature = "gdrive")]
+pub use adapter::GoogleDriveStorage;
+
+#[cfg(feature = "gdrive")]
+mod adapter {
+    use super::GoogleDriveConfig;
+    use crate::storages::checksum::{digest_stream, ChecksumAlgorithm};
+    use crate::storages::google_auth::GoogleAuth;
+    use crate::storages::{
+        join_path, StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage,
+    };
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use futures_util::stream::BoxStream;
+    use futures_util::TryStreamExt;
+    use lru::LruCache;
+    use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::num::NonZeroUsize;
+    use std::path::{Component, Path};
+    use std::sync::Mutex;
+
+    const API: &str = "https://www.googleapis.com/drive/v3";
+    const UPLOAD_API: &str = "https://www.googleapis.com/upload/drive/v3";
+    const SCOPE: &str = "https://www.googleapis.com/auth/drive";
+    const FOLDER: &str = "application/vnd.google-apps.folder";
+    const FIELDS: &str = "id,name,mimeType,size,modifiedTime";
+    // Uploads are sent in chunks of this size, a multiple of the 256 KiB Drive requires
+    const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+    // Paths whose IDs are remembered
+    const CACHE_SIZE: usize = 10_000;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct File {
+        id: String,
+        name: String,
+        mime_type: String,
+        // Drive sends 64-bit integers as strings
+        size: Option<String>,
+        modified_time: Option<String>,
+    }
+
+    // Digests Drive keeps of uploaded files, not of Google Docs
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Checksums {
+        md5_checksum: Option<String>,
+        sha1_checksum: Option<String>,
+        sha256_checksum: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FileList {
+        #[serde(default)]
+        files: Vec<File>,
+        next_page_token: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct About {
+        storage_quota: StorageQuota,
+    }
+
+    #[derive(Deserialize)]
+    struct StorageQuota {
+        // Missing for unlimited accounts
+        limit: Option<String>,
+        usage: Option<String>,
+    }
+
+    // What a path points to on Drive
+    #[derive(Clone, Debug, PartialEq)]
+    struct Node {
+        id: String,
+        mime_type: String,
+    }
+
+    impl Node {
+        fn is_folder(&self) -> bool {
+            self.mime_type == FOLDER
+        }
+    }
+
+    impl From<&File> for Node {
+        fn from(file: &File) -> Self {
+            Node {
+                id: file.id.clone(),
+                mime_type: file.mime_type.clone(),
+            }
+        }
+    }
+
+    fn io_error(error: impl std::fmt::Display) -> StorageError {
+        StorageError::Io(std::io::Error::other(error.to_string()))
+    }
+
+    fn parse_time(value: &str) -> Option<u64> {
+        time::OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339)
+            .ok()
+            .and_then(|time| u64::try_from(time.unix_timestamp()).ok())
+    }
+
+    // A string in a Drive search query
+    fn literal(value: &str) -> String {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+
+    // Parent key and name of a key, `("a", "b")` for `a/b`
+    fn split(key: &str) -> (&str, &str) {
+        key.rsplit_once('/').unwrap_or(("", key))
+    }
+
+    // Google Drive folder. Drive addresses items by ID, so paths are looked up
+    // one folder at a time and the IDs found are remembered, forgotten again
+    // when the item is changed through the adapter or Drive answers 404. Drive
+    // allows several items with the same name in a folder, the oldest wins.
+    pub struct GoogleDriveStorage {
+        name: String,
+        root: String,
+        auth: GoogleAuth,
+        client: Client,
+        // Keyed by the path below the root, e.g. `docs/a.md`
+        ids: Mutex<LruCache<String, Node>>,
+    }
+
+    impl GoogleDriveStorage {
+        pub fn new(config: &GoogleDriveConfig) -> Result<Self, StorageError> {
+            Ok(Self::with_auth(
+                config,
+                GoogleAuth::from_file(&config.credentials, SCOPE)?,
+            ))
+        }
+
+        fn with_auth(config: &GoogleDriveConfig, auth: GoogleAuth) -> Self {
+            Self {
+                name: config.name.clone(),
+                root: config.root.clone(),
+                auth,
+                client: Client::new(),
+                ids: Mutex::new(LruCache::new(
+                    NonZeroUsize::new(CACHE_SIZE).expect("cache size is not zero"),
+                )),
+            }
+        }
+
+        // Path below the root, rejecting anything but plain relative paths
+        fn key(&self, path: &str) -> Result<String, StorageError> {
+            let clean_path = path
+                .strip_prefix(&format!("{}://", self.name))
+                .unwrap_or(path)
+                .trim_matches('/');
+
+            if Path::new(clean_path)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+            {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            Ok(clean_path.to_string())
+        }
+
+        fn storage_path(&self, key: &str) -> String {
+            format!("{}://{}", self.name, key)
+        }
+
+        fn root_node(&self) -> Node {
+            Node {
+                id: self.root.clone(),
+                mime_type: FOLDER.to_string(),
+            }
+        }
+
+        fn cached(&self, key: &str) -> Option<Node> {
+            self.ids
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(key)
+                .cloned()
+        }
+
+        fn remember(&self, key: &str, node: Node) {
+            self.ids
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .put(key.to_string(), node);
+        }
+
+        // Forget `key` and everything below it
+        fn forget(&self, key: &str) {
+            let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+            let prefix = format!("{}/", key);
+            let stale: Vec<String> = ids
+                .iter()
+                .map(|(cached, _)| cached)
+                .filter(|cached| *cached == key || cached.starts_with(&prefix))
+                .cloned()
+                .collect();
+            for cached in stale {
+                ids.pop(&cached);
+            }
+        }
+
+        // Send a request, answering 404 with `NotFound` for `path`
+        async fn send(
+            &self,
+            request: RequestBuilder,
+            path: &str,
+        ) -> Result<Response, StorageError> {
+            let response = request
+                .bearer_auth(self.auth.access_token().await?)
+                .send()
+                .await
+                .map_err(io_error)?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(StorageError::NotFound(path.to_string()));
+            }
+            response.error_for_status().map_err(io_error)
+        }
+
+        // Forget `key` when Drive no longer knows its cached ID
+        fn stale<T>(&self, key: &str, result: Result<T, StorageError>) -> Result<T, StorageError> {
+            if let Err(StorageError::NotFound(_)) = &result {
+                self.forget(key);
+            }
+            result
+        }
+
+        // Items in a folder, only those called `name` when given
+        async fn children(
+            &self,
+            folder: &str,
+            name: Option<&str>,
+        ) -> Result<Vec<File>, StorageError> {
+            let mut query = format!("{} in parents and trashed = false", literal(folder));
+            if let Some(name) = name {
+                query.push_str(&format!(" and name = {}", literal(name)));
+            }
+
+            let mut files = Vec::new();
+            let mut page_token = None;
+            loop {
+                let mut request = self.client.get(format!("{}/files", API)).query(&[
+                    ("q", query.as_str()),
+                    ("fields", &format!("nextPageToken,files({})", FIELDS)),
+                    ("orderBy", "createdTime"),
+                    ("pageSize", "1000"),
+                    ("supportsAllDrives", "true"),
+                    ("includeItemsFromAllDrives", "true"),
+                ]);
+                if let Some(page_token) = &page_token {
+                    request = request.query(&[("pageToken", page_token)]);
+                }
+                let page: FileList = self
+                    .send(request, folder)
+                    .await?
+                    .json()
+                    .await
+                    .map_err(io_error)?;
+                files.extend(page.files);
+                match page.next_page_token {
+                    Some(token) => page_token = Some(token),
+                    None => return Ok(files),
+                }
+            }
+        }
+
+        // The item at `key`, looked up from the nearest remembered folder
+        async fn resolve(&self, key: &str) -> Result<Option<Node>, StorageError> {
+            let mut node = self.root_node();
+            let mut current = String::new();
+            for name in key.split('/').filter(|name| !name.is_empty()) {
+                current = if current.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", current, name)
+                };
+                if let Some(cached) = self.cached(&current) {
+                    node = cached;
+                    continue;
+                }
+                if !node.is_folder() {
+                    return Ok(None);
+                }
+                let children = self.children(&node.id, Some(name)).await;
+                let Some(file) = self.stale(split(&current).0, children)?.into_iter().next() else {
+                    return Ok(None);
+                };
+                node = Node::from(&file);
+                self.remember(&current, node.clone());
+            }
+            Ok(Some(node))
+        }
+
+        async fn node(&self, path: &str) -> Result<(String, Node), StorageError> {
+            let key = self.key(path)?;
+            match self.resolve(&key).await? {
+                Some(node) => Ok((key, node)),
+                None => Err(StorageError::NotFound(path.to_string())),
+            }
+        }
+
+        // The folder a new item at `key` goes in
+        async fn parent(&self, path: &str, key: &str) -> Result<Node, StorageError> {
+            match self.resolve(split(key).0).await? {
+                Some(node) if node.is_folder() => Ok(node),
+                Some(_) => Err(StorageError::NotADirectory(path.to_string())),
+                None => Err(StorageError::NotFound(path.to_string())),
+            }
+        }
+
+        async fn download(
+            &self,
+            path: &str,
+            range: Option<String>,
+        ) -> Result<Vec<u8>, StorageError> {
+            let (key, node) = self.node(path).await?;
+            if node.is_folder() {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            // Docs, Sheets and the like only exist as exports
+            if node.mime_type.starts_with("application/vnd.google-apps.") {
+                return Err(StorageError::Unsupported(format!(
+                    "{} is a {} document",
+                    path, node.mime_type
+                )));
+            }
+
+            let mut request = self
+                .client
+                .get(format!("{}/files/{}", API, node.id))
+                .query(&[("alt", "media"), ("supportsAllDrives", "true")]);
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+            let response = request
+                .bearer_auth(self.auth.access_token().await?)
+                .send()
+                .await
+                .map_err(io_error)?;
+            match response.status() {
+                StatusCode::NOT_FOUND => {
+                    self.forget(&key);
+                    Err(StorageError::NotFound(path.to_string()))
+                }
+                // The range starts past the end of the file
+                StatusCode::RANGE_NOT_SATISFIABLE => Ok(Vec::new()),
+                _ => Ok(response
+                    .error_for_status()
+                    .map_err(io_error)?
+                    .bytes()
+                    .await
+                    .map_err(io_error)?
+                    .to_vec()),
+            }
+        }
+
+        // Send part of a resumable upload starting at `offset`, `total` being
+        // known with the last part
+        async fn put_chunk(
+            &self,
+            session: &str,
+            offset: u64,
+            part: Vec<u8>,
+            total: Option<u64>,
+            path: &str,
+        ) -> Result<Response, StorageError> {
+            let total = total.map_or("*".to_string(), |total| total.to_string());
+            let range = if part.is_empty() {
+                format!("bytes */{}", total)
+            } else {
+                format!(
+                    "bytes {}-{}/{}",
+                    offset,
+                    offset + part.len() as u64 - 1,
+                    total
+                )
+            };
+            let request = self
+                .client
+                .put(session)
+                .header(header::CONTENT_RANGE, range)
+                .body(part);
+            self.send(request, path).await
+        }
+
+        fn to_item(&self, key: &str, file: &File) -> StorageItem {
+            let path = self.storage_path(key);
+            if file.mime_type == FOLDER {
+                return StorageItem {
+                    node_type: "dir".to_string(),
+                    path,
+                    basename: file.name.clone(),
+                    extension: None,
+                    mime_type: None,
+                    last_modified: file.modified_time.as_deref().and_then(parse_time),
+                    size: None,
+                };
+            }
+
+            StorageItem {
+                node_type: "file".to_string(),
+                path,
+                extension: Path::new(&file.name)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned()),
+                mime_type: Some(file.mime_type.clone()),
+                basename: file.name.clone(),
+                last_modified: file.modified_time.as_deref().and_then(parse_time),
+                size: file.size.as_deref().and_then(|size| size.parse().ok()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl StorageAdapter for GoogleDriveStorage {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+            let (key, node) = self.node(path).await?;
+            if !node.is_folder() {
+                return Err(StorageError::NotADirectory(path.to_string()));
+            }
+            let children = self.children(&node.id, None).await;
+            let files = self.stale(&key, children)?;
+
+            Ok(files
+                .iter()
+                .map(|file| {
+                    let child = if key.is_empty() {
+                        file.name.clone()
+                    } else {
+                        join_path(&key, &file.name)
+                    };
+                    self.remember(&child, Node::from(file));
+                    self.to_item(&child, file)
+                })
+                .collect())
+        }
+
+        async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+            self.download(path, None).await
+        }
+
+        async fn read_range(
+            &self,
+            path: &str,
+            offset: u64,
+            length: u64,
+        ) -> Result<Vec<u8>, StorageError> {
+            if length == 0 {
+                return Ok(Vec::new());
+            }
+            let end = offset.saturating_add(length - 1);
+            self.download(path, Some(format!("bytes={}-{}", offset, end)))
+                .await
+        }
+
+        async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+            let (key, node) = self.node(path).await?;
+            let request = self
+                .client
+                .get(format!("{}/files/{}", API, node.id))
+                .query(&[("fields", FIELDS), ("supportsAllDrives", "true")]);
+            let response = self.send(request, path).await;
+            let file: File = self.stale(&key, response)?.json().await.map_err(io_error)?;
+            Ok(file
+                .size
+                .as_deref()
+                .and_then(|size| size.parse().ok())
+                .unwrap_or_default())
+        }
+
+        async fn checksum(
+            &self,
+            path: &str,
+            algorithm: ChecksumAlgorithm,
+        ) -> Result<String, StorageError> {
+            let (key, node) = self.node(path).await?;
+            let request = self
+                .client
+                .get(format!("{}/files/{}", API, node.id))
+                .query(&[
+                    ("fields", "md5Checksum,sha1Checksum,sha256Checksum"),
+                    ("supportsAllDrives", "true"),
+                ]);
+            let response = self.send(request, path).await;
+            let checksums: Checksums =
+                self.stale(&key, response)?.json().await.map_err(io_error)?;
+            let stored = match algorithm {
+                ChecksumAlgorithm::Md5 => checksums.md5_checksum,
+                ChecksumAlgorithm::Sha1 => checksums.sha1_checksum,
+                ChecksumAlgorithm::Sha256 => checksums.sha256_checksum,
+            };
+            match stored {
+                Some(stored) => Ok(stored),
+                None => digest_stream(self.read_stream(path).await?, algorithm).await,
+            }
+        }
+
+        async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+            let stream = futures_util::stream::once(async move { Ok(Bytes::from(contents)) });
+            self.write_stream(path, Box::pin(stream)).await
+        }
+
+        async fn write_stream(
+            &self,
+            path: &str,
+            mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+        ) -> Result<(), StorageError> {
+            let key = self.key(path)?;
+            if key.is_empty() {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            let parent = self.parent(path, &key).await?;
+
+            // Replace the file's contents when it exists, keeping its ID and sharing
+            let request = match self.resolve(&key).await? {
+                Some(node) if node.is_folder() => {
+                    return Err(StorageError::InvalidPath(path.to_string()))
+                }
+                Some(node) => self
+                    .client
+                    .patch(format!("{}/files/{}", UPLOAD_API, node.id))
+                    .json(&json!({})),
+                None => self
+                    .client
+                    .post(format!("{}/files", UPLOAD_API))
+                    .json(&json!({ "name": split(&key).1, "parents": [parent.id] })),
+            };
+            let request = request.query(&[
+                ("uploadType", "resumable"),
+                ("fields", FIELDS),
+                ("supportsAllDrives", "true"),
+            ]);
+            let response = self.send(request, path).await;
+            let session = self
+                .stale(&key, response)?
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(str::to_string)
+                .ok_or_else(|| io_error("Drive started no upload session"))?;
+
+            // A part is sent once more follows, so the last one carries the size.
+            // An error from the stream leaves the session unfinished and the file as it was.
+            let mut buffer = Vec::new();
+            let mut offset = 0;
+            loop {
+                let next = stream.try_next().await?;
+                if let Some(bytes) = &next {
+                    buffer.extend_from_slice(bytes);
+                }
+                while buffer.len() > CHUNK_SIZE {
+                    let part: Vec<u8> = buffer.drain(..CHUNK_SIZE).collect();
+                    self.put_chunk(&session, offset, part, None, path).await?;
+                    offset += CHUNK_SIZE as u64;
+                }
+                if next.is_none() {
+                    break;
+                }
+            }
+            let total = offset + buffer.len() as u64;
+            let file: File = self
+                .put_chunk(&session, offset, buffer, Some(total), path)
+                .await?
+                .json()
+                .await
+                .map_err(io_error)?;
+            self.remember(&key, Node::from(&file));
+            Ok(())
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), StorageError> {
+            let (key, node) = self.node(path).await?;
+            if key.is_empty() {
+                return Err(StorageError::InvalidPath(path.to_string()));
+            }
+            let request = self
+                .client
+                .delete(format!("{}/files/{}", API, node.id))
+                .query(&[("supportsAllDrives", "true")]);
+            let response = self.send(request, path).await;
+            self.forget(&key);
+            response.map(|_| ())
+        }
+
+        async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+            let key = self.key(path)?;
+            // Missing parents are created too
+            let mut parent = self.root_node();
+            let mut current = String::new();
+            for name in key.split('/').filter(|name| !name.is_empty()) {
+                current = if current.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", current, name)
+                };
+                parent = match self.resolve(&current).await? {
+                    Some(node) if node.is_folder() => node,
+                    Some(_) => return Err(StorageError::AlreadyExists(path.to_string())),
+                    None => {
+                        let request = self
+                            .client
+                            .post(format!("{}/files", API))
+                            .query(&[("fields", FIELDS), ("supportsAllDrives", "true")])
+                            .json(&json!({
+                                "name": name,
+                                "mimeType": FOLDER,
+                                "parents": [parent.id],
+                            }));
+                        let file: File = self
+                            .send(request, path)
+                            .await?
+                            .json()
+                            .await
+                            .map_err(io_error)?;
+                        let node = Node::from(&file);
+                        self.remember(&current, node.clone());
+                        node
+                    }
+                };
+            }
+            Ok(())
+        }
+
+        async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+            Ok(self.resolve(&self.key(path)?).await?.is_some())
+        }
+
+        async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+            Ok(self
+                .resolve(&self.key(path)?)
+                .await?
+                .is_some_and(|node| node.is_folder()))
+        }
+
+        async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+            let (from_key, node) = self.node(from).await?;
+            let to_key = self.key(to)?;
+            if from_key.is_empty() || to_key.is_empty() {
+                return Err(StorageError::InvalidPath(from.to_string()));
+            }
+            if self.resolve(&to_key).await?.is_some() {
+                return Err(StorageError::AlreadyExists(to.to_string()));
+            }
+            let old_parent = self.parent(from, &from_key).await?;
+            let new_parent = self.parent(to, &to_key).await?;
+
+            let request = self
+                .client
+                .patch(format!("{}/files/{}", API, node.id))
+                .query(&[
+                    ("addParents", new_parent.id.as_str()),
+                    ("removeParents", old_parent.id.as_str()),
+                    ("supportsAllDrives", "true"),
+                ])
+                .json(&json!({ "name": split(&to_key).1 }));
+            let response = self.send(request, from).await;
+            self.forget(&from_key);
+            self.forget(&to_key);
+            response.map(|_| ())
+        }
+
+        async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+            let request = self
+                .client
+                .get(format!("{}/about", API))
+                .query(&[("fields", "storageQuota")]);
+            let about: About = self
+                .send(request, &self.storage_path(""))
+                .await?
+                .json()
+                .await
+                .map_err(io_error)?;
+            let quota = about.storage_quota;
+            let parse = |value: Option<String>| value.and_then(|value| value.parse::<u64>().ok());
+            Ok(parse(quota.limit).map(|total| StorageUsage {
+                total,
+                available: total.saturating_sub(parse(quota.usage).unwrap_or_default()),
+            }))
+        }
+
+        fn capabilities(&self) -> StorageCapabilities {
+            StorageCapabilities {
+                streaming: true,
+                rename: true,
+                range_read: true,
+                quota: true,
+                ..StorageCapabilities::default()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::storages::google_auth::Credentials;
+
+        fn storage() -> GoogleDriveStorage {
+            let config = GoogleDriveConfig {
+                name: "gdrive".to_string(),
+                credentials: String::new(),
+                root: "root".to_string(),
+            };
+            let credentials = Credentials::AuthorizedUser {
+                client_id: String::new(),
+                client_secret: String::new(),
+                refresh_token: String::new(),
+            };
+            GoogleDriveStorage::with_auth(&config, GoogleAuth::new(credentials, SCOPE))
+        }
+
+        fn node(id: &str, mime_type: &str) -> Node {
+            Node {
+                id: id.to_string(),
+                mime_type: mime_type.to_string(),
+            }
+        }
+
+        #[tokio::test]
+        async fn test_path_cache() {
+            let storage = storage();
+            assert_eq!(storage.key("gdrive://docs/a.md/").unwrap(), "docs/a.md");
+            assert!(storage.key("gdrive://../secret").is_err());
+            assert_eq!(literal("it's a\\b"), r"'it\'s a\\b'");
+
+            // Remembered paths resolve without asking Drive
+            storage.remember("docs", node("1", FOLDER));
+            storage.remember("docs/a.md", node("2", "text/markdown"));
+            storage.remember("docs/guide", node("3", FOLDER));
+            assert_eq!(
+                storage.resolve("").await.unwrap(),
+                Some(storage.root_node())
+            );
+            assert_eq!(
+                storage.resolve("docs/a.md").await.unwrap(),
+                Some(node("2", "text/markdown"))
+            );
+            // Nothing is inside a file
+            assert_eq!(storage.resolve("docs/a.md/b").await.unwrap(), None);
+
+            storage.forget("docs");
+            assert!(storage.cached("docs/guide").is_none());
+            assert!(storage.cached("docs").is_none());
+        }
+
+        #[test]
+        fn test_listing() {
+            let storage = storage();
+            let list: FileList = serde_json::from_str(
+                r#"{
+                    "files": [
+                        {"id": "3", "name": "guide", "mimeType": "application/vnd.google-apps.folder"},
+                        {"id": "2", "name": "a.md", "mimeType": "text/markdown", "size": "42",
+                         "modifiedTime": "2024-02-29T13:45:30.123Z"}
+                    ]
+                }"#,
+            )
+            .unwrap();
+
+            let items: Vec<_> = list
+                .files
+                .iter()
+                .map(|file| storage.to_item(&join_path("docs", &file.name), file))
+                .collect();
+            assert_eq!(items[0].node_type, "dir");
+            assert_eq!(items[0].path, "gdrive://docs/guide");
+            assert_eq!(items[1].path, "gdrive://docs/a.md");
+            assert_eq!(items[1].size, Some(42));
+            assert_eq!(items[1].last_modified, Some(1_709_214_330));
+            assert_eq!(items[1].mime_type.as_deref(), Some("text/markdown"));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex;
+
+use super::StorageError;
+
+// Token endpoint for refresh tokens, service accounts name theirs
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+// Refresh access tokens this long before they expire
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+// A Google credentials file
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Credentials {
+    // A service account key
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        token_uri: String,
+    },
+    // An OAuth client and a user's refresh token, as written by
+    // `gcloud auth application-default login`
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+fn io_error(error: impl std::fmt::Display) -> StorageError {
+    StorageError::Io(std::io::Error::other(error.to_string()))
+}
+
+// OAuth access tokens for the Google APIs, cached until close to expiry
+pub(crate) struct GoogleAuth {
+    credentials: Credentials,
+    scope: &'static str,
+    client: Client,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl GoogleAuth {
+    pub(crate) fn new(credentials: Credentials, scope: &'static str) -> Self {
+        Self {
+            credentials,
+            scope,
+            client: Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn from_file(path: &str, scope: &'static str) -> Result<Self, StorageError> {
+        let key = std::fs::read(path)?;
+        let credentials = serde_json::from_slice(&key).map_err(io_error)?;
+        Ok(Self::new(credentials, scope))
+    }
+
+    pub(crate) async fn access_token(&self) -> Result<String, StorageError> {
+        let mut token = self.token.lock().await;
+        if let Some((value, expires)) = token.as_ref() {
+            if Instant::now() + TOKEN_MARGIN < *expires {
+                return Ok(value.clone());
+            }
+        }
+
+        let request = match &self.credentials {
+            // Exchange a signed assertion
+            Credentials::ServiceAccount {
+                client_email,
+                private_key,
+                token_uri,
+            } => {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let claims = Claims {
+                    iss: client_email,
+                    scope: self.scope,
+                    aud: token_uri,
+                    iat: now,
+                    exp: now + 3600,
+                };
+                let key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())
+                    .map_err(io_error)?;
+                let assertion = jsonwebtoken::encode(
+                    &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+                    &claims,
+                    &key,
+                )
+                .map_err(io_error)?;
+                self.client.post(token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            }
+            Credentials::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => self.client.post(TOKEN_URI).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ]),
+        };
+
+        let response: TokenResponse = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(io_error)?
+            .json()
+            .await
+            .map_err(io_error)?;
+
+        let expires = Instant::now() + Duration::from_secs(response.expires_in);
+        *token = Some((response.access_token.clone(), expires));
+        Ok(response.access_token)
+    }
+}
@@ -1,38 +1,75 @@
-use super::{StorageAdapter, StorageError, StorageItem};
+use super::{
+    lock, validate_windows_key, StorageAdapter, StorageCapabilities, StorageError, StorageItem,
+    StorageUsage, Storages,
+};
 use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
 use mime_guess::from_path;
 use std::collections::HashMap;
-use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::fs::Metadata;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::fs;
-
-const LOCAL_SCHEME: &str = "local://";
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 
 #[derive(Debug)]
 pub struct LocalStorage {
+    // Adapter name and path scheme, `local` unless mounted under another name
+    name: String,
     root: String,
+    // Skip entries deleted while a listing is in progress instead of failing
+    skip_vanished: bool,
+    // Apply Windows naming rules to keys, on by default on Windows
+    windows_names: bool,
 }
 
 impl LocalStorage {
     pub fn new(root: &str) -> Self {
         Self {
+            name: "local".to_string(),
             root: root.to_string(),
+            skip_vanished: true,
+            windows_names: cfg!(windows),
         }
     }
 
-    pub fn setup(path: &str) -> Arc<HashMap<String, Arc<dyn StorageAdapter>>> {
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    fn scheme(&self) -> String {
+        format!("{}://", self.name)
+    }
+
+    pub fn with_skip_vanished(mut self, skip_vanished: bool) -> Self {
+        self.skip_vanished = skip_vanished;
+        self
+    }
+
+    pub fn setup(path: &str) -> Storages {
         let mut storages = HashMap::new();
         let storage = Arc::new(Self::new(path)) as Arc<dyn StorageAdapter>;
         storages.insert(storage.name(), storage);
         Arc::new(storages)
     }
 
+    pub fn with_windows_names(mut self, windows_names: bool) -> Self {
+        self.windows_names = windows_names;
+        self
+    }
+
     // Parse and validate path
     fn resolve_path(&self, path: &str) -> Result<PathBuf, StorageError> {
+        let scheme = self.scheme();
         let clean_path = path
-            .trim_start_matches(LOCAL_SCHEME)
+            .trim_start_matches(scheme.as_str())
             .trim_start_matches('/');
 
         // Convert to absolute path and normalize
@@ -45,17 +82,30 @@ impl LocalStorage {
         let canonical_path = if full_path.exists() {
             full_path.canonicalize().map_err(StorageError::Io)?
         } else {
-            // For non-existent paths, canonicalize the parent and then append the filename
-            let parent = full_path.parent().ok_or_else(|| {
-                StorageError::InvalidPath("Invalid path: no parent directory".to_string())
-            })?;
-            let filename = full_path.file_name().ok_or_else(|| {
-                StorageError::InvalidPath("Invalid path: no filename".to_string())
-            })?;
-            parent
-                .canonicalize()
-                .map_err(StorageError::Io)?
-                .join(filename)
+            // For non-existent paths, canonicalize the deepest existing ancestor and
+            // append the remaining names. This doesn't depend on a just-created parent
+            // being visible yet, and `..` below a missing directory has no filename
+            // so it is rejected here.
+            let mut ancestor = full_path.as_path();
+            let mut missing = Vec::new();
+            loop {
+                match ancestor.canonicalize() {
+                    Ok(canonical) => {
+                        break canonical.join(missing.iter().rev().collect::<PathBuf>())
+                    }
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        missing.push(ancestor.file_name().ok_or_else(|| {
+                            StorageError::InvalidPath("Invalid path: no filename".to_string())
+                        })?);
+                        ancestor = ancestor.parent().ok_or_else(|| {
+                            StorageError::InvalidPath(
+                                "Invalid path: no parent directory".to_string(),
+                            )
+                        })?;
+                    }
+                    Err(e) => return Err(StorageError::Io(e)),
+                }
+            }
         };
 
         // Get canonical root path
@@ -72,88 +122,155 @@ impl LocalStorage {
 
         Ok(canonical_path)
     }
-}
 
-#[async_trait]
-impl StorageAdapter for LocalStorage {
-    fn name(&self) -> String {
-        LOCAL_SCHEME.trim_end_matches("://").to_string()
+    // Open a file positioned at `offset`
+    async fn open_at(&self, path: &str, offset: u64) -> Result<fs::File, StorageError> {
+        let full_path = self.resolve_path(path)?;
+
+        let mut file = match fs::File::open(&full_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(StorageError::NotFound(path.to_string()))
+            }
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(file)
     }
 
-    async fn list_contents(
+    // Item for the file or folder at `path_buf` under the canonical `root_path`
+    fn to_storage_item(
         &self,
-        path: &str,
-    ) -> Result<Vec<StorageItem>, Box<dyn std::error::Error>> {
-        let full_path = self.resolve_path(path)?;
-        let mut entries = Vec::new();
+        path_buf: &Path,
+        root_path: &Path,
+        metadata: &Metadata,
+    ) -> StorageItem {
+        // Calculate relative path from root
+        let relative_path = path_buf
+            .strip_prefix(root_path)
+            .unwrap_or(path_buf)
+            .to_string_lossy()
+            .into_owned();
+
+        let basename = path_buf
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        let extension = path_buf
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned());
+
+        let mime_type = if metadata.is_file() {
+            Some(
+                from_path(path_buf)
+                    .first_or_octet_stream()
+                    .essence_str()
+                    .to_owned(),
+            )
+        } else {
+            None
+        };
 
-        let mut read_dir = fs::read_dir(&full_path).await?;
+        let last_modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
 
-        // Get canonical root path
-        let root_path = PathBuf::from(&self.root)
-            .canonicalize()
-            .map_err(StorageError::Io)?;
+        let size = if metadata.is_file() {
+            Some(metadata.len())
+        } else {
+            None
+        };
 
-        while let Some(entry) = read_dir.next_entry().await? {
-            let metadata = entry.metadata().await?;
-            let path_buf = entry.path();
-
-            // Calculate relative path from root
-            let relative_path = path_buf
-                .strip_prefix(&root_path)
-                .unwrap_or(&path_buf)
-                .to_string_lossy()
-                .into_owned();
-
-            let basename = path_buf
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .into_owned();
-
-            let extension = path_buf
-                .extension()
-                .map(|ext| ext.to_string_lossy().into_owned());
-
-            let mime_type = if metadata.is_file() {
-                Some(
-                    from_path(&path_buf)
-                        .first_or_octet_stream()
-                        .essence_str()
-                        .to_owned(),
-                )
+        StorageItem {
+            node_type: if metadata.is_dir() {
+                "dir".to_string()
             } else {
-                None
-            };
+                "file".to_string()
+            },
+            path: format!("{}{}", self.scheme(), relative_path),
+            basename,
+            extension,
+            mime_type,
+            last_modified,
+            size,
+        }
+    }
+}
 
-            let last_modified = metadata
-                .modified()
-                .ok()
-                .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs());
+// Metadata of a listed entry, `None` when the entry was removed between
+// `read_dir` returning it and the metadata lookup.
+fn entry_metadata(
+    result: std::io::Result<Metadata>,
+    skip_vanished: bool,
+) -> std::io::Result<Option<Metadata>> {
+    match result {
+        Ok(metadata) => Ok(Some(metadata)),
+        Err(e)
+            if skip_vanished
+                && matches!(
+                    e.kind(),
+                    ErrorKind::NotFound | ErrorKind::StaleNetworkFileHandle
+                ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
 
-            let size = if metadata.is_file() {
-                Some(metadata.len())
-            } else {
-                None
-            };
+#[async_trait]
+impl StorageAdapter for LocalStorage {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
 
-            entries.push(StorageItem {
-                node_type: if metadata.is_dir() {
-                    "dir".to_string()
-                } else {
-                    "file".to_string()
-                },
-                path: format!("{}{}", LOCAL_SCHEME, relative_path),
-                basename,
-                extension,
-                mime_type,
-                last_modified,
-                size,
-            });
-        }
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        self.list_stream(path).try_collect().await
+    }
 
-        Ok(entries)
+    fn list_stream<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> BoxStream<'a, Result<StorageItem, StorageError>> {
+        Box::pin(async_stream::try_stream! {
+            let full_path = self.resolve_path(path)?;
+            let mut read_dir = fs::read_dir(&full_path).await.map_err(|e| match e.kind() {
+                ErrorKind::NotFound => StorageError::NotFound(path.to_string()),
+                ErrorKind::NotADirectory => StorageError::NotADirectory(path.to_string()),
+                ErrorKind::PermissionDenied => {
+                    StorageError::PermissionDenied(path.to_string())
+                }
+                _ => StorageError::Io(e),
+            })?;
+
+            // Get canonical root path
+            let root_path = PathBuf::from(&self.root)
+                .canonicalize()
+                .map_err(StorageError::Io)?;
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let metadata = match entry_metadata(entry.metadata().await, self.skip_vanished)? {
+                    Some(metadata) => metadata,
+                    None => continue,
+                };
+                yield self.to_storage_item(&entry.path(), &root_path, &metadata);
+            }
+        })
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        self.resolve_path(path).ok()
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        if self.windows_names {
+            validate_windows_key(name)?;
+        }
+        Ok(())
     }
 
     async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
@@ -168,6 +285,60 @@ impl StorageAdapter for LocalStorage {
         }
     }
 
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let full_path = self.resolve_path(path)?;
+
+        match fs::File::open(&full_path).await {
+            Ok(file) => Ok(Box::pin(ReaderStream::new(file).map_err(StorageError::Io))),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Err(StorageError::NotFound(path.to_string()))
+            }
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        let mut contents = Vec::new();
+        self.open_at(path, offset)
+            .await?
+            .take(length)
+            .read_to_end(&mut contents)
+            .await?;
+        Ok(contents)
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let file = self.open_at(path, offset).await?;
+        Ok(Box::pin(
+            ReaderStream::new(file.take(length)).map_err(StorageError::Io),
+        ))
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let full_path = self.resolve_path(path)?;
+        match fs::metadata(&full_path).await {
+            Ok(metadata) if metadata.is_file() => Ok(metadata.len()),
+            Ok(_) => Err(StorageError::InvalidPath(path.to_string())),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Err(StorageError::NotFound(path.to_string()))
+            }
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
     async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
         let full_path = self.resolve_path(path)?;
 
@@ -176,7 +347,76 @@ impl StorageAdapter for LocalStorage {
             fs::create_dir_all(parent).await?;
         }
 
-        fs::write(&full_path, contents).await?;
+        match fs::write(&full_path, contents).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::StorageFull => {
+                // Don't leave a truncated file behind
+                let _ = fs::remove_file(&full_path).await;
+                Err(StorageError::InsufficientStorage(path.to_string()))
+            }
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let full_path = self.resolve_path(path)?;
+        let (Some(parent), Some(name)) = (full_path.parent(), full_path.file_name()) else {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        };
+        fs::create_dir_all(parent).await?;
+
+        // Write next to the target and rename once complete, so a failed transfer
+        // never leaves a partial file under the requested name
+        let partial = parent.join(format!(
+            ".{}.{}-{}.part",
+            name.to_string_lossy(),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = async {
+            let mut file = fs::File::create(&partial).await?;
+            while let Some(chunk) = stream.try_next().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            drop(file);
+            fs::rename(&partial, &full_path).await?;
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_file(&partial).await;
+        }
+        result.map_err(|e| match e {
+            StorageError::Io(e) if e.kind() == ErrorKind::StorageFull => {
+                StorageError::InsufficientStorage(path.to_string())
+            }
+            e => e,
+        })
+    }
+
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let _guard = lock::lock_path(&format!("{}:{}", self.name(), path)).await;
+        let full_path = self.resolve_path(path)?;
+
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&full_path)
+            .await?;
+        file.write_all(&contents).await?;
+        file.flush().await?;
         Ok(())
     }
 
@@ -213,6 +453,73 @@ impl StorageAdapter for LocalStorage {
         let full_path = self.resolve_path(path)?;
         Ok(fs::try_exists(&full_path).await?)
     }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let full_path = self.resolve_path(path)?;
+        let metadata = match fs::metadata(&full_path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(StorageError::NotFound(path.to_string()))
+            }
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+        let root_path = PathBuf::from(&self.root).canonicalize()?;
+        Ok(self.to_storage_item(&full_path, &root_path, &metadata))
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from_path = self.resolve_path(from)?;
+        let to_path = self.resolve_path(to)?;
+
+        if from_path == PathBuf::from(&self.root).canonicalize()? {
+            return Err(StorageError::InvalidPath(from.to_string()));
+        }
+        if !fs::try_exists(&from_path).await? {
+            return Err(StorageError::NotFound(from.to_string()));
+        }
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        match fs::rename(&from_path, &to_path).await {
+            // A mount point inside the root, let the caller copy instead
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+                Err(StorageError::Unsupported(e.to_string()))
+            }
+            result => Ok(result?),
+        }
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        let full_path = self.resolve_path(path)?;
+        match fs::metadata(&full_path).await {
+            Ok(metadata) => Ok(metadata.is_dir()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        let root = self.root.clone();
+        let stats = tokio::task::spawn_blocking(move || fs4::statvfs(root))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(Some(StorageUsage {
+            total: stats.total_space(),
+            available: stats.available_space(),
+        }))
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            streaming: true,
+            rename: true,
+            range_read: true,
+            quota: true,
+            ..StorageCapabilities::default()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -247,6 +554,36 @@ mod tests {
         assert_eq!(entries[0].node_type, "file");
         assert_eq!(entries[0].extension.as_deref(), Some("txt"));
         assert_eq!(entries[0].mime_type.as_deref(), Some("text/plain"));
+        assert!(matches!(
+            storage.list_contents("test_dir/test.txt").await,
+            Err(StorageError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            storage.list_contents("missing").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        // Test streamed write, an aborted stream leaves nothing behind
+        let chunks =
+            futures_util::stream::iter(vec![Ok(Bytes::from("Hel")), Ok(Bytes::from("lo"))]);
+        storage
+            .write_stream("test_dir/streamed.txt", Box::pin(chunks))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.read("test_dir/streamed.txt").await.unwrap(),
+            b"Hello"
+        );
+        let chunks = futures_util::stream::iter(vec![
+            Ok(Bytes::from("Hel")),
+            Err(StorageError::Io(std::io::Error::other("aborted"))),
+        ]);
+        assert!(storage
+            .write_stream("test_dir/aborted.txt", Box::pin(chunks))
+            .await
+            .is_err());
+        assert_eq!(storage.list_contents("test_dir").await.unwrap().len(), 2);
+        storage.delete("test_dir/streamed.txt").await.unwrap();
 
         // Test delete
         storage.delete("test_dir/test.txt").await.unwrap();
@@ -257,6 +594,197 @@ mod tests {
         assert!(!storage.exists("test_dir").await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_list_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+        for i in 0..5 {
+            storage
+                .write(&format!("file{}.txt", i), vec![])
+                .await
+                .unwrap();
+        }
+
+        // Consume only part of the listing
+        let mut stream = storage.list_stream("");
+        let first = stream.try_next().await.unwrap().unwrap();
+        let second = stream.try_next().await.unwrap().unwrap();
+        assert!(first.basename.starts_with("file"));
+        assert_ne!(first.path, second.path);
+        drop(stream);
+
+        // Missing directories surface as an error item
+        let mut stream = storage.list_stream("missing");
+        assert!(stream.try_next().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_usage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+
+        let usage = storage.usage().await.unwrap().unwrap();
+        assert!(usage.total > 0);
+        assert!(usage.available <= usage.total);
+    }
+
+    #[test]
+    fn test_vanished_entry() {
+        let vanished = || Err(std::io::Error::from(ErrorKind::NotFound));
+
+        assert!(entry_metadata(vanished(), true).unwrap().is_none());
+        assert!(entry_metadata(vanished(), false).is_err());
+        assert!(
+            entry_metadata(Err(std::io::Error::from(ErrorKind::PermissionDenied)), true).is_err()
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let metadata = std::fs::metadata(temp_dir.path());
+        assert!(entry_metadata(metadata, true).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+        storage
+            .write("local://dir/nested/a.txt", b"a".to_vec())
+            .await
+            .unwrap();
+
+        storage
+            .rename("local://dir", "local://moved")
+            .await
+            .unwrap();
+        assert!(!temp_dir.path().join("dir").exists());
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("moved/nested/a.txt")).unwrap(),
+            b"a"
+        );
+
+        storage
+            .rename("local://moved/nested/a.txt", "local://b.txt")
+            .await
+            .unwrap();
+        assert!(temp_dir.path().join("b.txt").is_file());
+
+        assert!(matches!(
+            storage.rename("local://missing", "local://other").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(storage.rename("local://", "local://other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_stream_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+        storage
+            .write("local://a.txt", b"0123456789".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(storage.file_size("local://a.txt").await.unwrap(), 10);
+        let chunks: Vec<Bytes> = storage
+            .read_stream_range("local://a.txt", 2, 5)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"23456");
+        assert!(matches!(
+            storage.file_size("local://").await,
+            Err(StorageError::InvalidPath(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+        let contents: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        storage
+            .write("local://large.bin", contents.clone())
+            .await
+            .unwrap();
+
+        let chunks: Vec<Bytes> = storage
+            .read_stream("local://large.bin")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), contents);
+
+        assert!(matches!(
+            storage.read_stream("local://missing.bin").await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+        storage
+            .write("local://range.txt", b"0123456789".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.read_range("local://range.txt", 2, 3).await.unwrap(),
+            b"234"
+        );
+        assert_eq!(
+            storage
+                .read_range("local://range.txt", 8, 10)
+                .await
+                .unwrap(),
+            b"89"
+        );
+        assert!(storage
+            .read_range("local://range.txt", 20, 5)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_into_new_folder() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path().to_str().unwrap());
+
+        for i in 0..50 {
+            let dir = format!("local://dir{}", i);
+            storage.create_dir(&dir).await.unwrap();
+            storage
+                .write(&format!("{}/file.txt", dir), vec![i as u8])
+                .await
+                .unwrap();
+            assert_eq!(
+                storage.read(&format!("{}/file.txt", dir)).await.unwrap(),
+                vec![i as u8]
+            );
+        }
+
+        // Parents that don't exist yet are created on write
+        storage
+            .write("local://a/b/c.txt", b"nested".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("a/b/c.txt")).unwrap(),
+            b"nested"
+        );
+        assert!(storage
+            .write("local://x/../../escape.txt", vec![])
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_invalid_paths() {
         let temp_dir = TempDir::new().unwrap();
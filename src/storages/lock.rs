@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use tokio::sync::OwnedMutexGuard;
+
+type PathLocks = Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>;
+
+static PATH_LOCKS: OnceLock<PathLocks> = OnceLock::new();
+
+// Acquire the process-wide lock for a path key, serializing read-modify-write
+// operations on the same path. The lock is released when the guard is dropped.
+pub async fn lock_path(key: &str) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = PATH_LOCKS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        // Forget locks nobody holds anymore
+        locks.retain(|_, lock| lock.strong_count() > 0);
+
+        match locks.get(key).and_then(Weak::upgrade) {
+            Some(lock) => lock,
+            None => {
+                let lock = Arc::new(tokio::sync::Mutex::new(()));
+                locks.insert(key.to_string(), Arc::downgrade(&lock));
+                lock
+            }
+        }
+    };
+
+    lock.lock_owned().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lock_path() {
+        let guard = lock_path("local:a.txt").await;
+
+        // Another path is independent
+        let other = tokio::time::timeout(Duration::from_millis(50), lock_path("local:b.txt")).await;
+        assert!(other.is_ok());
+
+        // The same path waits until the guard is released
+        let same = tokio::time::timeout(Duration::from_millis(50), lock_path("local:a.txt")).await;
+        assert!(same.is_err());
+
+        drop(guard);
+        let same = tokio::time::timeout(Duration::from_millis(50), lock_path("local:a.txt")).await;
+        assert!(same.is_ok());
+    }
+}
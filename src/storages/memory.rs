@@ -0,0 +1,394 @@
+use super::{
+    relative_key, StorageAdapter, StorageCapabilities, StorageError, StorageItem, Storages,
+};
+use async_trait::async_trait;
+use mime_guess::from_path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+enum MemoryEntry {
+    Dir,
+    File { contents: Vec<u8>, modified: u64 },
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Volatile storage kept in process memory, handy for tests, demos and scratch space
+#[derive(Debug)]
+pub struct MemoryStorage {
+    scheme: String,
+    // Keyed by `/`-separated path relative to the root, parents are always present
+    entries: RwLock<BTreeMap<String, MemoryEntry>>,
+}
+
+impl MemoryStorage {
+    pub fn new(scheme: &str) -> Self {
+        Self {
+            scheme: scheme.to_string(),
+            entries: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn setup(scheme: &str) -> Storages {
+        let mut storages = HashMap::new();
+        let storage = Arc::new(Self::new(scheme)) as Arc<dyn StorageAdapter>;
+        storages.insert(storage.name(), storage);
+        Arc::new(storages)
+    }
+
+    // Start with `files`, keyed by `/`-separated path relative to the root.
+    // Keys ending in `/` are empty folders, e.g. `[("docs/a.md", "# A"), ("photos/", "")]`.
+    pub fn with_files<K, C>(self, files: impl IntoIterator<Item = (K, C)>) -> Self
+    where
+        K: AsRef<str>,
+        C: Into<Vec<u8>>,
+    {
+        let modified = now();
+        for (key, contents) in files {
+            let key = key.as_ref();
+            let entry = if key.ends_with('/') {
+                MemoryEntry::Dir
+            } else {
+                MemoryEntry::File {
+                    contents: contents.into(),
+                    modified,
+                }
+            };
+            self.seed(key, entry);
+        }
+        self
+    }
+
+    // Start with a copy of the files and folders below the local folder
+    // `root`, so a demo starts from the same tree on every run
+    pub fn with_dir(self, root: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut dirs = vec![(root.as_ref().to_path_buf(), String::new())];
+        while let Some((dir, prefix)) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let key = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    self.seed(&key, MemoryEntry::Dir);
+                    dirs.push((entry.path(), format!("{}/", key)));
+                } else {
+                    let modified = metadata
+                        .modified()
+                        .ok()
+                        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                        .map_or_else(now, |d| d.as_secs());
+                    let contents = std::fs::read(entry.path())?;
+                    self.seed(&key, MemoryEntry::File { contents, modified });
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    fn seed(&self, key: &str, entry: MemoryEntry) {
+        let Ok(key) = relative_key(&self.scheme, key) else {
+            log::warn!(
+                "Not seeding {}://{}, not a plain relative path",
+                self.scheme,
+                key
+            );
+            return;
+        };
+        if key.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.write().unwrap();
+        Self::insert_parents(&mut entries, key);
+        entries.insert(key.to_string(), entry);
+    }
+
+    // Register every ancestor of `key` as a directory
+    fn insert_parents(entries: &mut BTreeMap<String, MemoryEntry>, key: &str) {
+        let mut parent = key;
+        while let Some((prefix, _)) = parent.rsplit_once('/') {
+            entries
+                .entry(prefix.to_string())
+                .or_insert(MemoryEntry::Dir);
+            parent = prefix;
+        }
+    }
+
+    fn to_storage_item(&self, key: &str, entry: &MemoryEntry) -> StorageItem {
+        let basename = key.rsplit('/').next().unwrap_or(key).to_string();
+
+        match entry {
+            MemoryEntry::Dir => StorageItem {
+                node_type: "dir".to_string(),
+                path: format!("{}://{}", self.scheme, key),
+                basename,
+                extension: None,
+                mime_type: None,
+                last_modified: None,
+                size: None,
+            },
+            MemoryEntry::File { contents, modified } => StorageItem {
+                node_type: "file".to_string(),
+                path: format!("{}://{}", self.scheme, key),
+                extension: Path::new(&basename)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().into_owned()),
+                mime_type: Some(
+                    from_path(&basename)
+                        .first_or_octet_stream()
+                        .essence_str()
+                        .to_owned(),
+                ),
+                basename,
+                last_modified: Some(*modified),
+                size: Some(contents.len() as u64),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for MemoryStorage {
+    fn name(&self) -> String {
+        self.scheme.clone()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        let entries = self.entries.read().unwrap();
+
+        if !key.is_empty() {
+            match entries.get(key) {
+                Some(MemoryEntry::Dir) => {}
+                Some(MemoryEntry::File { .. }) => {
+                    return Err(StorageError::NotADirectory(path.to_string()))
+                }
+                None => return Err(StorageError::NotFound(path.to_string())),
+            }
+        }
+
+        let prefix = if key.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", key)
+        };
+        Ok(entries
+            .range(prefix.clone()..)
+            .take_while(|(child, _)| child.starts_with(&prefix))
+            .filter(|(child, _)| !child[prefix.len()..].contains('/'))
+            .map(|(child, entry)| self.to_storage_item(child, entry))
+            .collect())
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        if key.is_empty() {
+            return Ok(self.to_storage_item(key, &MemoryEntry::Dir));
+        }
+        match self.entries.read().unwrap().get(key) {
+            Some(entry) => Ok(self.to_storage_item(key, entry)),
+            None => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        match self.entries.read().unwrap().get(key) {
+            Some(MemoryEntry::File { contents, .. }) => Ok(contents.clone()),
+            _ => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        match self.entries.read().unwrap().get(key) {
+            Some(MemoryEntry::File { contents, .. }) => Ok(contents.len() as u64),
+            _ => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+
+        let modified = now();
+
+        let mut entries = self.entries.write().unwrap();
+        if let Some(MemoryEntry::Dir) = entries.get(key) {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        Self::insert_parents(&mut entries, key);
+        entries.insert(key.to_string(), MemoryEntry::File { contents, modified });
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        let mut entries = self.entries.write().unwrap();
+
+        if entries.remove(key).is_none() {
+            return Err(StorageError::NotFound(path.to_string()));
+        }
+        let prefix = format!("{}/", key);
+        entries.retain(|child, _| !child.starts_with(&prefix));
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        if key.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        if let Some(MemoryEntry::File { .. }) = entries.get(key) {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        Self::insert_parents(&mut entries, key);
+        entries.insert(key.to_string(), MemoryEntry::Dir);
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from_key = relative_key(&self.scheme, from)?;
+        let to_key = relative_key(&self.scheme, to)?;
+        if from_key.is_empty() || to_key.is_empty() || to_key.starts_with(&format!("{}/", from_key))
+        {
+            return Err(StorageError::InvalidPath(to.to_string()));
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries
+            .remove(from_key)
+            .ok_or_else(|| StorageError::NotFound(from.to_string()))?;
+
+        // Re-key the entry and everything below it
+        let prefix = format!("{}/", from_key);
+        let children: Vec<_> = entries
+            .range(prefix.clone()..)
+            .take_while(|(child, _)| child.starts_with(&prefix))
+            .map(|(child, _)| child.clone())
+            .collect();
+        Self::insert_parents(&mut entries, to_key);
+        entries.insert(to_key.to_string(), entry);
+        for child in children {
+            let moved = entries.remove(&child).unwrap();
+            entries.insert(format!("{}/{}", to_key, &child[prefix.len()..]), moved);
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        Ok(key.is_empty() || self.entries.read().unwrap().contains_key(key))
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        let key = relative_key(&self.scheme, path)?;
+        Ok(key.is_empty()
+            || matches!(
+                self.entries.read().unwrap().get(key),
+                Some(MemoryEntry::Dir)
+            ))
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            rename: true,
+            ..StorageCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_storage() {
+        let storage = MemoryStorage::new("mem");
+
+        storage
+            .write("mem://docs/guide/intro.md", b"# Intro".to_vec())
+            .await
+            .unwrap();
+        storage.write("mem://a.txt", b"a".to_vec()).await.unwrap();
+        storage.create_dir("mem://empty").await.unwrap();
+
+        let entries = storage.list_contents("mem://").await.unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["mem://a.txt", "mem://docs", "mem://empty"]);
+
+        let entries = storage.list_contents("mem://docs/guide").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].basename, "intro.md");
+        assert_eq!(entries[0].size, Some(7));
+        assert_eq!(entries[0].mime_type.as_deref(), Some("text/markdown"));
+
+        assert!(storage.is_dir("mem://docs").await.unwrap());
+        assert!(!storage.is_dir("mem://a.txt").await.unwrap());
+        assert!(matches!(
+            storage.list_contents("mem://a.txt").await,
+            Err(StorageError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            storage.list_contents("mem://missing").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert_eq!(storage.read("mem://a.txt").await.unwrap(), b"a");
+
+        storage.rename("mem://docs", "mem://manual").await.unwrap();
+        assert_eq!(
+            storage.read("mem://manual/guide/intro.md").await.unwrap(),
+            b"# Intro"
+        );
+        assert!(!storage.exists("mem://docs/guide").await.unwrap());
+
+        storage.delete("mem://manual").await.unwrap();
+        assert!(!storage.exists("mem://manual/guide/intro.md").await.unwrap());
+
+        assert!(matches!(
+            storage.read("mem://missing.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(storage.read("mem://../escape").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_seed() {
+        let storage = MemoryStorage::new("demo").with_files([
+            ("docs/guide/intro.md", "# Intro"),
+            ("photos/", ""),
+            ("../escape", "x"),
+        ]);
+        let entries = storage.list_contents("demo://").await.unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["demo://docs", "demo://photos"]);
+        assert!(storage.is_dir("demo://docs/guide").await.unwrap());
+        assert_eq!(
+            storage.read("demo://docs/guide/intro.md").await.unwrap(),
+            b"# Intro"
+        );
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("a/empty")).unwrap();
+        std::fs::write(temp_dir.path().join("a/b.txt"), "b").unwrap();
+        let storage = MemoryStorage::new("demo")
+            .with_dir(temp_dir.path())
+            .unwrap();
+        assert_eq!(storage.read("demo://a/b.txt").await.unwrap(), b"b");
+        assert!(storage.is_dir("demo://a/empty").await.unwrap());
+        assert!(MemoryStorage::new("demo")
+            .with_dir("/missing/folder")
+            .is_err());
+    }
+}
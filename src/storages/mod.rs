@@ -1,7 +1,18 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
 use async_trait::async_trait;
-use serde::Serialize;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::sync::Arc;
 use thiserror::Error;
 
+use checksum::{digest_stream, ChecksumAlgorithm};
+
 #[derive(Error, Debug)]
 pub enum StorageError {
     #[error("IO error: {0}")]
@@ -10,23 +21,365 @@ pub enum StorageError {
     NotFound(String),
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+    #[error("Storage is read-only: {0}")]
+    ReadOnly(String),
+    #[error("Too many entries, limit is {0}")]
+    LimitExceeded(usize),
+    #[error("Insufficient storage: {0}")]
+    InsufficientStorage(String),
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+    #[error("Not supported by this storage: {0}")]
+    Unsupported(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+}
+
+// Capacity of the volume backing a storage, in bytes
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct StorageUsage {
+    pub total: u64,
+    pub available: u64,
+}
+
+// What an adapter does natively, so clients can offer the actions that work well on it
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageCapabilities {
+    // Uploads are passed on in chunks instead of buffered whole
+    pub streaming: bool,
+    // `rename` moves entries without copying them
+    pub rename: bool,
+    // `read_range` fetches only the requested bytes
+    pub range_read: bool,
+    // Files have URLs clients can fetch directly, see `public_links`
+    pub public_urls: bool,
+    // `usage` reports capacity
+    pub quota: bool,
+    // Every change is refused
+    pub read_only: bool,
+}
+
+// Strip `scheme://` and reject anything that is not a plain relative path,
+// `mem://a/b.txt` => `a/b.txt`. For adapters keying entries by that path.
+pub(crate) fn relative_key<'a>(scheme: &str, path: &'a str) -> Result<&'a str, StorageError> {
+    let key = path
+        .strip_prefix(&format!("{}://", scheme))
+        .unwrap_or(path)
+        .trim_matches('/');
+
+    if Path::new(key)
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(StorageError::InvalidPath(path.to_string()));
+    }
+
+    Ok(key)
+}
+
+// Join a storage directory path and an entry name, `local://` + `a.txt` => `local://a.txt`
+pub fn join_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// Directory containing `path`, `local://a/b.txt` => `local://a`, `local://a.txt` => `local://`
+pub fn parent_path(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((dir, _)) if dir.ends_with(":/") => format!("{}/", dir),
+        Some((dir, _)) => dir.to_string(),
+        None => String::new(),
+    }
+}
+
+// Windows silently strips trailing dots and spaces and maps reserved device
+// names to devices, so such names would create a different file than requested
+pub fn validate_windows_name(name: &str) -> Result<(), String> {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err("Name must not end with a dot or space.".to_string());
+    }
+
+    // `CON.txt` is as reserved as `CON`
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        return Err(format!("\"{}\" is a reserved name.", stem));
+    }
+    Ok(())
+}
+
+// Apply `validate_windows_name` to every component of a `/`-separated key
+pub fn validate_windows_key(key: &str) -> Result<(), StorageError> {
+    for component in key.split('/') {
+        validate_windows_name(component)
+            .map_err(|message| StorageError::InvalidPath(format!("{}: {}", key, message)))?;
+    }
+    Ok(())
+}
+
+// Mounted adapters by name, which is also their path scheme
+pub type Storages = Arc<HashMap<String, Arc<dyn StorageAdapter>>>;
+
+// Hex-encoded SHA-256 digest used to compare file contents
+pub fn content_hash(contents: &[u8]) -> String {
+    hex::encode(Sha256::digest(contents))
+}
+
+// Hex-encoded 128 random bits, for ids that mustn't be guessable
+pub fn random_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
 }
 
 #[async_trait]
 pub trait StorageAdapter: Send + Sync {
     fn name(&self) -> String;
-    async fn list_contents(
-        &self,
-        path: &str,
-    ) -> Result<Vec<StorageItem>, Box<dyn std::error::Error>>;
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError>;
+
+    // Lazily yield directory entries so large listings can be consumed with bounded memory.
+    // The default buffers `list_contents`; adapters that can iterate natively should override.
+    fn list_stream<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> BoxStream<'a, Result<StorageItem, StorageError>> {
+        Box::pin(async_stream::stream! {
+            match self.list_contents(path).await {
+                Ok(items) => {
+                    for item in items {
+                        yield Ok(item);
+                    }
+                }
+                Err(e) => yield Err(e),
+            }
+        })
+    }
+
     async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError>;
     async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError>;
+
+    // Write a file from a stream of chunks so large uploads aren't held in memory.
+    // An error from the stream aborts the write without creating the file. The
+    // default buffers the stream and calls `write`.
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        let mut contents = Vec::new();
+        while let Some(chunk) = stream.try_next().await? {
+            contents.extend_from_slice(&chunk);
+        }
+        self.write(path, contents).await
+    }
     async fn delete(&self, path: &str) -> Result<(), StorageError>;
     async fn create_dir(&self, path: &str) -> Result<(), StorageError>;
     async fn exists(&self, path: &str) -> Result<bool, StorageError>;
+
+    // Move a file or directory within this storage without copying its contents.
+    // Adapters that can't do that natively return `Unsupported` so callers can copy and delete.
+    async fn rename(&self, _from: &str, _to: &str) -> Result<(), StorageError> {
+        Err(StorageError::Unsupported("rename".to_string()))
+    }
+
+    // Check that a relative `/`-separated key can be stored as-is by this adapter
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        if cfg!(windows) {
+            validate_windows_key(name)?;
+        }
+        Ok(())
+    }
+
+    // Read a file as a stream of chunks instead of one buffer
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let contents = self.read(path).await?;
+        Ok(Box::pin(futures_util::stream::once(async move {
+            Ok(Bytes::from(contents))
+        })))
+    }
+
+    // Stream at most `length` bytes starting at `offset`
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let contents = self.read_range(path, offset, length).await?;
+        Ok(Box::pin(futures_util::stream::once(async move {
+            Ok(Bytes::from(contents))
+        })))
+    }
+
+    // Size of a file in bytes
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        Ok(self.read(path).await?.len() as u64)
+    }
+
+    // Read at most `length` bytes starting at `offset`
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        let contents = self.read(path).await?;
+        let start = (offset as usize).min(contents.len());
+        let end = start.saturating_add(length as usize).min(contents.len());
+        Ok(contents[start..end].to_vec())
+    }
+
+    // Directory on the local filesystem backing `path`, if any
+    fn local_path(&self, _path: &str) -> Option<std::path::PathBuf> {
+        None
+    }
+
+    // `path` on the adapter everyone shares, for state kept across requests
+    // such as share links and caches. Differs for a user's `ScopedStorage`.
+    fn shared_path(&self, path: &str) -> String {
+        path.to_string()
+    }
+
+    // Hex digest of a file's contents, by default computed from `read_stream`.
+    // Backends that store digests, such as an S3 ETag, can skip reading the file.
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        digest_stream(self.read_stream(path).await?, algorithm).await
+    }
+
+    // Metadata of a single file or folder, by default looked up in its parent's listing
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let (scheme, key) = path.split_once("://").unwrap_or(("", path));
+        let key = key.trim_matches('/');
+        if key.is_empty() {
+            return Ok(StorageItem::dir(format!("{}://", scheme), String::new()));
+        }
+
+        let path = format!("{}://{}", scheme, key);
+        let basename = key.rsplit('/').next().unwrap_or(key);
+        let items = match self.list_contents(&parent_path(&path)).await {
+            Ok(items) => items,
+            Err(StorageError::NotFound(_)) | Err(StorageError::NotADirectory(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        items
+            .into_iter()
+            .find(|item| item.basename == basename)
+            .ok_or(StorageError::NotFound(path))
+    }
+
+    // Whether `path` is a directory, by default whether it can be listed
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        Ok(self.list_contents(path).await.is_ok())
+    }
+
+    // Report capacity when the backend knows it, `None` otherwise
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        Ok(None)
+    }
+
+    // What the adapter does natively, nothing beyond the basics by default
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities::default()
+    }
+
+    // Append to the end of a file, creating it when missing
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let _guard = lock::lock_path(&format!("{}:{}", self.name(), path)).await;
+
+        let mut current = match self.read(path).await {
+            Ok(current) => current,
+            Err(StorageError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        current.extend_from_slice(&contents);
+        self.write(path, current).await
+    }
+
+    // Write only if the current contents hash to `expected_hash` (empty for a missing file).
+    // Returns whether the write happened.
+    async fn write_if_match(
+        &self,
+        path: &str,
+        expected_hash: &str,
+        contents: Vec<u8>,
+    ) -> Result<bool, StorageError> {
+        let _guard = lock::lock_path(&format!("{}:{}", self.name(), path)).await;
+
+        let current_hash = match self.read(path).await {
+            Ok(current) => content_hash(&current),
+            Err(StorageError::NotFound(_)) => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        if !current_hash.eq_ignore_ascii_case(expected_hash) {
+            return Ok(false);
+        }
+
+        self.write(path, contents).await?;
+        Ok(true)
+    }
+}
+
+// An entry found by `walk`, with its path relative to the walked directory
+#[derive(Debug)]
+pub struct WalkEntry {
+    pub relative_path: String,
+    pub item: StorageItem,
 }
 
-#[derive(Debug, Serialize)]
+// Recursively list everything below `path`, parents before children.
+// Fails with `LimitExceeded` instead of traversing more than `limit` entries.
+pub async fn walk(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    limit: usize,
+) -> Result<Vec<WalkEntry>, StorageError> {
+    let mut entries = Vec::new();
+    let mut pending = vec![(path.to_string(), String::new())];
+
+    while let Some((dir_path, prefix)) = pending.pop() {
+        let mut contents = storage.list_stream(&dir_path);
+
+        while let Some(item) = contents.try_next().await? {
+            if entries.len() >= limit {
+                return Err(StorageError::LimitExceeded(limit));
+            }
+
+            let relative_path = if prefix.is_empty() {
+                item.basename.clone()
+            } else {
+                format!("{}/{}", prefix, item.basename)
+            };
+            if item.node_type == "dir" {
+                pending.push((item.path.clone(), relative_path.clone()));
+            }
+            entries.push(WalkEntry {
+                relative_path,
+                item,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct StorageItem {
     #[serde(rename = "type")]
     pub node_type: String,
@@ -39,4 +392,41 @@ pub struct StorageItem {
     pub size: Option<u64>,
 }
 
+impl StorageItem {
+    // A folder, which has no extension, type, time or size
+    pub fn dir(path: String, basename: String) -> Self {
+        Self {
+            node_type: "dir".to_string(),
+            path,
+            basename,
+            extension: None,
+            mime_type: None,
+            last_modified: None,
+            size: None,
+        }
+    }
+}
+
+pub mod cached;
+pub mod checksum;
+pub mod collision;
+pub mod config;
+pub mod dropbox;
+pub mod embedded;
+pub mod encrypted;
+pub mod gcs;
+pub mod gdrive;
+#[cfg(any(feature = "gcs", feature = "gdrive"))]
+mod google_auth;
 pub mod local;
+pub mod lock;
+pub mod memory;
+#[cfg(feature = "opendal")]
+pub mod opendal;
+pub mod read_only;
+pub mod scoped;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod traced;
+pub mod transfer;
+pub mod union;
@@ -0,0 +1,401 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+use mime_guess::from_path;
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+use ::opendal::{EntryMode, ErrorKind, Metadata, Metakey, Operator, Scheme};
+
+use super::checksum::{digest_stream, etag_md5, ChecksumAlgorithm};
+use super::{StorageAdapter, StorageCapabilities, StorageError, StorageItem};
+
+fn storage_error(path: &str, error: ::opendal::Error) -> StorageError {
+    match error.kind() {
+        ErrorKind::NotFound => StorageError::NotFound(path.to_string()),
+        ErrorKind::AlreadyExists => StorageError::AlreadyExists(path.to_string()),
+        ErrorKind::PermissionDenied => StorageError::PermissionDenied(path.to_string()),
+        ErrorKind::NotADirectory => StorageError::NotADirectory(path.to_string()),
+        ErrorKind::Unsupported => StorageError::Unsupported(error.to_string()),
+        _ => StorageError::Io(std::io::Error::other(error.to_string())),
+    }
+}
+
+// Any backend Apache OpenDAL supports, S3, Azure Blob, OSS or WebDAV among
+// them, configured by its scheme and the service's own options
+pub struct OpendalStorage {
+    name: String,
+    operator: Operator,
+}
+
+impl OpendalStorage {
+    pub fn new(name: &str, operator: Operator) -> Self {
+        Self {
+            name: name.to_string(),
+            operator,
+        }
+    }
+
+    // `scheme` such as `s3` and the options of that service, e.g. `bucket`,
+    // `region` and `root` for S3
+    pub fn from_options(
+        name: &str,
+        scheme: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<Self, StorageError> {
+        let scheme = Scheme::from_str(scheme).map_err(|e| storage_error(name, e))?;
+        let operator =
+            Operator::via_iter(scheme, options.clone()).map_err(|e| storage_error(name, e))?;
+        Ok(Self::new(name, operator))
+    }
+
+    // Path relative to the operator's root, rejecting anything but plain relative paths
+    fn key(&self, path: &str) -> Result<String, StorageError> {
+        let clean_path = path
+            .strip_prefix(&format!("{}://", self.name))
+            .unwrap_or(path)
+            .trim_matches('/');
+
+        if Path::new(clean_path)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        Ok(clean_path.to_string())
+    }
+
+    // OpenDAL tells folders apart by their trailing `/`
+    fn dir_key(key: &str) -> String {
+        if key.is_empty() {
+            "/".to_string()
+        } else {
+            format!("{}/", key)
+        }
+    }
+
+    // Metadata of `key` as a file or else as a folder, `None` when it's neither
+    async fn stat(&self, path: &str, key: &str) -> Result<Option<Metadata>, StorageError> {
+        if key.is_empty() {
+            return Ok(Some(Metadata::new(EntryMode::DIR)));
+        }
+        for candidate in [key.to_string(), Self::dir_key(key)] {
+            match self.operator.stat(&candidate).await {
+                Ok(metadata) => return Ok(Some(metadata)),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(storage_error(path, e)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn to_storage_item(&self, key: &str, metadata: &Metadata) -> StorageItem {
+        let basename = key.rsplit('/').next().unwrap_or(key).to_string();
+        let path = format!("{}://{}", self.name, key);
+
+        if metadata.is_dir() {
+            return StorageItem {
+                node_type: "dir".to_string(),
+                path,
+                basename,
+                extension: None,
+                mime_type: None,
+                last_modified: None,
+                size: None,
+            };
+        }
+
+        StorageItem {
+            node_type: "file".to_string(),
+            path,
+            extension: Path::new(&basename)
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned()),
+            mime_type: Some(
+                metadata
+                    .content_type()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        from_path(&basename)
+                            .first_or_octet_stream()
+                            .essence_str()
+                            .to_owned()
+                    }),
+            ),
+            basename,
+            last_modified: metadata
+                .last_modified()
+                .and_then(|time| u64::try_from(time.timestamp()).ok()),
+            size: Some(metadata.content_length()),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for OpendalStorage {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let key = self.key(path)?;
+        match self.stat(path, &key).await? {
+            Some(metadata) if metadata.is_dir() => {}
+            Some(_) => return Err(StorageError::NotADirectory(path.to_string())),
+            None => return Err(StorageError::NotFound(path.to_string())),
+        }
+
+        let dir = Self::dir_key(&key);
+        let entries = self
+            .operator
+            .list_with(&dir)
+            .metakey(Metakey::Mode | Metakey::ContentLength | Metakey::LastModified)
+            .await
+            .map_err(|e| storage_error(path, e))?;
+        Ok(entries
+            .iter()
+            // Listings include the folder itself
+            .filter(|entry| entry.path() != dir && entry.path() != dir.trim_start_matches('/'))
+            .map(|entry| {
+                let child = entry.path().trim_end_matches('/');
+                self.to_storage_item(child, entry.metadata())
+            })
+            .collect())
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let key = self.key(path)?;
+        match self.stat(path, &key).await? {
+            Some(metadata) => Ok(self.to_storage_item(&key, &metadata)),
+            None => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let key = self.key(path)?;
+        Ok(self
+            .operator
+            .read(&key)
+            .await
+            .map_err(|e| storage_error(path, e))?
+            .to_vec())
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        let key = self.key(path)?;
+        let size = self.file_size(path).await?;
+        let end = offset.saturating_add(length).min(size);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .operator
+            .read_with(&key)
+            .range(offset..end)
+            .await
+            .map_err(|e| storage_error(path, e))?
+            .to_vec())
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let key = self.key(path)?;
+        match self.operator.stat(&key).await {
+            Ok(metadata) if metadata.is_file() => Ok(metadata.content_length()),
+            Ok(_) => Err(StorageError::InvalidPath(path.to_string())),
+            Err(e) => Err(storage_error(path, e)),
+        }
+    }
+
+    // ETags that are an MD5, like S3's for single-part uploads, save reading the file
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        if algorithm == ChecksumAlgorithm::Md5 {
+            let key = self.key(path)?;
+            let metadata = self
+                .operator
+                .stat(&key)
+                .await
+                .map_err(|e| storage_error(path, e))?;
+            if let Some(stored) = metadata.etag().and_then(etag_md5) {
+                return Ok(stored);
+            }
+        }
+        digest_stream(self.read_stream(path).await?, algorithm).await
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let key = self.key(path)?;
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        self.operator
+            .write(&key, contents)
+            .await
+            .map_err(|e| storage_error(path, e))?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        let key = self.key(path)?;
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        // Services upload in parts where they can, so nothing is kept whole in memory
+        let mut writer = self
+            .operator
+            .writer(&key)
+            .await
+            .map_err(|e| storage_error(path, e))?;
+        loop {
+            let chunk = match stream.try_next().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = writer.abort().await;
+                    return Err(e);
+                }
+            };
+            if let Err(e) = writer.write(chunk).await {
+                let _ = writer.abort().await;
+                return Err(storage_error(path, e));
+            }
+        }
+        writer.close().await.map_err(|e| storage_error(path, e))?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let key = self.key(path)?;
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        match self.stat(path, &key).await? {
+            Some(metadata) if metadata.is_dir() => self
+                .operator
+                .remove_all(&Self::dir_key(&key))
+                .await
+                .map_err(|e| storage_error(path, e)),
+            Some(_) => self
+                .operator
+                .delete(&key)
+                .await
+                .map_err(|e| storage_error(path, e)),
+            None => Err(StorageError::NotFound(path.to_string())),
+        }
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        let key = self.key(path)?;
+        if key.is_empty() {
+            return Ok(());
+        }
+        self.operator
+            .create_dir(&Self::dir_key(&key))
+            .await
+            .map_err(|e| storage_error(path, e))
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        let key = self.key(path)?;
+        Ok(self.stat(path, &key).await?.is_some())
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        let key = self.key(path)?;
+        Ok(self
+            .stat(path, &key)
+            .await?
+            .is_some_and(|metadata| metadata.is_dir()))
+    }
+
+    // Services without a native rename answer `Unsupported`, so callers copy instead
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from_key = self.key(from)?;
+        let to_key = self.key(to)?;
+        if from_key.is_empty() || to_key.is_empty() {
+            return Err(StorageError::InvalidPath(from.to_string()));
+        }
+        self.operator
+            .rename(&from_key, &to_key)
+            .await
+            .map_err(|e| storage_error(from, e))
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        // Depends on the service, not every one can rename
+        let capability = self.operator.info().full_capability();
+        StorageCapabilities {
+            streaming: capability.write_can_multi,
+            rename: capability.rename,
+            range_read: true,
+            ..StorageCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_opendal_storage() {
+        let options = HashMap::from([("root".to_string(), "/files".to_string())]);
+        let storage = OpendalStorage::from_options("dal", "memory", &options).unwrap();
+        assert!(OpendalStorage::from_options("dal", "nope", &options).is_err());
+
+        storage
+            .write("dal://docs/intro.md", b"# Intro".to_vec())
+            .await
+            .unwrap();
+        let chunks = vec![Ok(Bytes::from("a")), Ok(Bytes::from("bc"))];
+        storage
+            .write_stream("dal://a.txt", Box::pin(futures_util::stream::iter(chunks)))
+            .await
+            .unwrap();
+        storage.create_dir("dal://empty").await.unwrap();
+
+        let mut paths: Vec<_> = storage
+            .list_contents("dal://")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| (item.path, item.node_type))
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                ("dal://a.txt".to_string(), "file".to_string()),
+                ("dal://docs".to_string(), "dir".to_string()),
+                ("dal://empty".to_string(), "dir".to_string()),
+            ]
+        );
+        assert_eq!(
+            storage.read_range("dal://a.txt", 1, 5).await.unwrap(),
+            b"bc"
+        );
+        assert!(storage.is_dir("dal://docs").await.unwrap());
+        assert!(matches!(
+            storage.read("dal://missing.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        storage.delete("dal://docs").await.unwrap();
+        assert!(!storage.exists("dal://docs/intro.md").await.unwrap());
+        assert!(storage.read("dal://../escape").await.is_err());
+    }
+}
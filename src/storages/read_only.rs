@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use std::sync::Arc;
+
+use super::checksum::ChecksumAlgorithm;
+use super::{StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage};
+
+// Serves another adapter's contents and refuses every change with `ReadOnly`
+pub struct ReadOnlyStorage {
+    inner: Arc<dyn StorageAdapter>,
+}
+
+impl ReadOnlyStorage {
+    pub fn new(inner: Arc<dyn StorageAdapter>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for ReadOnlyStorage {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        self.inner.list_contents(path).await
+    }
+
+    fn list_stream<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> BoxStream<'a, Result<StorageItem, StorageError>> {
+        self.inner.list_stream(path)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        self.inner.read(path).await
+    }
+
+    async fn write(&self, path: &str, _contents: Vec<u8>) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        _stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.exists(path).await
+    }
+
+    async fn rename(&self, from: &str, _to: &str) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(from.to_string()))
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        self.inner.validate_key(name)
+    }
+
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.inner.read_stream(path).await
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.inner.read_stream_range(path, offset, length).await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        self.inner.file_size(path).await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.inner.read_range(path, offset, length).await
+    }
+
+    fn local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.local_path(path)
+    }
+
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        self.inner.checksum(path, algorithm).await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        self.inner.metadata(path).await
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        self.inner.is_dir(path).await
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.inner.usage().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            rename: false,
+            read_only: true,
+            ..self.inner.capabilities()
+        }
+    }
+
+    async fn append(&self, path: &str, _contents: Vec<u8>) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+
+    async fn write_if_match(
+        &self,
+        path: &str,
+        _expected_hash: &str,
+        _contents: Vec<u8>,
+    ) -> Result<bool, StorageError> {
+        Err(StorageError::ReadOnly(path.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_read_only_storage() {
+        let inner = Arc::new(MemoryStorage::new("demo"));
+        inner.write("demo://a.txt", b"a".to_vec()).await.unwrap();
+        let storage = ReadOnlyStorage::new(inner);
+
+        assert_eq!(storage.read("demo://a.txt").await.unwrap(), b"a");
+        assert_eq!(storage.list_contents("demo://").await.unwrap().len(), 1);
+        assert!(matches!(
+            storage.write("demo://b.txt", Vec::new()).await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.delete("demo://a.txt").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.create_dir("demo://docs").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            storage.rename("demo://a.txt", "demo://b.txt").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        assert!(storage.exists("demo://a.txt").await.unwrap());
+    }
+}
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use std::sync::Arc;
+
+use super::checksum::ChecksumAlgorithm;
+use super::{StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage};
+
+// Shows only the folder `root` of another adapter, as if it were the whole
+// adapter: `local://a.txt` is `local://<root>/a.txt` on the wrapped one. The
+// folder is created by the first write, until then it lists as empty.
+pub struct ScopedStorage {
+    inner: Arc<dyn StorageAdapter>,
+    root: String,
+}
+
+impl ScopedStorage {
+    pub fn new(inner: Arc<dyn StorageAdapter>, root: &str) -> Self {
+        Self {
+            inner,
+            root: root.trim_matches('/').to_string(),
+        }
+    }
+
+    // `scheme://key` -> (`scheme`, `key` without surrounding slashes)
+    fn split(path: &str) -> (&str, &str) {
+        match path.split_once("://") {
+            Some((scheme, key)) => (scheme, key.trim_matches('/')),
+            None => ("", path.trim_matches('/')),
+        }
+    }
+
+    fn is_root(path: &str) -> bool {
+        Self::split(path).1.is_empty()
+    }
+
+    // Path on the wrapped adapter, refusing `..` so nothing outside `root` is reachable
+    fn inner_path(&self, path: &str) -> Result<String, StorageError> {
+        let (scheme, key) = Self::split(path);
+        if key.split('/').any(|segment| segment == "..") {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        Ok(match key {
+            "" => format!("{}://{}", scheme, self.root),
+            key => format!("{}://{}/{}", scheme, self.root, key),
+        })
+    }
+
+    fn outer_path(&self, path: &str) -> String {
+        let (scheme, key) = Self::split(path);
+        let key = key
+            .strip_prefix(self.root.as_str())
+            .unwrap_or(key)
+            .trim_start_matches('/');
+        format!("{}://{}", scheme, key)
+    }
+
+    fn outer_item(&self, mut item: StorageItem) -> StorageItem {
+        item.path = self.outer_path(&item.path);
+        item
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for ScopedStorage {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        match self.inner.list_contents(&self.inner_path(path)?).await {
+            Ok(items) => Ok(items
+                .into_iter()
+                .map(|item| self.outer_item(item))
+                .collect()),
+            Err(StorageError::NotFound(_)) if Self::is_root(path) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn list_stream<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> BoxStream<'a, Result<StorageItem, StorageError>> {
+        Box::pin(async_stream::stream! {
+            let inner_path = match self.inner_path(path) {
+                Ok(inner_path) => inner_path,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut items = self.inner.list_stream(&inner_path);
+            while let Some(item) = items.next().await {
+                match item {
+                    Ok(item) => yield Ok(self.outer_item(item)),
+                    Err(StorageError::NotFound(_)) if Self::is_root(path) => return,
+                    Err(e) => yield Err(e),
+                }
+            }
+        })
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        self.inner.read(&self.inner_path(path)?).await
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        self.inner.write(&self.inner_path(path)?, contents).await
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        self.inner
+            .write_stream(&self.inner_path(path)?, stream)
+            .await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        if Self::is_root(path) {
+            return Err(StorageError::PermissionDenied(path.to_string()));
+        }
+        self.inner.delete(&self.inner_path(path)?).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        self.inner.create_dir(&self.inner_path(path)?).await
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        if Self::is_root(path) {
+            return Ok(true);
+        }
+        self.inner.exists(&self.inner_path(path)?).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.inner
+            .rename(&self.inner_path(from)?, &self.inner_path(to)?)
+            .await
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        self.inner.validate_key(name)
+    }
+
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.inner.read_stream(&self.inner_path(path)?).await
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.inner
+            .read_stream_range(&self.inner_path(path)?, offset, length)
+            .await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        self.inner.file_size(&self.inner_path(path)?).await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.inner
+            .read_range(&self.inner_path(path)?, offset, length)
+            .await
+    }
+
+    fn local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.local_path(&self.inner_path(path).ok()?)
+    }
+
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        self.inner
+            .checksum(&self.inner_path(path)?, algorithm)
+            .await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        // The folder may not be created yet, see `list_contents`
+        if Self::is_root(path) {
+            let (scheme, _) = Self::split(path);
+            return Ok(StorageItem::dir(format!("{}://", scheme), String::new()));
+        }
+        let item = self.inner.metadata(&self.inner_path(path)?).await?;
+        Ok(self.outer_item(item))
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        if Self::is_root(path) {
+            return Ok(true);
+        }
+        self.inner.is_dir(&self.inner_path(path)?).await
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.inner.usage().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        self.inner.append(&self.inner_path(path)?, contents).await
+    }
+
+    async fn write_if_match(
+        &self,
+        path: &str,
+        expected_hash: &str,
+        contents: Vec<u8>,
+    ) -> Result<bool, StorageError> {
+        self.inner
+            .write_if_match(&self.inner_path(path)?, expected_hash, contents)
+            .await
+    }
+
+    fn shared_path(&self, path: &str) -> String {
+        self.inner_path(path)
+            .map(|inner_path| self.inner.shared_path(&inner_path))
+            .unwrap_or_else(|_| path.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_scoped_storage() {
+        let inner = Arc::new(MemoryStorage::new("home"));
+        inner
+            .write("home://users/bob/b.txt", b"bob".to_vec())
+            .await
+            .unwrap();
+        let storage = ScopedStorage::new(inner.clone(), "users/alice");
+
+        // Nothing written yet
+        assert!(storage.list_contents("home://").await.unwrap().is_empty());
+        assert!(storage.is_dir("home://").await.unwrap());
+
+        storage
+            .write("home://docs/a.txt", b"alice".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            inner.read("home://users/alice/docs/a.txt").await.unwrap(),
+            b"alice"
+        );
+        let items = storage.list_contents("home://docs").await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].path, "home://docs/a.txt");
+        assert_eq!(
+            storage.shared_path("home://docs/a.txt"),
+            "home://users/alice/docs/a.txt"
+        );
+
+        // Other users' folders stay out of reach
+        assert!(storage.read("home://b.txt").await.is_err());
+        assert!(matches!(
+            storage.read("home://../bob/b.txt").await,
+            Err(StorageError::InvalidPath(_))
+        ));
+    }
+}
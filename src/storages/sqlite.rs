@@ -0,0 +1,508 @@
+use async_trait::async_trait;
+use mime_guess::from_path;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::{relative_key, StorageAdapter, StorageCapabilities, StorageError, StorageItem};
+
+// Paths are keyed relative to the root without the scheme, `parent` being
+// `''` for top-level entries. The root itself has no row.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        path TEXT PRIMARY KEY,
+        parent TEXT NOT NULL,
+        is_dir INTEGER NOT NULL,
+        contents BLOB,
+        size INTEGER,
+        modified INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS entries_parent ON entries (parent);
+";
+
+fn sql_error(error: rusqlite::Error) -> StorageError {
+    StorageError::Io(std::io::Error::other(error.to_string()))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn parent_key(key: &str) -> &str {
+    key.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+// Bounds of the keys below `key`: `key/` up to `key0`, `0` following `/`
+fn subtree(key: &str) -> (String, String) {
+    (format!("{}/", key), format!("{}0", key))
+}
+
+// Whether `key` is a folder, `None` when missing. The root always is.
+fn is_dir(tx: &Transaction, key: &str) -> Result<Option<bool>, StorageError> {
+    if key.is_empty() {
+        return Ok(Some(true));
+    }
+    tx.query_row(
+        "SELECT is_dir FROM entries WHERE path = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(sql_error)
+}
+
+// Create the missing folders above `key`, refusing to go through a file
+fn insert_parents(tx: &Transaction, key: &str, path: &str) -> Result<(), StorageError> {
+    let mut ancestors = Vec::new();
+    let mut parent = parent_key(key);
+    while !parent.is_empty() {
+        ancestors.push(parent);
+        parent = parent_key(parent);
+    }
+    for ancestor in ancestors.into_iter().rev() {
+        match is_dir(tx, ancestor)? {
+            Some(true) => {}
+            Some(false) => return Err(StorageError::NotADirectory(path.to_string())),
+            None => {
+                tx.execute(
+                    "INSERT INTO entries (path, parent, is_dir, modified) VALUES (?1, ?2, 1, ?3)",
+                    params![ancestor, parent_key(ancestor), now()],
+                )
+                .map_err(sql_error)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Storage kept in a single SQLite database file, contents included. Every
+// change is one transaction, so renames of whole folders are atomic and a
+// crash never leaves half a file behind.
+pub struct SqliteStorage {
+    scheme: String,
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    // Open or create the database at `path`, `:memory:` for a throwaway one
+    pub fn open(scheme: &str, path: &str) -> Result<Self, StorageError> {
+        let connection = Connection::open(path).map_err(sql_error)?;
+        // WAL lets readers in while a write is going on
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(sql_error)?;
+        connection.execute_batch(SCHEMA).map_err(sql_error)?;
+        Ok(Self {
+            scheme: scheme.to_string(),
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    // Run `f` in a transaction on a blocking thread, committing when it succeeds
+    async fn transaction<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Transaction) -> Result<T, StorageError> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+            let tx = connection.transaction().map_err(sql_error)?;
+            let result = f(&tx)?;
+            tx.commit().map_err(sql_error)?;
+            Ok(result)
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))?
+    }
+
+    fn to_storage_item(
+        scheme: &str,
+        key: &str,
+        is_dir: bool,
+        size: Option<i64>,
+        modified: i64,
+    ) -> StorageItem {
+        let basename = key.rsplit('/').next().unwrap_or(key).to_string();
+        let path = format!("{}://{}", scheme, key);
+
+        if is_dir {
+            return StorageItem {
+                node_type: "dir".to_string(),
+                path,
+                basename,
+                extension: None,
+                mime_type: None,
+                last_modified: None,
+                size: None,
+            };
+        }
+
+        StorageItem {
+            node_type: "file".to_string(),
+            path,
+            extension: Path::new(&basename)
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned()),
+            mime_type: Some(
+                from_path(&basename)
+                    .first_or_octet_stream()
+                    .essence_str()
+                    .to_owned(),
+            ),
+            basename,
+            last_modified: u64::try_from(modified).ok(),
+            size: size.and_then(|size| u64::try_from(size).ok()),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for SqliteStorage {
+    fn name(&self) -> String {
+        self.scheme.clone()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        let path = path.to_string();
+        let scheme = self.scheme.clone();
+        self.transaction(move |tx| {
+            match is_dir(tx, &key)? {
+                Some(true) => {}
+                Some(false) => return Err(StorageError::NotADirectory(path)),
+                None => return Err(StorageError::NotFound(path)),
+            }
+
+            let mut statement = tx
+                .prepare(
+                    "SELECT path, is_dir, size, modified FROM entries
+                     WHERE parent = ?1 ORDER BY path",
+                )
+                .map_err(sql_error)?;
+            let items = statement
+                .query_map(params![key], |row| {
+                    Ok(Self::to_storage_item(
+                        &scheme,
+                        &row.get::<_, String>(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                    ))
+                })
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(sql_error)?;
+            Ok(items)
+        })
+        .await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        if key.is_empty() {
+            return Ok(Self::to_storage_item(&self.scheme, &key, true, None, 0));
+        }
+        let path = path.to_string();
+        let scheme = self.scheme.clone();
+        self.transaction(move |tx| {
+            tx.query_row(
+                "SELECT is_dir, size, modified FROM entries WHERE path = ?1",
+                params![key],
+                |row| {
+                    Ok(Self::to_storage_item(
+                        &scheme,
+                        &key,
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(sql_error)?
+            .ok_or(StorageError::NotFound(path))
+        })
+        .await
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        let path = path.to_string();
+        self.transaction(move |tx| {
+            tx.query_row(
+                "SELECT contents FROM entries WHERE path = ?1 AND is_dir = 0",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?
+            .ok_or(StorageError::NotFound(path))
+        })
+        .await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        let path = path.to_string();
+        // `substr` counts bytes of blobs, from 1
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX - 1) + 1;
+        let length = i64::try_from(length).unwrap_or(i64::MAX);
+        self.transaction(move |tx| {
+            tx.query_row(
+                "SELECT substr(contents, ?2, ?3) FROM entries WHERE path = ?1 AND is_dir = 0",
+                params![key, offset, length],
+                |row| row.get::<_, Option<Vec<u8>>>(0),
+            )
+            .optional()
+            .map_err(sql_error)?
+            .map(Option::unwrap_or_default)
+            .ok_or(StorageError::NotFound(path))
+        })
+        .await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        let path = path.to_string();
+        self.transaction(move |tx| {
+            tx.query_row(
+                "SELECT size FROM entries WHERE path = ?1 AND is_dir = 0",
+                params![key],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map_err(sql_error)?
+            .map(|size| size as u64)
+            .ok_or(StorageError::NotFound(path))
+        })
+        .await
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        let path = path.to_string();
+        self.transaction(move |tx| {
+            if is_dir(tx, &key)? == Some(true) {
+                return Err(StorageError::InvalidPath(path));
+            }
+            insert_parents(tx, &key, &path)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO entries (path, parent, is_dir, contents, size, modified)
+                 VALUES (?1, ?2, 0, ?3, ?4, ?5)",
+                params![
+                    key,
+                    parent_key(&key),
+                    contents,
+                    contents.len() as i64,
+                    now()
+                ],
+            )
+            .map_err(sql_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        let path = path.to_string();
+        // Read and written back in one transaction, `||` would turn blobs into text
+        self.transaction(move |tx| {
+            if is_dir(tx, &key)? == Some(true) {
+                return Err(StorageError::InvalidPath(path));
+            }
+            insert_parents(tx, &key, &path)?;
+            let mut current: Vec<u8> = tx
+                .query_row(
+                    "SELECT contents FROM entries WHERE path = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(sql_error)?
+                .unwrap_or_default();
+            current.extend_from_slice(&contents);
+            tx.execute(
+                "INSERT OR REPLACE INTO entries (path, parent, is_dir, contents, size, modified)
+                 VALUES (?1, ?2, 0, ?3, ?4, ?5)",
+                params![key, parent_key(&key), current, current.len() as i64, now()],
+            )
+            .map_err(sql_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        if key.is_empty() {
+            return Err(StorageError::InvalidPath(path.to_string()));
+        }
+        let path = path.to_string();
+        self.transaction(move |tx| {
+            let (from, to) = subtree(&key);
+            let deleted = tx
+                .execute(
+                    "DELETE FROM entries WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
+                    params![key, from, to],
+                )
+                .map_err(sql_error)?;
+            if deleted == 0 {
+                return Err(StorageError::NotFound(path));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        let path = path.to_string();
+        self.transaction(move |tx| match is_dir(tx, &key)? {
+            Some(true) => Ok(()),
+            Some(false) => Err(StorageError::InvalidPath(path)),
+            None => {
+                insert_parents(tx, &key, &path)?;
+                tx.execute(
+                    "INSERT INTO entries (path, parent, is_dir, modified) VALUES (?1, ?2, 1, ?3)",
+                    params![key, parent_key(&key), now()],
+                )
+                .map_err(sql_error)?;
+                Ok(())
+            }
+        })
+        .await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from_key = relative_key(&self.scheme, from)?.to_string();
+        let to_key = relative_key(&self.scheme, to)?.to_string();
+        if from_key.is_empty() || to_key.is_empty() || to_key.starts_with(&format!("{}/", from_key))
+        {
+            return Err(StorageError::InvalidPath(to.to_string()));
+        }
+        if from_key == to_key {
+            return Ok(());
+        }
+        let (from, to) = (from.to_string(), to.to_string());
+        self.transaction(move |tx| {
+            if is_dir(tx, &from_key)?.is_none() {
+                return Err(StorageError::NotFound(from));
+            }
+            // Whatever was at the target is replaced
+            let (start, end) = subtree(&to_key);
+            tx.execute(
+                "DELETE FROM entries WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
+                params![to_key, start, end],
+            )
+            .map_err(sql_error)?;
+            insert_parents(tx, &to_key, &to)?;
+
+            tx.execute(
+                "UPDATE entries SET path = ?2, parent = ?3 WHERE path = ?1",
+                params![from_key, to_key, parent_key(&to_key)],
+            )
+            .map_err(sql_error)?;
+            // Re-key everything below, `substr` counting characters of text from 1
+            let (start, end) = subtree(&from_key);
+            let rest = from_key.chars().count() as i64 + 1;
+            tx.execute(
+                "UPDATE entries SET path = ?1 || substr(path, ?2), parent = ?1 || substr(parent, ?2)
+                 WHERE path >= ?3 AND path < ?4",
+                params![to_key, rest, start, end],
+            )
+            .map_err(sql_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        self.transaction(move |tx| Ok(is_dir(tx, &key)?.is_some()))
+            .await
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        let key = relative_key(&self.scheme, path)?.to_string();
+        self.transaction(move |tx| Ok(is_dir(tx, &key)? == Some(true)))
+            .await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities {
+            rename: true,
+            range_read: true,
+            ..StorageCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_sqlite_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = temp_dir.path().join("files.db");
+        let storage = SqliteStorage::open("db", &db.to_string_lossy()).unwrap();
+
+        storage
+            .write("db://docs/guide/intro.md", b"# Intro".to_vec())
+            .await
+            .unwrap();
+        storage.write("db://a.txt", b"a".to_vec()).await.unwrap();
+        storage.append("db://a.txt", b"bc".to_vec()).await.unwrap();
+        storage.create_dir("db://empty").await.unwrap();
+
+        let entries = storage.list_contents("db://").await.unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["db://a.txt", "db://docs", "db://empty"]);
+        assert_eq!(entries[0].size, Some(3));
+        assert_eq!(storage.read_range("db://a.txt", 1, 5).await.unwrap(), b"bc");
+        assert!(matches!(
+            storage.list_contents("db://a.txt").await,
+            Err(StorageError::NotADirectory(_))
+        ));
+        assert!(storage.write("db://a.txt/b", Vec::new()).await.is_err());
+
+        storage.rename("db://docs", "db://manual").await.unwrap();
+        assert_eq!(
+            storage.read("db://manual/guide/intro.md").await.unwrap(),
+            b"# Intro"
+        );
+        let entries = storage.list_contents("db://manual").await.unwrap();
+        assert_eq!(entries[0].path, "db://manual/guide");
+        assert!(!storage.exists("db://docs/guide").await.unwrap());
+
+        // Everything is kept in the file
+        drop(storage);
+        let storage = SqliteStorage::open("db", &db.to_string_lossy()).unwrap();
+        assert!(storage.is_dir("db://manual/guide").await.unwrap());
+
+        storage.delete("db://manual").await.unwrap();
+        assert!(!storage.exists("db://manual/guide/intro.md").await.unwrap());
+        assert!(matches!(
+            storage.read("db://missing.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(storage.read("db://../escape").await.is_err());
+    }
+}
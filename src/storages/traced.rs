@@ -0,0 +1,216 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{field, Instrument};
+
+use super::checksum::ChecksumAlgorithm;
+use super::{StorageAdapter, StorageCapabilities, StorageError, StorageItem, StorageUsage};
+
+// Runs every call of another adapter in a `storage` span carrying the
+// operation, adapter name and path, and the error of failed calls
+pub struct TracedStorage {
+    inner: Arc<dyn StorageAdapter>,
+    name: String,
+}
+
+impl TracedStorage {
+    pub fn new(inner: Arc<dyn StorageAdapter>) -> Self {
+        Self {
+            name: inner.name(),
+            inner,
+        }
+    }
+
+    async fn traced<T>(
+        &self,
+        operation: &'static str,
+        path: &str,
+        call: impl Future<Output = Result<T, StorageError>>,
+    ) -> Result<T, StorageError> {
+        let span = tracing::info_span!(
+            "storage",
+            otel.name = %format_args!("storage.{}", operation),
+            operation,
+            adapter = %self.name,
+            path,
+            error = field::Empty,
+        );
+        let result = call.instrument(span.clone()).await;
+        if let Err(e) = &result {
+            span.record("error", field::display(e));
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for TracedStorage {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        self.traced("list_contents", path, self.inner.list_contents(path))
+            .await
+    }
+
+    fn list_stream<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> BoxStream<'a, Result<StorageItem, StorageError>> {
+        self.inner.list_stream(path)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        self.traced("read", path, self.inner.read(path)).await
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        self.traced("write", path, self.inner.write(path, contents))
+            .await
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        self.traced("write_stream", path, self.inner.write_stream(path, stream))
+            .await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        self.traced("delete", path, self.inner.delete(path)).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        self.traced("create_dir", path, self.inner.create_dir(path))
+            .await
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        self.traced("exists", path, self.inner.exists(path)).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.traced("rename", from, self.inner.rename(from, to))
+            .await
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        self.inner.validate_key(name)
+    }
+
+    // Only opening the stream is traced, not reading it
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.traced("read_stream", path, self.inner.read_stream(path))
+            .await
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        self.traced(
+            "read_stream_range",
+            path,
+            self.inner.read_stream_range(path, offset, length),
+        )
+        .await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        self.traced("file_size", path, self.inner.file_size(path))
+            .await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.traced(
+            "read_range",
+            path,
+            self.inner.read_range(path, offset, length),
+        )
+        .await
+    }
+
+    fn local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        self.inner.local_path(path)
+    }
+
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        self.traced("checksum", path, self.inner.checksum(path, algorithm))
+            .await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        self.traced("metadata", path, self.inner.metadata(path))
+            .await
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        self.traced("is_dir", path, self.inner.is_dir(path)).await
+    }
+
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.traced("usage", "", self.inner.usage()).await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn append(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        self.traced("append", path, self.inner.append(path, contents))
+            .await
+    }
+
+    async fn write_if_match(
+        &self,
+        path: &str,
+        expected_hash: &str,
+        contents: Vec<u8>,
+    ) -> Result<bool, StorageError> {
+        self.traced(
+            "write_if_match",
+            path,
+            self.inner.write_if_match(path, expected_hash, contents),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_traced_storage() {
+        let storage = TracedStorage::new(Arc::new(MemoryStorage::new("demo")));
+
+        assert_eq!(storage.name(), "demo");
+        storage.write("demo://a.txt", b"a".to_vec()).await.unwrap();
+        assert_eq!(storage.read("demo://a.txt").await.unwrap(), b"a");
+        assert_eq!(storage.list_contents("demo://").await.unwrap().len(), 1);
+        assert!(matches!(
+            storage.read("demo://missing.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
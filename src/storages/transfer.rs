@@ -0,0 +1,167 @@
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+
+use super::{walk, StorageAdapter, StorageError};
+
+// Told how many bytes were copied, chunk by chunk
+pub trait CopyProgress: Send + Sync {
+    fn copied(&self, bytes: u64);
+}
+
+// Move `source` to `target` natively when the storage can, by copying then
+// deleting the source otherwise
+pub async fn move_item(
+    storage: &dyn StorageAdapter,
+    source: &str,
+    target: &str,
+    limit: usize,
+) -> Result<(), StorageError> {
+    // Copying onto itself and then deleting the source would lose the item
+    if source == target {
+        return Ok(());
+    }
+    // Replacing a folder would merge into it or discard its contents
+    if storage.is_dir(target).await.unwrap_or(false) {
+        return Err(StorageError::AlreadyExists(target.to_string()));
+    }
+
+    match storage.rename(source, target).await {
+        Err(StorageError::Unsupported(_)) => {
+            copy_item(storage, source, storage, target, limit, None).await?;
+            storage.delete(source).await
+        }
+        result => result,
+    }
+}
+
+// Move `source` to another storage by copying it, then deleting the source
+pub async fn move_between(
+    from: &dyn StorageAdapter,
+    source: &str,
+    to: &dyn StorageAdapter,
+    target: &str,
+    limit: usize,
+    progress: Option<&dyn CopyProgress>,
+) -> Result<(), StorageError> {
+    if to.is_dir(target).await.unwrap_or(false) {
+        return Err(StorageError::AlreadyExists(target.to_string()));
+    }
+    copy_item(from, source, to, target, limit, progress).await?;
+    from.delete(source).await
+}
+
+// Copy a file chunk by chunk, reporting the bytes to `progress` when given
+pub async fn copy_file(
+    from: &dyn StorageAdapter,
+    source: &str,
+    to: &dyn StorageAdapter,
+    target: &str,
+    progress: Option<&dyn CopyProgress>,
+) -> Result<(), StorageError> {
+    let stream = from.read_stream(source).await?;
+    let stream: BoxStream<'_, _> = match progress {
+        Some(progress) => {
+            Box::pin(stream.inspect_ok(move |chunk| progress.copied(chunk.len() as u64)))
+        }
+        None => stream,
+    };
+    to.write_stream(target, stream).await
+}
+
+// Recreate the directory `source` and everything below it at `target`,
+// possibly on another storage. Returns the number of files copied.
+async fn copy_tree(
+    from: &dyn StorageAdapter,
+    source: &str,
+    to: &dyn StorageAdapter,
+    target: &str,
+    limit: usize,
+    progress: Option<&dyn CopyProgress>,
+) -> Result<usize, StorageError> {
+    let entries = walk(from, source, limit).await?;
+
+    to.create_dir(target).await?;
+    let mut files = 0;
+    for entry in entries {
+        let path = format!("{}/{}", target, entry.relative_path);
+        if entry.item.node_type == "dir" {
+            to.create_dir(&path).await?;
+        } else {
+            copy_file(from, &entry.item.path, to, &path, progress).await?;
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+// Copy the whole subtree for directories, the contents for files.
+// Returns the number of files copied.
+pub async fn copy_item(
+    from: &dyn StorageAdapter,
+    source: &str,
+    to: &dyn StorageAdapter,
+    target: &str,
+    limit: usize,
+    progress: Option<&dyn CopyProgress>,
+) -> Result<usize, StorageError> {
+    if from.is_dir(source).await.unwrap_or(false) {
+        copy_tree(from, source, to, target, limit, progress).await
+    } else {
+        copy_file(from, source, to, target, progress).await?;
+        Ok(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Default)]
+    struct Counter(AtomicU64);
+
+    impl CopyProgress for Counter {
+        fn copied(&self, bytes: u64) {
+            self.0.fetch_add(bytes, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_move() {
+        let from = MemoryStorage::new("mem");
+        let to = MemoryStorage::new("mem");
+        from.create_dir("mem://docs").await.unwrap();
+        from.write("mem://docs/a.txt", b"hello".to_vec())
+            .await
+            .unwrap();
+        from.create_dir("mem://docs/sub").await.unwrap();
+        from.write("mem://docs/sub/b.txt", b"hi".to_vec())
+            .await
+            .unwrap();
+
+        let progress = Counter::default();
+        let files = copy_item(&from, "mem://docs", &to, "mem://copy", 100, Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(files, 2);
+        assert_eq!(progress.0.load(Ordering::SeqCst), 7);
+        assert_eq!(to.read("mem://copy/sub/b.txt").await.unwrap(), b"hi");
+
+        // Onto itself is a no-op, onto a folder is refused
+        move_item(&from, "mem://docs", "mem://docs", 100)
+            .await
+            .unwrap();
+        assert!(from.exists("mem://docs/a.txt").await.unwrap());
+        assert!(matches!(
+            move_item(&from, "mem://docs/a.txt", "mem://docs/sub", 100).await,
+            Err(StorageError::AlreadyExists(_))
+        ));
+
+        move_between(&from, "mem://docs", &to, "mem://moved", 100, None)
+            .await
+            .unwrap();
+        assert!(!from.exists("mem://docs").await.unwrap());
+        assert_eq!(to.read("mem://moved/a.txt").await.unwrap(), b"hello");
+    }
+}
@@ -0,0 +1,381 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::checksum::ChecksumAlgorithm;
+use super::{
+    join_path, parent_path, StorageAdapter, StorageCapabilities, StorageError, StorageItem,
+    StorageUsage,
+};
+
+// Which layer of a `UnionStorage` changes go to
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WritePolicy {
+    // Only the first layer is written, the others are a read-only base that
+    // edited files are copied up from
+    #[default]
+    First,
+    // Every layer is written, in place: changes go to the first layer holding
+    // the entry, or else its folder
+    Existing,
+}
+
+// Several adapters merged into one namespace. Entries of earlier layers shadow
+// those of later ones at the same path, folders list the entries of all layers.
+pub struct UnionStorage {
+    name: String,
+    layers: Vec<Arc<dyn StorageAdapter>>,
+    write_policy: WritePolicy,
+}
+
+impl UnionStorage {
+    // `layers` must not be empty, the first one is on top
+    pub fn new(name: &str, layers: Vec<Arc<dyn StorageAdapter>>) -> Self {
+        assert!(!layers.is_empty(), "a union needs at least one layer");
+        Self {
+            name: name.to_string(),
+            layers,
+            write_policy: WritePolicy::default(),
+        }
+    }
+
+    pub fn with_write_policy(mut self, write_policy: WritePolicy) -> Self {
+        self.write_policy = write_policy;
+        self
+    }
+
+    fn key<'a>(&self, path: &'a str) -> &'a str {
+        path.strip_prefix(&format!("{}://", self.name))
+            .unwrap_or(path)
+            .trim_start_matches('/')
+    }
+
+    // `path` as the layer names it
+    fn at(&self, layer: &Arc<dyn StorageAdapter>, path: &str) -> String {
+        format!("{}://{}", layer.name(), self.key(path))
+    }
+
+    // Indexes of the layers holding `path`, top first
+    async fn holders(&self, path: &str) -> Result<Vec<usize>, StorageError> {
+        let mut holders = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.exists(&self.at(layer, path)).await? {
+                holders.push(index);
+            }
+        }
+        Ok(holders)
+    }
+
+    // The layer whose entry at `path` is visible
+    async fn find(&self, path: &str) -> Result<&Arc<dyn StorageAdapter>, StorageError> {
+        for layer in &self.layers {
+            if layer.exists(&self.at(layer, path)).await? {
+                return Ok(layer);
+            }
+        }
+        Err(StorageError::NotFound(path.to_string()))
+    }
+
+    // The layer a write to `path` goes to
+    async fn target(&self, path: &str) -> Result<&Arc<dyn StorageAdapter>, StorageError> {
+        if self.write_policy == WritePolicy::Existing {
+            for candidate in [path.to_string(), parent_path(path)] {
+                for layer in &self.layers {
+                    if layer.exists(&self.at(layer, &candidate)).await? {
+                        return Ok(layer);
+                    }
+                }
+            }
+        }
+        Ok(&self.layers[0])
+    }
+
+    // Under `First`, entries of lower layers can't be removed or moved
+    fn check_writable(&self, path: &str, holders: &[usize]) -> Result<(), StorageError> {
+        if self.write_policy == WritePolicy::First && holders.iter().any(|&index| index > 0) {
+            return Err(StorageError::ReadOnly(path.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for UnionStorage {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn list_contents(&self, path: &str) -> Result<Vec<StorageItem>, StorageError> {
+        let dir = format!("{}://{}", self.name, self.key(path));
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        let mut found = false;
+
+        for layer in &self.layers {
+            let contents = match layer.list_contents(&self.at(layer, path)).await {
+                Ok(contents) => contents,
+                Err(StorageError::NotFound(_)) | Err(StorageError::NotADirectory(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            found = true;
+            for mut item in contents {
+                if seen.insert(item.basename.clone()) {
+                    item.path = join_path(&dir, &item.basename);
+                    items.push(item);
+                }
+            }
+        }
+
+        if !found {
+            return Err(StorageError::NotFound(path.to_string()));
+        }
+        Ok(items)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let layer = self.find(path).await?;
+        layer.read(&self.at(layer, path)).await
+    }
+
+    async fn write(&self, path: &str, contents: Vec<u8>) -> Result<(), StorageError> {
+        let layer = self.target(path).await?;
+        layer.write(&self.at(layer, path), contents).await
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        stream: BoxStream<'_, Result<Bytes, StorageError>>,
+    ) -> Result<(), StorageError> {
+        let layer = self.target(path).await?;
+        layer.write_stream(&self.at(layer, path), stream).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let holders = self.holders(path).await?;
+        if holders.is_empty() {
+            return Err(StorageError::NotFound(path.to_string()));
+        }
+        self.check_writable(path, &holders)?;
+
+        // Otherwise the entry of a lower layer would show up in its place
+        for index in holders {
+            let layer = &self.layers[index];
+            layer.delete(&self.at(layer, path)).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), StorageError> {
+        let layer = self.target(path).await?;
+        layer.create_dir(&self.at(layer, path)).await
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        match self.find(path).await {
+            Ok(_) => Ok(true),
+            Err(StorageError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Moves within a single layer only, callers copy and delete otherwise
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let holders = self.holders(from).await?;
+        self.check_writable(from, &holders)?;
+        match holders[..] {
+            [] => Err(StorageError::NotFound(from.to_string())),
+            [index] => {
+                let layer = &self.layers[index];
+                layer
+                    .rename(&self.at(layer, from), &self.at(layer, to))
+                    .await
+            }
+            _ => Err(StorageError::Unsupported("rename".to_string())),
+        }
+    }
+
+    fn validate_key(&self, name: &str) -> Result<(), StorageError> {
+        self.layers
+            .iter()
+            .try_for_each(|layer| layer.validate_key(name))
+    }
+
+    async fn read_stream(
+        &self,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let layer = self.find(path).await?;
+        layer.read_stream(&self.at(layer, path)).await
+    }
+
+    async fn read_stream_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<BoxStream<'static, Result<Bytes, StorageError>>, StorageError> {
+        let layer = self.find(path).await?;
+        layer
+            .read_stream_range(&self.at(layer, path), offset, length)
+            .await
+    }
+
+    async fn file_size(&self, path: &str) -> Result<u64, StorageError> {
+        let layer = self.find(path).await?;
+        layer.file_size(&self.at(layer, path)).await
+    }
+
+    async fn read_range(
+        &self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, StorageError> {
+        let layer = self.find(path).await?;
+        layer
+            .read_range(&self.at(layer, path), offset, length)
+            .await
+    }
+
+    async fn checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, StorageError> {
+        let layer = self.find(path).await?;
+        layer.checksum(&self.at(layer, path), algorithm).await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<StorageItem, StorageError> {
+        let layer = self.find(path).await?;
+        let mut item = layer.metadata(&self.at(layer, path)).await?;
+        item.path = format!("{}://{}", self.name, self.key(path).trim_end_matches('/'));
+        Ok(item)
+    }
+
+    async fn is_dir(&self, path: &str) -> Result<bool, StorageError> {
+        match self.find(path).await {
+            Ok(layer) => layer.is_dir(&self.at(layer, path)).await,
+            Err(StorageError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // New files land on the first layer
+    async fn usage(&self) -> Result<Option<StorageUsage>, StorageError> {
+        self.layers[0].usage().await
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        // New entries go to the first layer, see `WritePolicy`
+        let first = self.layers[0].capabilities();
+        StorageCapabilities {
+            streaming: first.streaming,
+            rename: first.rename,
+            range_read: self
+                .layers
+                .iter()
+                .all(|layer| layer.capabilities().range_read),
+            public_urls: false,
+            quota: first.quota,
+            read_only: self
+                .layers
+                .iter()
+                .all(|layer| layer.capabilities().read_only),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+    use crate::storages::read_only::ReadOnlyStorage;
+
+    fn layers() -> (Arc<MemoryStorage>, Arc<MemoryStorage>) {
+        let overlay = Arc::new(MemoryStorage::new("overlay"));
+        let base = Arc::new(MemoryStorage::new("base").with_files([
+            ("templates/", ""),
+            ("templates/memo.md", "# Memo"),
+            ("readme.txt", "base"),
+        ]));
+        (overlay, base)
+    }
+
+    #[tokio::test]
+    async fn test_overlay() {
+        let (overlay, base) = layers();
+        let storage = UnionStorage::new(
+            "team",
+            vec![
+                overlay.clone(),
+                Arc::new(ReadOnlyStorage::new(base.clone())),
+            ],
+        );
+
+        storage
+            .write("team://readme.txt", b"edited".to_vec())
+            .await
+            .unwrap();
+        storage
+            .write("team://templates/mine.md", b"# Mine".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(storage.read("team://readme.txt").await.unwrap(), b"edited");
+        assert_eq!(base.read("base://readme.txt").await.unwrap(), b"base");
+
+        let mut names: Vec<_> = storage
+            .list_contents("team://templates")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.path)
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["team://templates/memo.md", "team://templates/mine.md"]
+        );
+        assert_eq!(storage.list_contents("team://").await.unwrap().len(), 2);
+
+        assert!(matches!(
+            storage.delete("team://templates/memo.md").await,
+            Err(StorageError::ReadOnly(_))
+        ));
+        storage
+            .rename("team://templates/mine.md", "team://templates/ours.md")
+            .await
+            .unwrap();
+        assert!(overlay.exists("overlay://templates/ours.md").await.unwrap());
+        assert!(matches!(
+            storage.read("team://missing.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_existing() {
+        let (overlay, base) = layers();
+        let storage = UnionStorage::new("team", vec![overlay.clone(), base.clone()])
+            .with_write_policy(WritePolicy::Existing);
+
+        storage
+            .write("team://templates/new.md", b"new".to_vec())
+            .await
+            .unwrap();
+        assert!(base.exists("base://templates/new.md").await.unwrap());
+        storage
+            .write("team://notes.txt", b"notes".to_vec())
+            .await
+            .unwrap();
+        assert!(overlay.exists("overlay://notes.txt").await.unwrap());
+
+        storage.delete("team://templates").await.unwrap();
+        assert!(!storage.exists("team://templates/memo.md").await.unwrap());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::payload::DiffCompare;
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::{walk, StorageAdapter, StorageError, StorageItem};
+use crate::trash::TRASH_DIR;
+use crate::versions::VERSIONS_DIR;
+
+// What `sync` changes below the target folder to mirror the source, by paths
+// relative to both folders
+#[derive(Default)]
+pub struct SyncPlan {
+    // Entries in the way of a source entry of the other kind and, when
+    // deleting extraneous entries, those the source doesn't have. Folders are
+    // listed without their contents.
+    pub delete: Vec<String>,
+    // Folders to create, parents first
+    pub create: Vec<String>,
+    // Files to copy with their source
+    pub copy: Vec<(String, StorageItem)>,
+    pub unchanged: usize,
+    // Entries of the other kind in the way, kept since nothing may be deleted
+    pub conflicts: Vec<String>,
+}
+
+impl SyncPlan {
+    // Bytes to copy
+    pub fn size(&self) -> u64 {
+        self.copy
+            .iter()
+            .map(|(_, item)| item.size.unwrap_or_default())
+            .sum()
+    }
+}
+
+// Compare the folder `source` of `from` with `target` of `to`, which may not
+// exist yet. `delete` also removes what the source doesn't have.
+pub async fn plan(
+    from: &dyn StorageAdapter,
+    source: &str,
+    to: &dyn StorageAdapter,
+    target: &str,
+    compare: DiffCompare,
+    delete: bool,
+    limit: usize,
+) -> Result<SyncPlan, StorageError> {
+    let sources = entries(from, source, limit).await?;
+    let targets = if to.exists(target).await? {
+        entries(to, target, limit).await?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut plan = SyncPlan::default();
+    // Folders that can't be synced, their contents are left alone too
+    let mut blocked = HashSet::new();
+    for (relative, item) in &sources {
+        if has_ancestor(&blocked, relative) {
+            continue;
+        }
+        let is_dir = item.node_type == "dir";
+        let existing = targets.get(relative);
+        let other_kind = existing.is_some_and(|existing| (existing.node_type == "dir") != is_dir);
+        if other_kind {
+            if !delete {
+                plan.conflicts.push(relative.clone());
+                blocked.insert(relative.clone());
+                continue;
+            }
+            plan.delete.push(relative.clone());
+        }
+
+        let existing = existing.filter(|_| !other_kind);
+        if is_dir {
+            if existing.is_none() {
+                plan.create.push(relative.clone());
+            }
+        } else if differs(from, item, to, existing, compare).await? {
+            plan.copy.push((relative.clone(), item.clone()));
+        } else {
+            plan.unchanged += 1;
+        }
+    }
+
+    if delete {
+        let mut deleted = HashSet::new();
+        for (relative, item) in &targets {
+            if sources.contains_key(relative) || has_ancestor(&deleted, relative) {
+                continue;
+            }
+            if item.node_type == "dir" {
+                deleted.insert(relative.clone());
+            }
+            plan.delete.push(relative.clone());
+        }
+    }
+    Ok(plan)
+}
+
+// Whether the target file is out of date. By metadata that's when the sizes
+// differ or the source was modified after the target was written.
+async fn differs(
+    from: &dyn StorageAdapter,
+    source: &StorageItem,
+    to: &dyn StorageAdapter,
+    target: Option<&StorageItem>,
+    compare: DiffCompare,
+) -> Result<bool, StorageError> {
+    let Some(target) = target else {
+        return Ok(true);
+    };
+    if source.size != target.size {
+        return Ok(true);
+    }
+    Ok(match compare {
+        DiffCompare::Meta => match (source.last_modified, target.last_modified) {
+            (Some(source), Some(target)) => source > target,
+            _ => true,
+        },
+        DiffCompare::Hash => {
+            let (source, target) = tokio::try_join!(
+                from.checksum(&source.path, ChecksumAlgorithm::Sha256),
+                to.checksum(&target.path, ChecksumAlgorithm::Sha256)
+            )?;
+            source != target
+        }
+    })
+}
+
+// Everything below `path` by relative path, parents first, without the trash
+// and versions folders
+async fn entries(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    limit: usize,
+) -> Result<BTreeMap<String, StorageItem>, StorageError> {
+    let root = format!("{}://", storage.name());
+    let internal = [
+        format!("{}{}", root, TRASH_DIR),
+        format!("{}{}", root, VERSIONS_DIR),
+    ];
+    Ok(walk(storage, path, limit)
+        .await?
+        .into_iter()
+        .filter(|entry| {
+            !internal.iter().any(|dir| {
+                entry.item.path == *dir || entry.item.path.starts_with(&format!("{}/", dir))
+            })
+        })
+        .map(|entry| (entry.relative_path, entry.item))
+        .collect())
+}
+
+fn has_ancestor(dirs: &HashSet<String>, relative: &str) -> bool {
+    relative
+        .match_indices('/')
+        .any(|(i, _)| dirs.contains(&relative[..i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_plan() {
+        let from = MemoryStorage::new("src").with_files([
+            ("site/a.txt", "a"),
+            ("site/b.txt", "bb"),
+            ("site/c.txt", "c"),
+            ("site/img/logo.png", "png"),
+            ("site/x/", ""),
+        ]);
+        let to = MemoryStorage::new("dst").with_files([
+            ("out/a.txt", "a"),
+            ("out/b.txt", "b"),
+            ("out/c.txt", "z"),
+            ("out/img", "not a folder"),
+            ("out/old/deep.txt", "old"),
+            ("out/x/keep.txt", "keep"),
+        ]);
+        let sync =
+            |target, compare, delete| plan(&from, "src://site", &to, target, compare, delete, 100);
+        let copied = |plan: &SyncPlan| -> Vec<String> {
+            plan.copy.iter().map(|(path, _)| path.clone()).collect()
+        };
+
+        // Without `delete` the file in the way of `img` stays, and what's below
+        // `img` isn't copied. Same sizes and older sources count as unchanged.
+        let kept = sync("dst://out", DiffCompare::Meta, false).await.unwrap();
+        assert_eq!(copied(&kept), ["b.txt"]);
+        assert_eq!(kept.conflicts, ["img"]);
+        assert_eq!(kept.unchanged, 2);
+        assert!(kept.delete.is_empty() && kept.create.is_empty());
+
+        let mirrored = sync("dst://out", DiffCompare::Hash, true).await.unwrap();
+        assert_eq!(copied(&mirrored), ["b.txt", "c.txt", "img/logo.png"]);
+        assert_eq!(mirrored.create, ["img"]);
+        assert_eq!(mirrored.delete, ["img", "old", "x/keep.txt"]);
+        assert_eq!((mirrored.unchanged, mirrored.size()), (1, 6));
+
+        // A missing target is created in full
+        let fresh = sync("dst://new", DiffCompare::Meta, true).await.unwrap();
+        assert_eq!(fresh.create, ["img", "x"]);
+        assert_eq!(copied(&fresh).len(), 4);
+    }
+}
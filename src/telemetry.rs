@@ -0,0 +1,40 @@
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::layer::SubscriberExt;
+
+// Keeps exporting spans while alive, sends the pending ones when dropped
+pub struct OtlpGuard(SdkTracerProvider);
+
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.shutdown() {
+            log::warn!("Failed to flush OTLP spans: {}", e);
+        }
+    }
+}
+
+// Install a global `tracing` subscriber exporting every span to the OTLP/HTTP
+// traces `endpoint`, e.g. `http://localhost:4318/v1/traces`
+pub fn init_otlp(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<OtlpGuard, Box<dyn std::error::Error + Send + Sync>> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("vuefinder"));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))?;
+    Ok(OtlpGuard(provider))
+}
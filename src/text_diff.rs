@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+
+// Lines of context around each change in a unified diff
+const CONTEXT: usize = 3;
+
+// Edits Myers' search explores before giving up and replacing everything that
+// differs, which bounds its memory to a few megabytes
+const MAX_EDITS: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TextDiff {
+    // Unified diff, empty when both sides are the same
+    pub diff: String,
+    // Lines added and removed
+    pub added: usize,
+    pub removed: usize,
+}
+
+// Unified diff turning `old` into `new`, with `---`/`+++` headers naming them
+pub fn unified_diff(old_label: &str, old: &str, new_label: &str, new: &str) -> TextDiff {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&a, &b);
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&i| ops[i].0 != Op::Equal).collect();
+    let mut result = TextDiff {
+        added: ops.iter().filter(|(op, _)| *op == Op::Insert).count(),
+        removed: ops.iter().filter(|(op, _)| *op == Op::Delete).count(),
+        ..TextDiff::default()
+    };
+    if changes.is_empty() {
+        return result;
+    }
+
+    result.diff = format!("--- {}\n+++ {}\n", old_label, new_label);
+    // Changes closer than twice the context share a hunk
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        match groups.last_mut() {
+            Some((_, last)) if i - *last <= 2 * CONTEXT => *last = i,
+            _ => groups.push((i, i)),
+        }
+    }
+    for (first, last) in groups {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(ops.len());
+        let count = |range: &[(Op, &str)], side: Op| {
+            range
+                .iter()
+                .filter(|(op, _)| *op == Op::Equal || *op == side)
+                .count()
+        };
+        let (old_before, new_before) = (
+            count(&ops[..start], Op::Delete),
+            count(&ops[..start], Op::Insert),
+        );
+        let (old_len, new_len) = (
+            count(&ops[start..end], Op::Delete),
+            count(&ops[start..end], Op::Insert),
+        );
+        // Empty ranges are given by the line before them
+        let line = |before: usize, len: usize| if len == 0 { before } else { before + 1 };
+        result.diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            line(old_before, old_len),
+            old_len,
+            line(new_before, new_len),
+            new_len
+        ));
+        for (op, text) in &ops[start..end] {
+            result.diff.push(match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            });
+            result.diff.push_str(text);
+            if !text.ends_with('\n') {
+                result.diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    result
+}
+
+// Shortest edit script between `a` and `b` by Myers' algorithm, after taking
+// off the lines both start and end with
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (middle_a, middle_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<(Op, &str)> = a[..prefix].iter().map(|line| (Op::Equal, *line)).collect();
+    match myers(middle_a, middle_b) {
+        Some(middle) => ops.extend(middle),
+        None => {
+            ops.extend(middle_a.iter().map(|line| (Op::Delete, *line)));
+            ops.extend(middle_b.iter().map(|line| (Op::Insert, *line)));
+        }
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|line| (Op::Equal, *line)));
+    ops
+}
+
+// `None` when more than `MAX_EDITS` edits are needed
+fn myers<'a>(a: &[&'a str], b: &[&'a str]) -> Option<Vec<(Op, &'a str)>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    // Furthest `x` reached on each diagonal `k = x - y`
+    let mut v = vec![0isize; 2 * max + 3];
+    // `v` before each round `d`, for diagonals `-d - 1..=d + 1`
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut done = false;
+    for d in 0..=max.min(MAX_EDITS) as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                done = true;
+                break;
+            }
+        }
+        if done {
+            break;
+        }
+    }
+    if !done {
+        return None;
+    }
+
+    let (mut x, mut y) = (n, m);
+    let mut ops = Vec::new();
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push((Op::Equal, a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push((Op::Insert, b[y as usize - 1]));
+                y -= 1;
+            } else {
+                ops.push((Op::Delete, a[x as usize - 1]));
+                x -= 1;
+            }
+        }
+    }
+    ops.reverse();
+    Some(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+        let diff = unified_diff("a/x.txt", old, "b/x.txt", new);
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        assert_eq!(
+            diff.diff,
+            "--- a/x.txt\n+++ b/x.txt\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -8,3 +8,4 @@\n h\n i\n j\n+k\n\\ No newline at end of file\n"
+        );
+
+        assert_eq!(unified_diff("a", old, "b", old), TextDiff::default());
+        let diff = unified_diff("a", "", "b", "x\n");
+        assert_eq!(diff.diff, "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n");
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let a = ["x\n", "a\n", "b\n", "c\n"];
+        let b = ["a\n", "c\n", "b\n", "c\n", "y\n"];
+        let ops = diff_lines(&a, &b);
+        let removed: Vec<_> = ops.iter().filter(|(op, _)| *op == Op::Delete).collect();
+        let added: Vec<_> = ops.iter().filter(|(op, _)| *op == Op::Insert).collect();
+        assert_eq!((removed.len(), added.len()), (1, 2));
+
+        // Replaying the script gives back both sides
+        let old: Vec<_> = ops
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .map(|(_, l)| *l)
+            .collect();
+        let new: Vec<_> = ops
+            .iter()
+            .filter(|(op, _)| *op != Op::Delete)
+            .map(|(_, l)| *l)
+            .collect();
+        assert_eq!(old, a);
+        assert_eq!(new, b);
+    }
+}
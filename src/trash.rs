@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use crate::storages::collision::{resolve_collision, CollisionPolicy};
+use crate::storages::transfer::move_item;
+use crate::storages::{parent_path, StorageAdapter, StorageError};
+
+// Folder at each adapter's root holding deleted items. An item is moved to
+// `.trash/<id>` and its original location is kept in `.trash/<id>.json`.
+pub const TRASH_DIR: &str = ".trash";
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TrashEntry {
+    pub id: String,
+    // Where the item was deleted from
+    pub path: String,
+    // Unix seconds
+    pub deleted_at: u64,
+    #[serde(rename = "type")]
+    pub node_type: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn trash_root(storage: &dyn StorageAdapter) -> String {
+    format!("{}://{}", storage.name(), TRASH_DIR)
+}
+
+// Whether `path` is the trash folder or inside it
+pub fn in_trash(storage: &dyn StorageAdapter, path: &str) -> bool {
+    let root = trash_root(storage);
+    let path = path.trim_end_matches('/');
+    path == root || path.starts_with(&format!("{}/", root))
+}
+
+fn item_path(storage: &dyn StorageAdapter, id: &str) -> Result<String, StorageError> {
+    // Ids come from clients on restore, keep them inside the trash folder
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(StorageError::InvalidPath(id.to_string()));
+    }
+    Ok(format!("{}/{}", trash_root(storage), id))
+}
+
+fn new_id(path: &str, deleted_at: u64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("{}-{}", deleted_at, hex::encode(&hasher.finalize()[..6]))
+}
+
+// Move `path` into the trash
+pub async fn move_to_trash(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    limit: usize,
+) -> Result<TrashEntry, StorageError> {
+    if !storage.exists(path).await? {
+        return Err(StorageError::NotFound(path.to_string()));
+    }
+
+    let deleted_at = now();
+    let entry = TrashEntry {
+        id: new_id(path, deleted_at),
+        path: path.to_string(),
+        deleted_at,
+        node_type: if storage.is_dir(path).await? {
+            "dir".to_string()
+        } else {
+            "file".to_string()
+        },
+    };
+
+    storage.create_dir(&trash_root(storage)).await?;
+    let target = item_path(storage, &entry.id)?;
+    move_item(storage, path, &target, limit).await?;
+    let info = serde_json::to_vec(&entry).map_err(|e| StorageError::Io(e.into()))?;
+    storage.write(&format!("{}.json", target), info).await?;
+    Ok(entry)
+}
+
+// Trashed items, most recently deleted first
+pub async fn list(storage: &dyn StorageAdapter) -> Result<Vec<TrashEntry>, StorageError> {
+    let root = trash_root(storage);
+    if !storage.is_dir(&root).await? {
+        return Ok(Vec::new());
+    }
+
+    let items = storage.list_contents(&root).await?;
+    let mut entries = Vec::new();
+    for item in items {
+        if item.node_type != "file" || !item.basename.ends_with(".json") {
+            continue;
+        }
+        // Skip info files that are unreadable or half written
+        if let Ok(info) = storage.read(&item.path).await {
+            if let Ok(entry) = serde_json::from_slice::<TrashEntry>(&info) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then(b.id.cmp(&a.id)));
+    Ok(entries)
+}
+
+async fn read_entry(storage: &dyn StorageAdapter, id: &str) -> Result<TrashEntry, StorageError> {
+    let info = storage
+        .read(&format!("{}.json", item_path(storage, id)?))
+        .await?;
+    serde_json::from_slice(&info).map_err(|e| StorageError::Io(e.into()))
+}
+
+// Put a trashed item back where it was deleted from. Returns the restored path,
+// which differs from the original when `policy` renamed it.
+pub async fn restore(
+    storage: &dyn StorageAdapter,
+    id: &str,
+    policy: CollisionPolicy,
+    limit: usize,
+) -> Result<Option<String>, StorageError> {
+    let entry = read_entry(storage, id).await?;
+
+    // The parent may have been deleted since
+    storage.create_dir(&parent_path(&entry.path)).await?;
+    let resolved = resolve_collision(storage, &entry.path, policy).await?;
+    if resolved.skip {
+        return Ok(None);
+    }
+
+    let source = item_path(storage, id)?;
+    move_item(storage, &source, &resolved.path, limit).await?;
+    storage.delete(&format!("{}.json", source)).await?;
+    Ok(Some(resolved.path.clone()))
+}
+
+// Permanently delete one trashed item
+pub async fn remove(storage: &dyn StorageAdapter, id: &str) -> Result<(), StorageError> {
+    let path = item_path(storage, id)?;
+    match storage.delete(&path).await {
+        Ok(()) | Err(StorageError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    storage.delete(&format!("{}.json", path)).await
+}
+
+// Permanently delete items trashed more than `max_age_secs` ago
+pub async fn purge(storage: &dyn StorageAdapter, max_age_secs: u64) -> Result<(), StorageError> {
+    let cutoff = now().saturating_sub(max_age_secs);
+    for entry in list(storage).await? {
+        if entry.deleted_at <= cutoff {
+            remove(storage, &entry.id).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_trash_and_restore() {
+        let storage = MemoryStorage::new("mem");
+        storage
+            .write("mem://docs/a.txt", b"a".to_vec())
+            .await
+            .unwrap();
+
+        let entry = move_to_trash(&storage, "mem://docs", 100).await.unwrap();
+        assert_eq!(entry.node_type, "dir");
+        assert!(!storage.exists("mem://docs").await.unwrap());
+        assert_eq!(list(&storage).await.unwrap(), vec![entry.clone()]);
+
+        // Restored next to a newer item of the same name
+        storage.create_dir("mem://docs").await.unwrap();
+        let restored = restore(&storage, &entry.id, CollisionPolicy::Rename, 100)
+            .await
+            .unwrap();
+        assert_eq!(restored.as_deref(), Some("mem://docs (1)"));
+        assert_eq!(storage.read("mem://docs (1)/a.txt").await.unwrap(), b"a");
+        assert!(list(&storage).await.unwrap().is_empty());
+
+        assert!(restore(&storage, "../docs", CollisionPolicy::Rename, 100)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_purge() {
+        let storage = MemoryStorage::new("mem");
+        storage.write("mem://old.txt", vec![]).await.unwrap();
+        storage.write("mem://new.txt", vec![]).await.unwrap();
+
+        let old = move_to_trash(&storage, "mem://old.txt", 100).await.unwrap();
+        let new = move_to_trash(&storage, "mem://new.txt", 100).await.unwrap();
+
+        // Backdate one entry
+        let info = serde_json::to_vec(&TrashEntry {
+            deleted_at: old.deleted_at - 10 * 86_400,
+            ..old.clone()
+        })
+        .unwrap();
+        storage
+            .write(&format!("mem://.trash/{}.json", old.id), info)
+            .await
+            .unwrap();
+
+        purge(&storage, 86_400).await.unwrap();
+        let remaining = list(&storage).await.unwrap();
+        assert_eq!(remaining, vec![new]);
+        assert!(!storage
+            .exists(&format!("mem://.trash/{}", old.id))
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,334 @@
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+use crate::storages::random_id;
+
+#[derive(Error, Debug)]
+pub enum UploadSessionError {
+    #[error("Unknown or expired upload session: {0}")]
+    NotFound(String),
+    // Carries the offset the next chunk must start at
+    #[error("Chunk should start at offset {0}")]
+    OffsetMismatch(u64),
+    #[error("Upload exceeds its declared size of {0} bytes")]
+    TooLarge(u64),
+    #[error("Upload incomplete: expected {expected} bytes, received {received}")]
+    Incomplete { expected: u64, received: u64 },
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// A resumable upload in progress. Chunks are appended to a staging file on the
+// server and only written through the adapter once the upload is finalized.
+#[derive(Debug, Clone)]
+pub struct UploadSession {
+    // Who opened it, the only one who can use its id
+    pub owner: Option<String>,
+    pub adapter: String,
+    // Directory the file is uploaded into
+    pub path: String,
+    pub name: String,
+    // Declared total size, when the client knows it
+    pub size: Option<u64>,
+    // Bytes received so far, where the next chunk starts
+    pub offset: u64,
+    file: PathBuf,
+    touched: Instant,
+}
+
+// Open upload sessions, shared by all workers
+#[derive(Debug)]
+pub struct UploadSessions {
+    dir: PathBuf,
+    sessions: Mutex<HashMap<String, Arc<tokio::sync::Mutex<UploadSession>>>>,
+}
+
+// A finalized upload, its staging file is removed when dropped
+pub struct StagedUpload {
+    pub session: UploadSession,
+}
+
+impl StagedUpload {
+    // The assembled contents, read from the staging file in chunks
+    pub async fn contents(
+        &self,
+    ) -> Result<BoxStream<'static, Result<Bytes, std::io::Error>>, std::io::Error> {
+        let file = fs::File::open(&self.session.file).await?;
+        Ok(ReaderStream::new(file).boxed())
+    }
+}
+
+impl Drop for StagedUpload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.session.file);
+    }
+}
+
+impl Default for UploadSessions {
+    fn default() -> Self {
+        Self::new(std::env::temp_dir().join("vuefinder-uploads"))
+    }
+}
+
+impl UploadSessions {
+    // Stage chunks below `dir`, created on first use
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Session `id` locked for `owner`, sessions of others are reported as
+    // missing
+    async fn get(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+    ) -> Result<tokio::sync::OwnedMutexGuard<UploadSession>, UploadSessionError> {
+        let session = self.sessions.lock().unwrap().get(id).cloned();
+        match session {
+            Some(session) => {
+                let session = session.lock_owned().await;
+                // Finished or removed while waiting for the lock
+                let open = self.sessions.lock().unwrap().contains_key(id);
+                if !open || session.owner.as_deref() != owner {
+                    return Err(UploadSessionError::NotFound(id.to_string()));
+                }
+                Ok(session)
+            }
+            None => Err(UploadSessionError::NotFound(id.to_string())),
+        }
+    }
+
+    // Open a session and return its id
+    pub async fn create(
+        &self,
+        owner: Option<&str>,
+        adapter: &str,
+        path: &str,
+        name: &str,
+        size: Option<u64>,
+    ) -> Result<String, UploadSessionError> {
+        fs::create_dir_all(&self.dir).await?;
+
+        let id = random_id();
+        let file = self.dir.join(format!("{}.part", id));
+        fs::File::create(&file).await?;
+
+        let session = UploadSession {
+            owner: owner.map(str::to_string),
+            adapter: adapter.to_string(),
+            path: path.to_string(),
+            name: name.to_string(),
+            size,
+            offset: 0,
+            file,
+            touched: Instant::now(),
+        };
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Arc::new(tokio::sync::Mutex::new(session)));
+        Ok(id)
+    }
+
+    pub async fn status(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+    ) -> Result<UploadSession, UploadSessionError> {
+        Ok(self.get(id, owner).await?.clone())
+    }
+
+    // Append a chunk that must start where the previous one ended, so a client
+    // resuming after a failure can't leave a gap or write twice. Returns the new offset.
+    pub async fn append(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        offset: u64,
+        chunk: &[u8],
+    ) -> Result<u64, UploadSessionError> {
+        let mut session = self.get(id, owner).await?;
+
+        if offset != session.offset {
+            return Err(UploadSessionError::OffsetMismatch(session.offset));
+        }
+        let offset = session.offset + chunk.len() as u64;
+        if let Some(size) = session.size {
+            if offset > size {
+                return Err(UploadSessionError::TooLarge(size));
+            }
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&session.file)
+            .await?;
+        file.write_all(chunk).await?;
+        file.flush().await?;
+
+        session.offset = offset;
+        session.touched = Instant::now();
+        Ok(offset)
+    }
+
+    // Close the session and hand over its staging file
+    pub async fn finish(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+    ) -> Result<StagedUpload, UploadSessionError> {
+        let session = self.get(id, owner).await?;
+        if let Some(size) = session.size {
+            if session.offset != size {
+                return Err(UploadSessionError::Incomplete {
+                    expected: size,
+                    received: session.offset,
+                });
+            }
+        }
+
+        // Still locked, so no chunk is appended after this
+        self.sessions.lock().unwrap().remove(id);
+        Ok(StagedUpload {
+            session: session.clone(),
+        })
+    }
+
+    // Drop a session of `owner` and its staged chunks
+    pub async fn remove(&self, id: &str, owner: Option<&str>) -> bool {
+        if self.get(id, owner).await.is_err() {
+            return false;
+        }
+        self.discard(id).await
+    }
+
+    async fn discard(&self, id: &str) -> bool {
+        let session = self.sessions.lock().unwrap().remove(id);
+        match session {
+            Some(session) => {
+                let _ = fs::remove_file(&session.lock().await.file).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Remove sessions without activity for longer than `ttl`
+    pub async fn prune(&self, ttl: Duration) {
+        let stale: Vec<_> = {
+            let sessions = self.sessions.lock().unwrap();
+            sessions
+                .iter()
+                .filter(|(_, session)| {
+                    // A locked session is receiving a chunk right now
+                    session
+                        .try_lock()
+                        .map(|session| session.touched.elapsed() > ttl)
+                        .unwrap_or(false)
+                })
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+        for id in stale {
+            self.discard(&id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_upload_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let sessions = UploadSessions::new(temp_dir.path());
+
+        let id = sessions
+            .create(Some("ann"), "local", "local://", "big.bin", Some(6))
+            .await
+            .unwrap();
+        assert_eq!(
+            sessions.append(&id, Some("ann"), 0, b"abc").await.unwrap(),
+            3
+        );
+
+        // Other users can't see or use the session
+        assert!(matches!(
+            sessions.append(&id, Some("bob"), 3, b"def").await,
+            Err(UploadSessionError::NotFound(_))
+        ));
+        assert!(sessions.status(&id, Some("bob")).await.is_err());
+        assert!(!sessions.remove(&id, Some("bob")).await);
+
+        // A retried chunk is refused and reports where to resume
+        assert!(matches!(
+            sessions.append(&id, Some("ann"), 0, b"abc").await,
+            Err(UploadSessionError::OffsetMismatch(3))
+        ));
+        assert!(matches!(
+            sessions.append(&id, Some("ann"), 3, b"defg").await,
+            Err(UploadSessionError::TooLarge(6))
+        ));
+        assert!(matches!(
+            sessions.finish(&id, Some("ann")).await,
+            Err(UploadSessionError::Incomplete { .. })
+        ));
+
+        assert_eq!(
+            sessions.append(&id, Some("ann"), 3, b"def").await.unwrap(),
+            6
+        );
+        let staged = sessions.finish(&id, Some("ann")).await.unwrap();
+        assert_eq!(staged.session.name, "big.bin");
+        let contents: Vec<Bytes> = staged
+            .contents()
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(contents.concat(), b"abcdef");
+        drop(staged);
+        assert!(matches!(
+            sessions.status(&id, Some("ann")).await,
+            Err(UploadSessionError::NotFound(_))
+        ));
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_prune_stale_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let sessions = UploadSessions::new(temp_dir.path());
+
+        let stale = sessions
+            .create(Some("ann"), "local", "local://", "a.bin", None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let fresh = sessions
+            .create(Some("ann"), "local", "local://", "b.bin", None)
+            .await
+            .unwrap();
+
+        sessions.prune(Duration::from_millis(20)).await;
+        assert!(sessions.status(&stale, Some("ann")).await.is_err());
+        assert!(sessions.status(&fresh, Some("ann")).await.is_ok());
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use crate::storages::checksum::ChecksumAlgorithm;
+use crate::storages::{content_hash, StorageAdapter, StorageError};
+
+// Folder at each adapter's root holding earlier contents of overwritten files.
+// Versions of a file are kept in `.versions/<key>/<id>` with their details in
+// `<id>.json`, where `key` is derived from the file's path.
+pub const VERSIONS_DIR: &str = ".versions";
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FileVersion {
+    pub id: String,
+    // The file this is a version of
+    pub path: String,
+    pub size: u64,
+    // Hex SHA-256 of the contents
+    pub hash: String,
+    // Unix seconds when it was replaced
+    pub saved_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Ids sort by when the version was taken, even within the same second
+fn new_id(path: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("{:020}-{}", nanos, hex::encode(&hasher.finalize()[..6]))
+}
+
+fn history_path(storage: &dyn StorageAdapter, path: &str) -> String {
+    format!(
+        "{}://{}/{}",
+        storage.name(),
+        VERSIONS_DIR,
+        &content_hash(path.as_bytes())[..16]
+    )
+}
+
+fn version_path(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    id: &str,
+) -> Result<String, StorageError> {
+    // Ids come from clients on restore, keep them inside the file's history
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(StorageError::InvalidPath(id.to_string()));
+    }
+    Ok(format!("{}/{}", history_path(storage, path), id))
+}
+
+// Keep the current contents of `path` as a version. Nothing is kept for
+// missing files, folders, or contents the newest version already has.
+pub async fn snapshot(
+    storage: &dyn StorageAdapter,
+    path: &str,
+) -> Result<Option<FileVersion>, StorageError> {
+    let item = match storage.metadata(path).await {
+        Ok(item) if item.node_type == "file" => item,
+        Ok(_) | Err(StorageError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let hash = storage.checksum(path, ChecksumAlgorithm::Sha256).await?;
+    if list(storage, path)
+        .await?
+        .first()
+        .is_some_and(|newest| newest.hash == hash)
+    {
+        return Ok(None);
+    }
+
+    let saved_at = now();
+    let version = FileVersion {
+        id: new_id(path),
+        path: path.to_string(),
+        size: match item.size {
+            Some(size) => size,
+            None => storage.file_size(path).await?,
+        },
+        hash,
+        saved_at,
+    };
+    storage.create_dir(&history_path(storage, path)).await?;
+    let target = version_path(storage, path, &version.id)?;
+    storage
+        .write_stream(&target, storage.read_stream(path).await?)
+        .await?;
+    let info = serde_json::to_vec(&version).map_err(|e| StorageError::Io(e.into()))?;
+    storage.write(&format!("{}.json", target), info).await?;
+    Ok(Some(version))
+}
+
+// Versions of `path`, newest first
+pub async fn list(
+    storage: &dyn StorageAdapter,
+    path: &str,
+) -> Result<Vec<FileVersion>, StorageError> {
+    let root = history_path(storage, path);
+    let items = match storage.list_contents(&root).await {
+        Ok(items) => items,
+        Err(StorageError::NotFound(_)) | Err(StorageError::NotADirectory(_)) => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut versions = Vec::new();
+    for item in items {
+        if item.node_type != "file" || !item.basename.ends_with(".json") {
+            continue;
+        }
+        // Skip info files that are unreadable or half written
+        if let Ok(info) = storage.read(&item.path).await {
+            if let Ok(version) = serde_json::from_slice::<FileVersion>(&info) {
+                // Another path sharing the key
+                if version.path == path {
+                    versions.push(version);
+                }
+            }
+        }
+    }
+    versions.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(versions)
+}
+
+pub async fn read(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    id: &str,
+) -> Result<Vec<u8>, StorageError> {
+    storage.read(&version_path(storage, path, id)?).await
+}
+
+// Write version `id` back to `path`, keeping the contents it replaces as a
+// version of their own so the restore can be undone
+pub async fn restore(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    id: &str,
+) -> Result<FileVersion, StorageError> {
+    let source = version_path(storage, path, id)?;
+    let info = storage.read(&format!("{}.json", source)).await?;
+    let version: FileVersion =
+        serde_json::from_slice(&info).map_err(|e| StorageError::Io(e.into()))?;
+    if version.path != path {
+        return Err(StorageError::NotFound(source));
+    }
+
+    snapshot(storage, path).await?;
+    storage
+        .write_stream(path, storage.read_stream(&source).await?)
+        .await?;
+    Ok(version)
+}
+
+pub async fn remove(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    id: &str,
+) -> Result<(), StorageError> {
+    let target = version_path(storage, path, id)?;
+    match storage.delete(&target).await {
+        Ok(()) | Err(StorageError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    storage.delete(&format!("{}.json", target)).await
+}
+
+// Drop the versions of `path` beyond the newest `max_versions`, and those
+// saved more than `max_age_secs` ago
+pub async fn prune(
+    storage: &dyn StorageAdapter,
+    path: &str,
+    max_versions: Option<usize>,
+    max_age_secs: Option<u64>,
+) -> Result<(), StorageError> {
+    let cutoff = max_age_secs.map(|age| now().saturating_sub(age));
+    for (index, version) in list(storage, path).await?.into_iter().enumerate() {
+        let too_many = max_versions.is_some_and(|max| index >= max);
+        let too_old = cutoff.is_some_and(|cutoff| version.saved_at <= cutoff);
+        if too_many || too_old {
+            remove(storage, path, &version.id).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_versions() {
+        let storage = MemoryStorage::new("mem");
+        assert_eq!(snapshot(&storage, "mem://a.txt").await.unwrap(), None);
+
+        storage.write("mem://a.txt", b"v1".to_vec()).await.unwrap();
+        let v1 = snapshot(&storage, "mem://a.txt").await.unwrap().unwrap();
+        assert_eq!((v1.size, v1.hash.clone()), (2, content_hash(b"v1")));
+        // Unchanged contents aren't kept twice
+        assert_eq!(snapshot(&storage, "mem://a.txt").await.unwrap(), None);
+
+        storage.write("mem://a.txt", b"v2".to_vec()).await.unwrap();
+        let v2 = snapshot(&storage, "mem://a.txt").await.unwrap().unwrap();
+        storage.write("mem://a.txt", b"v3".to_vec()).await.unwrap();
+        assert_eq!(
+            list(&storage, "mem://a.txt").await.unwrap(),
+            vec![v2.clone(), v1.clone()]
+        );
+        assert_eq!(read(&storage, "mem://a.txt", &v1.id).await.unwrap(), b"v1");
+        assert!(list(&storage, "mem://b.txt").await.unwrap().is_empty());
+
+        restore(&storage, "mem://a.txt", &v1.id).await.unwrap();
+        assert_eq!(storage.read("mem://a.txt").await.unwrap(), b"v1");
+        let versions = list(&storage, "mem://a.txt").await.unwrap();
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions[0].hash, content_hash(b"v3"));
+
+        assert!(restore(&storage, "mem://a.txt", "../a.txt").await.is_err());
+        assert!(restore(&storage, "mem://b.txt", &v1.id).await.is_err());
+
+        prune(&storage, "mem://a.txt", Some(1), None).await.unwrap();
+        let versions = list(&storage, "mem://a.txt").await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].hash, content_hash(b"v3"));
+        prune(&storage, "mem://a.txt", None, Some(0)).await.unwrap();
+        assert!(list(&storage, "mem://a.txt").await.unwrap().is_empty());
+    }
+}
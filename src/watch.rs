@@ -0,0 +1,372 @@
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::storages::{join_path, StorageAdapter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+// A change to an entry directly inside the watched directory
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WatchEvent {
+    pub kind: WatchKind,
+    pub path: String,
+}
+
+// Fold a new change into the pending batch; a create followed by writes is still a create
+fn merge(pending: &mut BTreeMap<String, WatchKind>, path: String, kind: WatchKind) {
+    match (pending.get(&path), kind) {
+        (Some(WatchKind::Created), WatchKind::Modified) => {}
+        _ => {
+            pending.insert(path, kind);
+        }
+    }
+}
+
+fn event_kind(kind: &EventKind) -> Option<WatchKind> {
+    match kind {
+        EventKind::Create(_) => Some(WatchKind::Created),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Some(WatchKind::Deleted),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Some(WatchKind::Created),
+        EventKind::Modify(_) => Some(WatchKind::Modified),
+        EventKind::Remove(_) => Some(WatchKind::Deleted),
+        _ => None,
+    }
+}
+
+// Stream changes below `path`. Storages with a local directory use a filesystem
+// watcher, others are polled every `poll_interval`. Events arriving within
+// `debounce` of each other are merged per path. Dropping the stream stops watching.
+pub fn watch(
+    storage: Arc<dyn StorageAdapter>,
+    path: String,
+    debounce: Duration,
+    poll_interval: Duration,
+) -> BoxStream<'static, WatchEvent> {
+    if let Some(dir) = storage.local_path(&path) {
+        if let Ok(stream) = watch_local(dir, path.clone(), debounce) {
+            return stream;
+        }
+    }
+    poll(storage, path, poll_interval)
+}
+
+fn watch_local(
+    dir: PathBuf,
+    path: String,
+    debounce: Duration,
+) -> notify::Result<BoxStream<'static, WatchEvent>> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    let collect = move |pending: &mut BTreeMap<String, WatchKind>, event: notify::Event| {
+        if let Some(kind) = event_kind(&event.kind) {
+            for changed in event.paths {
+                if let Ok(name) = changed.strip_prefix(&dir) {
+                    if !name.as_os_str().is_empty() {
+                        merge(pending, join_path(&path, &name.to_string_lossy()), kind);
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(Box::pin(async_stream::stream! {
+        // Owned by the stream so the OS watch ends with the connection
+        let _watcher = watcher;
+
+        while let Some(event) = rx.recv().await {
+            let mut pending = BTreeMap::new();
+            collect(&mut pending, event);
+
+            let deadline = tokio::time::sleep(debounce);
+            tokio::pin!(deadline);
+            loop {
+                tokio::select! {
+                    _ = &mut deadline => break,
+                    event = rx.recv() => match event {
+                        Some(event) => collect(&mut pending, event),
+                        None => break,
+                    },
+                }
+            }
+
+            for (path, kind) in pending {
+                yield WatchEvent { kind, path };
+            }
+        }
+    }))
+}
+
+// Shares one watch per storage path between everyone watching it, so a folder
+// open in many browsers is watched or polled once
+#[derive(Default)]
+pub struct WatchHub {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<WatchEvent>>>>,
+}
+
+impl WatchHub {
+    // Like `watch`, joining the watch of `path` when there is one
+    pub fn subscribe(
+        &self,
+        storage: Arc<dyn StorageAdapter>,
+        path: String,
+        debounce: Duration,
+        poll_interval: Duration,
+    ) -> BoxStream<'static, WatchEvent> {
+        // Users see the same folder under different paths, each gets its own watch
+        let key = format!("{}:{}", storage.shared_path(&path), path);
+        let mut receiver = {
+            let mut channels = self.channels.lock().unwrap();
+            match channels.get(&key) {
+                Some(sender) => sender.subscribe(),
+                None => {
+                    let (sender, receiver) = broadcast::channel(256);
+                    channels.insert(key.clone(), sender.clone());
+                    let events = watch(storage, path, debounce, poll_interval);
+                    tokio::spawn(forward(
+                        self.channels.clone(),
+                        key,
+                        sender,
+                        events,
+                        debounce.max(poll_interval),
+                    ));
+                    receiver
+                }
+            }
+        };
+
+        Box::pin(async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => yield event,
+                    // A slow client misses some events rather than holding up the others
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    // Paths currently watched
+    pub fn len(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Send a watch's events to its subscribers until the last one leaves, checking
+// every `idle_check` when nothing changes
+async fn forward(
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<WatchEvent>>>>,
+    key: String,
+    sender: broadcast::Sender<WatchEvent>,
+    mut events: BoxStream<'static, WatchEvent>,
+    idle_check: Duration,
+) {
+    loop {
+        match tokio::time::timeout(idle_check, events.next()).await {
+            Ok(Some(event)) => {
+                let _ = sender.send(event);
+            }
+            Ok(None) => break,
+            Err(_) => {}
+        }
+
+        // Checked under the lock so nobody subscribes to a watch being removed
+        let mut channels = channels.lock().unwrap();
+        if sender.receiver_count() == 0 {
+            channels.remove(&key);
+            return;
+        }
+    }
+    channels.lock().unwrap().remove(&key);
+}
+
+// Compare listings over time for storages without change notifications
+fn poll(
+    storage: Arc<dyn StorageAdapter>,
+    path: String,
+    interval: Duration,
+) -> BoxStream<'static, WatchEvent> {
+    async fn snapshot(
+        storage: &dyn StorageAdapter,
+        path: &str,
+    ) -> HashMap<String, (Option<u64>, Option<u64>)> {
+        match storage.list_contents(path).await {
+            Ok(items) => items
+                .into_iter()
+                .map(|item| (item.path, (item.last_modified, item.size)))
+                .collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    Box::pin(async_stream::stream! {
+        let mut previous = snapshot(storage.as_ref(), &path).await;
+
+        loop {
+            tokio::time::sleep(interval).await;
+            let current = snapshot(storage.as_ref(), &path).await;
+
+            let mut changes = BTreeMap::new();
+            for (entry, meta) in &current {
+                match previous.get(entry) {
+                    None => merge(&mut changes, entry.clone(), WatchKind::Created),
+                    Some(old) if old != meta => merge(&mut changes, entry.clone(), WatchKind::Modified),
+                    _ => {}
+                }
+            }
+            for entry in previous.keys() {
+                if !current.contains_key(entry) {
+                    merge(&mut changes, entry.clone(), WatchKind::Deleted);
+                }
+            }
+
+            previous = current;
+            for (path, kind) in changes {
+                yield WatchEvent { kind, path };
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::local::LocalStorage;
+    use crate::storages::memory::MemoryStorage;
+    use tempfile::TempDir;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn test_watch_local() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage: Arc<dyn StorageAdapter> =
+            Arc::new(LocalStorage::new(temp_dir.path().to_str().unwrap()));
+
+        let mut events = watch(
+            storage.clone(),
+            "local://".to_string(),
+            Duration::from_millis(50),
+            Duration::from_secs(60),
+        );
+
+        storage
+            .write("local://new.txt", b"hello".to_vec())
+            .await
+            .unwrap();
+
+        let event = tokio::time::timeout(TIMEOUT, events.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event,
+            WatchEvent {
+                kind: WatchKind::Created,
+                path: "local://new.txt".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_polling() {
+        let storage: Arc<dyn StorageAdapter> = Arc::new(MemoryStorage::new("mem"));
+        storage.write("mem://old.txt", vec![]).await.unwrap();
+
+        let mut events = watch(
+            storage.clone(),
+            "mem://".to_string(),
+            Duration::from_millis(50),
+            Duration::from_millis(20),
+        );
+        // Let the first snapshot happen before changing anything
+        let first = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+        assert!(first.is_err());
+
+        storage.write("mem://new.txt", vec![]).await.unwrap();
+        storage.delete("mem://old.txt").await.unwrap();
+
+        let mut seen = Vec::new();
+        while seen.len() < 2 {
+            seen.push(
+                tokio::time::timeout(TIMEOUT, events.next())
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        assert!(seen.contains(&WatchEvent {
+            kind: WatchKind::Created,
+            path: "mem://new.txt".to_string(),
+        }));
+        assert!(seen.contains(&WatchEvent {
+            kind: WatchKind::Deleted,
+            path: "mem://old.txt".to_string(),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_watch_hub_shares_watches() {
+        let storage: Arc<dyn StorageAdapter> = Arc::new(MemoryStorage::new("mem"));
+        let hub = WatchHub::default();
+        let subscribe = || {
+            hub.subscribe(
+                storage.clone(),
+                "mem://".to_string(),
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+            )
+        };
+
+        let mut first = subscribe();
+        let mut second = subscribe();
+        assert_eq!(hub.len(), 1);
+        // Let the first snapshot happen before changing anything
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        storage.write("mem://new.txt", vec![]).await.unwrap();
+        let expected = WatchEvent {
+            kind: WatchKind::Created,
+            path: "mem://new.txt".to_string(),
+        };
+        for events in [&mut first, &mut second] {
+            let event = tokio::time::timeout(TIMEOUT, events.next()).await.unwrap();
+            assert_eq!(event, Some(expected.clone()));
+        }
+
+        // The watch stops once nobody is left
+        drop(first);
+        drop(second);
+        tokio::time::timeout(TIMEOUT, async {
+            while !hub.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,343 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_util::task::TaskTracker;
+
+// Header carrying `sha256=<hex HMAC of the body>` when the webhook has a secret
+pub const SIGNATURE_HEADER: &str = "X-VueFinder-Signature";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    // Signs every request body, see `SIGNATURE_HEADER`
+    pub secret: Option<String>,
+    // Events to send, e.g. `["upload", "delete"]`, all of them when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl WebhookConfig {
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|wanted| wanted == event)
+    }
+}
+
+// Body POSTed to webhooks after a command succeeded
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookEvent {
+    pub event: String,
+    pub adapter: Option<String>,
+    // Folder the command ran in
+    pub path: Option<String>,
+    // `{ paths }` for commands, the adapter paths they named or changed
+    pub payload: Value,
+    // Unix seconds
+    pub timestamp: u64,
+}
+
+impl WebhookEvent {
+    pub fn new(event: &str, adapter: Option<String>, path: Option<String>, payload: Value) -> Self {
+        Self {
+            event: event.to_string(),
+            adapter,
+            path,
+            payload,
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+
+    // Add `paths` the command changed to those of the payload, once each
+    pub fn add_paths(&mut self, paths: impl IntoIterator<Item = String>) {
+        let Some(known) = self.payload["paths"].as_array_mut() else {
+            return;
+        };
+        for path in paths.into_iter().map(Value::String) {
+            if !known.contains(&path) {
+                known.push(path);
+            }
+        }
+    }
+}
+
+// Event sent for a command, `None` for commands that don't change anything
+pub fn event_name(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "upload" | "upload-finalize" | "fetch-url" => "upload",
+        "delete" => "delete",
+        "move" => "move",
+        "copy" => "copy",
+        "sync" => "sync",
+        "rename" | "bulk-rename" => "rename",
+        "archive" => "archive",
+        "unarchive" => "unarchive",
+        "newfolder" => "newfolder",
+        "newfile" => "newfile",
+        "save" | "cas-save" | "append" | "versions/restore" => "save",
+        _ => return None,
+    })
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// Delivers webhook requests, replaceable to send them some other way
+#[async_trait]
+pub trait WebhookTransport: Send + Sync {
+    async fn post(&self, url: &str, body: Vec<u8>, signature: Option<String>)
+        -> Result<(), String>;
+}
+
+#[cfg(feature = "webhooks")]
+struct HttpTransport(reqwest::Client);
+
+#[cfg(feature = "webhooks")]
+#[async_trait]
+impl WebhookTransport for HttpTransport {
+    async fn post(
+        &self,
+        url: &str,
+        body: Vec<u8>,
+        signature: Option<String>,
+    ) -> Result<(), String> {
+        let mut request = self
+            .0
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(signature) = signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// Sends events to the webhooks of `VueFinderConfig::webhooks`
+pub struct Webhooks {
+    // `None` without the `webhooks` feature and a custom transport
+    transport: Option<Arc<dyn WebhookTransport>>,
+    // Deliveries running in the background, waited for on shutdown
+    deliveries: TaskTracker,
+}
+
+impl Default for Webhooks {
+    #[cfg(feature = "webhooks")]
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self::new(HttpTransport(client))
+    }
+
+    #[cfg(not(feature = "webhooks"))]
+    fn default() -> Self {
+        Self {
+            transport: None,
+            deliveries: TaskTracker::new(),
+        }
+    }
+}
+
+impl Webhooks {
+    pub fn new(transport: impl WebhookTransport + 'static) -> Self {
+        Self {
+            transport: Some(Arc::new(transport)),
+            deliveries: TaskTracker::new(),
+        }
+    }
+
+    // Event of `command` naming `paths`. The request `payload` isn't sent,
+    // it may hold file contents.
+    pub fn event(
+        command: &str,
+        adapter: Option<String>,
+        path: Option<String>,
+        payload: Option<&Value>,
+        paths: Vec<String>,
+    ) -> Option<WebhookEvent> {
+        // Previews don't change anything
+        if payload.is_some_and(|payload| payload["dry_run"] == true) {
+            return None;
+        }
+        let mut event = WebhookEvent::new(
+            event_name(command)?,
+            adapter,
+            path,
+            serde_json::json!({ "paths": [] }),
+        );
+        event.add_paths(paths);
+        Some(event)
+    }
+
+    // Notify in the background so slow receivers don't hold up the response
+    pub fn spawn_notify(self: &Arc<Self>, webhooks: &[WebhookConfig], event: WebhookEvent) {
+        if self.transport.is_none() || webhooks.is_empty() {
+            return;
+        }
+        let this = self.clone();
+        let webhooks = webhooks.to_vec();
+        self.deliveries
+            .spawn(async move { this.notify(&webhooks, &event).await });
+    }
+
+    // Wait up to `timeout` for the background deliveries, `false` when some
+    // were still running
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.deliveries.close();
+        tokio::time::timeout(timeout, self.deliveries.wait())
+            .await
+            .is_ok()
+    }
+
+    // POST `event` to every webhook that wants it. Failures are logged, not retried.
+    pub async fn notify(&self, webhooks: &[WebhookConfig], event: &WebhookEvent) {
+        // The server warns about this on startup
+        let Some(transport) = &self.transport else {
+            return;
+        };
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("Cannot encode webhook event: {}", e);
+                return;
+            }
+        };
+
+        for webhook in webhooks
+            .iter()
+            .filter(|webhook| webhook.wants(&event.event))
+        {
+            let signature = webhook
+                .secret
+                .as_deref()
+                .map(|secret| signature(secret, &body));
+            if let Err(e) = transport.post(&webhook.url, body.clone(), signature).await {
+                log::warn!("Webhook {} failed: {}", webhook.url, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, Value, Option<String>)>>);
+
+    #[async_trait]
+    impl WebhookTransport for Arc<Recorder> {
+        async fn post(
+            &self,
+            url: &str,
+            body: Vec<u8>,
+            signature: Option<String>,
+        ) -> Result<(), String> {
+            let body = serde_json::from_slice(&body).unwrap();
+            self.0
+                .lock()
+                .unwrap()
+                .push((url.to_string(), body, signature));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhooks() {
+        let recorder = Arc::new(Recorder::default());
+        let webhooks = Webhooks::new(recorder.clone());
+        let configs = vec![
+            WebhookConfig {
+                url: "http://all".to_string(),
+                secret: Some("secret".to_string()),
+                events: Vec::new(),
+            },
+            WebhookConfig {
+                url: "http://uploads".to_string(),
+                secret: None,
+                events: vec!["upload".to_string()],
+            },
+        ];
+
+        assert!(Webhooks::event("index", None, None, None, Vec::new()).is_none());
+        let payload = json!({ "content": "private" });
+        let mut event = Webhooks::event(
+            "save",
+            Some("local".to_string()),
+            Some("local://a.txt".to_string()),
+            Some(&payload),
+            vec!["local://a.txt".to_string()],
+        )
+        .unwrap();
+        event.add_paths(["local://a.txt".to_string(), "local://b.txt".to_string()]);
+        webhooks.notify(&configs, &event).await;
+
+        let sent = recorder.0.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let (url, body, sent_signature) = &sent[0];
+        assert_eq!(url, "http://all");
+        assert_eq!(body["event"], "save");
+        // Only the paths, never the contents
+        assert_eq!(
+            body["payload"],
+            json!({ "paths": ["local://a.txt", "local://b.txt"] })
+        );
+        let expected = signature("secret", &serde_json::to_vec(&event).unwrap());
+        assert_eq!(sent_signature.as_deref(), Some(expected.as_str()));
+    }
+
+    // Takes its time, like a receiver on the other side of the world
+    struct Slow(Arc<Recorder>);
+
+    #[async_trait]
+    impl WebhookTransport for Slow {
+        async fn post(
+            &self,
+            url: &str,
+            body: Vec<u8>,
+            signature: Option<String>,
+        ) -> Result<(), String> {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            self.0.post(url, body, signature).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let recorder = Arc::new(Recorder::default());
+        let webhooks = Arc::new(Webhooks::new(Slow(recorder.clone())));
+        let configs = vec![WebhookConfig {
+            url: "http://all".to_string(),
+            secret: None,
+            events: Vec::new(),
+        }];
+        let event = Webhooks::event("delete", None, None, None, Vec::new()).unwrap();
+
+        webhooks.spawn_notify(&configs, event.clone());
+        assert!(recorder.0.lock().unwrap().is_empty());
+        assert!(webhooks.drain(Duration::from_secs(5)).await);
+        assert_eq!(recorder.0.lock().unwrap().len(), 1);
+
+        // Gives up on deliveries outlasting the timeout
+        webhooks.spawn_notify(&configs, event);
+        assert!(!webhooks.drain(Duration::from_millis(10)).await);
+    }
+}
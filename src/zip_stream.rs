@@ -0,0 +1,436 @@
+use bytes::Bytes;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::storages::{StorageAdapter, StorageError};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const DATA_DESCRIPTOR: u32 = 0x08074b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP64_END: u32 = 0x06064b50;
+const ZIP64_LOCATOR: u32 = 0x07064b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+// Sizes and offsets from here on are stored in the ZIP64 extra field
+const ZIP64_LIMIT: u64 = 0xFFFF_FFFF;
+
+// Sizes follow the data in a descriptor, names are UTF-8
+const FLAG_DESCRIPTOR: u16 = 0x0008;
+const FLAG_UTF8: u16 = 0x0800;
+
+// One member of a streamed archive
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    // Path inside the archive, `/`-separated
+    pub name: String,
+    // File to read the contents from, `None` for a directory
+    pub source: Option<String>,
+    // Unix seconds
+    pub last_modified: Option<u64>,
+    // Size of `source` when known. Sizes are only written after the contents,
+    // so files of unknown or nearly 4GB size get ZIP64 headers up front.
+    pub size: Option<u64>,
+}
+
+struct Written {
+    name: String,
+    dir: bool,
+    // Local header carries the ZIP64 extra field and the descriptor 8 byte sizes
+    zip64: bool,
+    dos_time: u16,
+    dos_date: u16,
+    crc: u32,
+    compressed: u64,
+    uncompressed: u64,
+    offset: u64,
+}
+
+// Deflate may grow incompressible data a little, so sizes close to the limit
+// already need ZIP64
+fn needs_zip64(size: Option<u64>) -> bool {
+    size.is_none_or(|size| size + size / 1000 + 64 >= ZIP64_LIMIT)
+}
+
+// MS-DOS date and time, which can't go below 1980
+fn dos_datetime(unix: Option<u64>) -> (u16, u16) {
+    let secs = unix.unwrap_or(0).max(315_532_800);
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since the epoch
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = (yoe + era * 400 + i64::from(month <= 2)).min(2107);
+
+    let time = ((rem / 3_600) << 11) | (((rem % 3_600) / 60) << 5) | ((rem % 60) / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+fn local_header(entry: &Written) -> Vec<u8> {
+    let mut header = Vec::with_capacity(50 + entry.name.len());
+    header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+    header.extend_from_slice(&(if entry.zip64 { 45u16 } else { 20u16 }).to_le_bytes());
+    let flags = if entry.dir {
+        FLAG_UTF8
+    } else {
+        FLAG_UTF8 | FLAG_DESCRIPTOR
+    };
+    header.extend_from_slice(&flags.to_le_bytes());
+    header.extend_from_slice(&(if entry.dir { 0u16 } else { 8u16 }).to_le_bytes());
+    header.extend_from_slice(&entry.dos_time.to_le_bytes());
+    header.extend_from_slice(&entry.dos_date.to_le_bytes());
+    // CRC and sizes follow in the data descriptor
+    header.extend_from_slice(&0u32.to_le_bytes());
+    if entry.zip64 {
+        // Both sizes point to the ZIP64 extra field, which is zeroed as well
+        header.extend_from_slice(&[0xFF; 8]);
+    } else {
+        header.extend_from_slice(&[0; 8]);
+    }
+    header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    header.extend_from_slice(&(if entry.zip64 { 20u16 } else { 0u16 }).to_le_bytes());
+    header.extend_from_slice(entry.name.as_bytes());
+    if entry.zip64 {
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(&[0; 16]);
+    }
+    header
+}
+
+fn data_descriptor(entry: &Written) -> Vec<u8> {
+    let mut descriptor = Vec::with_capacity(24);
+    descriptor.extend_from_slice(&DATA_DESCRIPTOR.to_le_bytes());
+    descriptor.extend_from_slice(&entry.crc.to_le_bytes());
+    if entry.zip64 {
+        descriptor.extend_from_slice(&entry.compressed.to_le_bytes());
+        descriptor.extend_from_slice(&entry.uncompressed.to_le_bytes());
+    } else {
+        descriptor.extend_from_slice(&(entry.compressed as u32).to_le_bytes());
+        descriptor.extend_from_slice(&(entry.uncompressed as u32).to_le_bytes());
+    }
+    descriptor
+}
+
+fn central_header(entry: &Written) -> Vec<u8> {
+    // Values that don't fit are moved to the ZIP64 extra field, in this order.
+    // Sizes of entries with a ZIP64 local header always are, as readers
+    // size the data descriptor from either header.
+    let mut zip64 = Vec::new();
+    let mut field = |value: u64, forced: bool| {
+        if forced || value >= ZIP64_LIMIT {
+            zip64.extend_from_slice(&value.to_le_bytes());
+            ZIP64_LIMIT as u32
+        } else {
+            value as u32
+        }
+    };
+    let uncompressed = field(entry.uncompressed, entry.zip64);
+    let compressed = field(entry.compressed, entry.zip64);
+    let offset = field(entry.offset, false);
+
+    let mut extra = Vec::new();
+    if !zip64.is_empty() {
+        extra.extend_from_slice(&1u16.to_le_bytes());
+        extra.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+        extra.extend_from_slice(&zip64);
+    }
+    let version: u16 = if entry.zip64 || !zip64.is_empty() {
+        45
+    } else {
+        20
+    };
+    let flags = if entry.dir {
+        FLAG_UTF8
+    } else {
+        FLAG_UTF8 | FLAG_DESCRIPTOR
+    };
+
+    let mut header = Vec::with_capacity(46 + entry.name.len() + extra.len());
+    header.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+    header.extend_from_slice(&version.to_le_bytes());
+    header.extend_from_slice(&version.to_le_bytes());
+    header.extend_from_slice(&flags.to_le_bytes());
+    header.extend_from_slice(&(if entry.dir { 0u16 } else { 8u16 }).to_le_bytes());
+    header.extend_from_slice(&entry.dos_time.to_le_bytes());
+    header.extend_from_slice(&entry.dos_date.to_le_bytes());
+    header.extend_from_slice(&entry.crc.to_le_bytes());
+    header.extend_from_slice(&compressed.to_le_bytes());
+    header.extend_from_slice(&uncompressed.to_le_bytes());
+    header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    // Comment length, disk number, internal attributes
+    header.extend_from_slice(&[0; 6]);
+    // MS-DOS directory attribute
+    header.extend_from_slice(&(if entry.dir { 0x10u32 } else { 0 }).to_le_bytes());
+    header.extend_from_slice(&offset.to_le_bytes());
+    header.extend_from_slice(entry.name.as_bytes());
+    header.extend_from_slice(&extra);
+    header
+}
+
+fn end_of_central_directory(count: u64, offset: u64, size: u64) -> Vec<u8> {
+    let mut end = Vec::new();
+
+    if count >= 0xFFFF || offset >= ZIP64_LIMIT || size >= ZIP64_LIMIT {
+        let zip64_offset = offset + size;
+        end.extend_from_slice(&ZIP64_END.to_le_bytes());
+        end.extend_from_slice(&44u64.to_le_bytes());
+        end.extend_from_slice(&45u16.to_le_bytes());
+        end.extend_from_slice(&45u16.to_le_bytes());
+        end.extend_from_slice(&[0; 8]);
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&offset.to_le_bytes());
+
+        end.extend_from_slice(&ZIP64_LOCATOR.to_le_bytes());
+        end.extend_from_slice(&0u32.to_le_bytes());
+        end.extend_from_slice(&zip64_offset.to_le_bytes());
+        end.extend_from_slice(&1u32.to_le_bytes());
+    }
+
+    end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+    end.extend_from_slice(&[0; 4]);
+    let count = count.min(0xFFFF) as u16;
+    end.extend_from_slice(&count.to_le_bytes());
+    end.extend_from_slice(&count.to_le_bytes());
+    end.extend_from_slice(&(size.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    end.extend_from_slice(&(offset.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes());
+    end
+}
+
+// Stream a ZIP of `entries` as it is built. Each file is read and deflated chunk
+// by chunk, so neither the files nor the archive are ever held in memory whole.
+pub fn zip_stream(
+    storage: Arc<dyn StorageAdapter>,
+    entries: Vec<ZipEntry>,
+) -> BoxStream<'static, Result<Bytes, StorageError>> {
+    Box::pin(async_stream::try_stream! {
+        let mut offset = 0u64;
+        let mut written = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let (dos_time, dos_date) = dos_datetime(entry.last_modified);
+            let mut record = Written {
+                name: entry.name,
+                dir: entry.source.is_none(),
+                zip64: entry.source.is_some() && needs_zip64(entry.size),
+                dos_time,
+                dos_date,
+                crc: 0,
+                compressed: 0,
+                uncompressed: 0,
+                offset,
+            };
+            if record.dir && !record.name.ends_with('/') {
+                record.name.push('/');
+            }
+
+            let header = local_header(&record);
+            offset += header.len() as u64;
+            yield Bytes::from(header);
+
+            if let Some(source) = entry.source {
+                let mut hasher = crc32fast::Hasher::new();
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                let mut contents = storage.read_stream(&source).await?;
+
+                while let Some(chunk) = contents.try_next().await? {
+                    hasher.update(&chunk);
+                    record.uncompressed += chunk.len() as u64;
+                    encoder.write_all(&chunk)?;
+
+                    let compressed = std::mem::take(encoder.get_mut());
+                    if !compressed.is_empty() {
+                        record.compressed += compressed.len() as u64;
+                        yield Bytes::from(compressed);
+                    }
+                }
+
+                let compressed = encoder.finish()?;
+                record.compressed += compressed.len() as u64;
+                yield Bytes::from(compressed);
+                record.crc = hasher.finalize();
+                // Without ZIP64 headers the sizes can't be written any more
+                if !record.zip64
+                    && (record.compressed >= ZIP64_LIMIT || record.uncompressed >= ZIP64_LIMIT)
+                {
+                    Err(std::io::Error::other(format!(
+                        "{} grew past its listed size",
+                        source
+                    )))?;
+                }
+
+                let descriptor = data_descriptor(&record);
+                offset += record.compressed + descriptor.len() as u64;
+                yield Bytes::from(descriptor);
+            }
+
+            written.push(record);
+        }
+
+        let central_offset = offset;
+        let mut central = Vec::new();
+        for record in &written {
+            central.extend_from_slice(&central_header(record));
+        }
+        let central_size = central.len() as u64;
+        central.extend_from_slice(&end_of_central_directory(
+            written.len() as u64,
+            central_offset,
+            central_size,
+        ));
+        yield Bytes::from(central);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages::memory::MemoryStorage;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_dos_datetime() {
+        // 2024-02-29 13:45:30 UTC
+        let (time, date) = dos_datetime(Some(1_709_214_330));
+        assert_eq!(time, (13 << 11) | (45 << 5) | 15);
+        assert_eq!(date, (44 << 9) | (2 << 5) | 29);
+        assert_eq!(dos_datetime(None), (0, (1 << 5) | 1));
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream() {
+        let storage = Arc::new(MemoryStorage::new("mem"));
+        let large: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        storage
+            .write("mem://a.txt", b"hello".to_vec())
+            .await
+            .unwrap();
+        storage
+            .write("mem://docs/large.bin", large.clone())
+            .await
+            .unwrap();
+
+        let entries = vec![
+            ZipEntry {
+                name: "a.txt".to_string(),
+                source: Some("mem://a.txt".to_string()),
+                last_modified: None,
+                size: Some(5),
+            },
+            ZipEntry {
+                name: "docs".to_string(),
+                source: None,
+                last_modified: None,
+                size: None,
+            },
+            ZipEntry {
+                name: "docs/large.bin".to_string(),
+                source: Some("mem://docs/large.bin".to_string()),
+                last_modified: None,
+                size: None,
+            },
+        ];
+        let chunks: Vec<Bytes> = zip_stream(storage, entries).try_collect().await.unwrap();
+        assert!(chunks.len() > 3);
+
+        // Only the file of unknown size gets a ZIP64 local header
+        let zip64_headers = chunks
+            .iter()
+            .filter(|chunk| chunk.starts_with(&LOCAL_HEADER.to_le_bytes()))
+            .map(|chunk| chunk[4..6] == 45u16.to_le_bytes() && chunk[18..26] == [0xFF; 8])
+            .collect::<Vec<_>>();
+        assert_eq!(zip64_headers, [false, false, true]);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(chunks.concat())).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert!(archive.by_name("docs/").unwrap().is_dir());
+
+        let mut contents = Vec::new();
+        archive
+            .by_name("docs/large.bin")
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, large);
+
+        let mut contents = String::new();
+        archive
+            .by_name("a.txt")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream_zip64() {
+        // More entries than the end of central directory record can count
+        let storage = Arc::new(MemoryStorage::new("mem"));
+        let entries = (0..70_000)
+            .map(|i| ZipEntry {
+                name: format!("{}", i),
+                source: None,
+                last_modified: None,
+                size: None,
+            })
+            .collect();
+        let chunks: Vec<Bytes> = zip_stream(storage, entries).try_collect().await.unwrap();
+        let contents = chunks.concat();
+        assert!(contents
+            .windows(4)
+            .rev()
+            .any(|window| window == ZIP64_LOCATOR.to_le_bytes()));
+        let archive = zip::ZipArchive::new(Cursor::new(contents)).unwrap();
+        assert_eq!(archive.len(), 70_000);
+
+        // Sizes and offsets past 4GB
+        let header = central_header(&Written {
+            name: "big.bin".to_string(),
+            dir: false,
+            zip64: true,
+            dos_time: 0,
+            dos_date: 0,
+            crc: 0,
+            compressed: 5,
+            uncompressed: 6_000_000_000,
+            offset: 7_000_000_000,
+        });
+        assert_eq!(header[6..8], 45u16.to_le_bytes());
+        assert_eq!(header[20..28], [0xFF; 8]);
+        assert_eq!(header[42..46], [0xFF; 4]);
+        let extra = &header[46 + "big.bin".len()..];
+        assert_eq!(extra[..4], [1, 0, 24, 0]);
+        assert_eq!(extra[4..12], 6_000_000_000u64.to_le_bytes());
+        assert_eq!(extra[12..20], 5u64.to_le_bytes());
+        assert_eq!(extra[20..28], 7_000_000_000u64.to_le_bytes());
+        assert!(needs_zip64(Some(ZIP64_LIMIT - 1000)));
+        assert!(!needs_zip64(Some(1 << 30)));
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream_missing_file() {
+        let storage = Arc::new(MemoryStorage::new("mem"));
+        let entries = vec![ZipEntry {
+            name: "gone.txt".to_string(),
+            source: Some("mem://gone.txt".to_string()),
+            last_modified: None,
+            size: None,
+        }];
+        let result: Result<Vec<Bytes>, _> = zip_stream(storage, entries).try_collect().await;
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    }
+}